@@ -0,0 +1,58 @@
+//! End to end frame compression and decompression of every input at a few configurations.
+mod common;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use slide::{frame, lz::Config};
+use std::hint::black_box;
+
+const LEN: usize = 1 << 20;
+
+fn configs() -> [(&'static str, Config<'static>); 4] {
+    [
+        ("level1", Config::level(1)),
+        ("level5", Config::level(5)),
+        ("level9", Config::level(9)),
+        (
+            "block_sort",
+            Config::builder().block_sort(1 << 18).build().unwrap(),
+        ),
+    ]
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    group.sample_size(10);
+    for (name, data) in common::inputs(LEN) {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        for (config_name, config) in configs() {
+            let len = frame::compress(&data, config.clone()).len();
+            eprintln!(
+                "{name}/{config_name}: {len} bytes ({:.2}%)",
+                len as f64 * 100.0 / data.len() as f64
+            );
+            group.bench_with_input(BenchmarkId::new(config_name, name), &data, |b, data| {
+                b.iter(|| frame::compress(black_box(data), config.clone()))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, data) in common::inputs(LEN) {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        for (config_name, config) in configs() {
+            let compressed = frame::compress(&data, config);
+            group.bench_with_input(
+                BenchmarkId::new(config_name, name),
+                &compressed,
+                |b, compressed| b.iter(|| frame::decompress(black_box(compressed), None).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
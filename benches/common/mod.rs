@@ -0,0 +1,120 @@
+//! Inputs shared by the benchmarks: the silesia corpus where it can be found, and synthetic data
+//! generated the same way on every run.
+#![allow(dead_code)]
+
+use std::{env, fs, path::PathBuf, sync::Once};
+
+/// Where the silesia corpus can be downloaded from.
+pub const SILESIA_URL: &str = "https://sun.aei.polsl.pl/~sdeor/corpus/silesia.zip";
+/// Files of the silesia corpus.
+pub const SILESIA: [&str; 12] = [
+    "dickens", "mozilla", "mr", "nci", "ooffice", "osdb", "reymont", "samba", "sao", "webster",
+    "xml", "x-ray",
+];
+
+/// Directory holding the silesia corpus: `$SILESIA_DIR`, or `res/silesia`.
+pub fn silesia_dir() -> PathBuf {
+    env::var_os("SILESIA_DIR").map_or_else(|| PathBuf::from("res/silesia"), PathBuf::from)
+}
+
+/// The first `limit` bytes of the silesia file `name`, or None after telling where to put the
+/// corpus if it is missing.
+pub fn silesia(name: &str, limit: usize) -> Option<Vec<u8>> {
+    static MISSING: Once = Once::new();
+    let path = silesia_dir().join(name);
+    match fs::read(&path) {
+        Ok(mut data) => {
+            data.truncate(limit);
+            Some(data)
+        }
+        Err(_) => {
+            MISSING.call_once(|| {
+                eprintln!(
+                    "{} not found, skipping silesia inputs; unpack {SILESIA_URL} into {} or \
+                     set SILESIA_DIR",
+                    path.display(),
+                    silesia_dir().display()
+                )
+            });
+            None
+        }
+    }
+}
+
+/// Small xorshift* generator, so synthetic inputs don't depend on a random number crate.
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed | 1)
+    }
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        // Plain xorshift's low bits follow from the previous ones, which block sorting finds.
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+    pub fn byte(&mut self) -> u8 {
+        (self.next() >> 56) as u8
+    }
+    /// Uniform below `n`, near enough for generating inputs.
+    pub fn below(&mut self, n: usize) -> usize {
+        ((self.next() >> 32) % n as u64) as usize
+    }
+}
+
+/// Incompressible bytes.
+pub fn random(len: usize) -> Vec<u8> {
+    let mut rng = Rng::new(0x5eed);
+    Vec::from_iter((0..len).map(|_| rng.byte()))
+}
+
+/// Runs of a single byte, between 1 and 256 long.
+pub fn runs(len: usize) -> Vec<u8> {
+    let mut rng = Rng::new(0x2b7e);
+    let mut data = Vec::with_capacity(len);
+    while data.len() < len {
+        let (value, run) = (rng.byte(), 1 + rng.below(256));
+        data.extend(std::iter::repeat_n(value, run.min(len - data.len())));
+    }
+    data
+}
+
+/// Words of a small vocabulary, the common ones far more frequent, with some punctuation.
+pub fn text(len: usize) -> Vec<u8> {
+    const WORDS: [&str; 32] = [
+        "the", "of", "and", "to", "a", "in", "is", "it", "you", "that", "he", "was", "for", "on",
+        "are", "with", "as", "his", "they", "be", "at", "one", "have", "this", "from", "window",
+        "match", "slide", "buffer", "compress", "search", "distance",
+    ];
+    let mut rng = Rng::new(0x7e47);
+    let mut data = Vec::with_capacity(len + 16);
+    while data.len() < len {
+        // Squaring skews the choice towards the start of the vocabulary.
+        let x = rng.below(WORDS.len() * WORDS.len());
+        data.extend_from_slice(WORDS[x.isqrt() ^ (WORDS.len() - 1)].as_bytes());
+        data.push(match rng.below(16) {
+            0 => b'.',
+            1 => b',',
+            2 => b'\n',
+            _ => b' ',
+        });
+    }
+    data.truncate(len);
+    data
+}
+
+/// The synthetic inputs of `len` bytes, followed by the silesia files found, each cut to `len`.
+pub fn inputs(len: usize) -> Vec<(&'static str, Vec<u8>)> {
+    let mut inputs = vec![
+        ("random", random(len)),
+        ("runs", runs(len)),
+        ("text", text(len)),
+    ];
+    for name in SILESIA {
+        if let Some(data) = silesia(name, len) {
+            inputs.push((name, data));
+        }
+    }
+    inputs
+}
@@ -0,0 +1,51 @@
+//! Compares the match strategies on the silesia corpus, see [`common::silesia`].
+//!
+//! Only the first MiB of each file is used, so `Strategy::Optimal` finishes in reasonable time.
+//! The compressed size of every file and strategy is printed before it is benchmarked.
+mod common;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use slide::{
+    frame,
+    lz::{Config, Strategy},
+};
+use std::hint::black_box;
+
+const LIMIT: usize = 1 << 20;
+const STRATEGIES: [(&str, Strategy); 4] = [
+    ("greedy", Strategy::Greedy),
+    ("lazy2", Strategy::Lazy(2)),
+    ("best_of8", Strategy::BestOf(8)),
+    ("optimal", Strategy::Optimal),
+];
+
+fn strategies(c: &mut Criterion) {
+    let mut group = c.benchmark_group("strategy");
+    group.sample_size(10);
+    for name in common::SILESIA {
+        let Some(data) = common::silesia(name, LIMIT) else {
+            continue;
+        };
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        for (strategy_name, strategy) in STRATEGIES {
+            let config = Config {
+                max_buffer_len: 1 << 16,
+                match_lengths: 4..0x1000,
+                strategy,
+                ..Config::default()
+            };
+            let len = frame::compress(&data, config.clone()).len();
+            eprintln!(
+                "{name}/{strategy_name}: {len} bytes ({:.2}%)",
+                len as f64 * 100.0 / data.len() as f64
+            );
+            group.bench_with_input(BenchmarkId::new(strategy_name, name), &data, |b, data| {
+                b.iter(|| frame::compress(black_box(data), config.clone()))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, strategies);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Benchmarks the windows everything else is built on: pushing values into a [`Slide`] and
+//! draining them again, and searching a full [`SearchBuffer`] for matches.
+mod common;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use slide::{Slide, search_buffer::SearchBuffer};
+use std::hint::black_box;
+
+const LEN: usize = 1 << 20;
+/// Values drained at once, about what a block of short items covers.
+const DRAIN: usize = 0x1000;
+/// Length of the lookaheads searched for, and step between them.
+const PATTERN_LEN: usize = 0x40;
+/// Hash chain entries visited per search, so that repetitive inputs, whose chains span the whole
+/// window, finish in reasonable time.
+const MAX_CHAIN: usize = 0x400;
+
+fn push_drain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("slide");
+    let data = common::text(LEN);
+    group.throughput(Throughput::Bytes(LEN as u64));
+    group.bench_function("push", |b| {
+        b.iter(|| {
+            let mut slide = Slide::new();
+            for &value in black_box(&data) {
+                slide.push(value);
+            }
+            slide
+        })
+    });
+    group.bench_function("push_drain", |b| {
+        b.iter(|| {
+            let mut slide = Slide::new();
+            for chunk in black_box(&data).chunks(DRAIN) {
+                slide.extend(chunk.iter().copied());
+                if slide.len() > 4 * DRAIN {
+                    slide.drain(0..DRAIN).for_each(drop);
+                }
+            }
+            slide
+        })
+    });
+    group.finish();
+}
+
+fn find_longest_match(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_longest_match");
+    for (name, data) in common::inputs(LEN) {
+        let (window, ahead) = data.split_at(data.len() / 2);
+        let mut search_buffer = SearchBuffer::<u8, 4>::from_iter(window.iter().copied());
+        search_buffer.set_max_chain(Some(MAX_CHAIN));
+        let patterns = Vec::from_iter(ahead.chunks_exact(PATTERN_LEN));
+        group.throughput(Throughput::Elements(patterns.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(name),
+            &patterns,
+            |b, patterns| {
+                b.iter(|| {
+                    (patterns.iter())
+                        .filter_map(|pattern| search_buffer.find_longest_match(black_box(pattern)))
+                        .count()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, push_drain, find_longest_match);
+criterion_main!(benches);
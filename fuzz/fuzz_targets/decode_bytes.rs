@@ -0,0 +1,21 @@
+//! Feeds arbitrary bytes to every item deserializer and to the frame decoder, which must fail
+//! cleanly instead of panicking or allocating more than the input justifies.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use slide::{
+    frame,
+    lz::{self, Item},
+};
+
+/// Output buffer of the frame decoder, larger frames fail with `OutputTooSmall`.
+const MAX_DECODED: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = postcard::from_bytes::<Item<u8>>(data);
+    let _ = postcard::from_bytes::<Vec<Item<u8>>>(data);
+    let _ = lz::from_cbor(data);
+    let _ = lz::from_msgpack(data);
+    let mut out = vec![0; MAX_DECODED];
+    let _ = frame::decompress_into(data, None, &mut out);
+});
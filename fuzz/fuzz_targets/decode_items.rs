@@ -0,0 +1,33 @@
+//! Deserializes items from arbitrary bytes and decodes them with all checks enabled, which must
+//! fail cleanly instead of panicking.
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use slide::{
+    Slide,
+    lz::{Config, Item},
+};
+
+/// Values decoded before giving up, as a single run may stand for `usize::MAX` of them.
+const MAX_DECODED: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let Ok(mut config) = Config::arbitrary(&mut u) else {
+        return;
+    };
+    // References are only limited by the config, keep them from legitimately exhausting memory.
+    config.match_lengths.end = config.match_lengths.end.min(0x10000);
+    let mut bytes = u.take_rest();
+    let mut items = vec![];
+    while let Ok((item, rest)) = Item::take_from_bytes(bytes) {
+        items.push(item);
+        bytes = rest;
+    }
+    Slide::new()
+        .try_from_items(items, config)
+        .take(MAX_DECODED)
+        .take_while(Result::is_ok)
+        .for_each(drop);
+});
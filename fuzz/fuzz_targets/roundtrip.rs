@@ -0,0 +1,40 @@
+//! Encodes arbitrary data under an arbitrary config and checks that serializing, deserializing
+//! and decoding the items gives back the data.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use slide::{
+    Slide,
+    lz::{Config, Item},
+    search_buffer::SearchBuffer,
+};
+
+fn roundtrip<const N: usize>(data: &[u8], config: Config<u8>) {
+    let items =
+        Vec::from_iter(SearchBuffer::<u8, N>::new().to_items_from_slice(data, config.clone()));
+    let bytes = Vec::from_iter(
+        items
+            .iter()
+            .flat_map(|item| postcard::to_stdvec(item).unwrap()),
+    );
+    let mut rest = bytes.as_slice();
+    let mut read = vec![];
+    while !rest.is_empty() {
+        let item;
+        (item, rest) = Item::take_from_bytes(rest).unwrap();
+        read.push(item);
+    }
+    assert_eq!(read, items);
+    let decoded: Result<Vec<_>, _> = Slide::new().try_from_items(read, config).collect();
+    assert_eq!(decoded.unwrap(), data);
+}
+
+fuzz_target!(|input: (Config<u8>, &[u8])| {
+    let (config, data) = input;
+    match config.match_lengths.start {
+        1 => roundtrip::<1>(data, config),
+        2 => roundtrip::<2>(data, config),
+        3 => roundtrip::<3>(data, config),
+        _ => roundtrip::<4>(data, config),
+    }
+});
@@ -0,0 +1,71 @@
+/* C API of slide, see src/ffi.rs.
+ *
+ * Build the library with `cargo rustc --release --features ffi --crate-type cdylib`.
+ * No function panics across the boundary, failures are reported as negative status codes.
+ */
+#ifndef SLIDE_H
+#define SLIDE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SLIDE_OK 0
+#define SLIDE_NEED_INPUT 1
+#define SLIDE_DONE 2
+#define SLIDE_ERROR_NULL -1
+#define SLIDE_ERROR_INVALID_CONFIG -2
+#define SLIDE_ERROR_DST_TOO_SMALL -3
+#define SLIDE_ERROR_CORRUPT -4
+#define SLIDE_ERROR_FINISHED -5
+#define SLIDE_ERROR_PANIC -6
+
+#define SLIDE_STRATEGY_GREEDY 0
+#define SLIDE_STRATEGY_LAZY 1
+#define SLIDE_STRATEGY_OPTIMAL 2
+
+typedef struct SlideConfig {
+    size_t window_len;
+    size_t min_match;
+    /* Inclusive. */
+    size_t max_match;
+    uint32_t strategy;
+    size_t lazy_level;
+} SlideConfig;
+
+typedef struct SlideEncoder SlideEncoder;
+typedef struct SlideDecoder SlideDecoder;
+
+SlideConfig slide_config_default(void);
+
+/* A null config uses the default. If dst is too small, the required length is stored in
+ * dst_len and SLIDE_ERROR_DST_TOO_SMALL returned. */
+int32_t slide_compress(const SlideConfig *config, const uint8_t *src, size_t src_len,
+                       uint8_t *dst, size_t dst_capacity, size_t *dst_len);
+int32_t slide_decompress(const uint8_t *src, size_t src_len, uint8_t *dst,
+                         size_t dst_capacity, size_t *dst_len);
+
+/* Returns null if the config is invalid, streaming needs a min_match of at least 4. */
+SlideEncoder *slide_encoder_create(const SlideConfig *config);
+int32_t slide_encoder_feed(SlideEncoder *encoder, const uint8_t *src, size_t src_len);
+int32_t slide_encoder_finish(SlideEncoder *encoder);
+/* Returns SLIDE_DONE once finished and all output has been read. */
+int32_t slide_encoder_read(SlideEncoder *encoder, uint8_t *dst, size_t dst_capacity,
+                           size_t *written);
+void slide_encoder_destroy(SlideEncoder *encoder);
+
+SlideDecoder *slide_decoder_create(void);
+int32_t slide_decoder_feed(SlideDecoder *decoder, const uint8_t *src, size_t src_len);
+/* Returns SLIDE_NEED_INPUT until more input is fed, SLIDE_DONE once all output has been read. */
+int32_t slide_decoder_read(SlideDecoder *decoder, uint8_t *dst, size_t dst_capacity,
+                           size_t *written);
+void slide_decoder_destroy(SlideDecoder *decoder);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
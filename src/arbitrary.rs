@@ -0,0 +1,180 @@
+//! [`arbitrary::Arbitrary`] and [`quickcheck::Arbitrary`] implementations, for fuzz targets and
+//! property tests generating structured inputs.
+//!
+//! Lengths and distances are kept small, so generated items mostly refer to values actually
+//! decoded before them, and generated configs are always valid.
+
+use std::{borrow::Cow, num::NonZero};
+
+use arbitrary::{Result, Unstructured};
+use quickcheck::Gen;
+
+use crate::{
+    Slide,
+    lz::{Config, Item, REP_SLOTS, Strategy},
+};
+
+/// Longest [`Slide`] generated, also bounding item lengths and distances.
+const MAX_LEN: usize = 0x400;
+
+impl<'a> arbitrary::Arbitrary<'a> for Item<'a, u8> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(0..=MAX_LEN)?;
+        Ok(match u.int_in_range(0..=3)? {
+            0 => Item::Raw(Cow::Borrowed(u.bytes(len.min(u.len()))?)),
+            1 => Item::Ref {
+                back: NonZero::new(u.int_in_range(1..=MAX_LEN)?).unwrap(),
+                len,
+            },
+            2 => Item::Run {
+                value: u.arbitrary()?,
+                len,
+            },
+            _ => Item::RepRef {
+                slot: u.int_in_range(0..=REP_SLOTS as u8 - 1)?,
+                len,
+            },
+        })
+    }
+}
+impl quickcheck::Arbitrary for Item<'static, u8> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let len = usize::arbitrary(g) % MAX_LEN;
+        match u8::arbitrary(g) % 4 {
+            0 => Item::Raw(Cow::Owned(Vec::from_iter(
+                (0..len).map(|_| u8::arbitrary(g)),
+            ))),
+            1 => Item::Ref {
+                back: NonZero::new(usize::arbitrary(g) % MAX_LEN + 1).unwrap(),
+                len,
+            },
+            2 => Item::Run {
+                value: u8::arbitrary(g),
+                len,
+            },
+            _ => Item::RepRef {
+                slot: u8::arbitrary(g) % REP_SLOTS as u8,
+                len,
+            },
+        }
+    }
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        match self.clone() {
+            Item::Raw(raw) => Box::new(raw.into_owned().shrink().map(Item::from)),
+            Item::Ref { back, len } => {
+                Box::new(len.shrink().map(move |len| Item::Ref { back, len }))
+            }
+            Item::Run { value, len } => {
+                Box::new(len.shrink().map(move |len| Item::Run { value, len }))
+            }
+            Item::RepRef { slot, len } => {
+                Box::new(len.shrink().map(move |len| Item::RepRef { slot, len }))
+            }
+        }
+    }
+}
+
+/// Valid config drawing every field from `int(min, max)`, shared by both implementations.
+fn config<'a>(
+    mut int: impl FnMut(usize, usize) -> Result<usize>,
+    dictionary: Option<&'a [u8]>,
+) -> Result<Config<'a, u8>> {
+    let start = int(1, 16)?;
+    Ok(Config {
+        max_buffer_len: int(1, 0x10000)?,
+        match_lengths: start..match int(0, 3)? {
+            0 => usize::MAX,
+            _ => int(start + 1, start + 0x1000)?,
+        },
+        dictionary,
+        strategy: match int(0, 3)? {
+            0 => Strategy::Greedy,
+            1 => Strategy::Lazy(int(0, 4)?),
+            2 => Strategy::BestOf(int(0, 8)?),
+            _ => Strategy::Optimal,
+        },
+        ..Config::default()
+    })
+}
+impl<'a> arbitrary::Arbitrary<'a> for Config<'a, u8> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let dictionary = u.arbitrary()?;
+        config(|min, max| u.int_in_range(min..=max), dictionary)
+    }
+}
+impl quickcheck::Arbitrary for Config<'static, u8> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        config(
+            |min, max| Ok(min + usize::arbitrary(g) % (max - min + 1)),
+            None,
+        )
+        .unwrap()
+    }
+}
+
+/// Up to 1024 values, some of which are drained from the front again, so the values don't
+/// always start at the beginning of the buffer.
+impl<'a, T: arbitrary::Arbitrary<'a>> arbitrary::Arbitrary<'a> for Slide<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.arbitrary_len::<T>()?.min(MAX_LEN);
+        let skip = u.int_in_range(0..=len)?;
+        let mut ret = Slide::new();
+        for _ in 0..len {
+            ret.push(u.arbitrary()?);
+        }
+        ret.drain(0..skip).for_each(drop);
+        Ok(ret)
+    }
+}
+impl<T: quickcheck::Arbitrary> quickcheck::Arbitrary for Slide<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let mut ret = Slide::from_iter(Vec::<T>::arbitrary(g).into_iter().take(MAX_LEN));
+        let skip = usize::arbitrary(g) % (ret.len() + 1);
+        ret.drain(0..skip).for_each(drop);
+        ret
+    }
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(self.to_vec().shrink().map(Slide::from_iter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbitrary::Arbitrary as _;
+    use quickcheck_macros::quickcheck;
+
+    #[quickcheck]
+    fn config(config: Config<'static, u8>) -> bool {
+        config.validate().is_ok()
+    }
+    #[quickcheck]
+    fn item(item: Item<'static, u8>) {
+        // Empty runs are read back as empty raw items.
+        if matches!(item, Item::Run { len: 0, .. }) {
+            return;
+        }
+        let bytes = postcard::to_stdvec(&item).unwrap();
+        assert_eq!(Item::take_from_bytes(&bytes).unwrap(), (item, &[][..]));
+    }
+    #[quickcheck]
+    fn slide(slide: Slide<u8>) -> bool {
+        slide.len() <= MAX_LEN
+    }
+    #[quickcheck]
+    fn structured(data: Vec<u8>) {
+        let mut u = Unstructured::new(&data);
+        let (Ok(mut window), Ok(config)) = (Slide::arbitrary(&mut u), Config::arbitrary(&mut u))
+        else {
+            return;
+        };
+        assert!(config.validate().is_ok());
+        let items = Vec::from_iter(
+            u.arbitrary_iter::<Item<u8>>()
+                .unwrap()
+                .map_while(Result::ok),
+        );
+        // Checked decoding never panics, whatever the items.
+        window.try_from_items(items, config).for_each(drop);
+    }
+}
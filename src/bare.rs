@@ -0,0 +1,391 @@
+use core::fmt::{self, Display};
+
+/// Shortest match the token format can express.
+pub const MIN_MATCH: usize = 4;
+/// Longest match the token format can express.
+pub const MAX_MATCH: usize = MIN_MATCH + 0x7E;
+/// Most literals a single token can carry.
+pub const MAX_LITERALS: usize = 0x80;
+/// Farthest distance the token format can express.
+pub const MAX_DISTANCE: usize = u16::MAX as usize;
+/// Output space [`Encoder::encode_into`] needs to be sure to make progress, as it only writes
+/// whole tokens.
+pub const MAX_TOKEN_LEN: usize = 1 + MAX_LITERALS + 3;
+/// Shortest window accepted by [`Encoder::new`].
+pub const MIN_WINDOW_LEN: usize = 0x400;
+
+/// Token ending the stream.
+const END: u8 = 0xFF;
+
+/// Why [`Encoder::encode_into`] or [`Decoder::decode_into`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// All input was consumed, call again with more.
+    NeedInput,
+    /// The output is full, call again with more space.
+    NeedOutput,
+    /// The end of the stream was written or read.
+    Done,
+}
+
+/// Input consumed and output produced by a single call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub consumed: usize,
+    pub produced: usize,
+    pub status: Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// A match reaches back further than the decoded data or the window.
+    InvalidDistance { back: usize },
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidDistance { back } => {
+                write!(f, "match distance {back} exceeds the available window")
+            }
+        }
+    }
+}
+impl core::error::Error for Error {}
+
+/// Streaming encoder working entirely on caller provided buffers, without allocating.
+///
+/// The stream is a sequence of tokens: `0x00..=0x7F` is followed by that many literals plus one,
+/// `0x80..=0xFE` is a match of `token - 0x80 + MIN_MATCH` values, followed by its distance
+/// (u16 LE), and `0xFF` ends the stream.
+/// Matches reach back at most half the window, so decoding needs a window at least that long.
+pub struct Encoder<'w> {
+    window: &'w mut [u8],
+    /// Last position of each hash, plus one, as absolute stream position.
+    table: &'w mut [u32],
+    /// Absolute stream position of `window[0]`.
+    base: usize,
+    /// Values buffered in `window`.
+    filled: usize,
+    /// First value of `window` not covered by a written token.
+    pos: usize,
+    /// Start of the literals pending before `pos`.
+    literals: usize,
+    done: bool,
+}
+impl<'w> Encoder<'w> {
+    /// Panics if `window` is shorter than [`MIN_WINDOW_LEN`] or `table` is empty.
+    pub fn new(window: &'w mut [u8], table: &'w mut [u32]) -> Self {
+        assert!(
+            window.len() >= MIN_WINDOW_LEN,
+            "window must hold at least {MIN_WINDOW_LEN} values."
+        );
+        assert!(!table.is_empty(), "table must not be empty.");
+        table.fill(0);
+        Self {
+            window,
+            table,
+            base: 0,
+            filled: 0,
+            pos: 0,
+            literals: 0,
+            done: false,
+        }
+    }
+    /// Farthest distance matches reach back.
+    pub fn max_distance(&self) -> usize {
+        (self.window.len() / 2).min(MAX_DISTANCE)
+    }
+    /// Encodes as much of `input` into `output` as possible.
+    ///
+    /// Once `finish` is set, the remaining input is flushed and the stream ended, which may take
+    /// further calls if the output fills up. Only whole tokens are written, so `output` should
+    /// have room for at least [`MAX_TOKEN_LEN`] bytes.
+    pub fn encode_into(&mut self, input: &[u8], output: &mut [u8], finish: bool) -> Progress {
+        let (mut consumed, mut produced) = (0, 0);
+        let status = loop {
+            if self.done {
+                break Status::Done;
+            }
+            consumed += self.buffer(&input[consumed..]);
+            let available = self.filled - self.pos;
+            if available == 0 && finish && consumed == input.len() {
+                let Some(len) = self.flush_literals(&mut output[produced..], 1) else {
+                    break Status::NeedOutput;
+                };
+                output[produced + len] = END;
+                produced += len + 1;
+                self.done = true;
+                continue;
+            } else if available < MAX_MATCH && !(finish && consumed == input.len()) {
+                break Status::NeedInput;
+            }
+            let (back, len) = self.find_match();
+            if len >= MIN_MATCH {
+                let Some(flushed) = self.flush_literals(&mut output[produced..], 3) else {
+                    break Status::NeedOutput;
+                };
+                produced += flushed;
+                let back = back as u16;
+                output[produced..produced + 3].copy_from_slice(&[
+                    (len - MIN_MATCH) as u8 | 0x80,
+                    back as u8,
+                    (back >> 8) as u8,
+                ]);
+                produced += 3;
+                for _ in 0..len {
+                    self.insert();
+                    self.pos += 1;
+                }
+                self.literals = self.pos;
+            } else {
+                if self.pos - self.literals == MAX_LITERALS {
+                    let Some(flushed) = self.flush_literals(&mut output[produced..], 0) else {
+                        break Status::NeedOutput;
+                    };
+                    produced += flushed;
+                }
+                self.insert();
+                self.pos += 1;
+            }
+        };
+        Progress {
+            consumed,
+            produced,
+            status,
+        }
+    }
+    /// Copies as much of `input` into the window as fits, returning how much that was.
+    fn buffer(&mut self, input: &[u8]) -> usize {
+        let mut consumed = 0;
+        for _ in 0..2 {
+            if self.filled == self.window.len() && consumed < input.len() {
+                // Keep the pending literals and everything matches may still reach.
+                let drop = self
+                    .literals
+                    .min(self.pos.saturating_sub(self.max_distance()));
+                self.window.copy_within(drop..self.filled, 0);
+                self.base += drop;
+                self.filled -= drop;
+                self.pos -= drop;
+                self.literals -= drop;
+            }
+            let len = (input.len() - consumed).min(self.window.len() - self.filled);
+            self.window[self.filled..self.filled + len]
+                .copy_from_slice(&input[consumed..consumed + len]);
+            self.filled += len;
+            consumed += len;
+        }
+        consumed
+    }
+    fn hash(&self, at: usize) -> Option<usize> {
+        let key = self.window[at..self.filled].first_chunk::<MIN_MATCH>()?;
+        let hash = u32::from_le_bytes(*key).wrapping_mul(0x9E37_79B1) >> 8;
+        Some(hash as usize % self.table.len())
+    }
+    /// Records `pos` as the latest position of its hash.
+    fn insert(&mut self) {
+        if let Some(hash) = self.hash(self.pos) {
+            self.table[hash] = (self.base + self.pos + 1) as u32;
+        }
+    }
+    /// Distance and length of the match at `pos`, a length of 0 if there is none.
+    fn find_match(&self) -> (usize, usize) {
+        let Some(hash) = self.hash(self.pos) else {
+            return (0, 0);
+        };
+        let at = (self.base + self.pos) as u32;
+        let back = at.wrapping_sub(self.table[hash].wrapping_sub(1)) as usize;
+        if self.table[hash] == 0 || back == 0 || back > self.max_distance().min(self.pos) {
+            return (0, 0);
+        }
+        let end = self.filled.min(self.pos + MAX_MATCH);
+        let len = (self.pos..end)
+            .take_while(|&at| self.window[at] == self.window[at - back])
+            .count();
+        (back, len)
+    }
+    /// Writes a token for the pending literals, if there is room for it plus `reserve` bytes.
+    fn flush_literals(&mut self, output: &mut [u8], reserve: usize) -> Option<usize> {
+        let literals = &self.window[self.literals..self.pos];
+        if literals.is_empty() {
+            return (output.len() >= reserve).then_some(0);
+        }
+        let len = literals.len() + 1;
+        if output.len() < len + reserve {
+            return None;
+        }
+        output[0] = (literals.len() - 1) as u8;
+        output[1..len].copy_from_slice(literals);
+        self.literals = self.pos;
+        Some(len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Start,
+    Literals(usize),
+    Distance { len: usize, low: Option<u8> },
+    Match { len: usize, back: usize },
+    End,
+}
+
+/// Streaming decoder for the token format of [`Encoder`], keeping its history in a caller
+/// provided ring buffer.
+pub struct Decoder<'w> {
+    window: &'w mut [u8],
+    /// Values decoded so far.
+    total: usize,
+    token: Token,
+}
+impl<'w> Decoder<'w> {
+    /// `window` must be at least as long as the encoder's [`Encoder::max_distance`].
+    ///
+    /// Panics if `window` is empty.
+    pub fn new(window: &'w mut [u8]) -> Self {
+        assert!(!window.is_empty(), "window must not be empty.");
+        Self {
+            window,
+            total: 0,
+            token: Token::Start,
+        }
+    }
+    fn emit(&mut self, value: u8, output: &mut [u8], produced: &mut usize) {
+        output[*produced] = value;
+        *produced += 1;
+        let len = self.window.len();
+        self.window[self.total % len] = value;
+        self.total += 1;
+    }
+    /// Decodes as much of `input` into `output` as possible.
+    pub fn decode_into(&mut self, input: &[u8], output: &mut [u8]) -> Result<Progress, Error> {
+        let (mut consumed, mut produced) = (0, 0);
+        let status = loop {
+            let needs_input = matches!(self.token, Token::Start | Token::Distance { .. });
+            if needs_input && consumed == input.len() {
+                break Status::NeedInput;
+            }
+            match self.token {
+                Token::Start => {
+                    let token = input[consumed];
+                    consumed += 1;
+                    self.token = match token {
+                        END => Token::End,
+                        0x80.. => Token::Distance {
+                            len: (token & 0x7F) as usize + MIN_MATCH,
+                            low: None,
+                        },
+                        _ => Token::Literals(token as usize + 1),
+                    };
+                }
+                Token::Distance { len, low: None } => {
+                    let low = Some(input[consumed]);
+                    consumed += 1;
+                    self.token = Token::Distance { len, low };
+                }
+                Token::Distance {
+                    len,
+                    low: Some(low),
+                } => {
+                    let back = u16::from_le_bytes([low, input[consumed]]) as usize;
+                    consumed += 1;
+                    if back == 0 || back > self.total.min(self.window.len()) {
+                        return Err(Error::InvalidDistance { back });
+                    }
+                    self.token = Token::Match { len, back };
+                }
+                Token::Literals(0) | Token::Match { len: 0, .. } => self.token = Token::Start,
+                _ if produced == output.len() => break Status::NeedOutput,
+                Token::Literals(len) => {
+                    if consumed == input.len() {
+                        break Status::NeedInput;
+                    }
+                    self.token = Token::Literals(len - 1);
+                    let value = input[consumed];
+                    consumed += 1;
+                    self.emit(value, output, &mut produced);
+                }
+                Token::Match { len, back } => {
+                    self.token = Token::Match { len: len - 1, back };
+                    let value = self.window[(self.total - back) % self.window.len()];
+                    self.emit(value, output, &mut produced);
+                }
+                Token::End => break Status::Done,
+            }
+        };
+        Ok(Progress {
+            consumed,
+            produced,
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs data through encoder and decoder, `step` bytes of input and output at a time.
+    fn roundtrip(data: &[u8], step: usize) -> (usize, std::vec::Vec<u8>) {
+        let (mut window, mut table) = ([0; MIN_WINDOW_LEN * 2], [0; 0x1000]);
+        let mut encoder = Encoder::new(&mut window, &mut table);
+        let mut encoded = std::vec![];
+        let mut buf = std::vec![0; step.max(MAX_TOKEN_LEN)];
+        let mut input = data;
+        loop {
+            let chunk = &input[..input.len().min(step)];
+            let progress = encoder.encode_into(chunk, &mut buf, chunk.len() == input.len());
+            input = &input[progress.consumed..];
+            encoded.extend_from_slice(&buf[..progress.produced]);
+            if progress.status == Status::Done {
+                break;
+            }
+        }
+        let mut window = [0; MIN_WINDOW_LEN];
+        let mut decoder = Decoder::new(&mut window);
+        let mut decoded = std::vec![];
+        let mut buf = std::vec![0; step];
+        let mut input = encoded.as_slice();
+        loop {
+            let chunk = &input[..input.len().min(step)];
+            let progress = decoder.decode_into(chunk, &mut buf).unwrap();
+            input = &input[progress.consumed..];
+            decoded.extend_from_slice(&buf[..progress.produced]);
+            match progress.status {
+                Status::Done => break,
+                Status::NeedInput => assert!(!chunk.is_empty() || input.is_empty()),
+                Status::NeedOutput => {}
+            }
+        }
+        assert!(input.is_empty());
+        (encoded.len(), decoded)
+    }
+
+    #[test]
+    fn streaming() {
+        let data =
+            std::vec::Vec::from_iter((0..0x3000u32).map(|x| (x / 3 % 97) as u8 ^ (x >> 10) as u8));
+        for step in [1, 2, 7, 0x100, 0x10000] {
+            let (len, decoded) = roundtrip(&data, step);
+            assert_eq!(decoded, data);
+            assert!(len < data.len() / 2, "{len}");
+        }
+        assert_eq!(roundtrip(b"", 1).1, b"");
+        assert_eq!(roundtrip(b"abc", 3).1, b"abc");
+    }
+    #[test]
+    fn invalid_distance() {
+        let mut window = [0; 0x10];
+        let mut out = [0; 0x10];
+        let mut decoder = Decoder::new(&mut window);
+        assert_eq!(
+            decoder.decode_into(&[1, b'a', b'b', 0x80, 3, 0], &mut out),
+            Err(Error::InvalidDistance { back: 3 })
+        );
+    }
+    #[quickcheck_macros::quickcheck]
+    fn fuzz(data: std::vec::Vec<u8>, repeat: u8, step: u8) {
+        let data = data.repeat(repeat as usize % 8 + 1);
+        assert_eq!(roundtrip(&data, step as usize % 0x40 + 1).1, data);
+    }
+}
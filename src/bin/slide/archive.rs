@@ -0,0 +1,271 @@
+//! `slide archive` and `slide extract`: several files in one stream, like a compressed tarball.
+//!
+//! Every file is a skippable frame holding its [`Entry`], followed by a frame of its content.
+//! Plain decompression skips the entries and yields the content of every file in turn.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use slide::frame::{self, SKIPPABLE_MAGIC};
+
+use crate::{Counted, Error, Location, Offset, Options, copy, encode, walk::Walk};
+
+#[derive(Debug, clap::Args)]
+pub struct ArchiveArgs {
+    /// Files to pack, stored under the paths given.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    #[command(flatten)]
+    walk: Walk,
+    /// Where to write the archive, `-` for stdout.
+    #[arg(short, long, default_value = "-")]
+    output: PathBuf,
+    /// Overwrite the output if it exists.
+    #[arg(short, long)]
+    force: bool,
+    #[command(flatten)]
+    options: Options,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExtractArgs {
+    /// Archive to unpack, `-` or none for stdin.
+    input: Option<PathBuf>,
+    /// Directory to unpack into.
+    #[arg(short = 'C', long, default_value = ".")]
+    dir: PathBuf,
+    /// Overwrite files that exist.
+    #[arg(short, long)]
+    force: bool,
+    /// Keep the setuid, setgid and sticky bits of the archived files, which are dropped
+    /// otherwise.
+    #[arg(short = 'p', long)]
+    same_permissions: bool,
+}
+
+/// Metadata of an archived file, stored as the postcard encoded tuple of its fields.
+#[derive(Debug)]
+struct Entry {
+    /// Relative path, with `/` separators.
+    path: String,
+    len: u64,
+    /// Unix permission bits.
+    mode: u32,
+}
+impl Entry {
+    fn encode(&self) -> Vec<u8> {
+        postcard::to_stdvec(&(&self.path, self.len, self.mode)).expect("entries are serializable")
+    }
+    fn decode(payload: &[u8]) -> Option<Self> {
+        let (path, len, mode) = postcard::from_bytes(payload).ok()?;
+        Some(Self { path, len, mode })
+    }
+    /// Where to unpack the entry below `dir`, refusing paths that would leave it or are empty.
+    fn target(&self, dir: &Path) -> Option<PathBuf> {
+        let path = Path::new(&self.path);
+        let mut components = path.components().peekable();
+        (components.peek().is_some()
+            && components.all(|component| matches!(component, Component::Normal(_))))
+        .then(|| dir.join(path))
+    }
+}
+
+pub fn archive(args: ArchiveArgs) -> Result<(), Error> {
+    let output = Location(args.output);
+    let mut files = vec![];
+    for path in &args.files {
+        files.extend(args.walk.files(path)?);
+    }
+    let mut writer = output.create(args.force)?;
+    for path in &files {
+        archive_file(path, &mut writer, &output, &args.options).inspect_err(|_| output.remove())?;
+    }
+    writer
+        .flush()
+        .map_err(|source| output.io(source))
+        .inspect_err(|_| output.remove())
+}
+
+fn archive_file(
+    path: &Path,
+    writer: &mut impl Write,
+    output: &Location,
+    options: &Options,
+) -> Result<(), Error> {
+    let input = Location(path.into());
+    let io = |source| input.io(source);
+    let file = File::open(path).map_err(io)?;
+    let meta = file.metadata().map_err(io)?;
+    let entry = Entry {
+        path: stored_path(path).ok_or_else(|| Error::UnsafePath { path: path.into() })?,
+        len: meta.len(),
+        mode: mode(&meta),
+    };
+    let mut head = vec![];
+    frame::write_skippable(&mut head, &entry.encode());
+    writer
+        .write_all(&head)
+        .map_err(|source| output.io(source))?;
+    let mut reader = BufReader::new(file).take(entry.len);
+    let (read, _) = encode(&mut reader, &input, writer, output, options.config()?, None)?;
+    if read != entry.len {
+        return Err(io(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+/// `path` with `/` separators, without leading `./` or root, None if nothing is left of it.
+fn stored_path(path: &Path) -> Option<String> {
+    let mut parts = vec![];
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+#[cfg(unix)]
+fn mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+#[cfg(not(unix))]
+fn mode(meta: &fs::Metadata) -> u32 {
+    match meta.permissions().readonly() {
+        true => 0o444,
+        false => 0o644,
+    }
+}
+
+pub fn extract(args: ExtractArgs) -> Result<(), Error> {
+    let input = Location(args.input.unwrap_or_else(|| "-".into()));
+    let mut reader = Counted::new(input.open()?, false);
+    let decode = |source: frame::Error, offset| Error::Decode {
+        path: input.0.clone(),
+        source: source.into(),
+        offset: Some(offset),
+    };
+    while let Some(payload) =
+        read_skippable(&mut reader).map_err(|source| decode(source, Offset::Read(reader.count)))?
+    {
+        let entry = (Entry::decode(&payload))
+            .ok_or_else(|| decode(frame::Error::Malformed, Offset::Read(reader.count)))?;
+        let path = entry.target(&args.dir).ok_or_else(|| Error::UnsafePath {
+            path: entry.path.clone().into(),
+        })?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|source| Error::Io {
+                path: parent.into(),
+                source,
+            })?;
+        }
+        let output = Location(path);
+        let mut writer = output.create(args.force)?;
+        let start = reader.count;
+        let mut decoder = frame::Decoder::new(&mut reader, None)
+            .map_err(|source| decode(source, Offset::Frame(start)))?;
+        let len = copy(&mut decoder, &input, &mut writer, &output)
+            .and_then(|len| {
+                writer.flush().map_err(|source| output.io(source))?;
+                Ok(len)
+            })
+            .map_err(|err| err.at(decoder.get_ref().count))
+            .inspect_err(|_| output.remove())?;
+        if len != entry.len {
+            output.remove();
+            let mismatch = frame::Error::ContentSizeMismatch {
+                expected: entry.len as usize,
+                actual: len as usize,
+            };
+            return Err(decode(mismatch, Offset::Read(reader.count)));
+        }
+        drop(writer);
+        let mode = match args.same_permissions {
+            true => entry.mode & 0o7777,
+            false => entry.mode & 0o777,
+        };
+        set_mode(&output.0, mode).map_err(|source| output.io(source))?;
+    }
+    Ok(())
+}
+
+/// Reads the payload of the skippable frame starting `reader`, or None at its end.
+fn read_skippable(reader: &mut impl Read) -> Result<Option<Vec<u8>>, frame::Error> {
+    let mut magic = [0; 4];
+    match reader.read(&mut magic[..1])? {
+        0 => return Ok(None),
+        _ => reader.read_exact(&mut magic[1..])?,
+    }
+    if magic != SKIPPABLE_MAGIC {
+        return Err(frame::Error::InvalidMagic);
+    }
+    let mut len = vec![];
+    loop {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        len.push(byte[0]);
+        if byte[0] & 0x80 == 0 || len.len() == 10 {
+            break;
+        }
+    }
+    let (len, _) = postcard::take_from_bytes::<usize>(&len).map_err(|_| frame::Error::Malformed)?;
+    let mut payload = vec![];
+    reader.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() != len {
+        return Err(frame::Error::UnexpectedEof);
+    }
+    Ok(Some(payload))
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o222 == 0);
+    fs::set_permissions(path, permissions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_path() {
+        let stored = |path: &str| super::stored_path(Path::new(path));
+        assert_eq!(stored("a/b").as_deref(), Some("a/b"));
+        assert_eq!(stored("./a").as_deref(), Some("a"));
+        assert_eq!(stored("/a/b").as_deref(), Some("a/b"));
+        assert_eq!(stored("a/../b"), None);
+        assert_eq!(stored(".."), None);
+        assert_eq!(stored(""), None);
+        assert_eq!(stored("/"), None);
+    }
+
+    #[test]
+    fn target() {
+        let target = |path: &str| {
+            let entry = Entry {
+                path: path.into(),
+                len: 0,
+                mode: 0o644,
+            };
+            entry.target(Path::new("out"))
+        };
+        assert_eq!(target("a/b"), Some(PathBuf::from("out/a/b")));
+        assert_eq!(target("a/../b"), None);
+        assert_eq!(target(".."), None);
+        assert_eq!(target("/a"), None);
+        assert_eq!(target(""), None);
+        assert_eq!(target("./a"), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! `slide bench`: compresses every file of a corpus at several levels and tabulates the results,
+//! optionally next to those of other compressors.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use slide::{frame, lz::Config};
+
+use crate::{Error, MAX_LEVEL};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Directory holding the corpus, every file directly inside it is compressed.
+    dir: PathBuf,
+    /// Levels to compress at.
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        default_value = "1,3,6,9",
+        value_parser = clap::value_parser!(u8).range(1..=MAX_LEVEL as i64),
+    )]
+    levels: Vec<u8>,
+    /// Other compressors to run on the corpus too, at their default levels.
+    #[arg(long, value_delimiter = ',')]
+    baseline: Vec<Baseline>,
+}
+
+/// Compressor to compare against, each behind a feature of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Baseline {
+    Gzip,
+    Zstd,
+}
+impl Baseline {
+    fn name(self) -> &'static str {
+        match self {
+            Baseline::Gzip => "gzip",
+            Baseline::Zstd => "zstd",
+        }
+    }
+    /// Fails unless slide was built with the feature providing this baseline.
+    fn check(self) -> Result<(), Error> {
+        let (enabled, feature) = match self {
+            Baseline::Gzip => (cfg!(feature = "bench-gzip"), "bench-gzip"),
+            Baseline::Zstd => (cfg!(feature = "bench-zstd"), "bench-zstd"),
+        };
+        match enabled {
+            true => Ok(()),
+            false => Err(Error::MissingFeature {
+                baseline: self.name(),
+                feature,
+            }),
+        }
+    }
+    #[cfg_attr(
+        not(all(feature = "bench-gzip", feature = "bench-zstd")),
+        allow(unused_variables)
+    )]
+    fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "bench-gzip")]
+            Baseline::Gzip => {
+                use std::io::Write;
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "bench-zstd")]
+            Baseline::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("checked before benchmarking"),
+        }
+    }
+    #[cfg_attr(
+        not(all(feature = "bench-gzip", feature = "bench-zstd")),
+        allow(unused_variables)
+    )]
+    fn decompress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "bench-gzip")]
+            Baseline::Gzip => {
+                use std::io::Read;
+                let mut out = vec![];
+                flate2::read::GzDecoder::new(data).read_to_end(&mut out)?;
+                Ok(out)
+            }
+            #[cfg(feature = "bench-zstd")]
+            Baseline::Zstd => zstd::decode_all(data),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("checked before benchmarking"),
+        }
+    }
+}
+
+/// Sizes and timings of one file, or a sum of them.
+#[derive(Debug, Default, Clone, Copy)]
+struct Row {
+    len: usize,
+    compressed: usize,
+    encode: Duration,
+    decode: Duration,
+}
+impl Row {
+    fn add(&mut self, other: Row) {
+        self.len += other.len;
+        self.compressed += other.compressed;
+        self.encode += other.encode;
+        self.decode += other.decode;
+    }
+    fn print(&self, name: &str, level: &str) {
+        println!(
+            "{name:<24} {level:>5} {:>12} {:>12} {:>7.2}% {:>9.1} {:>9.1}",
+            self.len,
+            self.compressed,
+            self.compressed as f64 * 100f64 / self.len.max(1) as f64,
+            mb_per_sec(self.len, self.encode),
+            mb_per_sec(self.len, self.decode),
+        );
+    }
+}
+
+fn mb_per_sec(len: usize, time: Duration) -> f64 {
+    len as f64 / 1e6 / time.as_secs_f64().max(1e-9)
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    for baseline in &args.baseline {
+        baseline.check()?;
+    }
+    let io = |source| Error::Io {
+        path: args.dir.clone(),
+        source,
+    };
+    let mut files = vec![];
+    for entry in fs::read_dir(&args.dir).map_err(io)? {
+        let path = entry.map_err(io)?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    println!(
+        "{:<24} {:>5} {:>12} {:>12} {:>8} {:>9} {:>9}",
+        "file", "level", "size", "compressed", "ratio", "enc MB/s", "dec MB/s"
+    );
+    let labels = Vec::from_iter(
+        (args.levels.iter().map(u8::to_string))
+            .chain(args.baseline.iter().map(|baseline| baseline.name().into())),
+    );
+    let mut totals = vec![Row::default(); labels.len()];
+    for path in &files {
+        let data = fs::read(path).map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut rows = vec![];
+        for &level in &args.levels {
+            rows.push(measure(
+                path,
+                &data,
+                |data| Ok(frame::compress(data, Config::level(level))),
+                |data| {
+                    frame::decompress(data, None).map_err(|source| Error::Decode {
+                        path: path.clone(),
+                        source: source.into(),
+                        offset: None,
+                    })
+                },
+            )?);
+        }
+        for &baseline in &args.baseline {
+            let io = |source| Error::Io {
+                path: path.clone(),
+                source,
+            };
+            rows.push(measure(
+                path,
+                &data,
+                |data| baseline.compress(data).map_err(io),
+                |data| baseline.decompress(data).map_err(io),
+            )?);
+        }
+        for ((row, label), total) in rows.into_iter().zip(&labels).zip(&mut totals) {
+            row.print(&name, label);
+            total.add(row);
+        }
+    }
+    for (total, label) in totals.iter().zip(&labels) {
+        total.print("total", label);
+    }
+    Ok(())
+}
+
+/// Times compressing `data` of the file at `path` and decompressing it again.
+fn measure(
+    path: &Path,
+    data: &[u8],
+    compress: impl FnOnce(&[u8]) -> Result<Vec<u8>, Error>,
+    decompress: impl FnOnce(&[u8]) -> Result<Vec<u8>, Error>,
+) -> Result<Row, Error> {
+    let start = Instant::now();
+    let compressed = compress(data)?;
+    let encode = start.elapsed();
+    let start = Instant::now();
+    let decoded = decompress(&compressed)?;
+    let decode = start.elapsed();
+    if decoded != data {
+        return Err(Error::Mismatch { path: path.into() });
+    }
+    Ok(Row {
+        len: data.len(),
+        compressed: compressed.len(),
+        encode,
+        decode,
+    })
+}
@@ -0,0 +1,139 @@
+//! `slide dict` and `-D`: training dictionaries and compressing with them.
+//!
+//! Dictionary files are [`MAGIC`], the dictionary's ID (u32 LE) and its content. Frames
+//! compressed with one record its ID, so decompressing names the dictionary it needs. Files
+//! without the magic are used as dictionaries as is, without an ID.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use slide::{dict, frame, util::xxhash};
+
+use crate::{Error, Location, walk::Walk, write_all};
+
+/// Magic number starting dictionary files.
+pub const MAGIC: [u8; 4] = *b"SLZd";
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Train a dictionary on sample files similar to those it will compress.
+    Train(TrainArgs),
+}
+
+#[derive(Debug, clap::Args)]
+struct TrainArgs {
+    /// Sample files to train on.
+    #[arg(required = true)]
+    samples: Vec<PathBuf>,
+    #[command(flatten)]
+    walk: Walk,
+    /// Where to write the dictionary.
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Overwrite the output if it exists.
+    #[arg(short, long)]
+    force: bool,
+    /// Largest dictionary to train.
+    #[arg(long, value_name = "BYTES", default_value_t = 0x10000)]
+    max_size: usize,
+    /// ID recorded in frames compressed with the dictionary, the checksum of its content by
+    /// default.
+    #[arg(long)]
+    id: Option<u32>,
+}
+
+/// Content and ID of a dictionary file.
+#[derive(Debug)]
+pub struct Dictionary {
+    pub id: Option<u32>,
+    pub content: Vec<u8>,
+}
+impl Dictionary {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data = fs::read(path).map_err(|source| Error::Io {
+            path: path.into(),
+            source,
+        })?;
+        let Some(rest) = data.strip_prefix(&MAGIC) else {
+            return Ok(Self {
+                id: None,
+                content: data,
+            });
+        };
+        let (id, content) = rest.split_first_chunk().ok_or_else(|| Error::Decode {
+            path: path.into(),
+            source: frame::Error::UnexpectedEof.into(),
+            offset: None,
+        })?;
+        Ok(Self {
+            id: Some(u32::from_le_bytes(*id)),
+            content: content.to_vec(),
+        })
+    }
+    fn encode(&self) -> Vec<u8> {
+        let id = self.id.expect("trained dictionaries have an ID");
+        [&MAGIC[..], &id.to_le_bytes(), &self.content].concat()
+    }
+}
+
+/// Checks that the frame starting `head` was compressed with `dictionary`, or none, by the ID it
+/// records.
+///
+/// Frames without an ID, or whose header doesn't fit into `head`, are left to the decoder, which
+/// checks the dictionary's content.
+pub fn check(head: &[u8], dictionary: Option<&Dictionary>, path: &Path) -> Result<(), Error> {
+    let Ok(Some(id)) = frame::dictionary_id(head) else {
+        return Ok(());
+    };
+    match dictionary.and_then(|dictionary| dictionary.id) {
+        Some(actual) if actual == id => Ok(()),
+        Some(actual) => Err(Error::WrongDictionary {
+            path: path.into(),
+            id,
+            actual,
+        }),
+        None => Err(Error::NoDictionary {
+            path: path.into(),
+            id,
+        }),
+    }
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    match args.command {
+        Command::Train(args) => train(args),
+    }
+}
+
+fn train(args: TrainArgs) -> Result<(), Error> {
+    let mut samples = vec![];
+    for path in &args.samples {
+        for file in args.walk.files(path)? {
+            samples.push(fs::read(&file).map_err(|source| Error::Io { path: file, source })?);
+        }
+    }
+    let samples = Vec::from_iter(samples.iter().map(Vec::as_slice));
+    let content = dict::train(&samples, args.max_size).as_bytes().to_vec();
+    let dictionary = Dictionary {
+        id: Some(args.id.unwrap_or_else(|| xxhash::checksum(&content))),
+        content,
+    };
+    let output = Location(args.output);
+    write_all(&dictionary.encode(), &output, args.force)?;
+    eprintln!(
+        "{}: {} bytes from {} samples, id {}",
+        output.0.display(),
+        dictionary.content.len(),
+        samples.len(),
+        dictionary.id.unwrap_or_default()
+    );
+    Ok(())
+}
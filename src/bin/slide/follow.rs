@@ -0,0 +1,131 @@
+//! `compress --follow`: compresses a growing input like `tail -f`, ending a frame and flushing it
+//! to the output at least every [`FLUSH_INTERVAL`] so that readers see the data without waiting
+//! for the input to end.
+//!
+//! The output is a sequence of frames, which `decompress` reads back as one.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, Write},
+    path::Path,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use slide::{frame, lz::Config};
+
+use crate::{Counted, Error, Location, stats::Totals};
+
+/// Longest time data stays in an unfinished frame.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// Time waited for a followed file to grow once its end was reached.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Compresses `input` to `output` until the input ends, which a file never does.
+pub fn compress(
+    input: &Location,
+    output: &Location,
+    force: bool,
+    config: Config,
+) -> Result<Totals, Error> {
+    let chunks = read_chunks(input)?;
+    let writer = Counted::new(output.create(force)?, false);
+    let follow = match config.match_lengths.start {
+        1 => follow::<1, _>,
+        2 => follow::<2, _>,
+        3 => follow::<3, _>,
+        _ => follow::<4, _>,
+    };
+    let (read, writer) = follow(&chunks, input, writer, output, config)?;
+    Ok(Totals::file(read, writer.count))
+}
+
+/// Like [`compress`] with a SearchBuffer keyed by `N` bytes, at most `config`'s minimum match.
+///
+/// Returns the number of bytes read and `writer`.
+fn follow<const N: usize, W: Write>(
+    chunks: &Receiver<io::Result<Vec<u8>>>,
+    input: &Location,
+    mut writer: W,
+    output: &Location,
+    config: Config,
+) -> Result<(u64, W), Error> {
+    let mut read = 0;
+    // Each frame starts with the first chunk after the last one was flushed.
+    while let Ok(mut chunk) = chunks.recv() {
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        let mut encoder = frame::Encoder::<_, N>::new(&mut writer, config.clone())
+            .map_err(|source| output.io(source))?;
+        let ended = loop {
+            let data = chunk.map_err(|source| input.io(source))?;
+            (encoder.write_all(&data)).map_err(|source| output.io(source))?;
+            read += data.len() as u64;
+            match chunks.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(next) => chunk = next,
+                Err(ended) => break ended,
+            }
+        };
+        (encoder.finish())
+            .and_then(|writer| writer.flush())
+            .map_err(|source| output.io(source))?;
+        if ended == RecvTimeoutError::Disconnected {
+            break;
+        }
+    }
+    Ok((read, writer))
+}
+
+/// Reads `input` on a thread of its own, sending on whatever it yields as soon as it does.
+///
+/// Stdin ends the chunks at its end, while a file is polled for more data forever and read again
+/// from its start if truncated.
+fn read_chunks(input: &Location) -> Result<Receiver<io::Result<Vec<u8>>>, Error> {
+    let (sender, receiver) = mpsc::channel();
+    if input.is_std() {
+        thread::spawn(move || send_chunks(&mut io::stdin(), &sender));
+        return Ok(receiver);
+    }
+    let mut file = File::open(&input.0).map_err(|source| input.io(source))?;
+    let path = input.0.clone();
+    thread::spawn(move || {
+        while send_chunks(&mut file, &sender) {
+            thread::sleep(POLL_INTERVAL);
+            if let Err(err) = rewind_truncated(&mut file, &path) {
+                let _ = sender.send(Err(err));
+                return;
+            }
+        }
+    });
+    Ok(receiver)
+}
+
+/// Sends everything `reader` yields until its current end, returning whether the receiver is
+/// still there to take more.
+fn send_chunks(reader: &mut impl Read, sender: &Sender<io::Result<Vec<u8>>>) -> bool {
+    loop {
+        let mut chunk = vec![0; frame::BLOCK_LEN];
+        let chunk = match reader.read(&mut chunk) {
+            Ok(0) => return true,
+            Ok(len) => {
+                chunk.truncate(len);
+                Ok(chunk)
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => Err(err),
+        };
+        let failed = chunk.is_err();
+        if sender.send(chunk).is_err() || failed {
+            return false;
+        }
+    }
+}
+
+/// Goes back to the start of `file` if it shrank below the position read up to.
+fn rewind_truncated(file: &mut File, path: &Path) -> io::Result<()> {
+    if file.metadata()?.len() < file.stream_position()? {
+        eprintln!("slide: {}: file truncated", path.display());
+        file.rewind()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! `slide inspect`: prints the header and block layout of a compressed file.
+
+use std::{fs, path::PathBuf};
+
+use serde_json::json;
+use slide::frame::{self, FrameInfo};
+
+use crate::{Error, Location, ratio, stats::settings_json};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Compressed file to inspect.
+    input: PathBuf,
+    /// Print a JSON object instead of a table.
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    let input = Location(args.input);
+    let data = fs::read(&input.0).map_err(|source| input.io(source))?;
+    let info = frame::inspect(&data).map_err(|source| Error::Decode {
+        path: input.0.clone(),
+        source: source.into(),
+        offset: None,
+    })?;
+    match args.json {
+        true => println!("{:#}", to_json(&info)),
+        false => print(&info),
+    }
+    Ok(())
+}
+
+fn print(info: &FrameInfo) {
+    let content: usize = info.blocks.iter().map(|block| block.len).sum();
+    println!("version      {}", info.version);
+    println!("frame        {} bytes at offset {}", info.len, info.offset);
+    println!("window       {} bytes", info.max_buffer_len);
+    match info.content_size {
+        Some(size) => println!("content size {size} bytes"),
+        None => println!("content size unknown, blocks hold {content} bytes"),
+    }
+    println!("ratio        {}", ratio(info.len as u64, content as u64));
+    if let Some(checksum) = info.checksum {
+        println!("checksum     {checksum:08x}");
+    }
+    if let Some(dictionary) = info.dictionary {
+        match info.dictionary_id {
+            Some(id) => println!("dictionary   {dictionary:08x}, id {id}"),
+            None => println!("dictionary   {dictionary:08x}"),
+        }
+    }
+    if let Some(block_len) = info.block_sort {
+        println!("block sort   {block_len} bytes per block");
+    }
+    if let Some(filter) = info.filter {
+        println!("filter       {filter:?}");
+    }
+    if let Some(settings) = info.settings {
+        println!("settings     {settings}");
+    }
+    println!("independent  {}", info.independent);
+    println!("seek table   {}", info.seek_table);
+    println!();
+    println!(
+        "{:>6} {:>12} {:>12} {:>12} {:>8}",
+        "block", "offset", "compressed", "size", "ratio"
+    );
+    for (x, block) in info.blocks.iter().enumerate() {
+        println!(
+            "{x:>6} {:>12} {:>12} {:>12} {:>8}",
+            block.offset,
+            block.compressed_len,
+            block.len,
+            ratio(block.compressed_len as u64, block.len as u64)
+        );
+    }
+}
+
+fn to_json(info: &FrameInfo) -> serde_json::Value {
+    let blocks = Vec::from_iter(info.blocks.iter().map(|block| {
+        json!({
+            "offset": block.offset,
+            "compressed_len": block.compressed_len,
+            "len": block.len,
+        })
+    }));
+    json!({
+        "version": info.version,
+        "offset": info.offset,
+        "len": info.len,
+        "max_buffer_len": info.max_buffer_len,
+        "content_size": info.content_size,
+        "checksum": info.checksum,
+        "dictionary": info.dictionary,
+        "dictionary_id": info.dictionary_id,
+        "block_sort": info.block_sort,
+        "filter": info.filter.map(|filter| format!("{filter:?}")),
+        "settings": info.settings.as_ref().map(settings_json),
+        "independent": info.independent,
+        "seek_table": info.seek_table,
+        "blocks": blocks,
+    })
+}
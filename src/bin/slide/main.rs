@@ -0,0 +1,1088 @@
+//! The `slide` command line tool, built with the `cli` feature:
+//! `cargo install slide --features cli`.
+
+mod archive;
+mod bench;
+mod dict;
+mod follow;
+mod inspect;
+mod profile;
+mod range;
+mod raw;
+mod stats;
+mod verify;
+mod walk;
+
+use clap::{Parser, Subcommand};
+use dict::Dictionary;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::{ThreadPool, ThreadPoolBuilder, prelude::*};
+use slide::{
+    frame,
+    lz::{Config, ConfigError, MAX_LEVEL, Progress, Stats, Strategy},
+};
+use stats::{FrameConfig, Totals};
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+use walk::Walk;
+
+/// Extension appended to compressed files.
+const EXTENSION: &str = "slz";
+/// Level compressing without `--level`.
+const DEFAULT_LEVEL: u8 = 3;
+/// Input compressed per task with `--threads`.
+const CHUNK_LEN: usize = 1 << 22;
+
+/// Exit code of failures not covered below, like `verify` finding a mismatch.
+const EXIT_FAILURE: u8 = 1;
+/// Exit code of invalid arguments, the same clap uses.
+const EXIT_USAGE: u8 = 2;
+/// Exit code of failing to read or write a file.
+const EXIT_IO: u8 = 3;
+/// Exit code of input that isn't valid compressed data.
+const EXIT_CORRUPT: u8 = 4;
+/// Exit code of input that decoded, but not to the content its checksum was taken of.
+const EXIT_CHECKSUM: u8 = 5;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Sliding window compression",
+    after_help = "Exit codes: 0 success, 1 other failure, 2 invalid arguments, 3 I/O error, \
+                  4 corrupt input, 5 checksum mismatch."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Compress a file, writing `<input>.slz` by default, or stdin to stdout.
+    #[command(visible_alias = "c")]
+    Compress {
+        #[command(flatten)]
+        paths: Paths,
+        #[command(flatten)]
+        walk: Walk,
+        #[command(flatten)]
+        options: Options,
+        #[command(flatten)]
+        exec: Exec,
+        /// Write a bare stream of items instead of a frame, for embedding into other formats.
+        #[arg(long)]
+        raw: bool,
+        /// Compress with a dictionary trained by `slide dict train`, recording its ID.
+        #[arg(short = 'D', long, value_name = "FILE", conflicts_with = "raw")]
+        dictionary: Option<PathBuf>,
+        /// Compress blocks independently and append a seek table, so `decompress --range` can
+        /// decode parts of the output without the rest.
+        #[arg(long, conflicts_with = "raw")]
+        seekable: bool,
+        /// Run the match finder over the input without writing anything, reporting its chain
+        /// depths, hash map, search and serialization time and most referenced positions.
+        #[arg(
+            long,
+            conflicts_with_all = ["output", "recursive", "threads", "stats", "raw", "seekable"],
+        )]
+        profile: bool,
+        /// Keep compressing data appended to the input like `tail -f`, or stdin until it ends,
+        /// writing a complete frame at least every second.
+        #[arg(
+            long,
+            conflicts_with_all = ["recursive", "threads", "raw", "seekable", "profile"],
+        )]
+        follow: bool,
+    },
+    /// Decompress a file, stripping `.slz` from its name by default, or stdin to stdout.
+    #[command(visible_alias = "d")]
+    Decompress {
+        #[command(flatten)]
+        paths: Paths,
+        #[command(flatten)]
+        walk: Walk,
+        #[command(flatten)]
+        exec: Exec,
+        /// Read a bare stream of items as written by `compress --raw` instead of a frame.
+        #[arg(long)]
+        raw: bool,
+        /// Window of 2^N bytes the raw stream was compressed with, if not the highest level's.
+        #[arg(
+            long,
+            value_name = "N",
+            requires = "raw",
+            value_parser = clap::value_parser!(u8).range(10..usize::BITS as i64),
+        )]
+        window_log: Option<u8>,
+        /// Decompress with the dictionary the input was compressed with.
+        #[arg(short = 'D', long, value_name = "FILE", conflicts_with = "raw")]
+        dictionary: Option<PathBuf>,
+        /// Only decompress bytes START to END of the content, like `100M-200M`, decoding just the
+        /// blocks covering them. Needs a file compressed with `--seekable`.
+        #[arg(
+            long,
+            value_name = "START-END",
+            allow_hyphen_values = true,
+            conflicts_with_all = ["raw", "recursive"],
+        )]
+        range: Option<range::ByteRange>,
+    },
+    /// Compress and decompress every file of a corpus, tabulating ratio and speed.
+    Bench(bench::Args),
+    /// Check that a compressed file decompresses intact, without writing it.
+    Verify(verify::Args),
+    /// Show the header and blocks of a compressed file.
+    #[command(visible_alias = "list")]
+    Inspect(inspect::Args),
+    /// Pack files into a single compressed archive.
+    Archive(archive::ArchiveArgs),
+    /// Unpack the files of an archive.
+    Extract(archive::ExtractArgs),
+    /// Train dictionaries for compressing many small, similar files.
+    Dict(dict::Args),
+}
+
+#[derive(Debug, clap::Args)]
+struct Paths {
+    /// File to read, `-` or none for stdin.
+    #[arg(required_if_eq("recursive", "true"))]
+    input: Option<PathBuf>,
+    /// Where to write the result, `-` for stdout.
+    #[arg(short, long, conflicts_with = "recursive")]
+    output: Option<PathBuf>,
+    /// Overwrite the output if it exists.
+    #[arg(short, long)]
+    force: bool,
+}
+
+#[derive(Debug, clap::Args)]
+struct Options {
+    /// Compression level, from 1 (fastest) to 9 (smallest), also given as `-1` to `-9`.
+    #[arg(
+        short,
+        long,
+        default_value_t = DEFAULT_LEVEL,
+        value_parser = clap::value_parser!(u8).range(1..=MAX_LEVEL as i64),
+    )]
+    level: u8,
+    /// Window of 2^N bytes, overriding the level's.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(10..usize::BITS as i64),
+    )]
+    window_log: Option<u8>,
+    /// Shortest match, overriding the level's.
+    #[arg(long, value_name = "LEN", value_parser = clap::value_parser!(u32).range(1..))]
+    min_match: Option<u32>,
+    /// Longest match, unlimited by default.
+    #[arg(long, value_name = "LEN", value_parser = clap::value_parser!(u32).range(1..))]
+    max_match: Option<u32>,
+    /// How matches are chosen, overriding the level's: greedy, lazy=N, best-of=K or optimal.
+    #[arg(long, value_parser = parse_strategy)]
+    strategy: Option<Strategy>,
+    /// Most hash chain entries a single match search visits, unlimited by default.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_chain: Option<u32>,
+    /// Match length ending a search early, taking the match found.
+    #[arg(long, value_name = "LEN", value_parser = clap::value_parser!(u32).range(1..))]
+    nice_len: Option<u32>,
+    /// Most literal bytes held back before they are written, bounding output latency.
+    #[arg(long, value_name = "LEN", value_parser = clap::value_parser!(u32).range(1..))]
+    max_raw_len: Option<u32>,
+    /// Keep matches from overlapping their source, for decoders copying them with memcpy.
+    #[arg(long)]
+    no_overlap: bool,
+    /// Most bytes buffered ahead of the current position, overriding the level's.
+    #[arg(long, value_name = "SIZE", value_parser = range::parse_size)]
+    lookahead: Option<u64>,
+    /// Bytes the window, match finder and lookahead may take, shrinking the window to fit.
+    #[arg(long, value_name = "SIZE", value_parser = range::parse_size)]
+    memory: Option<u64>,
+    /// Block sort blocks of SIZE bytes instead of matching, often smaller for text.
+    #[arg(long, value_name = "SIZE", value_parser = range::parse_size)]
+    block_sort: Option<u64>,
+}
+impl Options {
+    fn config(&self) -> Result<Config<'static>, Error> {
+        let size = |size: u64| usize::try_from(size).unwrap_or(usize::MAX);
+        let mut config = Config::level(self.level);
+        if let Some(window_log) = self.window_log {
+            config.max_buffer_len = 1 << window_log;
+        }
+        if let Some(min_match) = self.min_match {
+            config.match_lengths.start = min_match as usize;
+        }
+        if let Some(max_match) = self.max_match {
+            config.match_lengths.end = max_match as usize + 1;
+        }
+        if let Some(strategy) = self.strategy {
+            config.strategy = strategy;
+        }
+        config.max_chain = self.max_chain.map(|max_chain| max_chain as usize);
+        config.nice_len = self.nice_len.map(|nice_len| nice_len as usize);
+        config.max_raw_len = self.max_raw_len.map(|max_raw_len| max_raw_len as usize);
+        config.forbid_overlap = self.no_overlap;
+        if let Some(lookahead) = self.lookahead {
+            config.lookahead = size(lookahead);
+        }
+        config.memory_budget = self.memory.map(size);
+        if let Some(block_sort) = self.block_sort {
+            config.block_sort = Some(size(block_sort));
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Parses `--strategy`.
+fn parse_strategy(s: &str) -> Result<Strategy, String> {
+    let (name, parameter) = match s.split_once('=') {
+        Some((name, parameter)) => (name, Some(parameter)),
+        None => (s, None),
+    };
+    let parameter = |default| match parameter {
+        Some(parameter) => {
+            (parameter.parse()).map_err(|_| format!("invalid {name} parameter {parameter:?}"))
+        }
+        None => Ok(default),
+    };
+    match name {
+        "greedy" => Ok(Strategy::Greedy),
+        "lazy" => Ok(Strategy::Lazy(parameter(1)?)),
+        "best-of" => Ok(Strategy::BestOf(parameter(4)?)),
+        "optimal" => Ok(Strategy::Optimal),
+        _ => Err(format!(
+            "unknown strategy {name:?}, expected greedy, lazy=N, best-of=K or optimal"
+        )),
+    }
+}
+
+#[derive(Debug, clap::Args)]
+struct Exec {
+    /// Work on N threads, 0 for one per core, handling each file in memory.
+    #[arg(short = 'T', long, value_name = "N")]
+    threads: Option<usize>,
+    /// Print sizes, item statistics, timing and per-thread utilization to stderr when done, as
+    /// text or json.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    stats: Option<stats::Format>,
+}
+impl Exec {
+    /// Thread pool of `--threads`, if given.
+    fn pool(&self) -> Option<ThreadPool> {
+        let threads = self.threads?;
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build();
+        Some(pool.expect("thread pool can be built"))
+    }
+    /// Runs `f`, on `--threads` if given, telling it whether to split files across threads and
+    /// whether to collect item statistics, and prints `--stats` for the totals it returns.
+    fn run(
+        &self,
+        decoding: bool,
+        f: impl FnOnce(bool, bool) -> Result<Totals, Error> + Send,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        let pool = self.pool();
+        let items = self.stats.is_some();
+        let totals = match &pool {
+            Some(pool) => pool.install(|| f(pool.current_num_threads() > 1, items)),
+            None => f(false, items),
+        }?;
+        if let Some(format) = self.stats {
+            let report = stats::Report {
+                totals: &totals,
+                time: start.elapsed(),
+                busy: pool.as_ref().and_then(stats::busy),
+                decoding,
+            };
+            report.print(format);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum Error {
+    #[error("{}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{}: {source}{}", path.display(), position(*offset))]
+    Decode {
+        path: PathBuf,
+        source: slide::Error,
+        offset: Option<Offset>,
+    },
+    #[error("{}: unknown extension, pass --output", path.display())]
+    NoOutput { path: PathBuf },
+    #[error("{}: already exists, pass --force to overwrite", path.display())]
+    Exists { path: PathBuf },
+    #[error("{}: not a .slz frame, pass --raw for bare item streams", path.display())]
+    NotFramed { path: PathBuf },
+    #[error("{}: compressed with dictionary {id}, pass it with -D", path.display())]
+    NoDictionary { path: PathBuf, id: u32 },
+    #[error("{}: compressed with dictionary {id}, not {actual}", path.display())]
+    WrongDictionary { path: PathBuf, id: u32, actual: u32 },
+    #[error("{}: not seekable, compress it with --seekable for --range", path.display())]
+    NotSeekable { path: PathBuf },
+    #[error("{}: decompressed data differs from the original", path.display())]
+    Mismatch { path: PathBuf },
+    #[error("{}: path leaves the archive root", path.display())]
+    UnsafePath { path: PathBuf },
+    #[error("{}: is a directory, pass --recursive", path.display())]
+    Directory { path: PathBuf },
+    #[error("{count} files failed")]
+    Failed {
+        count: usize,
+        /// Exit code of the first failure.
+        code: u8,
+    },
+    #[error("invalid compression options: {0}")]
+    Config(#[from] ConfigError),
+    #[error("{baseline} baseline needs slide built with --features {feature}")]
+    MissingFeature {
+        baseline: &'static str,
+        feature: &'static str,
+    },
+}
+
+impl Error {
+    /// Exit code telling scripts what kind of failure this is.
+    fn exit_code(&self) -> u8 {
+        match self {
+            Error::Io { .. } => EXIT_IO,
+            Error::Decode { source, .. } => match source {
+                slide::Error::Io(_) | slide::Error::Frame(frame::Error::Io(_)) => EXIT_IO,
+                slide::Error::Frame(frame::Error::ChecksumMismatch { .. }) => EXIT_CHECKSUM,
+                _ => EXIT_CORRUPT,
+            },
+            Error::NotFramed { .. } | Error::UnsafePath { .. } => EXIT_CORRUPT,
+            Error::NoOutput { .. }
+            | Error::Exists { .. }
+            | Error::NoDictionary { .. }
+            | Error::WrongDictionary { .. }
+            | Error::NotSeekable { .. }
+            | Error::Directory { .. }
+            | Error::Config(_)
+            | Error::MissingFeature { .. } => EXIT_USAGE,
+            Error::Mismatch { .. } => EXIT_FAILURE,
+            Error::Failed { code, .. } => *code,
+        }
+    }
+    /// Places a decode error without a position `offset` bytes into the input.
+    fn at(self, offset: u64) -> Self {
+        match self {
+            Error::Decode {
+                path,
+                source,
+                offset: None,
+            } => Error::Decode {
+                path,
+                source,
+                offset: Some(Offset::Read(offset)),
+            },
+            err => err,
+        }
+    }
+}
+
+/// Where in its input an [`Error::Decode`] was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Offset {
+    /// Bytes consumed when the error was found.
+    Read(u64),
+    /// Start of the frame the error is in, for frames decoded as a whole.
+    Frame(u64),
+}
+
+/// Position suffix of [`Error::Decode`] messages.
+fn position(offset: Option<Offset>) -> String {
+    match offset {
+        Some(Offset::Read(offset)) => format!(" at byte {offset}"),
+        Some(Offset::Frame(offset)) => format!(" in the frame at byte {offset}"),
+        None => String::new(),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse_from(env::args_os().map(expand_level))) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("slide: {err}");
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+/// Rewrites the gzip style `-1` to `-9` into `--level`.
+fn expand_level(arg: OsString) -> OsString {
+    match arg.to_str().and_then(|arg| arg.strip_prefix('-')) {
+        Some(level @ ("1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9")) => {
+            format!("--level={level}").into()
+        }
+        _ => arg,
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Error> {
+    match cli.command {
+        Command::Compress {
+            paths,
+            walk,
+            options,
+            exec,
+            raw,
+            dictionary,
+            seekable,
+            profile,
+            follow,
+        } => {
+            let dictionary = dictionary.as_deref().map(Dictionary::load).transpose()?;
+            let mut config = options.config()?;
+            if let Some(dictionary) = &dictionary {
+                config = config.with_dictionary(&dictionary.content);
+                config.dictionary_id = dictionary.id;
+            }
+            if profile {
+                return profile::run(&paths.input(), config);
+            }
+            let compressed = |input: &Path| {
+                let mut name = input.to_owned().into_os_string();
+                name.push(".");
+                name.push(EXTENSION);
+                Some(name.into())
+            };
+            if follow {
+                let output = paths.output(compressed)?;
+                return exec.run(false, |_, _| {
+                    follow::compress(&paths.input(), &output, paths.force, config)
+                });
+            }
+            exec.run(false, |parallel, items| {
+                let method = match seekable {
+                    true => Method::Seekable,
+                    false => Method::new(raw, parallel, None),
+                };
+                each_file(&paths, &walk, compressed, |input, output, progress| {
+                    let config = config.clone();
+                    compress(input, output, paths.force, config, progress, method, items)
+                })
+            })
+        }
+        Command::Decompress {
+            paths,
+            walk,
+            exec,
+            raw,
+            window_log,
+            dictionary,
+            range,
+        } => {
+            let dictionary = dictionary.as_deref().map(Dictionary::load).transpose()?;
+            let decompressed = |input: &Path| {
+                (input.extension())
+                    .is_some_and(|ext| ext == EXTENSION)
+                    .then(|| input.with_extension(""))
+            };
+            exec.run(true, |parallel, items| {
+                let method = Method::new(raw, parallel, window_log);
+                each_file(&paths, &walk, decompressed, |input, output, progress| {
+                    if let Some(range) = range {
+                        let dictionary = dictionary.as_ref();
+                        return range::decompress(
+                            input,
+                            output,
+                            paths.force,
+                            progress,
+                            range,
+                            dictionary,
+                        );
+                    }
+                    decompress(
+                        input,
+                        output,
+                        paths.force,
+                        progress,
+                        method,
+                        dictionary.as_ref(),
+                        items,
+                    )
+                })
+            })
+        }
+        Command::Bench(args) => bench::run(args),
+        Command::Verify(args) => verify::run(args),
+        Command::Inspect(args) => inspect::run(args),
+        Command::Archive(args) => archive::archive(args),
+        Command::Extract(args) => archive::extract(args),
+        Command::Dict(args) => dict::run(args),
+    }
+}
+
+/// Runs `f` on the input and output of `paths`, showing progress.
+///
+/// With `--recursive`, instead runs `f` in parallel without progress on every file below the
+/// input that `name` derives an output for, reporting each failure.
+///
+/// Returns the totals of all files.
+fn each_file(
+    paths: &Paths,
+    walk: &Walk,
+    name: impl Fn(&Path) -> Option<PathBuf>,
+    f: impl Fn(&Location, &Location, bool) -> Result<Totals, Error> + Sync,
+) -> Result<Totals, Error> {
+    let input = paths.input();
+    if !walk.recursive {
+        if input.0.is_dir() {
+            return Err(Error::Directory { path: input.0 });
+        }
+        return f(&input, &paths.output(name)?, true);
+    }
+    let files = Vec::from_iter(
+        (walk.files(&input.0)?.into_iter())
+            .filter_map(|file| Some((Location(name(&file)?), Location(file)))),
+    );
+    let results = Vec::from_par_iter((files.par_iter()).map(|(output, input)| {
+        f(input, output, false).inspect_err(|err| eprintln!("slide: {err}"))
+    }));
+    let mut totals = Totals::default();
+    let (mut failed, mut code) = (0, EXIT_FAILURE);
+    for result in results {
+        match result {
+            Ok(file) => totals.add(file),
+            Err(err) => {
+                if failed == 0 {
+                    code = err.exit_code();
+                }
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(totals),
+        count => Err(Error::Failed { count, code }),
+    }
+}
+
+/// Source or destination of a command, standing for stdin or stdout if `-`.
+#[derive(Debug, Clone)]
+struct Location(PathBuf);
+impl Location {
+    fn is_std(&self) -> bool {
+        self.0.as_os_str() == "-"
+    }
+    fn open(&self) -> Result<Box<dyn BufRead>, Error> {
+        if self.is_std() {
+            return Ok(Box::new(io::stdin().lock()));
+        }
+        let file = File::open(&self.0).map_err(|source| self.io(source))?;
+        Ok(Box::new(BufReader::new(file)))
+    }
+    fn len(&self) -> Option<u64> {
+        match self.is_std() {
+            true => None,
+            false => fs::metadata(&self.0).ok().map(|meta| meta.len()),
+        }
+    }
+    fn create(&self, force: bool) -> Result<Box<dyn Write>, Error> {
+        if self.is_std() {
+            return Ok(Box::new(io::stdout().lock()));
+        }
+        let file = match force {
+            true => File::create(&self.0),
+            false => File::create_new(&self.0),
+        };
+        let file = file.map_err(|source| match source.kind() {
+            io::ErrorKind::AlreadyExists => Error::Exists {
+                path: self.0.clone(),
+            },
+            _ => self.io(source),
+        })?;
+        Ok(Box::new(BufWriter::new(file)))
+    }
+    /// Removes a partly written output file.
+    fn remove(&self) {
+        if !self.is_std() {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+    fn io(&self, source: io::Error) -> Error {
+        Error::Io {
+            path: self.0.clone(),
+            source,
+        }
+    }
+    /// Error decoding the frame read from here, pointing at `--raw` if it has no header.
+    fn decode(&self, source: frame::Error) -> Error {
+        self.decode_at(source, None)
+    }
+    /// Like [`Location::decode`], found at `offset` in the input.
+    fn decode_at(&self, source: frame::Error, offset: Option<Offset>) -> Error {
+        match source {
+            frame::Error::InvalidMagic => Error::NotFramed {
+                path: self.0.clone(),
+            },
+            source => Error::Decode {
+                path: self.0.clone(),
+                source: source.into(),
+                offset,
+            },
+        }
+    }
+    /// Error reading from here, a decode error if a [`frame::Decoder`] reading it found the
+    /// frame invalid.
+    fn read_error(&self, source: io::Error) -> Error {
+        if source.kind() == io::ErrorKind::UnexpectedEof {
+            return self.decode(frame::Error::UnexpectedEof);
+        }
+        match source
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<frame::Error>())
+        {
+            Some(frame) => self.decode(frame.clone()),
+            None => self.io(source),
+        }
+    }
+}
+
+/// How a file is compressed or decompressed.
+#[derive(Debug, Clone, Copy)]
+enum Method {
+    /// Streamed through a frame encoder or decoder.
+    Stream,
+    /// In memory, splitting the work across the current thread pool.
+    Parallel,
+    /// In memory, as a bare item stream with a window of 2^N bytes, or the configured one.
+    Raw(Option<u8>),
+    /// In memory, as a seekable frame.
+    Seekable,
+}
+impl Method {
+    fn new(raw: bool, parallel: bool, window_log: Option<u8>) -> Self {
+        match (raw, parallel) {
+            (true, _) => Self::Raw(window_log),
+            (false, true) => Self::Parallel,
+            (false, false) => Self::Stream,
+        }
+    }
+}
+
+impl Paths {
+    fn input(&self) -> Location {
+        Location(self.input.clone().unwrap_or_else(|| "-".into()))
+    }
+    /// The output given, stdout for stdin, or the one `default` derives from the input path.
+    fn output(&self, default: impl FnOnce(&Path) -> Option<PathBuf>) -> Result<Location, Error> {
+        match (&self.output, &self.input) {
+            (Some(output), _) => Ok(Location(output.clone())),
+            (None, Some(input)) if input.as_os_str() != "-" => {
+                default(input).map(Location).ok_or_else(|| Error::NoOutput {
+                    path: input.clone(),
+                })
+            }
+            (None, _) => Ok(Location("-".into())),
+        }
+    }
+}
+
+fn compress(
+    input: &Location,
+    output: &Location,
+    force: bool,
+    config: Config,
+    progress: bool,
+    method: Method,
+    items: bool,
+) -> Result<Totals, Error> {
+    let mut reader = input.open()?;
+    let bar = match progress {
+        true => progress_bar(input.len()),
+        false => ProgressBar::hidden(),
+    };
+    if let Method::Parallel | Method::Raw(_) | Method::Seekable = method {
+        let data = read_all(&mut reader, input)?;
+        bar.set_position(data.len() as u64);
+        let frame = match method {
+            Method::Raw(_) => raw::encode(&data, config),
+            Method::Seekable => frame::compress_seekable(&data, config),
+            _ => frame::compress_parallel(&data, config, CHUNK_LEN, frame::ChunkWindow::Seeded),
+        };
+        write_all(&frame, output, force)?;
+        bar.finish_with_message(ratio(frame.len() as u64, data.len() as u64));
+        let mut totals = Totals::file(data.len() as u64, frame.len() as u64);
+        if items && !matches!(method, Method::Raw(_)) {
+            frame_stats(&frame, output, &mut totals)?;
+        }
+        return Ok(totals);
+    }
+    let writer = Counted::new(output.create(force)?, items);
+    let (read, writer) = encode(
+        &mut reader,
+        input,
+        writer,
+        output,
+        config,
+        Some(report(&bar, false)),
+    )
+    .inspect_err(|_| output.remove())?;
+    bar.finish_with_message(ratio(writer.count, read));
+    let mut totals = Totals::file(read, writer.count);
+    if let Some(frame) = &writer.kept {
+        frame_stats(frame, output, &mut totals)?;
+    }
+    Ok(totals)
+}
+
+/// Compresses everything `reader` yields into a single frame written to `writer`, blaming
+/// errors on `input` or `output`.
+///
+/// Returns the number of bytes read and `writer`.
+fn encode<W: Write>(
+    reader: &mut impl Read,
+    input: &Location,
+    writer: W,
+    output: &Location,
+    config: Config,
+    progress: Option<Progress>,
+) -> Result<(u64, W), Error> {
+    let encode = match config.match_lengths.start {
+        1 => encode_with::<1, _, _>,
+        2 => encode_with::<2, _, _>,
+        3 => encode_with::<3, _, _>,
+        _ => encode_with::<4, _, _>,
+    };
+    encode(reader, input, writer, output, config, progress)
+}
+
+/// Like [`encode`] with a SearchBuffer keyed by `N` bytes, at most `config`'s minimum match.
+fn encode_with<const N: usize, R: Read, W: Write>(
+    reader: &mut R,
+    input: &Location,
+    writer: W,
+    output: &Location,
+    config: Config,
+    progress: Option<Progress>,
+) -> Result<(u64, W), Error> {
+    let mut encoder =
+        frame::Encoder::<_, N>::new(writer, config).map_err(|source| output.io(source))?;
+    if let Some(progress) = progress {
+        encoder.set_progress(progress);
+    }
+    let read = copy(reader, input, &mut encoder, output)?;
+    let writer = encoder.finish().map_err(|source| output.io(source))?;
+    Ok((read, writer))
+}
+
+fn decompress(
+    input: &Location,
+    output: &Location,
+    force: bool,
+    progress: bool,
+    method: Method,
+    dictionary: Option<&Dictionary>,
+    items: bool,
+) -> Result<Totals, Error> {
+    let bar = match progress {
+        true => progress_bar(input.len()),
+        false => ProgressBar::hidden(),
+    };
+    let content = dictionary.map(|dictionary| dictionary.content.as_slice());
+    if let Method::Parallel | Method::Raw(_) = method {
+        let data = read_all(&mut input.open()?, input)?;
+        dict::check(&data, dictionary, &input.0)?;
+        bar.set_position(data.len() as u64);
+        let content = match method {
+            Method::Raw(window_log) => {
+                raw::decode(&data, window_log).map_err(|source| Error::Decode {
+                    path: input.0.clone(),
+                    source,
+                    offset: None,
+                })?
+            }
+            _ => decompress_parallel(&data, content, input)?,
+        };
+        write_all(&content, output, force)?;
+        bar.finish_with_message(ratio(data.len() as u64, content.len() as u64));
+        let mut totals = Totals::file(data.len() as u64, content.len() as u64);
+        if items && !matches!(method, Method::Raw(_)) {
+            frame_stats(&data, input, &mut totals)?;
+        }
+        return Ok(totals);
+    }
+    let mut reader = input.open()?;
+    let head = reader.fill_buf().map_err(|source| input.io(source))?;
+    dict::check(head, dictionary, &input.0)?;
+    let mut decoder = frame::Decoder::new(Counted::new(reader, items), content)
+        .map_err(|source| input.decode(source))?;
+    decoder.set_progress(report(&bar, true));
+    decoder.set_concatenated(true);
+    let mut writer = output.create(force)?;
+    let written = copy(&mut decoder, input, &mut writer, output)
+        .and_then(|written| {
+            writer.flush().map_err(|source| output.io(source))?;
+            Ok(written)
+        })
+        .map_err(|err| err.at(decoder.get_ref().count))
+        .inspect_err(|_| output.remove())?;
+    let read = decoder.get_ref().count;
+    bar.finish_with_message(ratio(read, written));
+    let mut totals = Totals::file(read, written);
+    if let Some(frame) = &decoder.get_ref().kept {
+        frame_stats(frame, input, &mut totals)?;
+    }
+    Ok(totals)
+}
+
+/// Decompresses every frame of `data` read from `input` in turn, decoding the blocks of each in
+/// parallel if they don't depend on each other.
+fn decompress_parallel(
+    data: &[u8],
+    dictionary: Option<&[u8]>,
+    input: &Location,
+) -> Result<Vec<u8>, Error> {
+    let mut out = vec![];
+    let mut offset = 0;
+    for frame in frame::frames(data) {
+        let decode = |source| input.decode_at(source, Some(Offset::Frame(offset as u64)));
+        let frame = frame.map_err(decode)?;
+        out.extend(frame::decompress_parallel(frame, dictionary).map_err(decode)?);
+        offset += frame.len();
+    }
+    Ok(out)
+}
+
+/// Adds the items of every frame of `data`, read from or written to `location`, to `totals`, and
+/// the configuration the first was compressed with.
+fn frame_stats(data: &[u8], location: &Location, totals: &mut Totals) -> Result<(), Error> {
+    let mut stats = Stats::new();
+    let mut offset = 0;
+    for frame in frame::frames(data) {
+        let decode = |source| location.decode_at(source, Some(Offset::Frame(offset as u64)));
+        let frame = frame.map_err(decode)?;
+        if totals.config.is_none() {
+            totals.config = Some(FrameConfig::new(&frame::inspect(frame).map_err(decode)?));
+        }
+        stats.merge(&frame::item_stats(frame).map_err(decode)?);
+        offset += frame.len();
+    }
+    totals.items.merge(&stats);
+    Ok(())
+}
+
+/// Reads everything from `reader`, blaming errors on `input`.
+fn read_all(reader: &mut impl Read, input: &Location) -> Result<Vec<u8>, Error> {
+    let mut data = vec![];
+    reader
+        .read_to_end(&mut data)
+        .map_err(|source| input.io(source))?;
+    Ok(data)
+}
+
+/// Writes `data` to `output`, removing it again on error.
+fn write_all(data: &[u8], output: &Location, force: bool) -> Result<(), Error> {
+    let mut writer = output.create(force)?;
+    (writer.write_all(data))
+        .and_then(|()| writer.flush())
+        .map_err(|source| output.io(source))
+        .inspect_err(|_| output.remove())
+}
+
+/// Copies everything from `reader` to `writer`, blaming errors on `input` or `output`.
+///
+/// Returns the number of bytes copied.
+fn copy(
+    reader: &mut impl Read,
+    input: &Location,
+    writer: &mut impl Write,
+    output: &Location,
+) -> Result<u64, Error> {
+    let mut buf = vec![0; frame::BLOCK_LEN];
+    let mut copied = 0;
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => return Ok(copied),
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(input.read_error(err)),
+        };
+        (writer.write_all(&buf[..len])).map_err(|source| output.io(source))?;
+        copied += len as u64;
+    }
+}
+
+/// Progress bar over the `len` bytes of input, if known, hidden unless stderr is a terminal.
+fn progress_bar(len: Option<u64>) -> ProgressBar {
+    if !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let (bar, template) = match len {
+        Some(len) => (
+            ProgressBar::new(len),
+            "{bar:40} {bytes}/{total_bytes} {bytes_per_sec} eta {eta} {msg}",
+        ),
+        None => (
+            ProgressBar::new_spinner(),
+            "{spinner} {bytes} {bytes_per_sec} {msg}",
+        ),
+    };
+    bar.set_style(ProgressStyle::with_template(template).expect("template is valid"));
+    bar
+}
+
+/// Advances `bar` by the input consumed every 64KiB, showing the ratio so far.
+///
+/// When `decoding`, the input is the compressed side.
+fn report(bar: &ProgressBar, decoding: bool) -> Progress<'static> {
+    let bar = bar.clone();
+    Progress::new(0x10000, move |processed| {
+        let (consumed, produced) = (processed.consumed as u64, processed.produced as u64);
+        bar.set_position(consumed);
+        bar.set_message(match decoding {
+            true => ratio(consumed, produced),
+            false => ratio(produced, consumed),
+        });
+    })
+}
+
+/// Size of `compressed` relative to `original`, in percent.
+fn ratio(compressed: u64, original: u64) -> String {
+    format!(
+        "{:.2}%",
+        compressed as f64 * 100f64 / original.max(1) as f64
+    )
+}
+
+/// Reader or writer counting the bytes passed through it.
+struct Counted<T> {
+    inner: T,
+    count: u64,
+    /// Copy of the bytes passed through, if asked to keep them.
+    kept: Option<Vec<u8>>,
+}
+impl<T> Counted<T> {
+    fn new(inner: T, keep: bool) -> Self {
+        Self {
+            inner,
+            count: 0,
+            kept: keep.then(Vec::new),
+        }
+    }
+}
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count += len as u64;
+        if let Some(kept) = &mut self.kept {
+            kept.extend_from_slice(&buf[..len]);
+        }
+        Ok(len)
+    }
+}
+impl<S: Seek> Seek for Counted<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.count += len as u64;
+        if let Some(kept) = &mut self.kept {
+            kept.extend_from_slice(&buf[..len]);
+        }
+        Ok(len)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strategies() {
+        assert_eq!(parse_strategy("greedy"), Ok(Strategy::Greedy));
+        assert_eq!(parse_strategy("lazy"), Ok(Strategy::Lazy(1)));
+        assert_eq!(parse_strategy("lazy=3"), Ok(Strategy::Lazy(3)));
+        assert_eq!(parse_strategy("best-of"), Ok(Strategy::BestOf(4)));
+        assert_eq!(parse_strategy("best-of=8"), Ok(Strategy::BestOf(8)));
+        assert_eq!(parse_strategy("optimal"), Ok(Strategy::Optimal));
+        assert!(parse_strategy("lazy=").is_err());
+        assert!(parse_strategy("lazy=x").is_err());
+        assert!(parse_strategy("best-of=-1").is_err());
+        assert!(parse_strategy("fastest").is_err());
+        assert!(parse_strategy("").is_err());
+    }
+
+    #[test]
+    fn levels() {
+        let expand = |arg: &str| expand_level(arg.into());
+        assert_eq!(expand("-1"), "--level=1");
+        assert_eq!(expand("-9"), "--level=9");
+        assert_eq!(expand("-0"), "-0");
+        assert_eq!(expand("-10"), "-10");
+        assert_eq!(expand("-f"), "-f");
+        assert_eq!(expand("5"), "5");
+        assert_eq!(expand("--level=3"), "--level=3");
+    }
+
+    #[test]
+    fn exit_codes() {
+        let path = PathBuf::from("file");
+        let decode = |source: slide::Error| Error::Decode {
+            path: path.clone(),
+            source,
+            offset: None,
+        };
+        let io = || io::Error::from(io::ErrorKind::NotFound);
+        let cases = [
+            (
+                Error::Io {
+                    path: path.clone(),
+                    source: io(),
+                },
+                EXIT_IO,
+            ),
+            (decode(slide::Error::Io(io())), EXIT_IO),
+            (
+                decode(frame::Error::Io(io::ErrorKind::NotFound).into()),
+                EXIT_IO,
+            ),
+            (
+                decode(
+                    frame::Error::ChecksumMismatch {
+                        expected: 1,
+                        actual: 2,
+                    }
+                    .into(),
+                ),
+                EXIT_CHECKSUM,
+            ),
+            (decode(frame::Error::Malformed.into()), EXIT_CORRUPT),
+            (Error::NotFramed { path: path.clone() }, EXIT_CORRUPT),
+            (Error::UnsafePath { path: path.clone() }, EXIT_CORRUPT),
+            (Error::Exists { path: path.clone() }, EXIT_USAGE),
+            (Error::NotSeekable { path: path.clone() }, EXIT_USAGE),
+            (Error::Config(ConfigError::EmptyWindow), EXIT_USAGE),
+            (Error::Mismatch { path: path.clone() }, EXIT_FAILURE),
+            (Error::Failed { count: 2, code: 7 }, 7),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{error:?}");
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! `compress --profile`: runs the match finder over a file and reports its internals instead of
+//! writing anything.
+
+use std::time::{Duration, Instant};
+
+use slide::{
+    lz::{Config, Stats},
+    search_buffer::SearchBuffer,
+    util::{SliceExt, histogram::Histogram},
+};
+
+use crate::{Error, Location, ratio, read_all, stats::buckets};
+
+/// Most referenced positions listed.
+const TOP: usize = 10;
+/// Values of each most referenced position shown.
+const PREVIEW_LEN: usize = 24;
+
+/// What profiling a file found.
+struct Profile {
+    stats: Stats,
+    /// Time spent finding the items.
+    search: Duration,
+    /// Time spent serializing them.
+    serialize: Duration,
+    /// Length of the items as a bare item stream.
+    compressed: usize,
+    /// Most referenced positions with their reference count and how many candidates the hash
+    /// chain of their key still reaches at the end of the input.
+    top: Vec<(usize, usize, Option<usize>)>,
+}
+
+pub fn run(input: &Location, config: Config) -> Result<(), Error> {
+    let data = read_all(&mut input.open()?, input)?;
+    let profile = match config.match_lengths.start {
+        1 => profile::<1>(&data, config),
+        2 => profile::<2>(&data, config),
+        3 => profile::<3>(&data, config),
+        _ => profile::<4>(&data, config),
+    };
+    print(&profile, &data);
+    Ok(())
+}
+
+fn profile<const N: usize>(data: &[u8], config: Config) -> Profile {
+    let mut stats = Stats::with_references();
+    let (mut search, mut serialize) = (Duration::ZERO, Duration::ZERO);
+    let mut compressed = 0;
+    let mut buffer = SearchBuffer::<u8, N>::new();
+    let mut items = buffer.to_items_with_stats(data.iter().copied(), config, &mut stats);
+    let mut out = vec![];
+    loop {
+        let start = Instant::now();
+        let Some(item) = items.next() else {
+            search += start.elapsed();
+            break;
+        };
+        let found = Instant::now();
+        out = postcard::to_extend(&item, out).expect("items are serializable");
+        compressed += out.len();
+        out.clear();
+        search += found - start;
+        serialize += found.elapsed();
+    }
+    drop(items);
+    let top = Vec::from_iter(stats.most_referenced(TOP).into_iter().map(|(pos, count)| {
+        let chain = (data.get(pos..).and_then(|rest| rest.first_chunk::<N>()))
+            .map(|key| buffer.dump_chains(key).len());
+        (pos, count, chain)
+    }));
+    Profile {
+        stats,
+        search,
+        serialize,
+        compressed,
+        top,
+    }
+}
+
+fn print(profile: &Profile, data: &[u8]) {
+    let stats = &profile.stats;
+    let total = (profile.search + profile.serialize).as_secs_f64().max(1e-9);
+    let bytes = Histogram::of_bytes(data);
+    println!(
+        "input        {} bytes, {:.3} bits per byte order-0 entropy, {} bytes at it",
+        data.len(),
+        bytes.entropy(),
+        bytes.estimated_len()
+    );
+    println!(
+        "items        {} bytes as a bare item stream, {}",
+        profile.compressed,
+        ratio(profile.compressed as u64, data.len() as u64)
+    );
+    for (name, time) in [("search", profile.search), ("serialize", profile.serialize)] {
+        let secs = time.as_secs_f64();
+        println!("{name:<12} {secs:.3}s, {:.1}%", secs * 100.0 / total);
+    }
+    println!(
+        "searches     {}, {:.2} chain entries each on average",
+        stats.searches,
+        stats.average_chain_depth()
+    );
+    let depths = Vec::from_iter(
+        buckets(&stats.chain_depth_histogram).map(|(start, count)| format!(">={start}: {count}")),
+    );
+    println!("chain depth  {}", depths.join(", "));
+    println!(
+        "hash map     {} heads for {} positions, {:.1}% distinct",
+        stats.heads,
+        data.len(),
+        stats.heads as f64 * 100.0 / data.len().max(1) as f64
+    );
+    println!();
+    println!("{:>12} {:>8} {:>8}  content", "position", "refs", "chain");
+    for &(pos, count, chain) in &profile.top {
+        let preview = data.get_clamped(pos..pos + PREVIEW_LEN);
+        let chain = chain.map_or("-".to_string(), |len| len.to_string());
+        println!(
+            "{pos:>12} {count:>8} {chain:>8}  {}",
+            preview.escape_ascii()
+        );
+    }
+}
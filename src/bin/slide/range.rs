@@ -0,0 +1,151 @@
+//! `decompress --range`: decodes only the blocks of a seekable frame that cover part of its
+//! content, as written by `compress --seekable`.
+
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    str::FromStr,
+};
+
+use indicatif::ProgressBar;
+use slide::frame::{self, BLOCK_LEN, SeekableDecoder};
+
+use crate::{Counted, Error, Location, dict::Dictionary, progress_bar, ratio, stats::Totals};
+
+/// Content decoded per read, a multiple of the blocks of seekable frames so that each is only
+/// decoded once.
+const CHUNK_LEN: u64 = BLOCK_LEN as u64 * 8;
+
+/// Part of the decompressed content, `START-END` with an exclusive end.
+///
+/// Either side may be left out for the start or end of the content, and sizes may end in `K`,
+/// `M`, `G` or `T` for binary multiples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+impl FromStr for ByteRange {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-').ok_or("expected START-END")?;
+        let start = match start {
+            "" => 0,
+            start => parse_size(start)?,
+        };
+        let end = match end {
+            "" => None,
+            end => Some(parse_size(end)?),
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(format!("{s} ends before it starts"));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+/// Parses a size in bytes, optionally ending in `K`, `M`, `G` or `T` for binary multiples.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let shift = match s.chars().last() {
+        Some('K' | 'k') => 10,
+        Some('M' | 'm') => 20,
+        Some('G' | 'g') => 30,
+        Some('T' | 't') => 40,
+        _ => 0,
+    };
+    let digits = match shift {
+        0 => s,
+        _ => &s[..s.len() - 1],
+    };
+    let value: u64 = digits.parse().map_err(|_| format!("invalid size {s:?}"))?;
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size {s} is too large"))
+}
+
+/// Writes `range` of the content of the seekable frame in `input` to `output`.
+pub fn decompress(
+    input: &Location,
+    output: &Location,
+    force: bool,
+    progress: bool,
+    range: ByteRange,
+    dictionary: Option<&Dictionary>,
+) -> Result<Totals, Error> {
+    if input.is_std() {
+        return Err(Error::NotSeekable {
+            path: input.0.clone(),
+        });
+    }
+    let file = File::open(&input.0).map_err(|source| input.io(source))?;
+    let content = dictionary.map(|dictionary| dictionary.content.as_slice());
+    let reader = Counted::new(BufReader::new(file), false);
+    let mut decoder = SeekableDecoder::new(reader, content).map_err(|source| match source {
+        frame::Error::NotSeekable => Error::NotSeekable {
+            path: input.0.clone(),
+        },
+        source => input.decode(source),
+    })?;
+    let end = range.end.unwrap_or(u64::MAX).min(decoder.len());
+    let mut pos = range.start.min(end);
+    let bar = match progress {
+        true => progress_bar(Some(end - pos)),
+        false => ProgressBar::hidden(),
+    };
+    let mut writer = output.create(force)?;
+    let written = end - pos;
+    while pos < end {
+        let chunk_end = ((pos / CHUNK_LEN + 1) * CHUNK_LEN).min(end);
+        let chunk = (decoder.read_at(pos..chunk_end))
+            .map_err(|source| input.decode(source))
+            .inspect_err(|_| output.remove())?;
+        (writer.write_all(&chunk))
+            .map_err(|source| output.io(source))
+            .inspect_err(|_| output.remove())?;
+        bar.inc(chunk.len() as u64);
+        pos = chunk_end;
+    }
+    writer.flush().map_err(|source| output.io(source))?;
+    let read = decoder.into_inner().count;
+    bar.finish_with_message(ratio(read, written));
+    Ok(Totals::file(read, written))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("123"), Ok(123));
+        assert_eq!(parse_size("2K"), Ok(2 << 10));
+        assert_eq!(parse_size("2k"), Ok(2 << 10));
+        assert_eq!(parse_size("3M"), Ok(3 << 20));
+        assert_eq!(parse_size("4G"), Ok(4 << 30));
+        assert_eq!(parse_size("5T"), Ok(5 << 40));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("1.5K").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("18446744073709551616").is_err());
+        assert!(parse_size("16777216T").is_err());
+        assert_eq!(parse_size("16777215T"), Ok(16777215 << 40));
+    }
+
+    #[test]
+    fn ranges() {
+        let range = |start, end| Ok(ByteRange { start, end });
+        assert_eq!("10-20".parse(), range(10, Some(20)));
+        assert_eq!("1K-2K".parse(), range(1 << 10, Some(2 << 10)));
+        assert_eq!("-20".parse(), range(0, Some(20)));
+        assert_eq!("10-".parse(), range(10, None));
+        assert_eq!("-".parse(), range(0, None));
+        assert_eq!("5-5".parse(), range(5, Some(5)));
+        assert!("20-10".parse::<ByteRange>().is_err());
+        assert!("10".parse::<ByteRange>().is_err());
+        assert!("a-b".parse::<ByteRange>().is_err());
+        assert!("1-2-3".parse::<ByteRange>().is_err());
+        assert!("99999999T-".parse::<ByteRange>().is_err());
+    }
+}
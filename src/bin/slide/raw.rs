@@ -0,0 +1,47 @@
+//! `--raw`: bare streams of postcard encoded items, without the header, block lengths and
+//! checksum of a frame.
+//!
+//! Nothing in such a stream records how it was compressed, so decoding it needs the window it was
+//! compressed with, and corruption is only noticed if it breaks the items.
+
+use slide::{
+    Error, Slide, frame,
+    lz::{Config, Item, MAX_LEVEL},
+    search_buffer::SearchBuffer,
+};
+
+/// Compresses `data` into a bare item stream.
+pub fn encode(data: &[u8], config: Config) -> Vec<u8> {
+    match config.match_lengths.start {
+        1 => encode_with::<1>(data, config),
+        2 => encode_with::<2>(data, config),
+        3 => encode_with::<3>(data, config),
+        _ => encode_with::<4>(data, config),
+    }
+}
+
+fn encode_with<const N: usize>(data: &[u8], config: Config) -> Vec<u8> {
+    let mut out = vec![];
+    for item in SearchBuffer::<u8, N>::new().to_items_from_slice(data, config) {
+        out = postcard::to_extend(&item, out).expect("items are serializable");
+    }
+    out
+}
+
+/// Decompresses a bare item stream compressed with a window of 2^`window_log` bytes, or that of
+/// the highest level if None.
+pub fn decode(mut data: &[u8], window_log: Option<u8>) -> Result<Vec<u8>, Error> {
+    let mut items = vec![];
+    while !data.is_empty() {
+        let item;
+        (item, data) = Item::take_from_bytes(data).map_err(|_| frame::Error::Malformed)?;
+        items.push(item);
+    }
+    let mut config = Config::level(MAX_LEVEL);
+    if let Some(window_log) = window_log {
+        config.max_buffer_len = 1 << window_log;
+    }
+    // Any match length the encoder may have used.
+    config.match_lengths = 1..usize::MAX;
+    Slide::new().try_from_items(items, config).collect()
+}
@@ -0,0 +1,244 @@
+//! `--stats`: sizes, item statistics, timing and thread utilization printed once a command is
+//! done, as text or JSON.
+
+use std::{
+    fmt::{self, Display},
+    fs,
+    time::Duration,
+};
+
+use rayon::ThreadPool;
+use serde_json::json;
+use slide::{
+    frame::{EncoderSettings, FrameInfo},
+    lz::Stats,
+};
+
+use crate::ratio;
+
+/// How `--stats` are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Text,
+    /// A single JSON object on stderr, for scripts.
+    Json,
+}
+
+/// Bytes read and written and the items of the frames involved for one file, or a sum of them.
+#[derive(Debug, Default, Clone)]
+pub struct Totals {
+    pub files: usize,
+    pub read: u64,
+    pub written: u64,
+    pub items: Stats,
+    /// Configuration of the first frame involved.
+    pub config: Option<FrameConfig>,
+}
+impl Totals {
+    pub fn file(read: u64, written: u64) -> Self {
+        Self {
+            files: 1,
+            read,
+            written,
+            items: Stats::new(),
+            config: None,
+        }
+    }
+    pub fn add(&mut self, other: Totals) {
+        self.files += other.files;
+        self.read += other.read;
+        self.written += other.written;
+        self.items.merge(&other.items);
+        self.config = self.config.or(other.config);
+    }
+}
+
+/// Configuration a frame records in its header: the window actually used and either the block
+/// length of block sorting or the encoder settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameConfig {
+    pub window: usize,
+    pub block_sort: Option<usize>,
+    pub settings: Option<EncoderSettings>,
+}
+impl FrameConfig {
+    pub fn new(info: &FrameInfo) -> Self {
+        Self {
+            window: info.max_buffer_len,
+            block_sort: info.block_sort,
+            settings: info.settings,
+        }
+    }
+}
+impl Display for FrameConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.block_sort {
+            Some(len) => write!(f, "block sort {len}")?,
+            None => write!(f, "window {}", self.window)?,
+        }
+        if let Some(settings) = self.settings {
+            write!(f, ", {settings}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything `--stats` prints about a run.
+pub struct Report<'a> {
+    pub totals: &'a Totals,
+    pub time: Duration,
+    /// Time each thread spent working, if known.
+    pub busy: Option<Vec<Duration>>,
+    /// Whether the bytes read are the compressed side.
+    pub decoding: bool,
+}
+impl Report<'_> {
+    /// Compressed and original size.
+    fn sides(&self) -> (u64, u64) {
+        match self.decoding {
+            true => (self.totals.read, self.totals.written),
+            false => (self.totals.written, self.totals.read),
+        }
+    }
+    pub fn print(&self, format: Format) {
+        match format {
+            Format::Text => self.print_text(),
+            Format::Json => eprintln!("{}", self.to_json()),
+        }
+    }
+    fn print_text(&self) {
+        let totals = self.totals;
+        let (compressed, original) = self.sides();
+        let secs = self.time.as_secs_f64().max(1e-9);
+        if let Some(config) = totals.config {
+            eprintln!("config    {config}");
+        }
+        eprintln!("files     {}", totals.files);
+        eprintln!("read      {} bytes", totals.read);
+        eprintln!("written   {} bytes", totals.written);
+        eprintln!("ratio     {}", ratio(compressed, original));
+        eprintln!(
+            "time      {secs:.3}s, {:.1} MB/s",
+            original as f64 / 1e6 / secs
+        );
+        let items = &totals.items;
+        if items.items > 0 {
+            let percent = |len: usize| len as f64 * 100.0 / items.total_len().max(1) as f64;
+            eprintln!(
+                "items     {} raw, {} ref, {} rep ref, {} run",
+                items.raw_items, items.ref_items, items.rep_ref_items, items.run_items
+            );
+            eprintln!(
+                "values    {:.1}% literal, {:.1}% matched, {:.1}% run",
+                percent(items.literal_len),
+                percent(items.match_len),
+                percent(items.run_len)
+            );
+            for (name, histogram) in [
+                ("lengths", &items.length_histogram),
+                ("distances", &items.distance_histogram),
+            ] {
+                let buckets = Vec::from_iter(
+                    buckets(histogram).map(|(start, count)| format!(">={start}: {count}")),
+                );
+                eprintln!("{name:<9} {}", buckets.join(", "));
+            }
+            eprintln!(
+                "entropy   {:.2} bits per length and {:.2} per distance bit length, {} bytes of \
+                 references at it",
+                items.length_entropy(),
+                items.distance_entropy(),
+                (items.reference_bits() / 8.0).ceil()
+            );
+        }
+        for (x, busy) in self.busy.iter().flatten().enumerate() {
+            let busy = busy.as_secs_f64();
+            eprintln!(
+                "thread {x:<2} {busy:.3}s busy, {:.1}%",
+                busy * 100f64 / secs
+            );
+        }
+    }
+    fn to_json(&self) -> serde_json::Value {
+        let totals = self.totals;
+        let items = &totals.items;
+        let (compressed, original) = self.sides();
+        let secs = self.time.as_secs_f64();
+        let histogram = |histogram| {
+            Vec::from_iter(
+                buckets(histogram).map(|(start, count)| json!({"min": start, "count": count})),
+            )
+        };
+        let threads = self.busy.as_ref().map(|busy| {
+            Vec::from_iter(busy.iter().map(|busy| {
+                json!({
+                    "busy_seconds": busy.as_secs_f64(),
+                    "utilization": busy.as_secs_f64() / secs.max(1e-9),
+                })
+            }))
+        });
+        let config = totals.config.map(|config| {
+            json!({
+                "window": config.window,
+                "block_sort": config.block_sort,
+                "settings": config.settings.as_ref().map(settings_json),
+            })
+        });
+        json!({
+            "config": config,
+            "files": totals.files,
+            "read": totals.read,
+            "written": totals.written,
+            "compressed": compressed,
+            "original": original,
+            "ratio": compressed as f64 / original.max(1) as f64,
+            "seconds": secs,
+            "mb_per_second": original as f64 / 1e6 / secs.max(1e-9),
+            "items": {
+                "raw": items.raw_items,
+                "ref": items.ref_items,
+                "rep_ref": items.rep_ref_items,
+                "run": items.run_items,
+                "literal_len": items.literal_len,
+                "match_len": items.match_len,
+                "run_len": items.run_len,
+                "length_histogram": histogram(&items.length_histogram),
+                "distance_histogram": histogram(&items.distance_histogram),
+                "length_entropy": items.length_entropy(),
+                "distance_entropy": items.distance_entropy(),
+                "reference_bits": items.reference_bits(),
+            },
+            "threads": threads,
+        })
+    }
+}
+
+/// JSON object of the fields of `settings`.
+pub fn settings_json(settings: &EncoderSettings) -> serde_json::Value {
+    json!({
+        "min_match": settings.min_match,
+        "max_match": settings.max_match,
+        "strategy": settings.strategy.to_string(),
+        "max_chain": settings.max_chain,
+        "nice_len": settings.nice_len,
+    })
+}
+
+/// Smallest value and count of the non-empty buckets of a [`Stats`] histogram.
+pub fn buckets(histogram: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (histogram.iter().enumerate())
+        .filter(|(_, count)| **count > 0)
+        .map(|(bucket, &count)| (bucket.checked_sub(1).map_or(0, |bits| 1 << bits), count))
+}
+
+/// CPU time every thread of `pool` used so far, indexed by thread.
+///
+/// Only Linux reports it, through `/proc/thread-self/schedstat`.
+pub fn busy(pool: &ThreadPool) -> Option<Vec<Duration>> {
+    let busy = pool.broadcast(|_| {
+        let schedstat = fs::read_to_string("/proc/thread-self/schedstat").ok()?;
+        let nanos = schedstat.split_whitespace().next()?.parse().ok()?;
+        Some(Duration::from_nanos(nanos))
+    });
+    busy.into_iter().collect()
+}
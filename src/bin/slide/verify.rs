@@ -0,0 +1,65 @@
+//! `slide verify`: checks that a file decompresses, or that a file survives a round trip.
+
+use std::{fs, io, path::PathBuf};
+
+use slide::frame;
+
+use crate::{Counted, Error, Location, Options, copy, ratio};
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Compressed file to check, `-` or none for stdin.
+    #[arg(conflicts_with = "roundtrip")]
+    input: Option<PathBuf>,
+    /// Compress this file instead, checking that it decompresses to the original.
+    #[arg(long, value_name = "FILE")]
+    roundtrip: Option<PathBuf>,
+    #[command(flatten)]
+    options: Options,
+}
+
+pub fn run(args: Args) -> Result<(), Error> {
+    match args.roundtrip {
+        Some(path) => roundtrip(path, &args.options),
+        None => verify(Location(args.input.unwrap_or_else(|| "-".into()))),
+    }
+}
+
+/// Decompresses `input` without writing it anywhere, so the decoder checks the content size and
+/// checksum.
+fn verify(input: Location) -> Result<(), Error> {
+    let reader = Counted::new(input.open()?, false);
+    let mut decoder = frame::Decoder::new(reader, None).map_err(|source| input.decode(source))?;
+    decoder.set_concatenated(true);
+    let len = copy(&mut decoder, &input, &mut io::sink(), &input)
+        .map_err(|err| err.at(decoder.get_ref().count))?;
+    eprintln!(
+        "{}: ok, {len} bytes at {}",
+        input.0.display(),
+        ratio(decoder.get_ref().count, len)
+    );
+    Ok(())
+}
+
+fn roundtrip(path: PathBuf, options: &Options) -> Result<(), Error> {
+    let data = fs::read(&path).map_err(|source| Error::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let compressed = frame::compress(&data, options.config()?);
+    let decoded = frame::decompress(&compressed, None).map_err(|source| Error::Decode {
+        path: path.clone(),
+        source: source.into(),
+        offset: None,
+    })?;
+    if decoded != data {
+        return Err(Error::Mismatch { path });
+    }
+    eprintln!(
+        "{}: ok, {} bytes at {}",
+        path.display(),
+        data.len(),
+        ratio(compressed.len() as u64, data.len() as u64)
+    );
+    Ok(())
+}
@@ -0,0 +1,72 @@
+//! Expanding directories given on the command line into the files below them.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::Error;
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct Walk {
+    /// Descend into directories, handling every file below them.
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Only handle files below a directory whose path relative to it matches one of these.
+    #[arg(long, value_name = "GLOB", value_parser = Glob::new)]
+    include: Vec<Glob>,
+    /// Skip files below a directory whose path relative to it matches one of these.
+    #[arg(long, value_name = "GLOB", value_parser = Glob::new)]
+    exclude: Vec<Glob>,
+}
+impl Walk {
+    /// `path` itself if it's not a directory, otherwise the files below it passing the filters,
+    /// sorted.
+    ///
+    /// Fails for directories unless `recursive`.
+    pub fn files(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        if !path.is_dir() {
+            return Ok(vec![path.into()]);
+        }
+        if !self.recursive {
+            return Err(Error::Directory { path: path.into() });
+        }
+        let include = build(&self.include);
+        let exclude = build(&self.exclude);
+        let mut files = vec![];
+        let mut dirs = vec![path.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            let io = |source| Error::Io {
+                path: dir.clone(),
+                source,
+            };
+            for entry in fs::read_dir(&dir).map_err(io)? {
+                let entry = entry.map_err(io)?;
+                let file_type = entry.file_type().map_err(io)?;
+                let entry = entry.path();
+                if file_type.is_dir() {
+                    dirs.push(entry);
+                    continue;
+                }
+                let relative = entry.strip_prefix(path).unwrap_or(&entry);
+                if (self.include.is_empty() || include.is_match(relative))
+                    && !exclude.is_match(relative)
+                {
+                    files.push(entry);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+fn build(globs: &[Glob]) -> GlobSet {
+    let mut set = GlobSetBuilder::new();
+    for glob in globs {
+        set.add(glob.clone());
+    }
+    set.build().expect("globs were parsed already")
+}
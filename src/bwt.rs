@@ -0,0 +1,246 @@
+//! Block sorting compression like bzip2: Burrows–Wheeler transform, move-to-front, run-length
+//! coding of zeros and Huffman coding.
+//!
+//! Sorting groups bytes by the context following them, so text and other data with many short
+//! repetitions often compresses better than with LZ matching. A compressed block is laid out as
+//! the decompressed length, the primary index and the number of coded symbols (varints each),
+//! followed by the Huffman coded symbols.
+
+use std::fmt::{self, Display};
+
+use crate::{huffman, util::varint};
+
+/// Symbols coding runs of zeros in bijective base 2, least significant digit first.
+const RUN_A: u16 = 0;
+const RUN_B: u16 = 1;
+/// Move-to-front indices 1..=255 are shifted past the run symbols.
+const ALPHABET: usize = 257;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input ended in the middle of a block.
+    UnexpectedEof,
+    /// The block's length, index or codes are inconsistent.
+    Malformed,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => write!(f, "unexpected end of block"),
+            Error::Malformed => write!(f, "malformed block"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// Burrows–Wheeler transform of `block` terminated by a virtual end marker sorting before every
+/// byte, returning the last column without the marker and the marker's row.
+///
+/// Suffixes are sorted by prefix doubling, taking `O(n log² n)` time.
+pub fn transform(block: &[u8]) -> (Vec<u8>, usize) {
+    let Some(&last) = block.last() else {
+        return (vec![], 0);
+    };
+    // The first row is the end marker followed by the block.
+    let mut out = Vec::with_capacity(block.len());
+    out.push(last);
+    let mut primary = 0;
+    for (row, suffix) in suffix_array(block).into_iter().enumerate() {
+        match suffix {
+            0 => primary = row + 1,
+            _ => out.push(block[suffix - 1]),
+        }
+    }
+    (out, primary)
+}
+
+fn suffix_array(data: &[u8]) -> Vec<usize> {
+    let n = data.len();
+    let mut suffixes = Vec::from_iter(0..n);
+    // Rank 0 is reserved for the empty suffix past the end.
+    let mut ranks = Vec::from_iter(data.iter().map(|&byte| byte as usize + 1));
+    let mut next = vec![0; n];
+    let mut width = 1;
+    loop {
+        let key = |suffix: usize| {
+            (
+                ranks[suffix],
+                ranks.get(suffix + width).copied().unwrap_or(0),
+            )
+        };
+        suffixes.sort_unstable_by_key(|&suffix| key(suffix));
+        next[suffixes[0]] = 1;
+        for pair in suffixes.windows(2) {
+            next[pair[1]] = next[pair[0]] + (key(pair[0]) != key(pair[1])) as usize;
+        }
+        ranks.copy_from_slice(&next);
+        if ranks[suffixes[n - 1]] == n {
+            return suffixes;
+        }
+        width *= 2;
+    }
+}
+
+/// Inverts [`transform`].
+pub fn inverse(last: &[u8], primary: usize) -> Result<Vec<u8>, Error> {
+    let n = last.len();
+    if (n == 0) != (primary == 0) || primary > n {
+        return Err(Error::Malformed);
+    }
+    // Row of the first column each row of the last column continues with.
+    let mut starts = [0; 256];
+    let mut start = 1;
+    for (byte, count) in histogram(last).into_iter().enumerate() {
+        starts[byte] = start;
+        start += count;
+    }
+    let mut lf = vec![0; n + 1];
+    for (row, &byte) in (0..=n).filter(|&row| row != primary).zip(last) {
+        lf[row] = starts[byte as usize];
+        starts[byte as usize] += 1;
+    }
+    let column = |row: usize| last[row - (row > primary) as usize];
+    let mut out = vec![0; n];
+    let mut row = 0;
+    for value in out.iter_mut().rev() {
+        // Only the row of the whole block ends with the marker.
+        if row == primary {
+            return Err(Error::Malformed);
+        }
+        *value = column(row);
+        row = lf[row];
+    }
+    Ok(out)
+}
+
+fn histogram(data: &[u8]) -> [usize; 256] {
+    let mut counts = [0; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    counts
+}
+
+/// Compresses `block` into a single self-delimiting block.
+pub fn compress(block: &[u8]) -> Vec<u8> {
+    let (last, primary) = transform(block);
+    let mut symbols = vec![];
+    let mut zeros = 0;
+    let mut order: [u8; 256] = std::array::from_fn(|x| x as u8);
+    for &byte in &last {
+        let index = order.iter().position(|&x| x == byte).unwrap();
+        order.copy_within(..index, 1);
+        order[0] = byte;
+        if index == 0 {
+            zeros += 1;
+            continue;
+        }
+        push_zeros(&mut symbols, zeros);
+        zeros = 0;
+        symbols.push(index as u16 + 1);
+    }
+    push_zeros(&mut symbols, zeros);
+
+    let mut out = vec![];
+    for value in [block.len(), primary, symbols.len()] {
+        varint::write(&mut out, value as u64);
+    }
+    huffman::encode(&symbols, ALPHABET, &mut out);
+    out
+}
+
+fn push_zeros(symbols: &mut Vec<u16>, mut zeros: usize) {
+    while zeros > 0 {
+        symbols.push(match zeros & 1 {
+            1 => RUN_A,
+            _ => RUN_B,
+        });
+        zeros = (zeros - 1) / 2;
+    }
+}
+
+/// Length `data` decompresses to, as announced by the block.
+pub fn decompress_len(data: &[u8]) -> Result<usize, Error> {
+    Ok(read_varint(data)?.0)
+}
+
+/// Decompresses a single block, returning it and the rest of `data`.
+pub fn decompress(data: &[u8]) -> Result<(Vec<u8>, &[u8]), Error> {
+    let (len, data) = read_varint(data)?;
+    let (primary, data) = read_varint(data)?;
+    let (count, data) = read_varint(data)?;
+    let (symbols, rest) = huffman::decode(data, ALPHABET, count).ok_or(Error::Malformed)?;
+    let mut last = vec![];
+    let mut order: [u8; 256] = std::array::from_fn(|x| x as u8);
+    let (mut zeros, mut weight) = (0usize, 1usize);
+    for symbol in symbols {
+        if symbol <= RUN_B {
+            zeros = weight
+                .checked_mul(symbol as usize + 1)
+                .and_then(|run| zeros.checked_add(run))
+                .filter(|&zeros| zeros <= len - last.len())
+                .ok_or(Error::Malformed)?;
+            weight = weight.saturating_mul(2);
+            continue;
+        }
+        last.extend(std::iter::repeat_n(order[0], zeros));
+        (zeros, weight) = (0, 1);
+        let index = symbol as usize - 1;
+        let byte = order[index];
+        order.copy_within(..index, 1);
+        order[0] = byte;
+        if last.len() == len {
+            return Err(Error::Malformed);
+        }
+        last.push(byte);
+    }
+    last.extend(std::iter::repeat_n(order[0], zeros));
+    if last.len() != len {
+        return Err(Error::Malformed);
+    }
+    Ok((inverse(&last, primary)?, rest))
+}
+
+fn read_varint(data: &[u8]) -> Result<(usize, &[u8]), Error> {
+    varint::read_usize(data).map_err(|err| match err {
+        varint::Error::UnexpectedEof => Error::UnexpectedEof,
+        varint::Error::Overflow => Error::Malformed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn banana() {
+        let (last, primary) = transform(b"banana");
+        // Rows: $banana, a$banan, ana$ban, anana$b, banana$, na$bana, nana$ba
+        assert_eq!((last.as_slice(), primary), (&b"annbaa"[..], 4));
+        assert_eq!(inverse(&last, primary).unwrap(), b"banana");
+        assert_eq!(inverse(&last, 0), Err(Error::Malformed));
+        assert_eq!(inverse(&last, 7), Err(Error::Malformed));
+    }
+    #[quickcheck]
+    fn roundtrip(data: Vec<u8>, modulo: u8) {
+        let data = Vec::from_iter(data.iter().map(|x| x % modulo.max(1)));
+        let (last, primary) = transform(&data);
+        assert_eq!(inverse(&last, primary).unwrap(), data);
+        let compressed = compress(&data);
+        assert_eq!(decompress_len(&compressed), Ok(data.len()));
+        assert_eq!(decompress(&compressed).unwrap(), (data, &[][..]));
+    }
+    #[test]
+    fn text() {
+        let text =
+            b"she sells sea shells by the sea shore, the shells she sells are surely seashells. "
+                .repeat(0x40);
+        let compressed = compress(&text);
+        assert!(compressed.len() * 20 < text.len());
+        assert_eq!(decompress(&compressed).unwrap().0, text);
+        for truncated in 0..compressed.len() {
+            assert!(decompress(&compressed[..truncated]).is_err());
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
+
+use crate::util::SliceExt;
+
+/// A shared dictionary of byte sequences common to a family of small inputs.
+///
+/// The most valuable content is placed at the end, closest to the data it primes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dictionary {
+    content: Box<[u8]>,
+}
+impl Dictionary {
+    pub fn new(content: impl Into<Box<[u8]>>) -> Self {
+        Self {
+            content: content.into(),
+        }
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.content
+    }
+}
+impl Deref for Dictionary {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.content
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    /// Length of the segments copied from the samples into the dictionary. Default: 64
+    pub segment_len: usize,
+    /// Length of the substrings (d-mers) segments are scored by. Default: 8
+    ///
+    /// Should be around the minimum match length used with the dictionary.
+    pub dmer_len: usize,
+}
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            segment_len: 64,
+            dmer_len: 8,
+        }
+    }
+}
+
+/// Trains a dictionary of at most `max_size` bytes from `samples` with default [`Params`].
+pub fn train(samples: &[&[u8]], max_size: usize) -> Dictionary {
+    train_with(samples, max_size, Params::default())
+}
+
+/// Trains a dictionary using cover-style segment selection.
+///
+/// Every d-mer is weighted by the number of samples containing it. The samples are split into
+/// one epoch per dictionary segment, and from each epoch the segment covering the highest total
+/// weight of not yet covered d-mers is selected. Segments selected first end up last.
+pub fn train_with(samples: &[&[u8]], max_size: usize, params: Params) -> Dictionary {
+    let Params {
+        segment_len,
+        dmer_len,
+    } = params;
+    assert!(
+        dmer_len > 0 && segment_len >= dmer_len,
+        "Invalid dictionary training parameters {params:?}."
+    );
+    let mut freqs = HashMap::<&[u8], u32>::new();
+    for sample in samples {
+        let mut seen = HashSet::<&[u8]>::new();
+        for dmer in sample.windows(dmer_len) {
+            if seen.insert(dmer) {
+                *freqs.entry(dmer).or_default() += 1;
+            }
+        }
+    }
+    let total_len: usize = samples.iter().map(|sample| sample.len()).sum();
+    let epochs = max_size.div_ceil(segment_len).max(1);
+    let epoch_len = total_len.div_ceil(epochs).max(1);
+
+    let mut segments: Vec<&[u8]> = vec![];
+    let mut selected_len = 0;
+    let mut epoch = 0..0;
+    let mut epoch_bytes = 0;
+    for (x, sample) in samples.iter().enumerate() {
+        epoch.end = x + 1;
+        epoch_bytes += sample.len();
+        if epoch_bytes < epoch_len && x + 1 < samples.len() {
+            continue;
+        }
+        if selected_len >= max_size {
+            break;
+        }
+        if let Some(segment) = best_segment(&samples[epoch.clone()], &freqs, params) {
+            for dmer in segment.windows(dmer_len) {
+                freqs.insert(dmer, 0);
+            }
+            selected_len += segment.len();
+            segments.push(segment);
+        }
+        epoch = epoch.end..epoch.end;
+        epoch_bytes = 0;
+    }
+    let mut content = Vec::with_capacity(selected_len.min(max_size));
+    for segment in segments.into_iter().rev() {
+        content.extend_from_slice(segment);
+    }
+    content.drain(..content.len().saturating_sub(max_size));
+    Dictionary::new(content)
+}
+
+fn best_segment<'a>(
+    samples: &[&'a [u8]],
+    freqs: &HashMap<&[u8], u32>,
+    Params {
+        segment_len,
+        dmer_len,
+    }: Params,
+) -> Option<&'a [u8]> {
+    let mut best: Option<(u64, &[u8])> = None;
+    for sample in samples {
+        let dmers = sample.windows(dmer_len).collect::<Vec<_>>();
+        let window_dmers = segment_len - dmer_len + 1;
+        let mut active = HashMap::<&[u8], u32>::new();
+        let mut score = 0;
+        for (end, &dmer) in dmers.iter().enumerate() {
+            let count = active.entry(dmer).or_default();
+            *count += 1;
+            if *count == 1 {
+                score += freqs[dmer] as u64;
+            }
+            if end >= window_dmers {
+                let removed = dmers[end - window_dmers];
+                let count = active.get_mut(removed).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    score -= freqs[removed] as u64;
+                }
+            }
+            let start = (end + 1).saturating_sub(window_dmers);
+            if score > 0 && best.is_none_or(|(max, _)| score > max) {
+                best = Some((score, sample.get_clamped(start..start + segment_len)));
+            }
+        }
+    }
+    best.map(|(_, segment)| segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_substrings() {
+        let samples = Vec::from_iter((0..200).map(|x| {
+            format!(
+                r#"{{"id":{x},"name":"user{}","active":true,"roles":["reader"]}}"#,
+                x * 7
+            )
+            .into_bytes()
+        }));
+        let samples = Vec::from_iter(samples.iter().map(Vec::as_slice));
+        let dict = train(&samples, 256);
+        assert!(!dict.is_empty() && dict.len() <= 256);
+        for common in [r#""active":true,"roles":["reader"]}"#, r#","name":"user"#] {
+            assert!(
+                dict.windows(common.len()).any(|w| w == common.as_bytes()),
+                "{common} missing from {:?}",
+                String::from_utf8_lossy(&dict)
+            );
+        }
+        assert!(train(&samples, 16).len() <= 16);
+    }
+    #[test]
+    fn degenerate() {
+        assert!(train(&[], 1024).is_empty());
+        assert!(train(&[b"short"], 1024).is_empty());
+        assert!(train(&[b"abcdefghijkl"], 0).is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+//! Crate-wide error type.
+
+use std::io;
+
+use thiserror::Error;
+
+use crate::{bare, bwt, frame, lz::ConfigError, lzss, rle, snappy};
+
+/// Any error returned by this crate.
+///
+/// Format specific functions return their own error type, which converts into this one. Frame
+/// errors, like dictionary and checksum mismatches, are kept as [`Error::Frame`].
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid config: {0}")]
+    Config(#[from] ConfigError),
+    /// A reference doesn't reach back at all.
+    #[error("reference does not reach back")]
+    ZeroDistance,
+    /// A reference reaches back further than the decoded values and dictionary in the window.
+    #[error("reference distance {back} exceeds the {available} values available")]
+    InvalidDistance { back: usize, available: usize },
+    /// A reference is shorter or longer than `match_lengths` allows.
+    #[error("reference length {len} outside of match_lengths {start}..{end}")]
+    InvalidLength {
+        len: usize,
+        start: usize,
+        end: usize,
+    },
+    /// A reference to a recent distance uses a slot not below [`REP_SLOTS`](crate::lz::REP_SLOTS).
+    #[error("recent distance slot {0} out of range")]
+    InvalidSlot(u8),
+    #[error(transparent)]
+    Frame(#[from] frame::Error),
+    #[error(transparent)]
+    Bare(#[from] bare::Error),
+    #[error(transparent)]
+    Bwt(#[from] bwt::Error),
+    #[error(transparent)]
+    Lzss(#[from] lzss::Error),
+    #[error(transparent)]
+    Rle(#[from] rle::Error),
+    #[error(transparent)]
+    Snappy(#[from] snappy::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// What decoding input that is cut short or corrupted still recovered, e.g. by
+/// [`frame::decompress_partial`].
+#[derive(Debug, Error)]
+#[error("{error} at offset {offset}, after recovering {} bytes", .data.len())]
+pub struct Partial {
+    /// Everything decoded before the error, unchecked.
+    pub data: Vec<u8>,
+    /// Offset into the input of the first unit, like a block or an item, that couldn't be
+    /// decoded.
+    pub offset: usize,
+    #[source]
+    pub error: Error,
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            Error::Frame(err) => err.into(),
+            Error::Config(_) => io::Error::new(io::ErrorKind::InvalidInput, err),
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
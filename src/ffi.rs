@@ -0,0 +1,533 @@
+use std::{
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::{
+    frame::{self, Encoder, PushDecoder},
+    lz::{Config, Strategy},
+};
+
+/// Success.
+pub const SLIDE_OK: i32 = 0;
+/// No output is available until more input is fed.
+pub const SLIDE_NEED_INPUT: i32 = 1;
+/// The frame is complete and all output has been read.
+pub const SLIDE_DONE: i32 = 2;
+/// A required pointer was null.
+pub const SLIDE_ERROR_NULL: i32 = -1;
+/// The config can't be used, see [`Config::validate`].
+pub const SLIDE_ERROR_INVALID_CONFIG: i32 = -2;
+/// The output buffer is too small, the required length was stored in `dst_len`.
+pub const SLIDE_ERROR_DST_TOO_SMALL: i32 = -3;
+/// The input is not a valid frame.
+pub const SLIDE_ERROR_CORRUPT: i32 = -4;
+/// The stream was already finished.
+pub const SLIDE_ERROR_FINISHED: i32 = -5;
+/// An internal error occurred, the context must not be used anymore.
+pub const SLIDE_ERROR_PANIC: i32 = -6;
+
+pub const SLIDE_STRATEGY_GREEDY: u32 = 0;
+pub const SLIDE_STRATEGY_LAZY: u32 = 1;
+pub const SLIDE_STRATEGY_OPTIMAL: u32 = 2;
+
+/// C view of [`Config`], without a dictionary.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlideConfig {
+    /// Default: 0x10000
+    pub window_len: usize,
+    /// Default: 4
+    pub min_match: usize,
+    /// Inclusive. Default: 0xFFF
+    pub max_match: usize,
+    /// One of the `SLIDE_STRATEGY_*` constants. Default: [`SLIDE_STRATEGY_GREEDY`]
+    pub strategy: u32,
+    /// Positions looked ahead by [`SLIDE_STRATEGY_LAZY`]. Default: 0
+    pub lazy_level: usize,
+}
+impl Default for SlideConfig {
+    fn default() -> Self {
+        Self {
+            window_len: 0x10000,
+            min_match: 4,
+            max_match: 0xFFF,
+            strategy: SLIDE_STRATEGY_GREEDY,
+            lazy_level: 0,
+        }
+    }
+}
+impl SlideConfig {
+    fn to_config(self) -> Option<Config<'static>> {
+        let config = Config {
+            max_buffer_len: self.window_len,
+            match_lengths: self.min_match..self.max_match.checked_add(1)?,
+            strategy: match self.strategy {
+                SLIDE_STRATEGY_GREEDY => Strategy::Greedy,
+                SLIDE_STRATEGY_LAZY => Strategy::Lazy(self.lazy_level),
+                SLIDE_STRATEGY_OPTIMAL => Strategy::Optimal,
+                _ => return None,
+            },
+            ..Config::default()
+        };
+        config.validate().ok().map(|()| config)
+    }
+}
+
+/// Runs `f`, turning a panic into [`SLIDE_ERROR_PANIC`] instead of unwinding into C.
+fn guard(f: impl FnOnce() -> i32) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(SLIDE_ERROR_PANIC)
+}
+/// Safety: `ptr` must be null or valid for reads of `len` bytes.
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match len {
+        0 => Some(&[]),
+        _ if ptr.is_null() => None,
+        _ => Some(unsafe { slice::from_raw_parts(ptr, len) }),
+    }
+}
+/// Safety: `ptr` must be null or valid for writes of `len` bytes.
+unsafe fn output<'a>(ptr: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    match len {
+        0 => Some(&mut []),
+        _ if ptr.is_null() => None,
+        _ => Some(unsafe { slice::from_raw_parts_mut(ptr, len) }),
+    }
+}
+/// Copies all of `data` to `dst`, or stores the required length if it doesn't fit.
+fn write_all(data: &[u8], dst: &mut [u8], dst_len: &mut usize) -> i32 {
+    *dst_len = data.len();
+    match dst.get_mut(..data.len()) {
+        Some(dst) => {
+            dst.copy_from_slice(data);
+            SLIDE_OK
+        }
+        None => SLIDE_ERROR_DST_TOO_SMALL,
+    }
+}
+/// Copies as much of `data` as fits into `dst`, returning how much that was.
+fn write_some(data: &[u8], dst: &mut [u8]) -> usize {
+    let len = data.len().min(dst.len());
+    dst[..len].copy_from_slice(&data[..len]);
+    len
+}
+
+/// Returns the default config.
+#[unsafe(no_mangle)]
+pub extern "C" fn slide_config_default() -> SlideConfig {
+    SlideConfig::default()
+}
+
+/// Compresses `src` into a single frame in `dst`, storing its length in `dst_len`.
+///
+/// A null `config` uses the default config.
+///
+/// # Safety
+/// `config` must be null or valid, `src` valid for reads of `src_len` bytes, `dst` valid for
+/// writes of `dst_capacity` bytes and `dst_len` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_compress(
+    config: *const SlideConfig,
+    src: *const u8,
+    src_len: usize,
+    dst: *mut u8,
+    dst_capacity: usize,
+    dst_len: *mut usize,
+) -> i32 {
+    guard(|| {
+        let config = unsafe { config.as_ref() }.copied().unwrap_or_default();
+        let (Some(src), Some(dst), Some(dst_len)) = (
+            unsafe { input(src, src_len) },
+            unsafe { output(dst, dst_capacity) },
+            unsafe { dst_len.as_mut() },
+        ) else {
+            return SLIDE_ERROR_NULL;
+        };
+        let Some(config) = config.to_config() else {
+            return SLIDE_ERROR_INVALID_CONFIG;
+        };
+        write_all(&frame::compress(src, config), dst, dst_len)
+    })
+}
+
+/// Decompresses the frame in `src` into `dst`, storing the decompressed length in `dst_len`.
+///
+/// # Safety
+/// `src` must be valid for reads of `src_len` bytes, `dst` valid for writes of `dst_capacity`
+/// bytes and `dst_len` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_decompress(
+    src: *const u8,
+    src_len: usize,
+    dst: *mut u8,
+    dst_capacity: usize,
+    dst_len: *mut usize,
+) -> i32 {
+    guard(|| {
+        let (Some(src), Some(dst), Some(dst_len)) = (
+            unsafe { input(src, src_len) },
+            unsafe { output(dst, dst_capacity) },
+            unsafe { dst_len.as_mut() },
+        ) else {
+            return SLIDE_ERROR_NULL;
+        };
+        match frame::decompress_into(src, None, dst) {
+            Ok(len) => {
+                *dst_len = len;
+                SLIDE_OK
+            }
+            // Only a full decode tells the required length of frames without a content size.
+            Err(frame::Error::OutputTooSmall) => match frame::decompress(src, None) {
+                Ok(data) => write_all(&data, dst, dst_len),
+                Err(_) => SLIDE_ERROR_CORRUPT,
+            },
+            Err(_) => SLIDE_ERROR_CORRUPT,
+        }
+    })
+}
+
+/// Streaming compression context, see [`slide_encoder_create`].
+pub struct SlideEncoder {
+    encoder: Option<Encoder<'static, Vec<u8>>>,
+    /// Rest of the frame once finished.
+    out: Vec<u8>,
+    pos: usize,
+}
+
+/// Creates a streaming compression context, or returns null if `config` is invalid.
+///
+/// A null `config` uses the default config. Streaming needs a `min_match` of at least 4.
+///
+/// # Safety
+/// `config` must be null or valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_encoder_create(config: *const SlideConfig) -> *mut SlideEncoder {
+    let config = unsafe { config.as_ref() }.copied().unwrap_or_default();
+    panic::catch_unwind(|| {
+        let encoder = Encoder::new(vec![], config.to_config()?).ok()?;
+        Some(Box::into_raw(Box::new(SlideEncoder {
+            encoder: Some(encoder),
+            out: vec![],
+            pos: 0,
+        })))
+    })
+    .ok()
+    .flatten()
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Compresses `src`, making the output available to [`slide_encoder_read`].
+///
+/// # Safety
+/// `encoder` must come from [`slide_encoder_create`] and `src` be valid for reads of `src_len`
+/// bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_encoder_feed(
+    encoder: *mut SlideEncoder,
+    src: *const u8,
+    src_len: usize,
+) -> i32 {
+    guard(|| {
+        let (Some(encoder), Some(src)) =
+            (unsafe { encoder.as_mut() }, unsafe { input(src, src_len) })
+        else {
+            return SLIDE_ERROR_NULL;
+        };
+        let Some(encoder) = &mut encoder.encoder else {
+            return SLIDE_ERROR_FINISHED;
+        };
+        match encoder.write_all(src) {
+            Ok(()) => SLIDE_OK,
+            Err(_) => SLIDE_ERROR_PANIC,
+        }
+    })
+}
+
+/// Compresses all pending input and ends the frame.
+///
+/// # Safety
+/// `encoder` must come from [`slide_encoder_create`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_encoder_finish(encoder: *mut SlideEncoder) -> i32 {
+    guard(|| {
+        let Some(encoder) = (unsafe { encoder.as_mut() }) else {
+            return SLIDE_ERROR_NULL;
+        };
+        let Some(inner) = encoder.encoder.take() else {
+            return SLIDE_ERROR_FINISHED;
+        };
+        match inner.finish() {
+            Ok(out) => {
+                encoder.out = out;
+                SLIDE_OK
+            }
+            Err(_) => SLIDE_ERROR_PANIC,
+        }
+    })
+}
+
+/// Moves up to `dst_capacity` bytes of compressed output into `dst`, storing how many in
+/// `written`.
+///
+/// Returns [`SLIDE_DONE`] once finished and all output has been read.
+///
+/// # Safety
+/// `encoder` must come from [`slide_encoder_create`], `dst` be valid for writes of
+/// `dst_capacity` bytes and `written` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_encoder_read(
+    encoder: *mut SlideEncoder,
+    dst: *mut u8,
+    dst_capacity: usize,
+    written: *mut usize,
+) -> i32 {
+    guard(|| {
+        let (Some(encoder), Some(dst), Some(written)) = (
+            unsafe { encoder.as_mut() },
+            unsafe { output(dst, dst_capacity) },
+            unsafe { written.as_mut() },
+        ) else {
+            return SLIDE_ERROR_NULL;
+        };
+        match &mut encoder.encoder {
+            Some(inner) => {
+                let out = inner.get_mut();
+                *written = write_some(out, dst);
+                out.drain(..*written);
+                SLIDE_OK
+            }
+            None => {
+                *written = write_some(&encoder.out[encoder.pos..], dst);
+                encoder.pos += *written;
+                if encoder.pos == encoder.out.len() {
+                    SLIDE_DONE
+                } else {
+                    SLIDE_OK
+                }
+            }
+        }
+    })
+}
+
+/// Frees a context created by [`slide_encoder_create`]. Null is ignored.
+///
+/// # Safety
+/// `encoder` must be null or come from [`slide_encoder_create`], and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_encoder_destroy(encoder: *mut SlideEncoder) {
+    if !encoder.is_null() {
+        drop(unsafe { Box::from_raw(encoder) });
+    }
+}
+
+/// Streaming decompression context, see [`slide_decoder_create`].
+pub struct SlideDecoder {
+    state: PushDecoder<'static>,
+}
+
+/// Creates a streaming decompression context.
+#[unsafe(no_mangle)]
+pub extern "C" fn slide_decoder_create() -> *mut SlideDecoder {
+    Box::into_raw(Box::new(SlideDecoder {
+        state: PushDecoder::new(None),
+    }))
+}
+
+/// Buffers more of the compressed frame.
+///
+/// # Safety
+/// `decoder` must come from [`slide_decoder_create`] and `src` be valid for reads of `src_len`
+/// bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_decoder_feed(
+    decoder: *mut SlideDecoder,
+    src: *const u8,
+    src_len: usize,
+) -> i32 {
+    guard(|| {
+        let (Some(decoder), Some(src)) =
+            (unsafe { decoder.as_mut() }, unsafe { input(src, src_len) })
+        else {
+            return SLIDE_ERROR_NULL;
+        };
+        decoder.state.feed(src);
+        SLIDE_OK
+    })
+}
+
+/// Decompresses up to `dst_capacity` bytes into `dst`, storing how many in `written`.
+///
+/// Returns [`SLIDE_NEED_INPUT`] if nothing can be decompressed before more input is fed, and
+/// [`SLIDE_DONE`] once the frame is complete and all output has been read.
+///
+/// # Safety
+/// `decoder` must come from [`slide_decoder_create`], `dst` be valid for writes of
+/// `dst_capacity` bytes and `written` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_decoder_read(
+    decoder: *mut SlideDecoder,
+    dst: *mut u8,
+    dst_capacity: usize,
+    written: *mut usize,
+) -> i32 {
+    guard(|| {
+        let (Some(decoder), Some(dst), Some(written)) = (
+            unsafe { decoder.as_mut() },
+            unsafe { output(dst, dst_capacity) },
+            unsafe { written.as_mut() },
+        ) else {
+            return SLIDE_ERROR_NULL;
+        };
+        *written = 0;
+        if dst.is_empty() {
+            return SLIDE_OK;
+        }
+        match decoder.state.read(dst) {
+            Ok(Some(0)) => SLIDE_DONE,
+            Ok(Some(len)) => {
+                *written = len;
+                SLIDE_OK
+            }
+            Ok(None) => SLIDE_NEED_INPUT,
+            Err(_) => SLIDE_ERROR_CORRUPT,
+        }
+    })
+}
+
+/// Frees a context created by [`slide_decoder_create`]. Null is ignored.
+///
+/// # Safety
+/// `decoder` must be null or come from [`slide_decoder_create`], and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn slide_decoder_destroy(decoder: *mut SlideDecoder) {
+    if !decoder.is_null() {
+        drop(unsafe { Box::from_raw(decoder) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Vec<u8> {
+        Vec::from_iter((0..0x30000u32).map(|x| (x / 5 % 199) as u8))
+    }
+
+    #[test]
+    fn oneshot() {
+        let data = data();
+        let mut frame = vec![0; 16];
+        let mut len = 0;
+        let compress = |frame: &mut Vec<u8>, len: &mut usize| unsafe {
+            slide_compress(
+                ptr::null(),
+                data.as_ptr(),
+                data.len(),
+                frame.as_mut_ptr(),
+                frame.len(),
+                len,
+            )
+        };
+        assert_eq!(compress(&mut frame, &mut len), SLIDE_ERROR_DST_TOO_SMALL);
+        frame.resize(len, 0);
+        assert_eq!(compress(&mut frame, &mut len), SLIDE_OK);
+        assert_eq!(frame.len(), len);
+
+        let mut decoded = vec![0; data.len()];
+        let status = unsafe {
+            slide_decompress(
+                frame.as_ptr(),
+                frame.len(),
+                decoded.as_mut_ptr(),
+                decoded.len(),
+                &mut len,
+            )
+        };
+        assert_eq!((status, len), (SLIDE_OK, data.len()));
+        assert_eq!(decoded, data);
+        let status = unsafe {
+            slide_decompress(
+                frame.as_ptr(),
+                frame.len(),
+                decoded.as_mut_ptr(),
+                decoded.len() - 1,
+                &mut len,
+            )
+        };
+        assert_eq!((status, len), (SLIDE_ERROR_DST_TOO_SMALL, data.len()));
+
+        frame[8] ^= 0xFF;
+        let status = unsafe {
+            slide_decompress(
+                frame.as_ptr(),
+                frame.len(),
+                decoded.as_mut_ptr(),
+                decoded.len(),
+                &mut len,
+            )
+        };
+        assert_eq!(status, SLIDE_ERROR_CORRUPT);
+        let config = SlideConfig {
+            min_match: 0,
+            ..slide_config_default()
+        };
+        let status =
+            unsafe { slide_compress(&config, ptr::null(), 0, ptr::null_mut(), 0, &mut len) };
+        assert_eq!(status, SLIDE_ERROR_INVALID_CONFIG);
+        let status = unsafe { slide_decompress(ptr::null(), 1, ptr::null_mut(), 0, &mut len) };
+        assert_eq!(status, SLIDE_ERROR_NULL);
+    }
+    #[test]
+    fn streaming() {
+        let data = data();
+        let mut buf = [0; 0x1000];
+        let mut written = 0;
+        let mut frame = vec![];
+        unsafe {
+            let encoder = slide_encoder_create(ptr::null());
+            assert!(!encoder.is_null());
+            for chunk in data.chunks(0x7777) {
+                assert_eq!(
+                    slide_encoder_feed(encoder, chunk.as_ptr(), chunk.len()),
+                    SLIDE_OK
+                );
+                assert_eq!(
+                    slide_encoder_read(encoder, buf.as_mut_ptr(), buf.len(), &mut written),
+                    SLIDE_OK
+                );
+                frame.extend_from_slice(&buf[..written]);
+            }
+            assert_eq!(slide_encoder_finish(encoder), SLIDE_OK);
+            assert_eq!(slide_encoder_finish(encoder), SLIDE_ERROR_FINISHED);
+            while slide_encoder_read(encoder, buf.as_mut_ptr(), buf.len(), &mut written) == SLIDE_OK
+            {
+                frame.extend_from_slice(&buf[..written]);
+            }
+            frame.extend_from_slice(&buf[..written]);
+            slide_encoder_destroy(encoder);
+        }
+        assert_eq!(frame::decompress(&frame, None).unwrap(), data);
+
+        let mut decoded = vec![];
+        unsafe {
+            let decoder = slide_decoder_create();
+            let mut chunks = frame.chunks(0x333);
+            loop {
+                match slide_decoder_read(decoder, buf.as_mut_ptr(), buf.len(), &mut written) {
+                    SLIDE_OK => decoded.extend_from_slice(&buf[..written]),
+                    SLIDE_NEED_INPUT => {
+                        let chunk = chunks.next().unwrap();
+                        slide_decoder_feed(decoder, chunk.as_ptr(), chunk.len());
+                    }
+                    status => {
+                        assert_eq!(status, SLIDE_DONE);
+                        break;
+                    }
+                }
+            }
+            slide_decoder_destroy(decoder);
+        }
+        assert_eq!(decoded, data);
+    }
+}
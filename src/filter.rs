@@ -0,0 +1,126 @@
+//! Reversible filters applied to the input before matching and undone after decoding.
+//!
+//! Both filters replace every byte with its difference to the byte `width` positions earlier, so
+//! slowly changing fields of fixed size records, like sensor readings or sorted integers, turn
+//! into long runs of small, repeating values.
+
+use std::num::NonZero;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Subtracts the byte `stride` positions earlier (wrapping), for numeric fields.
+    Delta(NonZero<usize>),
+    /// XORs with the byte one record of the given length earlier, for flags and bit fields.
+    Xor(NonZero<usize>),
+}
+impl Filter {
+    /// Distance to the byte each byte is combined with.
+    pub fn width(self) -> usize {
+        match self {
+            Filter::Delta(width) | Filter::Xor(width) => width.get(),
+        }
+    }
+    /// Identifier stored in frame headers.
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            Filter::Delta(_) => 0,
+            Filter::Xor(_) => 1,
+        }
+    }
+    pub(crate) fn from_id(id: u8, width: usize) -> Option<Self> {
+        let width = NonZero::new(width)?;
+        match id {
+            0 => Some(Filter::Delta(width)),
+            1 => Some(Filter::Xor(width)),
+            _ => None,
+        }
+    }
+}
+
+/// State of a [`Filter`] applied to consecutive chunks of one input.
+///
+/// Chunks may be split anywhere, the first `width` bytes of the input are combined with zero.
+#[derive(Debug, Clone)]
+pub struct Filtering {
+    filter: Filter,
+    /// The last `width` unfiltered bytes, indexed by position modulo `width`.
+    history: Vec<u8>,
+    pos: usize,
+}
+impl Filtering {
+    pub fn new(filter: Filter) -> Self {
+        Self {
+            filter,
+            history: vec![0; filter.width()],
+            pos: 0,
+        }
+    }
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+    /// Filters the next chunk of input in place.
+    pub fn encode(&mut self, data: &mut [u8]) {
+        for value in data {
+            let prev = self.next(*value);
+            *value = match self.filter {
+                Filter::Delta(_) => value.wrapping_sub(prev),
+                Filter::Xor(_) => *value ^ prev,
+            };
+        }
+    }
+    /// Restores the next chunk of filtered input in place.
+    pub fn decode(&mut self, data: &mut [u8]) {
+        for value in data {
+            *value = match self.filter {
+                Filter::Delta(_) => value.wrapping_add(self.history[self.pos]),
+                Filter::Xor(_) => *value ^ self.history[self.pos],
+            };
+            self.next(*value);
+        }
+    }
+    /// Records the unfiltered `value`, returning the one `width` positions before it.
+    fn next(&mut self, value: u8) -> u8 {
+        let prev = std::mem::replace(&mut self.history[self.pos], value);
+        self.pos = (self.pos + 1) % self.history.len();
+        prev
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[quickcheck]
+    fn roundtrip(data: Vec<u8>, width: u8, xor: bool, split: usize) {
+        let width = NonZero::new(width as usize % 16 + 1).unwrap();
+        let filter = match xor {
+            true => Filter::Xor(width),
+            false => Filter::Delta(width),
+        };
+        let mut filtered = data.clone();
+        Filtering::new(filter).encode(&mut filtered);
+        // Chunked application matches applying it at once.
+        let split = split % (data.len() + 1);
+        let mut chunked = data.clone();
+        let (head, tail) = chunked.split_at_mut(split);
+        let mut filtering = Filtering::new(filter);
+        filtering.encode(head);
+        filtering.encode(tail);
+        assert_eq!(chunked, filtered);
+
+        let (head, tail) = filtered.split_at_mut(split);
+        let mut filtering = Filtering::new(filter);
+        filtering.decode(head);
+        filtering.decode(tail);
+        assert_eq!(filtered, data);
+    }
+
+    #[test]
+    fn delta() {
+        let mut data = Vec::from_iter((0..0x100u16).flat_map(|x| (x * 3).to_le_bytes()));
+        Filtering::new(Filter::Delta(NonZero::new(2).unwrap())).encode(&mut data);
+        assert_eq!(data[..2], [0, 0]);
+        assert!(data[2..].chunks(2).all(|x| x == [3, 0] || x == [3, 1]));
+    }
+}
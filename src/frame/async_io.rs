@@ -0,0 +1,230 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use super::{
+    Flush,
+    stream::{EncoderState, PushDecoder},
+};
+use crate::lz::Config;
+
+/// Asynchronous counterpart of [`Encoder`](super::Encoder), compressing into a [`AsyncWrite`].
+///
+/// The frame is completed by closing the encoder. Unlike the blocking encoder, dropping it
+/// without closing leaves the frame incomplete.
+pub struct AsyncEncoder<'a, W, const N: usize = 4> {
+    writer: W,
+    state: EncoderState<'a, N>,
+    /// Bytes of `state.out` already written to `writer`.
+    written_out: usize,
+}
+impl<'a, W: AsyncWrite + Unpin, const N: usize> AsyncEncoder<'a, W, N> {
+    /// Starts a new frame, whose header is written along with the first block.
+    pub fn new(writer: W, config: Config<'a>) -> io::Result<Self> {
+        Self::with_header(writer, config, None)
+    }
+    /// Like [`AsyncEncoder::new`], but records `content_size` in the header.
+    ///
+    /// Writing more than `content_size` bytes, or closing after fewer, fails with
+    /// [`io::ErrorKind::InvalidInput`].
+    pub fn with_content_size(
+        writer: W,
+        config: Config<'a>,
+        content_size: usize,
+    ) -> io::Result<Self> {
+        Self::with_header(writer, config, Some(content_size))
+    }
+    fn with_header(writer: W, config: Config<'a>, content_size: Option<usize>) -> io::Result<Self> {
+        Ok(Self {
+            writer,
+            state: EncoderState::new(config, content_size, None)?,
+            written_out: 0,
+        })
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+    /// Writes everything compressed so far to the inner writer.
+    fn poll_write_out(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written_out < self.state.out.len() {
+            let out = &self.state.out[self.written_out..];
+            match ready!(Pin::new(&mut self.writer).poll_write(cx, out))? {
+                0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                len => self.written_out += len,
+            }
+        }
+        self.state.out.clear();
+        self.written_out = 0;
+        Poll::Ready(Ok(()))
+    }
+    /// Emits all pending input, see [`Flush`].
+    pub fn poll_flush_with(&mut self, cx: &mut Context<'_>, mode: Flush) -> Poll<io::Result<()>> {
+        if !self.state.is_finished() {
+            self.state.flush(mode);
+        }
+        ready!(self.poll_write_out(cx))?;
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+}
+impl<W: AsyncWrite + Unpin, const N: usize> AsyncWrite for AsyncEncoder<'_, W, N> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        Poll::Ready(this.state.write(buf))
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_flush_with(cx, Flush::Sync)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.state.is_finished() {
+            this.state.finish()?;
+        }
+        ready!(this.poll_write_out(cx))?;
+        Pin::new(&mut this.writer).poll_close(cx)
+    }
+}
+
+/// Asynchronous counterpart of [`Decoder`](super::Decoder), decompressing from a [`AsyncRead`].
+///
+/// Input is read in chunks, so the decoder may consume bytes past the end of the frame.
+pub struct AsyncDecoder<'a, R> {
+    reader: R,
+    state: PushDecoder<'a>,
+    eof: bool,
+}
+impl<'a, R: AsyncRead + Unpin> AsyncDecoder<'a, R> {
+    /// Creates a decoder, using `dictionary` if the frame was compressed with one.
+    ///
+    /// The header is only read, and checked, by the first read.
+    pub fn new(reader: R, dictionary: Option<&'a [u8]>) -> Self {
+        Self {
+            reader,
+            state: PushDecoder::new(dictionary),
+            eof: false,
+        }
+    }
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncDecoder<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            match this.state.read(buf)? {
+                Some(len) => return Poll::Ready(Ok(len)),
+                None if this.eof => return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into())),
+                None => {}
+            }
+            let mut chunk = [0; 0x2000];
+            match ready!(Pin::new(&mut this.reader).poll_read(cx, &mut chunk))? {
+                0 => this.eof = true,
+                len => this.state.feed(&chunk[..len]),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{BLOCK_LEN, compress, tests::shared_config as config};
+    use futures::{
+        executor::block_on,
+        io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    /// Reader handing out at most 3 bytes per read, returning pending every other poll.
+    struct Trickle<'a>(&'a [u8], bool);
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.1 = !self.1;
+            if self.1 {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let len = buf.len().min(self.0.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 + 100).map(|x| (x / 7 % 251) as u8));
+        block_on(async {
+            let mut encoder =
+                AsyncEncoder::<_>::with_content_size(vec![], config(), data.len()).unwrap();
+            for chunk in data.chunks(1000) {
+                encoder.write_all(chunk).await.unwrap();
+            }
+            encoder.close().await.unwrap();
+            let frame = encoder.into_inner();
+            assert_eq!(frame, compress(&data, config()));
+
+            let mut decoded = vec![];
+            AsyncDecoder::new(Trickle(&frame, false), None)
+                .read_to_end(&mut decoded)
+                .await
+                .unwrap();
+            assert_eq!(decoded, data);
+
+            let err = AsyncDecoder::new(Trickle(&frame[..frame.len() - 1], false), None)
+                .read_to_end(&mut vec![])
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+            let mut bad = frame.clone();
+            bad[0] = b'X';
+            let err = AsyncDecoder::new(bad.as_slice(), None)
+                .read_to_end(&mut vec![])
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+    }
+    #[test]
+    fn flush() {
+        block_on(async {
+            let mut encoder = AsyncEncoder::<_>::new(vec![], config()).unwrap();
+            encoder.write_all(b"hello hello ").await.unwrap();
+            encoder.flush().await.unwrap();
+            let mut decoder = AsyncDecoder::new(encoder.get_ref().as_slice(), None);
+            let mut buf = [0; 12];
+            decoder.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"hello hello ");
+
+            let mut encoder = AsyncEncoder::<_>::with_content_size(vec![], config(), 4).unwrap();
+            encoder.write_all(b"abc").await.unwrap();
+            let err = encoder.close().await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        });
+    }
+}
@@ -0,0 +1,340 @@
+use std::{borrow::Cow, num::NonZero};
+
+use super::{BLOCK_LEN, Error, check_len, read_varint, write_varint};
+use crate::{
+    huffman,
+    lz::{CostModel, Item, REP_SLOTS, varint_len},
+    trace::{enter, event},
+    util::histogram::Histogram,
+};
+
+const KIND_RAW: usize = 0;
+const KIND_REF: usize = 1;
+const KIND_REP_REF: usize = 2;
+const KIND_RUN: usize = 3;
+
+/// The block holds its content as is.
+const MODE_STORED: u8 = 0;
+/// The block holds its streams laid out by [`Streams::write`]. This is coding every stream
+/// with the built-in table, which gives each byte an 8 bit code.
+const MODE_FIXED: u8 = 1;
+/// The block holds the symbol counts of its literal, length and distance streams (varint each),
+/// followed by every stream Huffman coded with a table of its own, see [`huffman`].
+const MODE_DYNAMIC: u8 = 2;
+/// Every stream is coded as bytes.
+const ALPHABET: usize = 256;
+
+/// A block's items, split into separate literal, length and distance streams.
+///
+/// Laid out as the varint lengths of the literal and length streams, followed by the
+/// literal, length and distance streams:
+/// - literals: the values of raw items, and the value of runs.
+/// - lengths: every item's `len << 2 | kind` (varint), kind being raw, ref, rep ref or run.
+/// - distances: the distance of refs (varint) and the slot of rep refs (u8).
+#[derive(Debug, Default)]
+pub(super) struct Streams {
+    pub literals: Vec<u8>,
+    pub lengths: Vec<u8>,
+    pub distances: Vec<u8>,
+}
+impl Streams {
+    pub fn clear(&mut self) {
+        self.literals.clear();
+        self.lengths.clear();
+        self.distances.clear();
+    }
+    pub fn push(&mut self, item: Item<u8>) {
+        let (kind, len) = match item {
+            Item::Raw(raw) => {
+                self.literals.extend_from_slice(&raw);
+                (KIND_RAW, raw.len())
+            }
+            Item::Ref { back, len } => {
+                write_varint(&mut self.distances, back.get());
+                (KIND_REF, len)
+            }
+            Item::RepRef { slot, len } => {
+                self.distances.push(slot);
+                (KIND_REP_REF, len)
+            }
+            Item::Run { value, len } => {
+                self.literals.push(value);
+                (KIND_RUN, len)
+            }
+        };
+        write_varint(&mut self.lengths, len << 2 | kind);
+    }
+    pub fn write(&self, out: &mut Vec<u8>) {
+        write_varint(out, self.literals.len());
+        write_varint(out, self.lengths.len());
+        out.extend_from_slice(&self.literals);
+        out.extend_from_slice(&self.lengths);
+        out.extend_from_slice(&self.distances);
+    }
+}
+
+/// Cost of items in the stream layout of frame blocks, before any further compression of the
+/// block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockCost;
+impl CostModel<u8> for BlockCost {
+    fn raw(&self, len: usize) -> usize {
+        8 * varint_len(len << 2 | KIND_RAW)
+    }
+    fn literal(&self, _: &u8) -> usize {
+        8
+    }
+    fn reference(&self, back: NonZero<usize>, len: usize) -> usize {
+        8 * (varint_len(back.get()) + varint_len(len << 2 | KIND_REF))
+    }
+    fn rep_reference(&self, _: u8, len: usize) -> usize {
+        8 * (1 + varint_len(len << 2 | KIND_REP_REF))
+    }
+    fn run(&self, _: &u8, len: usize) -> usize {
+        8 * (1 + varint_len(len << 2 | KIND_RUN))
+    }
+}
+
+/// Writes the block payload of `streams`, which encode `data`, choosing whichever of stored,
+/// fixed and dynamic mode is cheapest. The payload starts with the mode byte.
+///
+/// Dynamic mode is only tried if the entropy of the streams leaves it a chance.
+pub(super) fn write_block(streams: &Streams, data: &[u8], out: &mut Vec<u8>) {
+    let _span = enter!(TRACE, "serialize", bytes = data.len());
+    let start = out.len();
+    out.push(MODE_FIXED);
+    streams.write(out);
+    let coded = [&streams.literals, &streams.lengths, &streams.distances];
+    // Huffman codes can't beat the entropy, and their tables only add to it.
+    let bound = 1
+        + (coded.iter())
+            .map(|stream| varint_len(stream.len()) + entropy_bits(stream) / 8)
+            .sum::<usize>();
+    if bound < out.len() - start {
+        let mut dynamic = vec![MODE_DYNAMIC];
+        for stream in coded {
+            write_varint(&mut dynamic, stream.len());
+        }
+        for stream in coded {
+            let symbols = Vec::from_iter(stream.iter().map(|&byte| byte as u16));
+            huffman::encode(&symbols, ALPHABET, &mut dynamic);
+        }
+        if dynamic.len() < out.len() - start {
+            out.truncate(start);
+            out.extend(dynamic);
+        }
+    }
+    if 1 + data.len() < out.len() - start {
+        out.truncate(start);
+        out.push(MODE_STORED);
+        out.extend_from_slice(data);
+    }
+    event!(
+        TRACE,
+        mode = out[start],
+        compressed = out.len() - start,
+        "block written"
+    );
+}
+
+/// Entropy of the bytes of `stream` in whole bits, rounded down with some slack for rounding
+/// errors so it never exceeds the bits of a Huffman code.
+fn entropy_bits(stream: &[u8]) -> usize {
+    (Histogram::of_bytes(stream).entropy_bits() * (1.0 - 1e-9)) as usize
+}
+
+/// Splits a block written by [`write_block`] back into its items, borrowing their literals
+/// unless the block is dynamic.
+///
+/// Fails with [`Error::LengthOutOfBounds`] if the items add up to more than [`BLOCK_LEN`].
+pub(super) fn read_items(block: &[u8]) -> Result<Vec<Item<'_, u8>>, Error> {
+    let (&mode, block) = block.split_first().ok_or(Error::Malformed)?;
+    match mode {
+        MODE_STORED if block.is_empty() => Ok(vec![]),
+        MODE_STORED => {
+            check_len(block.len(), BLOCK_LEN)?;
+            Ok(vec![Item::from(block)])
+        }
+        MODE_FIXED => {
+            let (literals_len, block) = read_varint(block)?;
+            let (lengths_len, block) = read_varint(block)?;
+            let (literals, block) = split(block, literals_len)?;
+            let (lengths, distances) = split(block, lengths_len)?;
+            parse_streams(literals, lengths, distances)
+        }
+        MODE_DYNAMIC => {
+            let mut counts = [0; 3];
+            let mut block = block;
+            for count in &mut counts {
+                (*count, block) = read_varint(block)?;
+            }
+            let mut streams = counts.map(|_| vec![]);
+            for (stream, count) in streams.iter_mut().zip(counts) {
+                let symbols;
+                (symbols, block) =
+                    huffman::decode(block, ALPHABET, count).ok_or(Error::Malformed)?;
+                *stream = Vec::from_iter(symbols.into_iter().map(|symbol| symbol as u8));
+            }
+            if !block.is_empty() {
+                return Err(Error::Malformed);
+            }
+            let [literals, lengths, distances] = &streams;
+            let items = parse_streams(literals, lengths, distances)?;
+            Ok(Vec::from_iter(items.into_iter().map(Item::into_owned)))
+        }
+        _ => Err(Error::Malformed),
+    }
+}
+
+/// Parses the items of a block's streams, borrowing their literals.
+fn parse_streams<'a>(
+    mut literals: &'a [u8],
+    mut lengths: &[u8],
+    mut distances: &[u8],
+) -> Result<Vec<Item<'a, u8>>, Error> {
+    let mut items = vec![];
+    let mut total = 0usize;
+    while !lengths.is_empty() {
+        let value;
+        (value, lengths) = read_varint(lengths).map_err(|_| Error::Malformed)?;
+        let len = value >> 2;
+        total = total.checked_add(len).ok_or(Error::Malformed)?;
+        check_len(total, BLOCK_LEN)?;
+        items.push(match value & 3 {
+            KIND_RAW => {
+                let raw;
+                (raw, literals) = split(literals, len)?;
+                Item::Raw(Cow::Borrowed(raw))
+            }
+            KIND_REF => {
+                let back;
+                (back, distances) = read_varint(distances).map_err(|_| Error::Malformed)?;
+                let back = NonZero::new(back).ok_or(Error::Malformed)?;
+                Item::Ref { back, len }
+            }
+            KIND_REP_REF => {
+                let (&slot, rest) = distances.split_first().ok_or(Error::Malformed)?;
+                if slot as usize >= REP_SLOTS {
+                    return Err(Error::Malformed);
+                }
+                distances = rest;
+                Item::RepRef { slot, len }
+            }
+            _ => {
+                let (&value, rest) = literals.split_first().ok_or(Error::Malformed)?;
+                literals = rest;
+                Item::Run { value, len }
+            }
+        });
+    }
+    if !literals.is_empty() || !distances.is_empty() {
+        return Err(Error::Malformed);
+    }
+    Ok(items)
+}
+
+fn split(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), Error> {
+    data.split_at_checked(len).ok_or(Error::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let items = vec![
+            Item::from(b"abc"),
+            Item::Ref {
+                back: NonZero::new(300).unwrap(),
+                len: 4,
+            },
+            Item::RepRef { slot: 3, len: 70 },
+            Item::Run {
+                value: b'x',
+                len: 1000,
+            },
+            Item::from(b"d"),
+        ];
+        let mut streams = Streams::default();
+        for item in items.clone() {
+            streams.push(item);
+        }
+        assert_eq!(streams.literals, b"abcxd");
+        assert_eq!(streams.lengths, [12, 17, 0x9A, 0x02, 0xA3, 0x1F, 4]);
+        assert_eq!(streams.distances, [0xAC, 0x02, 3]);
+        let cost: usize = items.iter().map(|item| BlockCost.item(item)).sum();
+        let len = streams.literals.len() + streams.lengths.len() + streams.distances.len();
+        assert_eq!(cost, 8 * len);
+        let mut block = vec![MODE_FIXED];
+        streams.write(&mut block);
+        assert_eq!(block[..3], [MODE_FIXED, 5, 7]);
+        assert_eq!(read_items(&block).unwrap(), items);
+        for truncated in 0..block.len() {
+            assert!(read_items(&block[..truncated]).is_err());
+        }
+    }
+    #[test]
+    fn modes() {
+        let block_of = |items: Vec<Item<u8>>| {
+            let data = Vec::from_iter(
+                items
+                    .iter()
+                    .flat_map(|item| item.as_raw().unwrap().to_vec()),
+            );
+            let mut streams = Streams::default();
+            for item in items.clone() {
+                streams.push(item);
+            }
+            let mut block = vec![];
+            write_block(&streams, &data, &mut block);
+            let decoded = read_items(&block).unwrap();
+            let decoded = decoded
+                .iter()
+                .flat_map(|item| item.as_raw().unwrap().to_vec());
+            assert_eq!(Vec::from_iter(decoded), data);
+            block
+        };
+        // A single raw item costs its header, so is cheaper stored.
+        assert_eq!(
+            block_of(vec![Item::from(b"abc")]),
+            [MODE_STORED, b'a', b'b', b'c']
+        );
+        // Few distinct literals are cheaper with their own codes.
+        let text = Vec::from_iter((0..4000).map(|x| b"ab"[x % 7 / 6]));
+        let block = block_of(Vec::from_iter(text.chunks(100).map(Item::from)));
+        assert_eq!(block[0], MODE_DYNAMIC);
+        assert!(block.len() < text.len() / 2, "{}", block.len());
+        for truncated in 1..block.len() {
+            assert!(read_items(&block[..truncated]).is_err());
+        }
+
+        assert_eq!(read_items(&[]), Err(Error::Malformed));
+        assert_eq!(read_items(&[3]), Err(Error::Malformed));
+        assert_eq!(read_items(&[MODE_DYNAMIC, 0, 0, 0]), Err(Error::Malformed));
+        let stored = [&[MODE_STORED][..], &[0; BLOCK_LEN + 1]].concat();
+        assert_eq!(
+            read_items(&stored),
+            Err(Error::LengthOutOfBounds {
+                len: BLOCK_LEN + 1,
+                max: BLOCK_LEN
+            })
+        );
+        // Two runs each within a block, but not together.
+        let mut lengths = vec![];
+        for _ in 0..2 {
+            write_varint(&mut lengths, BLOCK_LEN << 2 | KIND_RUN);
+        }
+        let mut block = vec![MODE_FIXED, 2];
+        write_varint(&mut block, lengths.len());
+        block.extend([0, 0]);
+        block.extend(lengths);
+        assert_eq!(
+            read_items(&block),
+            Err(Error::LengthOutOfBounds {
+                len: 2 * BLOCK_LEN,
+                max: BLOCK_LEN
+            })
+        );
+    }
+}
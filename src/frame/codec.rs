@@ -0,0 +1,204 @@
+use std::io;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec;
+
+use super::{
+    Error, Header, compress_blocks, compress_with, decompress, fit_frame, read_varint,
+    stream::BlockDecoder, write_header, write_varint,
+};
+use crate::{lz::Config, search_buffer::SearchBuffer};
+
+/// Default for the largest message [`FrameCodec`] accepts, compressed.
+pub const MAX_MESSAGE_LEN: usize = 0x800000;
+
+/// Codec for framed transports, compressing every message into a varint length prefixed payload.
+///
+/// Without a shared window, every payload is a complete frame. With one, all messages form a
+/// single frame: the first payload starts with its header and every payload holds the blocks
+/// of its message, which may reference earlier messages. Both ends must then be created with
+/// [`FrameCodec::shared`] and see every message in order.
+pub struct FrameCodec<'a, const N: usize = 4> {
+    config: Config<'a>,
+    shared: bool,
+    max_message_len: usize,
+    search_buffer: Option<SearchBuffer<u8, N>>,
+    blocks: Option<BlockDecoder<'a>>,
+}
+impl<'a, const N: usize> FrameCodec<'a, N> {
+    /// Codec compressing every message independently.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if `config` is invalid for a key width of `N`.
+    pub fn new(config: Config<'a>) -> io::Result<Self> {
+        Self::with_window(config, false)
+    }
+    /// Codec sharing the window across all messages in one direction.
+    pub fn shared(config: Config<'a>) -> io::Result<Self> {
+        Self::with_window(config, true)
+    }
+    fn with_window(config: Config<'a>, shared: bool) -> io::Result<Self> {
+        config
+            .validate_for(N)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        Ok(Self {
+            config: fit_frame(config, N),
+            shared,
+            max_message_len: MAX_MESSAGE_LEN,
+            search_buffer: None,
+            blocks: None,
+        })
+    }
+    /// Sets the largest compressed payload accepted by the decoder. Default: [`MAX_MESSAGE_LEN`]
+    pub fn max_message_len(mut self, max_message_len: usize) -> Self {
+        self.max_message_len = max_message_len;
+        self
+    }
+    fn decode_shared(&mut self, mut payload: &[u8]) -> Result<Vec<u8>, Error> {
+        let blocks = match &mut self.blocks {
+            Some(blocks) => blocks,
+            None => {
+                let (header, rest) = Header::read(payload)?;
+                payload = rest;
+                self.blocks
+                    .insert(BlockDecoder::new(header, self.config.dictionary)?)
+            }
+        };
+        let mut out = vec![];
+        while !payload.is_empty() {
+            let (len, rest) = read_varint(payload)?;
+            let (block, rest) = rest
+                .split_at_checked(len)
+                .filter(|_| len > 0)
+                .ok_or(Error::Malformed)?;
+            blocks.block(block, &mut out)?;
+            payload = rest;
+        }
+        Ok(out)
+    }
+}
+impl<const N: usize> codec::Encoder<&[u8]> for FrameCodec<'_, N> {
+    type Error = io::Error;
+    fn encode(&mut self, message: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+        let payload = if self.shared {
+            let mut payload = vec![];
+            let mut config = self.config.clone();
+            let search_buffer = match &mut self.search_buffer {
+                Some(search_buffer) => {
+                    // The dictionary only primes the window once.
+                    config.dictionary = None;
+                    search_buffer
+                }
+                None => {
+                    write_header(&mut payload, &config, 0, None, None);
+                    self.search_buffer.insert(SearchBuffer::new())
+                }
+            };
+            compress_blocks(search_buffer, message, config, &mut payload);
+            payload
+        } else {
+            compress_with::<N>(message, self.config.clone())
+        };
+        let mut prefix = vec![];
+        write_varint(&mut prefix, payload.len());
+        dst.reserve(prefix.len() + payload.len());
+        dst.put_slice(&prefix);
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
+impl<const N: usize> codec::Decoder for FrameCodec<'_, N> {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Vec<u8>>> {
+        let (len, rest) = match read_varint(src) {
+            Ok(prefix) => prefix,
+            Err(Error::UnexpectedEof) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        if len > self.max_message_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message of {len} bytes exceeds the maximum"),
+            ));
+        }
+        let prefix_len = src.len() - rest.len();
+        if rest.len() < len {
+            src.reserve(prefix_len + len - src.len());
+            return Ok(None);
+        }
+        src.advance(prefix_len);
+        let payload = src.split_to(len);
+        let message = if self.shared {
+            self.decode_shared(&payload)
+        } else {
+            decompress(&payload, self.config.dictionary)
+        };
+        // A complete payload can't end early.
+        message.map(Some).map_err(|err| match err {
+            Error::UnexpectedEof => Error::Malformed.into(),
+            err => err.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::tests::shared_config as config;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn roundtrip() {
+        let messages = Vec::from_iter((0..20).map(|x| {
+            format!(r#"{{"id":{x},"kind":"measurement","value":{}}}"#, x % 7).into_bytes()
+        }));
+        let mut sizes = vec![];
+        for shared in [false, true] {
+            let codec = || {
+                if shared {
+                    FrameCodec::<4>::shared(config()).unwrap()
+                } else {
+                    FrameCodec::<4>::new(config()).unwrap()
+                }
+            };
+            let (mut encoder, mut decoder) = (codec(), codec());
+            let mut wire = BytesMut::new();
+            for message in &messages {
+                encoder.encode(message.as_slice(), &mut wire).unwrap();
+            }
+            encoder.encode(b"".as_slice(), &mut wire).unwrap();
+            sizes.push(wire.len());
+            // Arriving byte by byte, messages are only decoded once complete.
+            let mut src = BytesMut::new();
+            let mut decoded = vec![];
+            for byte in wire {
+                src.put_u8(byte);
+                decoded.extend(decoder.decode(&mut src).unwrap());
+            }
+            assert!(src.is_empty());
+            assert_eq!(decoded.pop().unwrap(), b"");
+            assert_eq!(decoded, messages);
+        }
+        assert!(sizes[1] < sizes[0] / 2, "{sizes:?}");
+    }
+    #[test]
+    fn limits() {
+        let mut codec = FrameCodec::<4>::new(config()).unwrap().max_message_len(8);
+        let mut wire = BytesMut::new();
+        codec
+            .encode(b"too long to fit".as_slice(), &mut wire)
+            .unwrap();
+        let err = codec.decode(&mut wire).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut codec = FrameCodec::<4>::shared(config()).unwrap();
+        let mut wire = BytesMut::new();
+        codec.encode(b"message".as_slice(), &mut wire).unwrap();
+        let len = wire.len();
+        // Cut the payload short while keeping the length prefix consistent.
+        wire.truncate(len - 2);
+        wire[0] -= 2;
+        let err = codec.decode(&mut wire).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, DeserializeOwned, SeqAccess, Visitor},
+    ser,
+};
+
+use super::{compress, decompress};
+use crate::lz::Config;
+
+/// Value serialized compressed, for marking large fields of a larger structure.
+///
+/// Serializes `T` with postcard, compresses the bytes into a frame under the default [`Config`]
+/// and serializes the frame as bytes. Deserializing decompresses the frame and deserializes `T`
+/// from it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Compressed<T>(pub T);
+impl<T> Compressed<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+impl<T> From<T> for Compressed<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+impl<T> Deref for Compressed<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+impl<T> DerefMut for Compressed<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+impl<T: Serialize> Serialize for Compressed<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let bytes = postcard::to_allocvec(&self.0).map_err(ser::Error::custom)?;
+        serializer.serialize_bytes(&compress(&bytes, Config::default()))
+    }
+}
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Compressed<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let frame = deserializer.deserialize_bytes(Bytes)?;
+        let bytes = decompress(&frame, None).map_err(de::Error::custom)?;
+        postcard::from_bytes(&bytes)
+            .map(Self)
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Accepts bytes however the format represents them, including as a sequence.
+struct Bytes;
+impl<'de> Visitor<'de> for Bytes {
+    type Value = Vec<u8>;
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a compressed frame")
+    }
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(bytes.to_vec())
+    }
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(bytes)
+    }
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default().min(0x1000));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        id: u32,
+        lines: Compressed<Vec<String>>,
+    }
+
+    #[test]
+    fn compressed() {
+        let record = Record {
+            id: 7,
+            lines: Compressed(Vec::from_iter(
+                (0..0x100).map(|x| format!("line {} of the log", x % 10)),
+            )),
+        };
+        let bytes = postcard::to_allocvec(&record).unwrap();
+        let plain = postcard::to_allocvec(&*record.lines).unwrap();
+        assert!(bytes.len() < plain.len() / 4);
+        assert_eq!(postcard::from_bytes::<Record>(&bytes).unwrap(), record);
+
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(postcard::from_bytes::<Record>(&corrupt).is_err());
+    }
+}
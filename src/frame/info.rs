@@ -0,0 +1,202 @@
+use super::{
+    EncoderSettings, Error, FLAG_CHECKSUM, FLAG_INDEPENDENT, FLAG_SEEK_TABLE, Header, MAGIC,
+    SKIPPABLE_MAGIC, block::read_items, read_skippable, read_u32, read_varint,
+};
+use crate::{bwt, filter::Filter, lz::Stats};
+
+/// Layout of a frame, as read by [`inspect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// Format version, the last byte of [`MAGIC`].
+    pub version: u8,
+    /// Offset of the frame's magic, after any skippable frames.
+    pub offset: usize,
+    /// Length of the frame from its magic through its checksum, without a seek table.
+    pub len: usize,
+    pub max_buffer_len: usize,
+    pub content_size: Option<usize>,
+    /// Checksum of the dictionary the frame was compressed with.
+    pub dictionary: Option<u32>,
+    pub dictionary_id: Option<u32>,
+    /// Block length of the block sorting pipeline.
+    pub block_sort: Option<usize>,
+    pub filter: Option<Filter>,
+    /// How the encoder was configured, if recorded.
+    pub settings: Option<EncoderSettings>,
+    /// Every block starts with a fresh window.
+    pub independent: bool,
+    /// A seek table follows the frame.
+    pub seek_table: bool,
+    /// Checksum of the content, the low 32 bits of its XXH64.
+    pub checksum: Option<u32>,
+    pub blocks: Vec<BlockInfo>,
+}
+
+/// Position and sizes of a block of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Offset of the block's payload in the data passed to [`inspect`].
+    pub offset: usize,
+    pub compressed_len: usize,
+    /// Length of the block's content, before any filter is undone.
+    pub len: usize,
+}
+
+/// Reads the header and block boundaries of the frame starting `data`, after any skippable frames.
+///
+/// Block lengths are read from the items or block sorted payloads without decoding any content,
+/// so neither the dictionary nor the checksum are checked.
+pub fn inspect(data: &[u8]) -> Result<FrameInfo, Error> {
+    let (header, mut rest) = Header::read(data)?;
+    let offset_of = |rest: &[u8]| data.len() - rest.len();
+    let mut frame = data;
+    while frame.starts_with(&SKIPPABLE_MAGIC) {
+        (_, frame) = read_skippable(frame)?;
+    }
+    let offset = offset_of(frame);
+    let mut blocks = vec![];
+    loop {
+        let len;
+        (len, rest) = read_varint(rest)?;
+        if len == 0 {
+            break;
+        }
+        let block = rest.get(..len).ok_or(Error::UnexpectedEof)?;
+        let content_len = match header.block_sort {
+            Some(_) => bwt::decompress_len(block).map_err(|_| Error::Malformed)?,
+            None => read_items(block)?.iter().map(|item| item.len()).sum(),
+        };
+        blocks.push(BlockInfo {
+            offset: offset_of(rest),
+            compressed_len: len,
+            len: content_len,
+        });
+        rest = &rest[len..];
+    }
+    let mut checksum = None;
+    if header.flags & FLAG_CHECKSUM != 0 {
+        let value;
+        (value, rest) = read_u32(rest)?;
+        checksum = Some(value);
+    }
+    Ok(FrameInfo {
+        version: MAGIC[3],
+        offset,
+        len: offset_of(rest) - offset,
+        max_buffer_len: header.max_buffer_len,
+        content_size: header.content_size,
+        dictionary: header.dictionary,
+        dictionary_id: header.dictionary_id,
+        block_sort: header.block_sort,
+        filter: header.filter,
+        settings: header.settings,
+        independent: header.flags & FLAG_INDEPENDENT != 0,
+        seek_table: header.flags & FLAG_SEEK_TABLE != 0,
+        checksum,
+        blocks,
+    })
+}
+
+/// Statistics of the items in the blocks of the frame starting `data`, after any skippable frames.
+///
+/// Block sorted frames hold no items, so their statistics stay empty. Searches aren't recorded in
+/// frames and are left at zero.
+pub fn item_stats(data: &[u8]) -> Result<Stats, Error> {
+    let (header, mut rest) = Header::read(data)?;
+    let mut stats = Stats::new();
+    loop {
+        let len;
+        (len, rest) = read_varint(rest)?;
+        if len == 0 {
+            break;
+        }
+        let block = rest.get(..len).ok_or(Error::UnexpectedEof)?;
+        rest = &rest[len..];
+        if header.block_sort.is_some() {
+            continue;
+        }
+        // Recent distances start over with every block.
+        stats.restart();
+        for item in read_items(block)? {
+            stats.record(&item);
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        frame::{
+            BLOCK_LEN, compress, compress_seekable, tests::shared_config as config, write_skippable,
+        },
+        lz::Config,
+        util::xxhash,
+    };
+
+    #[test]
+    fn blocks() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 5 / 2).map(|x| (x / 5 % 253) as u8));
+        let mut frame = vec![];
+        write_skippable(&mut frame, b"meta");
+        let start = frame.len();
+        frame.extend(compress(&data, config()));
+        let info = inspect(&frame).unwrap();
+        assert_eq!(info.offset, start);
+        assert_eq!(info.len, frame.len() - start);
+        assert_eq!(info.max_buffer_len, 0x1000);
+        assert_eq!(info.content_size, Some(data.len()));
+        assert_eq!(info.checksum, Some(xxhash::checksum(&data)));
+        assert!(!info.independent && !info.seek_table);
+        assert_eq!(
+            Vec::from_iter(info.blocks.iter().map(|block| block.len)),
+            [BLOCK_LEN, BLOCK_LEN, BLOCK_LEN / 2]
+        );
+        for block in &info.blocks {
+            assert!(block.offset > start && block.compressed_len < block.len);
+        }
+
+        let frame = compress_seekable(&data, config());
+        let info = inspect(&frame).unwrap();
+        assert!(info.independent && info.seek_table);
+        assert!(info.len < frame.len());
+        assert_eq!(info.blocks.len(), 3);
+
+        let sorted = Config {
+            block_sort: Some(0x8000),
+            ..config()
+        };
+        let info = inspect(&compress(&data, sorted)).unwrap();
+        assert_eq!(info.block_sort, Some(0x8000));
+        assert_eq!(
+            info.blocks.iter().map(|block| block.len).sum::<usize>(),
+            data.len()
+        );
+    }
+    #[test]
+    fn items() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 3 / 2).map(|x| (x / 5 % 253) as u8));
+        let stats = item_stats(&compress(&data, config())).unwrap();
+        assert_eq!(stats.total_len(), data.len());
+        assert!(stats.match_len > stats.literal_len);
+        assert_eq!(
+            stats.length_histogram.iter().sum::<usize>(),
+            stats.ref_items
+        );
+        let sorted = Config {
+            block_sort: Some(0x8000),
+            ..config()
+        };
+        assert_eq!(item_stats(&compress(&data, sorted)).unwrap().items, 0);
+    }
+    #[test]
+    fn truncated() {
+        let frame = compress(b"abcabcabcabc", config());
+        assert_eq!(
+            inspect(&frame[..frame.len() - 5]),
+            Err(Error::UnexpectedEof)
+        );
+        assert_eq!(inspect(b"nope"), Err(Error::InvalidMagic));
+    }
+}
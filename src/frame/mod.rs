@@ -0,0 +1,1813 @@
+#[cfg(feature = "async")]
+mod async_io;
+mod block;
+#[cfg(feature = "codec")]
+mod codec;
+mod compressed;
+mod info;
+mod seekable;
+mod stream;
+#[cfg(feature = "async")]
+pub use async_io::*;
+pub use block::BlockCost;
+use block::{Streams, read_items, write_block};
+#[cfg(feature = "codec")]
+pub use codec::*;
+pub use compressed::*;
+pub use info::*;
+pub use seekable::*;
+#[cfg(any(feature = "ffi", feature = "wasm"))]
+pub(crate) use stream::PushDecoder;
+pub use stream::*;
+
+use std::{
+    fmt::{self, Display},
+    hash::BuildHasher,
+    io, iter, mem,
+};
+
+use crate::{
+    Partial, Slide, bwt,
+    filter::{Filter, Filtering},
+    lz::{Config, LongRange, LongRangeMatcher, RepDistances, Strategy, optimize},
+    search_buffer::SearchBuffer,
+    trace::enter,
+    util::{varint, xxhash},
+};
+
+/// Magic number starting every frame.
+pub const MAGIC: [u8; 4] = *b"SLZ\x05";
+/// Magic number starting skippable frames, see [`write_skippable`].
+pub const SKIPPABLE_MAGIC: [u8; 4] = *b"SLZm";
+/// Amount of input compressed into each block.
+pub const BLOCK_LEN: usize = 0x20000;
+/// Longest window a frame may ask decoders for. Encoders shrink longer windows to it.
+pub const MAX_WINDOW_LEN: usize = 1 << 31;
+/// Longest block sorted block a frame may hold. Encoders shrink longer blocks to it.
+pub const MAX_BLOCK_SORT_LEN: usize = 1 << 28;
+
+/// The header is followed by the checksum of the preset dictionary (u32 LE), see
+/// [`xxhash::checksum`].
+const FLAG_DICTIONARY: u16 = 1 << 0;
+/// The frame ends with the checksum of the decompressed content (u32 LE).
+const FLAG_CHECKSUM: u16 = 1 << 1;
+/// Every block starts with a fresh window, primed only by the dictionary.
+const FLAG_INDEPENDENT: u16 = 1 << 2;
+/// The frame is followed by a seek table, see [`SeekableDecoder`].
+const FLAG_SEEK_TABLE: u16 = 1 << 3;
+/// The window is followed by the length of the decompressed content (varint).
+const FLAG_CONTENT_SIZE: u16 = 1 << 4;
+/// The header ends with the [`Filter`] applied to the content (id byte, width varint). Checksum
+/// and content size refer to the unfiltered content.
+const FLAG_FILTER: u16 = 1 << 5;
+/// The header holds the block length of the [`bwt`] pipeline (varint) before the filter, and its
+/// blocks replace the item streams.
+const FLAG_BLOCK_SORT: u16 = 1 << 6;
+/// The dictionary checksum is followed by the ID the application assigned to the dictionary
+/// (varint), see [`dictionary_id`]. Flags are a varint, so this is the first one taking a
+/// second byte.
+const FLAG_DICTIONARY_ID: u16 = 1 << 7;
+/// The header ends with the [`EncoderSettings`] the frame was compressed with, after the filter.
+/// Block sorted frames don't record them.
+const FLAG_SETTINGS: u16 = 1 << 8;
+const FLAGS_KNOWN: u16 = FLAG_DICTIONARY
+    | FLAG_CHECKSUM
+    | FLAG_INDEPENDENT
+    | FLAG_SEEK_TABLE
+    | FLAG_CONTENT_SIZE
+    | FLAG_FILTER
+    | FLAG_BLOCK_SORT
+    | FLAG_DICTIONARY_ID
+    | FLAG_SETTINGS;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input does not start with [`MAGIC`].
+    InvalidMagic,
+    /// Data following a frame isn't another frame, when decoding concatenated frames.
+    TrailingData,
+    /// The header sets flags this version does not understand.
+    UnsupportedFlags(u16),
+    /// The input ended in the middle of the frame.
+    UnexpectedEof,
+    /// A varint, item or reference in the frame is malformed.
+    Malformed,
+    /// A window or block in the frame is longer than the format allows.
+    LengthOutOfBounds { len: usize, max: usize },
+    /// The frame was compressed with a different (or without a) dictionary than the one supplied.
+    DictionaryMismatch { expected: u32, actual: Option<u32> },
+    /// The decompressed content does not match the frame's checksum.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The frame decompresses to a different length than recorded or requested.
+    ContentSizeMismatch { expected: usize, actual: usize },
+    /// The frame has no seek table or its blocks depend on each other.
+    NotSeekable,
+    /// The output buffer is too small for the decompressed content.
+    OutputTooSmall,
+    /// Coding was aborted through a [`Cancel`](crate::lz::Cancel) token.
+    Cancelled,
+    /// Reading the frame failed.
+    Io(io::ErrorKind),
+}
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            kind => Error::Io(kind),
+        }
+    }
+}
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::UnexpectedEof => io::ErrorKind::UnexpectedEof.into(),
+            Error::Io(kind) => kind.into(),
+            Error::Cancelled => io::Error::other(err),
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidMagic => write!(f, "input is not a slide frame"),
+            Error::TrailingData => write!(f, "trailing data after the last frame"),
+            Error::UnsupportedFlags(flags) => write!(f, "unsupported frame flags {flags:#010b}"),
+            Error::UnexpectedEof => write!(f, "unexpected end of frame"),
+            Error::Malformed => write!(f, "malformed frame"),
+            Error::LengthOutOfBounds { len, max } => {
+                write!(f, "length {len} exceeds the maximum of {max}")
+            }
+            Error::DictionaryMismatch {
+                expected,
+                actual: Some(actual),
+            } => write!(
+                f,
+                "frame requires dictionary {expected:#010x}, got {actual:#010x}"
+            ),
+            Error::DictionaryMismatch {
+                expected,
+                actual: None,
+            } => write!(f, "frame requires dictionary {expected:#010x}"),
+            Error::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {expected:#010x}, got {actual:#010x}"
+            ),
+            Error::ContentSizeMismatch { expected, actual } => write!(
+                f,
+                "content size mismatch: expected {expected} bytes, got {actual}"
+            ),
+            Error::NotSeekable => write!(f, "frame is not seekable"),
+            Error::OutputTooSmall => write!(f, "output buffer too small"),
+            Error::Cancelled => write!(f, "cancelled"),
+            Error::Io(kind) => write!(f, "i/o error: {kind}"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// Compresses `data` into a single frame.
+///
+/// The SearchBuffer key width is the minimum match length, capped at 4.
+///
+/// The output only depends on `data` and `config`: the hasher seed is never observable,
+/// ties between matches are broken by position, and all lengths are encoded independently of
+/// the platform's pointer width. Compressing the same input twice yields identical frames.
+pub fn compress(data: &[u8], config: Config) -> Vec<u8> {
+    match config.match_lengths.start {
+        1 => compress_with::<1>(data, config),
+        2 => compress_with::<2>(data, config),
+        3 => compress_with::<3>(data, config),
+        _ => compress_with::<4>(data, config),
+    }
+}
+
+fn compress_with<const N: usize>(data: &[u8], config: Config) -> Vec<u8> {
+    let config = fit_frame(config, N);
+    let mut out = vec![];
+    write_header(&mut out, &config, 0, Some(data.len()), None);
+    compress_blocks(&mut SearchBuffer::<u8, N>::new(), data, config, &mut out);
+    write_trailer(&mut out, xxhash::checksum(data));
+    out
+}
+
+/// Like [`compress`], but applies `filter` to `data` before matching and records it in the
+/// header, so decompressing undoes it.
+pub fn compress_filtered(data: &[u8], config: Config, filter: Filter) -> Vec<u8> {
+    match config.match_lengths.start {
+        1 => compress_filtered_with::<1>(data, config, filter),
+        2 => compress_filtered_with::<2>(data, config, filter),
+        3 => compress_filtered_with::<3>(data, config, filter),
+        _ => compress_filtered_with::<4>(data, config, filter),
+    }
+}
+
+fn compress_filtered_with<const N: usize>(data: &[u8], config: Config, filter: Filter) -> Vec<u8> {
+    let config = fit_frame(config, N);
+    let mut filtered = data.to_vec();
+    Filtering::new(filter).encode(&mut filtered);
+    let mut out = vec![];
+    write_header(&mut out, &config, 0, Some(data.len()), Some(filter));
+    compress_blocks(
+        &mut SearchBuffer::<u8, N>::new(),
+        &filtered,
+        config,
+        &mut out,
+    );
+    write_trailer(&mut out, xxhash::checksum(data));
+    out
+}
+
+/// Like [`compress`], but also finds long matches up to `long.window_len` back with a
+/// [`LongRangeMatcher`].
+///
+/// Long matches are regular references, but the header asks decoders for a window of
+/// `long.window_len`, so they need that much memory. Block sorting doesn't match at all, so
+/// `long` is ignored if `config.block_sort` is set.
+///
+/// Panics if `long` is invalid.
+pub fn compress_long_range(data: &[u8], config: Config, long: LongRange) -> Vec<u8> {
+    match config.match_lengths.start {
+        1 => compress_long_range_with::<1>(data, config, long),
+        2 => compress_long_range_with::<2>(data, config, long),
+        3 => compress_long_range_with::<3>(data, config, long),
+        _ => compress_long_range_with::<4>(data, config, long),
+    }
+}
+
+fn compress_long_range_with<const N: usize>(
+    data: &[u8],
+    mut config: Config,
+    long: LongRange,
+) -> Vec<u8> {
+    let long = LongRange {
+        window_len: long.window_len.min(MAX_WINDOW_LEN),
+        ..long
+    };
+    let mut matcher = LongRangeMatcher::new(long).unwrap();
+    if config.block_sort.is_some() {
+        return compress_with::<N>(data, config);
+    }
+    config = fit_frame(config, N);
+    let mut out = vec![];
+    let header = Config {
+        max_buffer_len: config.max_buffer_len.max(long.window_len),
+        ..config.clone()
+    };
+    write_header(&mut out, &header, 0, Some(data.len()), None);
+    let mut search_buffer = SearchBuffer::<u8, N>::new();
+    let mut streams = Streams::default();
+    let mut block = vec![];
+    for start in (0..data.len()).step_by(BLOCK_LEN) {
+        let range = start..data.len().min(start + BLOCK_LEN);
+        let span = enter!(DEBUG, "encode_block", bytes = range.len(); items, compressed);
+        let items = {
+            let _span = enter!(
+                TRACE,
+                "match_search",
+                bytes = range.len(),
+                long_range = true
+            );
+            matcher.to_items(&mut search_buffer, data, range.clone(), config.clone())
+        };
+        streams.clear();
+        let mut count = 0;
+        for item in optimize(items, &data[range.clone()]) {
+            streams.push(item);
+            count += 1;
+        }
+        span.record("items", count);
+        config.dictionary = None;
+        block.clear();
+        write_block(&streams, &data[range], &mut block);
+        span.record("compressed", block.len());
+        write_varint(&mut out, block.len());
+        out.extend_from_slice(&block);
+    }
+    write_trailer(&mut out, xxhash::checksum(data));
+    out
+}
+
+/// How the window of each chunk compressed by [`compress_parallel`] is initialized.
+#[cfg(feature = "parallel")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkWindow {
+    /// Prime the window with the tail of the preceding input, so matches may cross chunks.
+    #[default]
+    Seeded,
+    /// Start every chunk with an empty window.
+    Independent,
+}
+
+/// Compresses `data` into a single frame, splitting it into chunks of `chunk_len` that are
+/// compressed on the rayon thread pool.
+///
+/// The result is a regular frame decodable by [`decompress`]. Like [`compress`], it only depends
+/// on the arguments, never on the size of the thread pool or the order chunks finish in.
+#[cfg(feature = "parallel")]
+pub fn compress_parallel(
+    data: &[u8],
+    config: Config,
+    chunk_len: usize,
+    window: ChunkWindow,
+) -> Vec<u8> {
+    match config.match_lengths.start {
+        1 => compress_parallel_with::<1>(data, config, chunk_len, window),
+        2 => compress_parallel_with::<2>(data, config, chunk_len, window),
+        3 => compress_parallel_with::<3>(data, config, chunk_len, window),
+        _ => compress_parallel_with::<4>(data, config, chunk_len, window),
+    }
+}
+
+#[cfg(feature = "parallel")]
+fn compress_parallel_with<const N: usize>(
+    data: &[u8],
+    config: Config,
+    chunk_len: usize,
+    window: ChunkWindow,
+) -> Vec<u8> {
+    use rayon::prelude::*;
+    use std::borrow::Cow;
+
+    assert!(chunk_len > 0, "chunk_len must not be zero.");
+    let config = fit_frame(config, N);
+    let mut out = vec![];
+    write_header(&mut out, &config, 0, Some(data.len()), None);
+    let chunks = Vec::from_par_iter(data.par_chunks(chunk_len).enumerate().map(|(x, chunk)| {
+        let start = x * chunk_len;
+        let seed = match window {
+            _ if x == 0 => config.dictionary.map(Cow::Borrowed),
+            ChunkWindow::Independent => None,
+            ChunkWindow::Seeded if start >= config.max_buffer_len => {
+                Some(Cow::Borrowed(&data[start - config.max_buffer_len..start]))
+            }
+            // The decoder's window still holds the tail of the dictionary.
+            ChunkWindow::Seeded => Some(Cow::Owned(
+                [config.dictionary_window(), &data[..start]].concat(),
+            )),
+        };
+        let config = Config {
+            dictionary: seed.as_deref(),
+            ..config.clone()
+        };
+        let mut out = vec![];
+        compress_blocks(&mut SearchBuffer::<u8, N>::new(), chunk, config, &mut out);
+        out
+    }));
+    for chunk in chunks {
+        out.extend(chunk);
+    }
+    write_trailer(&mut out, xxhash::checksum(data));
+    out
+}
+
+/// [`Config::fit_memory`], also shrinking the window and block sorted blocks to what frames may
+/// hold.
+fn fit_frame(config: Config, width: usize) -> Config {
+    let config = config.fit_memory(width);
+    Config {
+        max_buffer_len: config.max_buffer_len.min(MAX_WINDOW_LEN),
+        block_sort: config.block_sort.map(|len| len.min(MAX_BLOCK_SORT_LEN)),
+        ..config
+    }
+}
+
+fn write_header(
+    out: &mut Vec<u8>,
+    config: &Config,
+    mut flags: u16,
+    content_size: Option<usize>,
+    filter: Option<Filter>,
+) {
+    // Block sorting never looks at the dictionary.
+    let dictionary = (config.dictionary)
+        .filter(|_| config.block_sort.is_none())
+        .map(xxhash::checksum);
+    let dictionary_id = config.dictionary_id.filter(|_| dictionary.is_some());
+    flags |= FLAG_CHECKSUM;
+    flags |= dictionary.map_or(0, |_| FLAG_DICTIONARY);
+    flags |= dictionary_id.map_or(0, |_| FLAG_DICTIONARY_ID);
+    flags |= content_size.map_or(0, |_| FLAG_CONTENT_SIZE);
+    flags |= config.block_sort.map_or(0, |_| FLAG_BLOCK_SORT);
+    flags |= filter.map_or(0, |_| FLAG_FILTER);
+    flags |= config.block_sort.map_or(FLAG_SETTINGS, |_| 0);
+    out.extend(MAGIC);
+    write_varint(out, flags as usize);
+    write_varint(out, config.max_buffer_len);
+    if let Some(content_size) = content_size {
+        write_varint(out, content_size);
+    }
+    if let Some(checksum) = dictionary {
+        out.extend(checksum.to_le_bytes());
+    }
+    if let Some(id) = dictionary_id {
+        write_varint(out, id as usize);
+    }
+    if let Some(block_len) = config.block_sort {
+        write_varint(out, block_len);
+    }
+    if let Some(filter) = filter {
+        out.push(filter.id());
+        write_varint(out, filter.width());
+    }
+    if flags & FLAG_SETTINGS != 0 {
+        EncoderSettings::new(config).write(out);
+    }
+}
+
+/// Encoder side [`Config`] fields a frame was compressed with, recorded in its header.
+///
+/// Decoding needs none of them, they only tell how the frame came about, see [`inspect`] and
+/// [`settings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderSettings {
+    pub min_match: usize,
+    /// Longest match, if limited.
+    pub max_match: Option<usize>,
+    pub strategy: Strategy,
+    pub max_chain: Option<usize>,
+    pub nice_len: Option<usize>,
+}
+impl EncoderSettings {
+    /// Longest possible encoding: five varints and the strategy's id byte.
+    const MAX_LEN: usize = 5 * 10 + 1;
+
+    pub fn new(config: &Config) -> Self {
+        let end = config.match_lengths.end;
+        Self {
+            min_match: config.match_lengths.start,
+            max_match: (end != usize::MAX).then(|| end - 1),
+            strategy: config.strategy,
+            max_chain: config.max_chain,
+            nice_len: config.nice_len,
+        }
+    }
+    /// Writes the settings as the minimum match, the maximum match, max chain and nice length
+    /// (varints, optional ones as 0 for None or the value plus one) around the strategy (id byte
+    /// and varint parameter).
+    fn write(&self, out: &mut Vec<u8>) {
+        let option = |value: Option<usize>| value.map_or(0, |value| value.saturating_add(1));
+        write_varint(out, self.min_match);
+        write_varint(out, option(self.max_match));
+        let (id, parameter) = match self.strategy {
+            Strategy::Greedy => (0, 0),
+            Strategy::Lazy(level) => (1, level),
+            Strategy::Optimal => (2, 0),
+            Strategy::BestOf(k) => (3, k),
+        };
+        out.push(id);
+        write_varint(out, parameter);
+        write_varint(out, option(self.max_chain));
+        write_varint(out, option(self.nice_len));
+    }
+    fn read(data: &[u8]) -> Result<(Self, &[u8]), Error> {
+        let option = |data| -> Result<_, Error> {
+            let (value, data) = read_varint(data)?;
+            Ok((value.checked_sub(1), data))
+        };
+        let (min_match, data) = read_varint(data)?;
+        let (max_match, data) = option(data)?;
+        let (&id, data) = data.split_first().ok_or(Error::UnexpectedEof)?;
+        let (parameter, data) = read_varint(data)?;
+        let strategy = match id {
+            0 => Strategy::Greedy,
+            1 => Strategy::Lazy(parameter),
+            2 => Strategy::Optimal,
+            3 => Strategy::BestOf(parameter),
+            _ => return Err(Error::Malformed),
+        };
+        let (max_chain, data) = option(data)?;
+        let (nice_len, data) = option(data)?;
+        let settings = Self {
+            min_match,
+            max_match,
+            strategy,
+            max_chain,
+            nice_len,
+        };
+        Ok((settings, data))
+    }
+}
+impl Display for EncoderSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "matches {}..", self.min_match)?;
+        if let Some(max_match) = self.max_match {
+            write!(f, "={max_match}")?;
+        }
+        write!(f, ", {}", self.strategy)?;
+        if let Some(max_chain) = self.max_chain {
+            write!(f, ", max chain {max_chain}")?;
+        }
+        if let Some(nice_len) = self.nice_len {
+            write!(f, ", nice length {nice_len}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the items of `chunk` and pushes them to `streams`, returning how many there are.
+fn search<const N: usize, S: BuildHasher>(
+    search_buffer: &mut SearchBuffer<u8, N, S>,
+    chunk: &[u8],
+    config: Config,
+    streams: &mut Streams,
+) -> usize {
+    let span = enter!(TRACE, "match_search", bytes = chunk.len(); items);
+    let mut items = 0;
+    for item in optimize(search_buffer.to_items(chunk.iter().copied(), config), chunk) {
+        streams.push(item);
+        items += 1;
+    }
+    span.record("items", items);
+    items
+}
+
+fn compress_blocks<const N: usize, S: BuildHasher>(
+    search_buffer: &mut SearchBuffer<u8, N, S>,
+    data: &[u8],
+    mut config: Config,
+    out: &mut Vec<u8>,
+) {
+    if let Some(block_len) = config.block_sort {
+        for chunk in data.chunks(block_len) {
+            let span =
+                enter!(DEBUG, "encode_block", bytes = chunk.len(), block_sort = true; compressed);
+            let block = bwt::compress(chunk);
+            span.record("compressed", block.len());
+            write_varint(out, block.len());
+            out.extend_from_slice(&block);
+        }
+        return;
+    }
+    let mut streams = Streams::default();
+    let mut block = vec![];
+    for chunk in data.chunks(BLOCK_LEN) {
+        let span = enter!(DEBUG, "encode_block", bytes = chunk.len(); items, compressed);
+        streams.clear();
+        let items = search(search_buffer, chunk, config.clone(), &mut streams);
+        span.record("items", items);
+        // The dictionary only primes the window once.
+        config.dictionary = None;
+        block.clear();
+        write_block(&streams, chunk, &mut block);
+        span.record("compressed", block.len());
+        write_varint(out, block.len());
+        out.extend_from_slice(&block);
+    }
+}
+
+fn write_trailer(out: &mut Vec<u8>, checksum: u32) {
+    write_varint(out, 0);
+    out.extend(checksum.to_le_bytes());
+}
+
+struct Header {
+    flags: u16,
+    max_buffer_len: usize,
+    content_size: Option<usize>,
+    dictionary: Option<u32>,
+    dictionary_id: Option<u32>,
+    block_sort: Option<usize>,
+    filter: Option<Filter>,
+    settings: Option<EncoderSettings>,
+}
+impl Header {
+    /// Longest possible encoding: magic, flags, two varints, dictionary checksum and ID, block
+    /// length, filter and settings.
+    const MAX_LEN: usize =
+        MAGIC.len() + 3 + 10 + 10 + 4 + 5 + 10 + 1 + 10 + EncoderSettings::MAX_LEN;
+
+    /// Reads the header of the frame starting `data`, after any skippable frames.
+    fn read(mut data: &[u8]) -> Result<(Self, &[u8]), Error> {
+        while data.starts_with(&SKIPPABLE_MAGIC) {
+            (_, data) = read_skippable(data)?;
+        }
+        let data = data.strip_prefix(&MAGIC).ok_or(Error::InvalidMagic)?;
+        let (flags, data) = read_varint(data)?;
+        let flags = u16::try_from(flags).map_err(|_| Error::Malformed)?;
+        if flags & !FLAGS_KNOWN != 0 {
+            return Err(Error::UnsupportedFlags(flags));
+        }
+        let (max_buffer_len, mut data) = read_varint(data)?;
+        check_len(max_buffer_len, MAX_WINDOW_LEN)?;
+        let mut content_size = None;
+        if flags & FLAG_CONTENT_SIZE != 0 {
+            let size;
+            (size, data) = read_varint(data)?;
+            content_size = Some(size);
+        }
+        let mut dictionary = None;
+        if flags & FLAG_DICTIONARY != 0 {
+            let checksum;
+            (checksum, data) = read_u32(data)?;
+            dictionary = Some(checksum);
+        }
+        let mut dictionary_id = None;
+        if flags & FLAG_DICTIONARY_ID != 0 {
+            let id;
+            (id, data) = read_varint(data)?;
+            // Only dictionaries have an ID.
+            if dictionary.is_none() {
+                return Err(Error::Malformed);
+            }
+            dictionary_id = Some(u32::try_from(id).map_err(|_| Error::Malformed)?);
+        }
+        let mut block_sort = None;
+        if flags & FLAG_BLOCK_SORT != 0 {
+            let block_len;
+            (block_len, data) = read_varint(data)?;
+            check_len(block_len, MAX_BLOCK_SORT_LEN)?;
+            block_sort = Some(block_len);
+        }
+        let mut filter = None;
+        if flags & FLAG_FILTER != 0 {
+            let (&id, rest) = data.split_first().ok_or(Error::UnexpectedEof)?;
+            let width;
+            (width, data) = read_varint(rest)?;
+            filter = Some(Filter::from_id(id, width).ok_or(Error::Malformed)?);
+        }
+        let mut settings = None;
+        if flags & FLAG_SETTINGS != 0 {
+            let value;
+            (value, data) = EncoderSettings::read(data)?;
+            settings = Some(value);
+        }
+        Ok((
+            Self {
+                flags,
+                max_buffer_len,
+                content_size,
+                dictionary,
+                dictionary_id,
+                block_sort,
+                filter,
+                settings,
+            },
+            data,
+        ))
+    }
+    /// Decode config for this frame, checking that `dictionary` is the one it was compressed with.
+    fn config<'a>(&self, dictionary: Option<&'a [u8]>) -> Result<Config<'a>, Error> {
+        let mut config = Config {
+            max_buffer_len: self.max_buffer_len,
+            match_lengths: 0..usize::MAX,
+            block_sort: self.block_sort,
+            dictionary_id: self.dictionary_id,
+            ..Config::default()
+        };
+        if let Some(expected) = self.dictionary {
+            let actual = dictionary.map(xxhash::checksum);
+            if actual != Some(expected) {
+                return Err(Error::DictionaryMismatch { expected, actual });
+            }
+            config.dictionary = dictionary;
+        }
+        Ok(config)
+    }
+}
+
+/// Decodes one block's payload, appending to `out`.
+///
+/// `window` is primed with `config.dictionary` first, if any. Block sorted blocks don't use it.
+/// Fails with [`Error::LengthOutOfBounds`] before decoding anything if the block holds more than
+/// `max_len` bytes, or more than a block may.
+fn decompress_block(
+    block: &[u8],
+    window: &mut Slide<u8>,
+    config: &Config,
+    max_len: usize,
+    out: &mut impl Extend<u8>,
+) -> Result<(), Error> {
+    if let Some(block_len) = config.block_sort {
+        let malformed = |_| Error::Malformed;
+        check_len(
+            bwt::decompress_len(block).map_err(malformed)?,
+            block_len.min(max_len),
+        )?;
+        let span =
+            enter!(DEBUG, "decode_block", compressed = block.len(), block_sort = true; bytes);
+        let (data, rest) = bwt::decompress(block).map_err(malformed)?;
+        if !rest.is_empty() {
+            return Err(Error::Malformed);
+        }
+        span.record("bytes", data.len());
+        out.extend(data);
+        return Ok(());
+    }
+    let span = enter!(DEBUG, "decode_block", compressed = block.len(); items, bytes);
+    let mut available =
+        (window.len() + config.dictionary_window().len()).min(config.max_buffer_len);
+    let items = read_items(block)?;
+    let mut reps = RepDistances::default();
+    let mut bytes = 0usize;
+    for item in &items {
+        if reps.update(item) > available {
+            return Err(Error::Malformed);
+        }
+        bytes = bytes.checked_add(item.len()).ok_or(Error::Malformed)?;
+        check_len(bytes, BLOCK_LEN)?;
+        available = (available + item.len()).min(config.max_buffer_len);
+    }
+    check_len(bytes, max_len)?;
+    span.record("items", items.len());
+    span.record("bytes", bytes);
+    out.extend(window.from_items(items, config.clone()));
+    Ok(())
+}
+
+/// Fails with [`Error::LengthOutOfBounds`] if `len` exceeds `max`.
+fn check_len(len: usize, max: usize) -> Result<(), Error> {
+    if len > max {
+        return Err(Error::LengthOutOfBounds { len, max });
+    }
+    Ok(())
+}
+
+/// Reports a block running past the `expected` content length, of which `written` bytes are
+/// decoded, as [`Error::ContentSizeMismatch`].
+fn content_overrun(err: Error, expected: Option<usize>, written: usize) -> Error {
+    match (err, expected) {
+        (Error::LengthOutOfBounds { len, .. }, Some(expected)) if written + len > expected => {
+            Error::ContentSizeMismatch {
+                expected,
+                actual: written + len,
+            }
+        }
+        (err, _) => err,
+    }
+}
+
+/// Decompresses a single frame, using `dictionary` if the frame was compressed with one.
+///
+/// Fails if the frame records a content size that doesn't match its content.
+pub fn decompress(data: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+    let mut out = vec![];
+    decompress_to_vec(data, dictionary, &mut out)?;
+    Ok(out)
+}
+
+/// Like [`decompress`], but decompresses every frame of `data` in turn, as when several files
+/// were concatenated with `cat a.slz b.slz`, returning their contents concatenated.
+///
+/// Every frame is decompressed with `dictionary`, if compressed with one, and must be complete.
+pub fn decompress_concatenated(data: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+    let mut out = vec![];
+    for frame in frames(data) {
+        decompress_to_vec(frame?, dictionary, &mut out)?;
+    }
+    Ok(out)
+}
+
+/// Decompresses a single frame of exactly `len` bytes into a buffer allocated once.
+///
+/// Fails as soon as the frame turns out to be longer or shorter, or if it records a different
+/// content size.
+pub fn decompress_exact(
+    data: &[u8],
+    dictionary: Option<&[u8]>,
+    len: usize,
+) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(len);
+    decompress_frame(
+        data,
+        dictionary,
+        Some(len),
+        &mut Appended::new(&mut out),
+        &mut 0,
+    )?;
+    Ok(out)
+}
+
+/// Like [`decompress`], appending to `out` and returning the number of bytes appended.
+///
+/// Only grows `out` as needed, so reserving [`content_size`] beforehand allocates once. On error,
+/// `out` is truncated back to its original length.
+pub fn decompress_to_vec(
+    data: &[u8],
+    dictionary: Option<&[u8]>,
+    out: &mut Vec<u8>,
+) -> Result<usize, Error> {
+    let start = out.len();
+    let ret = decompress_frame(data, dictionary, None, &mut Appended::new(out), &mut 0);
+    if ret.is_err() {
+        out.truncate(start);
+    }
+    ret
+}
+
+/// Like [`decompress`], writing to the start of `out` and returning the number of bytes written.
+///
+/// Fails with [`Error::OutputTooSmall`] as soon as the content doesn't fit, leaving `out` partly
+/// written.
+pub fn decompress_into(
+    data: &[u8],
+    dictionary: Option<&[u8]>,
+    out: &mut [u8],
+) -> Result<usize, Error> {
+    decompress_frame(data, dictionary, None, &mut Filled::new(out), &mut 0)
+}
+
+/// Like [`decompress`], but recovers what it can of a frame that is cut short or corrupted:
+/// the content of all blocks before the first one that can't be decoded, along with its offset.
+///
+/// The recovered content is unchecked, as the checksum only covers all of it.
+pub fn decompress_partial(data: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>, Partial> {
+    let mut out = vec![];
+    let mut offset = 0;
+    match decompress_frame(
+        data,
+        dictionary,
+        None,
+        &mut Appended::new(&mut out),
+        &mut offset,
+    ) {
+        Ok(_) => Ok(out),
+        Err(error) => Err(Partial {
+            data: out,
+            offset,
+            error: error.into(),
+        }),
+    }
+}
+
+/// Like [`decompress`], decoding the blocks on the rayon thread pool if they don't depend on each
+/// other, as in seekable and block sorted frames.
+///
+/// Other frames are decompressed sequentially.
+#[cfg(feature = "parallel")]
+pub fn decompress_parallel(data: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+    use rayon::prelude::*;
+
+    let (header, mut rest) = Header::read(data)?;
+    if header.flags & FLAG_INDEPENDENT == 0 && header.block_sort.is_none() {
+        return decompress(data, dictionary);
+    }
+    let config = header.config(dictionary)?;
+    let mut blocks = vec![];
+    loop {
+        let len;
+        (len, rest) = read_varint(rest)?;
+        if len == 0 {
+            break;
+        }
+        blocks.push(rest.get(..len).ok_or(Error::UnexpectedEof)?);
+        rest = &rest[len..];
+    }
+    let decoded = Result::<Vec<_>, Error>::from_par_iter(blocks.par_iter().map(|block| {
+        let mut out = vec![];
+        decompress_block(block, &mut Slide::new(), &config, usize::MAX, &mut out)?;
+        Ok(out)
+    }))?;
+    let mut out = decoded.concat();
+    if let Some(filter) = header.filter {
+        Filtering::new(filter).decode(&mut out);
+    }
+    check_content(&header, header.content_size, &out, rest)?;
+    Ok(out)
+}
+
+/// ID of the dictionary the frame starting `data` was compressed with, if it recorded one.
+///
+/// Meant for picking the dictionary to decompress with out of several. Decompressing with a
+/// different dictionary fails with [`Error::DictionaryMismatch`] before decoding any block.
+pub fn dictionary_id(data: &[u8]) -> Result<Option<u32>, Error> {
+    Ok(Header::read(data)?.0.dictionary_id)
+}
+
+/// Appends a skippable frame holding `payload`, for application metadata like file names or
+/// timestamps.
+///
+/// The frame is laid out as [`SKIPPABLE_MAGIC`], the length of `payload` (varint) and `payload`
+/// itself. Decoders skip skippable frames in front of a frame, so they may precede it without
+/// breaking readers unaware of them.
+pub fn write_skippable(out: &mut Vec<u8>, payload: &[u8]) {
+    out.extend(SKIPPABLE_MAGIC);
+    write_varint(out, payload.len());
+    out.extend_from_slice(payload);
+}
+
+/// Payload of the skippable frame starting `data` and the rest of `data`.
+///
+/// Fails with [`Error::InvalidMagic`] if `data` doesn't start with [`SKIPPABLE_MAGIC`].
+pub fn read_skippable(data: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+    let data = (data.strip_prefix(&SKIPPABLE_MAGIC)).ok_or(Error::InvalidMagic)?;
+    let (len, data) = read_varint(data)?;
+    let payload = data.get(..len).ok_or(Error::UnexpectedEof)?;
+    Ok((payload, &data[len..]))
+}
+
+/// Length of the frame starting `data`, from any skippable frames in front of it through its
+/// checksum and seek table.
+///
+/// Only the block lengths are read, so nothing is decoded or checked.
+pub fn frame_len(data: &[u8]) -> Result<usize, Error> {
+    let (header, mut rest) = Header::read(data)?;
+    let mut blocks = 0;
+    loop {
+        let len;
+        (len, rest) = read_varint(rest)?;
+        if len == 0 {
+            break;
+        }
+        rest = rest.get(len..).ok_or(Error::UnexpectedEof)?;
+        blocks += 1;
+    }
+    if header.flags & FLAG_CHECKSUM != 0 {
+        (_, rest) = read_u32(rest)?;
+    }
+    if header.flags & FLAG_SEEK_TABLE != 0 {
+        // An entry per block, the block count and the magic.
+        let table = rest.get(..blocks * 8 + 8).ok_or(Error::UnexpectedEof)?;
+        if !table.ends_with(&SEEK_TABLE_MAGIC) {
+            return Err(Error::Malformed);
+        }
+        rest = &rest[table.len()..];
+    }
+    Ok(data.len() - rest.len())
+}
+
+/// Splits concatenated frames into the individual frames, each with the skippable frames in
+/// front of it, as measured by [`frame_len`].
+///
+/// Yields at least one item, so empty data fails like any other that isn't a frame, and nothing
+/// after the first error. Data following a frame that isn't one fails with
+/// [`Error::TrailingData`].
+pub fn frames(mut data: &[u8]) -> impl Iterator<Item = Result<&[u8], Error>> {
+    let mut first = true;
+    iter::from_fn(move || {
+        if data.is_empty() && !first {
+            return None;
+        }
+        let first = mem::replace(&mut first, false);
+        match frame_len(data) {
+            Ok(len) => {
+                let frame;
+                (frame, data) = data.split_at(len);
+                Some(Ok(frame))
+            }
+            Err(Error::InvalidMagic) if !first => {
+                data = &[];
+                Some(Err(Error::TrailingData))
+            }
+            Err(err) => {
+                data = &[];
+                Some(Err(err))
+            }
+        }
+    })
+}
+
+/// Settings recorded in the header of the frame starting `data`, if any.
+///
+/// Frames written before settings were recorded and block sorted frames have none.
+pub fn settings(data: &[u8]) -> Result<Option<EncoderSettings>, Error> {
+    Ok(Header::read(data)?.0.settings)
+}
+
+/// Content size recorded in the header of the frame starting `data`, if any.
+///
+/// Meant for sizing the output of [`decompress_into`] or [`decompress_to_vec`]. The size is only
+/// verified while decompressing.
+pub fn content_size(data: &[u8]) -> Result<Option<usize>, Error> {
+    Ok(Header::read(data)?.0.content_size)
+}
+
+/// Output of [`decompress_frame`].
+trait Sink: Extend<u8> {
+    /// Everything written so far.
+    fn written(&self) -> &[u8];
+    fn written_mut(&mut self) -> &mut [u8];
+    /// Whether bytes were dropped for lack of space.
+    fn is_full(&self) -> bool;
+}
+/// Appends to a Vec, growing it as needed.
+struct Appended<'a> {
+    out: &'a mut Vec<u8>,
+    start: usize,
+}
+impl<'a> Appended<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        let start = out.len();
+        Self { out, start }
+    }
+}
+impl Extend<u8> for Appended<'_> {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        self.out.extend(iter);
+    }
+}
+impl Sink for Appended<'_> {
+    fn written(&self) -> &[u8] {
+        &self.out[self.start..]
+    }
+    fn written_mut(&mut self) -> &mut [u8] {
+        &mut self.out[self.start..]
+    }
+    fn is_full(&self) -> bool {
+        false
+    }
+}
+/// Fills a slice, dropping what doesn't fit.
+struct Filled<'a> {
+    out: &'a mut [u8],
+    len: usize,
+    full: bool,
+}
+impl<'a> Filled<'a> {
+    fn new(out: &'a mut [u8]) -> Self {
+        Self {
+            out,
+            len: 0,
+            full: false,
+        }
+    }
+}
+impl Extend<u8> for Filled<'_> {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        let mut iter = iter.into_iter();
+        for (dst, value) in self.out[self.len..].iter_mut().zip(&mut iter) {
+            *dst = value;
+            self.len += 1;
+        }
+        self.full |= iter.next().is_some();
+    }
+}
+impl Sink for Filled<'_> {
+    fn written(&self) -> &[u8] {
+        &self.out[..self.len]
+    }
+    fn written_mut(&mut self) -> &mut [u8] {
+        &mut self.out[..self.len]
+    }
+    fn is_full(&self) -> bool {
+        self.full
+    }
+}
+
+/// Decompresses the frame in `data` into `out`, returning the number of bytes written.
+///
+/// Sets `decoded` to the offset of the first block not yet decoded, or the trailer.
+fn decompress_frame(
+    data: &[u8],
+    dictionary: Option<&[u8]>,
+    len: Option<usize>,
+    out: &mut impl Sink,
+    decoded: &mut usize,
+) -> Result<usize, Error> {
+    let total = data.len();
+    let (header, mut data) = Header::read(data)?;
+    *decoded = total - data.len();
+    let mut config = header.config(dictionary)?;
+    if let (Some(expected), Some(actual)) = (len, header.content_size)
+        && expected != actual
+    {
+        return Err(Error::ContentSizeMismatch { expected, actual });
+    }
+    let expected = len.or(header.content_size);
+    let independent = header.flags & FLAG_INDEPENDENT != 0;
+    let mut filtering = header.filter.map(Filtering::new);
+    let mut window = Slide::new();
+    loop {
+        let len;
+        (len, data) = read_varint(data)?;
+        if len == 0 {
+            break;
+        }
+        let block = data.get(..len).ok_or(Error::UnexpectedEof)?;
+        data = &data[len..];
+        if independent {
+            window.clear();
+        }
+        let start = out.written().len();
+        let max_len = expected.map_or(usize::MAX, |expected| expected.saturating_sub(start));
+        decompress_block(block, &mut window, &config, max_len, out)
+            .map_err(|err| content_overrun(err, expected, start))?;
+        if out.is_full() {
+            return Err(Error::OutputTooSmall);
+        }
+        if let Some(filtering) = &mut filtering {
+            filtering.decode(&mut out.written_mut()[start..]);
+        }
+        if !independent {
+            config.dictionary = None;
+        }
+        *decoded = total - data.len();
+    }
+    let out = out.written();
+    check_content(&header, expected, out, data)?;
+    Ok(out.len())
+}
+
+/// Checks the decompressed content `out` of a frame against its `expected` length and the
+/// checksum in `trailer`, if the frame has one.
+fn check_content(
+    header: &Header,
+    expected: Option<usize>,
+    out: &[u8],
+    trailer: &[u8],
+) -> Result<(), Error> {
+    if let Some(expected) = expected
+        && out.len() != expected
+    {
+        return Err(Error::ContentSizeMismatch {
+            expected,
+            actual: out.len(),
+        });
+    }
+    if header.flags & FLAG_CHECKSUM != 0 {
+        let (expected, _) = read_u32(trailer)?;
+        let actual = xxhash::checksum(out);
+        if actual != expected {
+            return Err(Error::ChecksumMismatch { expected, actual });
+        }
+    }
+    Ok(())
+}
+
+fn write_varint(out: &mut Vec<u8>, value: usize) {
+    varint::write(out, value as u64);
+}
+
+fn read_varint(data: &[u8]) -> Result<(usize, &[u8]), Error> {
+    varint::read_usize(data).map_err(|err| match err {
+        varint::Error::UnexpectedEof => Error::UnexpectedEof,
+        varint::Error::Overflow => Error::Malformed,
+    })
+}
+
+fn read_u32(data: &[u8]) -> Result<(u32, &[u8]), Error> {
+    let (bytes, data) = data.split_first_chunk().ok_or(Error::UnexpectedEof)?;
+    Ok((u32::from_le_bytes(*bytes), data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dict;
+    use quickcheck_macros::quickcheck;
+
+    fn config() -> Config<'static> {
+        Config {
+            max_buffer_len: 0x1000,
+            match_lengths: 3..usize::MAX,
+            ..Config::default()
+        }
+    }
+    /// Config of the tests of the other frame modules, whose minimum match fits every width.
+    pub(super) fn shared_config() -> Config<'static> {
+        Config {
+            match_lengths: 4..usize::MAX,
+            ..config()
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let frame = compress(b"abcabcabc", config());
+        let mut expected = Vec::from(MAGIC);
+        // flags, window, content size, settings (3.., greedy),
+        // fixed block of Raw("abc") + RepRef { slot: 2, len: 6 } as literal, length and distance
+        // streams, end of blocks
+        #[rustfmt::skip]
+        expected.extend([
+            (FLAG_CHECKSUM | FLAG_CONTENT_SIZE) as u8 | 0x80, 0x02, 0x80, 0x20, 9,
+            3, 0, 0, 0, 0, 0,
+            9, 1, 3, 2, b'a', b'b', b'c', 3 << 2, 6 << 2 | 2, 2, 0,
+        ]);
+        expected.extend(xxhash::checksum(b"abcabcabc").to_le_bytes());
+        assert_eq!(frame, expected);
+        assert_eq!(decompress(&frame, None).unwrap(), b"abcabcabc");
+    }
+    #[test]
+    fn partial() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 5 / 2).map(|x| (x / 9 % 31 * x) as u8));
+        let frame = compress(&data, config());
+        assert_eq!(decompress_partial(&frame, None).unwrap(), data);
+        let cut = frame.len() * 3 / 4;
+        let partial = decompress_partial(&frame[..cut], None).unwrap_err();
+        assert!(matches!(
+            partial.error,
+            crate::Error::Frame(Error::UnexpectedEof)
+        ));
+        assert!(!partial.data.is_empty() && partial.data.len().is_multiple_of(BLOCK_LEN));
+        assert!(data.starts_with(&partial.data));
+        assert!(partial.offset < cut);
+        let (len, _) = read_varint(&frame[partial.offset..]).unwrap();
+        assert!(partial.offset + len > cut);
+
+        let mut corrupt = frame.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        let partial = decompress_partial(&corrupt, None).unwrap_err();
+        assert!(matches!(
+            partial.error,
+            crate::Error::Frame(Error::ChecksumMismatch { .. })
+        ));
+        assert_eq!(partial.data, data);
+        assert_eq!(partial.offset, frame.len() - 5);
+    }
+    #[test]
+    fn settings() {
+        let config = Config {
+            match_lengths: 4..101,
+            strategy: Strategy::BestOf(3),
+            max_chain: Some(16),
+            nice_len: Some(32),
+            ..config()
+        };
+        let data = b"abcdabcdabcd".repeat(20);
+        let frame = compress(&data, config.clone());
+        let expected = EncoderSettings {
+            min_match: 4,
+            max_match: Some(100),
+            strategy: Strategy::BestOf(3),
+            max_chain: Some(16),
+            nice_len: Some(32),
+        };
+        assert_eq!(super::settings(&frame), Ok(Some(expected)));
+        assert_eq!(inspect(&frame).unwrap().settings, Some(expected));
+        assert_eq!(
+            expected.to_string(),
+            "matches 4..=100, best-of=3, max chain 16, nice length 32"
+        );
+        let mut out = vec![];
+        io::Read::read_to_end(&mut Decoder::new(frame.as_slice(), None).unwrap(), &mut out)
+            .unwrap();
+        assert_eq!(out, data);
+        let sorted = compress(
+            &data,
+            Config {
+                block_sort: Some(0x100),
+                ..config
+            },
+        );
+        assert_eq!(super::settings(&sorted), Ok(None));
+    }
+    #[test]
+    fn dictionary() {
+        let samples = Vec::from_iter((0..100).map(|x| {
+            format!(
+                r#"{{"id":{x},"kind":"measurement","unit":"celsius","value":{}}}"#,
+                x % 17
+            )
+            .into_bytes()
+        }));
+        let dict = dict::train(&Vec::from_iter(samples.iter().map(Vec::as_slice)), 512);
+        let sample = br#"{"id":1000,"kind":"measurement","unit":"celsius","value":3}"#;
+        let plain = compress(sample, config());
+        let primed = compress(sample, config().with_dictionary(&dict));
+        assert!(
+            primed.len() < plain.len() / 2,
+            "{} vs {}",
+            primed.len(),
+            plain.len()
+        );
+        assert_eq!(decompress(&primed, Some(&dict)).unwrap(), sample);
+        assert_eq!(
+            decompress(&primed, None),
+            Err(Error::DictionaryMismatch {
+                expected: xxhash::checksum(&dict),
+                actual: None
+            })
+        );
+        assert!(matches!(
+            decompress(&primed, Some(b"other")),
+            Err(Error::DictionaryMismatch { .. })
+        ));
+        assert_eq!(dictionary_id(&primed), Ok(None));
+
+        let config = Config {
+            dictionary_id: Some(0xabcdef),
+            ..config().with_dictionary(&dict)
+        };
+        let frame = compress(sample, config.clone());
+        // Just the ID, the settings flag already takes a second flags byte.
+        assert_eq!(frame.len(), primed.len() + 4);
+        assert_eq!(dictionary_id(&frame), Ok(Some(0xabcdef)));
+        assert_eq!(decompress(&frame, Some(&dict)).unwrap(), sample);
+        assert!(matches!(
+            decompress(&frame, Some(b"other")),
+            Err(Error::DictionaryMismatch { .. })
+        ));
+        let mut out = vec![];
+        io::Read::read_to_end(
+            &mut Decoder::new(frame.as_slice(), Some(&dict)).unwrap(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, sample);
+        // Without a dictionary, there is nothing to identify.
+        let frame = compress(
+            sample,
+            Config {
+                dictionary: None,
+                ..config
+            },
+        );
+        assert_eq!(dictionary_id(&frame), Ok(None));
+        assert_eq!(frame, plain);
+    }
+    #[test]
+    fn corrupt() {
+        assert_eq!(decompress(b"SLZ", None), Err(Error::InvalidMagic));
+        let frame = compress(b"abcabcabc", config());
+        assert_eq!(
+            decompress(&frame[..frame.len() - 1], None),
+            Err(Error::UnexpectedEof)
+        );
+        let mut bad = frame.clone();
+        bad[5] |= 0x04;
+        assert_eq!(decompress(&bad, None), Err(Error::UnsupportedFlags(0x312)));
+        let mut bad = frame.clone();
+        bad[19] = b'x';
+        assert!(matches!(
+            decompress(&bad, None),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        let mut bad = frame.clone();
+        bad[24] = 0x20;
+        assert_eq!(decompress(&bad, None), Err(Error::Malformed));
+        let mut bad = frame.clone();
+        bad[8] = 8;
+        assert_eq!(
+            decompress(&bad, None),
+            Err(Error::ContentSizeMismatch {
+                expected: 8,
+                actual: 9
+            })
+        );
+    }
+    #[test]
+    fn memory_budget() {
+        let mut x = 1u32;
+        let noise = Vec::from_iter((0..0x800).map(|_| {
+            x = x.wrapping_mul(0x2c9277b5).wrapping_add(0xac564b05);
+            (x >> 24) as u8
+        }));
+        let data = noise.repeat(4);
+        let budgeted = Config {
+            lookahead: 0x100,
+            memory_budget: Some(0x100 + 0x400 * Config::<u8>::window_value_bytes(3)),
+            ..config()
+        };
+        let frame = compress(&data, budgeted.clone());
+        let window = Header::read(&frame).unwrap().0.max_buffer_len;
+        assert_eq!(window, 0x400);
+        assert_eq!(window, budgeted.window_for(3));
+        assert_eq!(decompress(&frame, None).unwrap(), data);
+        // The repeats are out of reach of the shrunk window.
+        assert!(frame.len() > data.len());
+        let unbounded = compress(&data, config().fit_memory(3));
+        assert!(unbounded.len() < data.len() / 2);
+    }
+    #[test]
+    fn exact() {
+        let data = b"abcabcabc".repeat(BLOCK_LEN / 4);
+        let frame = compress(&data, config());
+        assert_eq!(decompress_exact(&frame, None, data.len()).unwrap(), data);
+        assert_eq!(
+            decompress_exact(&frame, None, 5),
+            Err(Error::ContentSizeMismatch {
+                expected: 5,
+                actual: data.len()
+            })
+        );
+        // Without a recorded size, the output is checked while decoding.
+        let mut unsized_frame = vec![];
+        write_header(&mut unsized_frame, &config(), 0, None, None);
+        unsized_frame.extend_from_slice(Header::read(&frame).unwrap().1);
+        assert_eq!(decompress(&unsized_frame, None).unwrap(), data);
+        assert_eq!(
+            decompress_exact(&unsized_frame, None, BLOCK_LEN),
+            Err(Error::ContentSizeMismatch {
+                expected: BLOCK_LEN,
+                actual: 2 * BLOCK_LEN
+            })
+        );
+    }
+    #[test]
+    fn into() {
+        let data = b"abcabcabc".repeat(BLOCK_LEN / 4);
+        let frame = compress(&data, config());
+        assert_eq!(content_size(&frame), Ok(Some(data.len())));
+        let mut out = vec![0; data.len() + 1];
+        assert_eq!(decompress_into(&frame, None, &mut out), Ok(data.len()));
+        assert_eq!(out[..data.len()], data);
+        assert_eq!(
+            decompress_into(&frame, None, &mut out[..data.len() - 1]),
+            Err(Error::OutputTooSmall)
+        );
+
+        let mut out = Vec::from(b"head");
+        out.reserve_exact(data.len());
+        let capacity = out.capacity();
+        assert_eq!(decompress_to_vec(&frame, None, &mut out), Ok(data.len()));
+        assert_eq!(out.capacity(), capacity);
+        assert_eq!(out[..4], *b"head");
+        assert_eq!(out[4..], data);
+        assert_eq!(
+            decompress_to_vec(&frame[..frame.len() - 1], None, &mut out),
+            Err(Error::UnexpectedEof)
+        );
+        assert_eq!(out.len(), 4 + data.len());
+    }
+    #[test]
+    fn hostile_lengths() {
+        let frame_of = |window: usize, content_size: Option<usize>, len: usize| {
+            let mut frame = Vec::from(MAGIC);
+            write_varint(
+                &mut frame,
+                content_size.map_or(0, |_| FLAG_CONTENT_SIZE as usize),
+            );
+            write_varint(&mut frame, window);
+            if let Some(size) = content_size {
+                write_varint(&mut frame, size);
+            }
+            // A fixed block of a single run of zeros.
+            let mut lengths = vec![];
+            write_varint(&mut lengths, len << 2 | 3);
+            let mut block = vec![1, 1];
+            write_varint(&mut block, lengths.len());
+            block.push(0);
+            block.extend(lengths);
+            write_varint(&mut frame, block.len());
+            frame.extend(block);
+            frame.push(0);
+            frame
+        };
+        assert_eq!(decompress(&frame_of(0x10, None, 5), None), Ok(vec![0; 5]));
+        assert_eq!(
+            decompress(&frame_of(usize::MAX, None, 5), None),
+            Err(Error::LengthOutOfBounds {
+                len: usize::MAX,
+                max: MAX_WINDOW_LEN
+            })
+        );
+        let huge = frame_of(0x10, None, usize::MAX >> 2);
+        for result in [
+            decompress(&huge, None),
+            decompress_into(&huge, None, &mut [0; 16]).map(|_| vec![]),
+        ] {
+            assert_eq!(
+                result,
+                Err(Error::LengthOutOfBounds {
+                    len: usize::MAX >> 2,
+                    max: BLOCK_LEN
+                })
+            );
+        }
+        assert_eq!(
+            decompress(&frame_of(0x10, Some(4), 5), None),
+            Err(Error::ContentSizeMismatch {
+                expected: 4,
+                actual: 5
+            })
+        );
+    }
+    #[test]
+    fn filtered() {
+        use std::io::{Read, Write};
+        use std::num::NonZero;
+
+        // Sorted integers with irregular gaps hardly repeat, but their differences do.
+        let data =
+            Vec::from_iter((0..BLOCK_LEN as u32).flat_map(|x| (x * 7 + x % 3).to_le_bytes()));
+        let filter = Filter::Delta(NonZero::new(4).unwrap());
+        let frame = compress_filtered(&data, config(), filter);
+        assert!(frame.len() * 20 < compress(&data, config()).len());
+        assert_eq!(Header::read(&frame).unwrap().0.filter, Some(filter));
+        assert_eq!(decompress(&frame, None).unwrap(), data);
+        let mut out = vec![0; data.len()];
+        assert_eq!(decompress_into(&frame, None, &mut out), Ok(data.len()));
+        assert_eq!(out, data);
+
+        let filter = Filter::Xor(NonZero::new(12).unwrap());
+        let mut encoder = Encoder::<_, 3>::with_filter(vec![], config(), filter).unwrap();
+        for chunk in data.chunks(0x1234) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let frame = encoder.finish().unwrap();
+        assert_eq!(decompress(&frame, None).unwrap(), data);
+        let mut out = vec![];
+        Decoder::new(frame.as_slice(), None)
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+
+        assert_eq!(
+            SeekableDecoder::new(io::Cursor::new(&frame), None).err(),
+            Some(Error::NotSeekable)
+        );
+        let mut bad = frame.clone();
+        // Id byte and one byte width end the header.
+        let id = frame.len() - Header::read(&frame).unwrap().1.len() - 2;
+        bad[id] = 0xff;
+        assert_eq!(decompress(&bad, None), Err(Error::Malformed));
+    }
+    #[test]
+    fn skippable() {
+        use std::io::Read;
+
+        let data = b"name: data.bin, mtime: 1700000000".repeat(0x100);
+        let mut archive = vec![];
+        write_skippable(&mut archive, b"data.bin");
+        write_skippable(&mut archive, &[]);
+        let frame_start = archive.len();
+        archive.extend(compress_seekable(&data, config()));
+
+        let (name, rest) = read_skippable(&archive).unwrap();
+        assert_eq!(name, b"data.bin");
+        let (empty, rest) = read_skippable(rest).unwrap();
+        assert_eq!((empty, rest.len()), (&[][..], archive.len() - frame_start));
+        assert_eq!(read_skippable(rest), Err(Error::InvalidMagic));
+
+        assert_eq!(decompress(&archive, None).unwrap(), data);
+        assert_eq!(content_size(&archive), Ok(Some(data.len())));
+        let mut out = vec![];
+        Decoder::new(archive.as_slice(), None)
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, data);
+        let mut decoder = SeekableDecoder::new(io::Cursor::new(&archive), None).unwrap();
+        assert_eq!(
+            decoder.read_at(0x1000..0x1010).unwrap(),
+            &data[0x1000..0x1010]
+        );
+
+        let truncated = &archive[..6];
+        assert_eq!(read_skippable(truncated), Err(Error::UnexpectedEof));
+        assert_eq!(decompress(truncated, None), Err(Error::UnexpectedEof));
+        assert!(matches!(
+            Decoder::new(truncated, None),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+    #[test]
+    fn long_range() {
+        let mut x = 7u32;
+        let mut noise = |len| {
+            Vec::from_iter((0..len).map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            }))
+        };
+        // An image repeated after more than a block, far outside of the window.
+        let image = noise(0x8000);
+        let data = [image.clone(), noise(BLOCK_LEN + 0x1234), image].concat();
+        let long = LongRange {
+            window_len: 0x100000,
+            min_match: 32,
+            memory: 0x4000,
+        };
+        let frame = compress_long_range(&data, config(), long);
+        assert!(frame.len() + 0x7000 < compress(&data, config()).len());
+        assert_eq!(
+            Header::read(&frame).unwrap().0.max_buffer_len,
+            long.window_len
+        );
+        assert_eq!(decompress(&frame, None).unwrap(), data);
+
+        let dictionary = noise(0x100);
+        let config = Config {
+            dictionary: Some(&dictionary),
+            ..config()
+        };
+        let frame = compress_long_range(&data, config.clone(), long);
+        assert_eq!(decompress(&frame, Some(&dictionary)).unwrap(), data);
+        let sorted = Config {
+            block_sort: Some(0x8000),
+            ..config
+        };
+        let frame = compress_long_range(&data, sorted.clone(), long);
+        assert_eq!(frame, compress(&data, sorted));
+    }
+    #[test]
+    fn block_sort() {
+        use crate::lz::ConfigError;
+        use std::io::Write;
+
+        let words = [
+            "lorem",
+            "ipsum",
+            "dolor",
+            "sit",
+            "amet",
+            "consectetur",
+            "adipiscing",
+        ];
+        let mut x = 1u32;
+        let text = Vec::from_iter((0..0x8000).flat_map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            [words[x as usize % words.len()].as_bytes(), b" "].concat()
+        }));
+        let sorted = Config {
+            block_sort: Some(0x10000),
+            ..config()
+        };
+        let frame = compress(&text, sorted.clone());
+        assert_eq!(Header::read(&frame).unwrap().0.block_sort, Some(0x10000));
+        assert!(frame.len() < compress(&text, config()).len());
+        assert_eq!(decompress(&frame, None).unwrap(), text);
+
+        let mut encoder = Encoder::<_, 3>::new(vec![], sorted.clone()).unwrap();
+        encoder.write_all(&text[..1000]).unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(&text[1000..]).unwrap();
+        assert_eq!(decompress(&encoder.finish().unwrap(), None).unwrap(), text);
+
+        let frame = compress_seekable(&text, sorted.clone());
+        let mut decoder = SeekableDecoder::new(io::Cursor::new(&frame), None).unwrap();
+        assert_eq!(decoder.block_count(), text.len().div_ceil(0x10000));
+        assert_eq!(
+            decoder.read_at(0xfff0..0x10010).unwrap(),
+            text[0xfff0..0x10010]
+        );
+
+        // Blocks longer than announced in the header are rejected.
+        let frame = compress(&text[..20], sorted.clone());
+        let mut bad = vec![];
+        let short = Config {
+            block_sort: Some(10),
+            ..sorted
+        };
+        write_header(&mut bad, &short, 0, None, None);
+        bad.extend_from_slice(Header::read(&frame).unwrap().1);
+        assert_eq!(
+            decompress(&bad, None),
+            Err(Error::LengthOutOfBounds { len: 20, max: 10 })
+        );
+        assert_eq!(
+            Config {
+                block_sort: Some(0),
+                ..config()
+            }
+            .validate(),
+            Err(ConfigError::EmptyBlock)
+        );
+    }
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel() {
+        let data = Vec::from_iter(
+            (0..BLOCK_LEN as u32 * 3).map(|x| (x / 3 % 251) as u8 ^ (x >> 14) as u8),
+        );
+        let config = Config {
+            max_buffer_len: 0x8000,
+            ..config()
+        };
+        let frame = compress_parallel(&data, config.clone(), BLOCK_LEN, ChunkWindow::Seeded);
+        assert_eq!(frame, compress(&data, config.clone()));
+        let frame = compress_parallel(&data, config.clone(), 0x9000, ChunkWindow::Independent);
+        assert_eq!(decompress(&frame, None).unwrap(), data);
+        let dictionary = b"dictionary".repeat(0x100);
+        let config = config.with_dictionary(&dictionary);
+        let frame = compress_parallel(&data[..0x4000], config, 0x1000, ChunkWindow::Seeded);
+        assert_eq!(
+            decompress(&frame, Some(&dictionary)).unwrap(),
+            &data[..0x4000]
+        );
+    }
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn concatenated() {
+        use std::io::Read;
+
+        let a = Vec::from_iter((0..BLOCK_LEN as u32 + 0x321).map(|x| (x / 3 % 251) as u8));
+        let b = b"second frame, second frame".repeat(0x40);
+        let mut data = compress(&a, config());
+        write_skippable(&mut data, b"meta");
+        data.extend(compress_seekable(&b, config()));
+        data.extend(compress(&a, config()));
+        let content = [&a[..], &b, &a].concat();
+
+        let lens = Vec::from_iter(frames(&data).map(|frame| frame.unwrap().len()));
+        assert_eq!(lens.len(), 3);
+        assert_eq!(lens.iter().sum::<usize>(), data.len());
+        assert_eq!(frame_len(&data).unwrap(), lens[0]);
+        assert_eq!(decompress_concatenated(&data, None).unwrap(), content);
+        assert_eq!(decompress(&data, None).unwrap(), a);
+
+        let mut decoded = vec![];
+        let mut decoder = Decoder::new(data.as_slice(), None).unwrap();
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, a);
+        decoded.clear();
+        let mut decoder = Decoder::new(data.as_slice(), None).unwrap();
+        decoder.set_concatenated(true);
+        decoder.read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded, content);
+
+        let mut garbage = data.clone();
+        garbage.extend(b"trailing");
+        assert_eq!(
+            decompress_concatenated(&garbage, None),
+            Err(Error::TrailingData)
+        );
+        let mut decoder = Decoder::new(garbage.as_slice(), None).unwrap();
+        decoder.set_concatenated(true);
+        let err = decoder.read_to_end(&mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), Error::TrailingData.to_string());
+        assert_eq!(
+            decompress_concatenated(&data[..data.len() - 1], None),
+            Err(Error::UnexpectedEof)
+        );
+        assert!(decompress_concatenated(&[], None).is_err());
+    }
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_decode() {
+        let data = Vec::from_iter(
+            (0..BLOCK_LEN as u32 * 3 + 0x123).map(|x| (x / 7 % 239) as u8 ^ (x >> 13) as u8),
+        );
+        let sorted = Config {
+            block_sort: Some(0x8000),
+            ..config()
+        };
+        for frame in [
+            compress_seekable(&data, config()),
+            compress(&data, sorted.clone()),
+            compress_seekable(&data, sorted),
+            compress(&data, config()),
+        ] {
+            assert_eq!(decompress_parallel(&frame, None).unwrap(), data);
+        }
+        let dictionary = b"dictionary".repeat(0x100);
+        let frame = compress_seekable(&data, config().with_dictionary(&dictionary));
+        assert_eq!(
+            decompress_parallel(&frame, Some(&dictionary)).unwrap(),
+            data
+        );
+
+        let mut frame = compress_seekable(&data, config());
+        let at = frame.len() / 2;
+        frame[at] ^= 1;
+        assert!(decompress_parallel(&frame, None).is_err());
+    }
+    #[test]
+    fn deterministic() {
+        use std::hash::{BuildHasherDefault, DefaultHasher};
+
+        let data = Vec::from_iter(
+            (0..BLOCK_LEN as u32 + 0x4000).map(|x| (x / 5 % 241) as u8 ^ (x >> 12) as u8),
+        );
+        for strategy in [Strategy::Greedy, Strategy::Lazy(2), Strategy::Optimal] {
+            let config = Config {
+                strategy,
+                ..config()
+            };
+            let frame = compress(&data, config.clone());
+            assert_eq!(frame, compress(&data, config.clone()));
+            // Neither a fixed nor a fresh random seed changes the output.
+            let mut fixed = vec![];
+            write_header(&mut fixed, &config, 0, Some(data.len()), None);
+            compress_blocks(
+                &mut SearchBuffer::<u8, 3, BuildHasherDefault<DefaultHasher>>::new(),
+                &data,
+                config.clone(),
+                &mut fixed,
+            );
+            write_trailer(&mut fixed, xxhash::checksum(&data));
+            assert_eq!(frame, fixed);
+            #[cfg(feature = "parallel")]
+            {
+                let mut independent = None;
+                for threads in [1, 4] {
+                    let pool = rayon::ThreadPoolBuilder::new()
+                        .num_threads(threads)
+                        .build()
+                        .unwrap();
+                    let parallel = |chunk_len, window| {
+                        pool.install(|| compress_parallel(&data, config.clone(), chunk_len, window))
+                    };
+                    assert_eq!(parallel(BLOCK_LEN, ChunkWindow::Seeded), frame);
+                    let frame = parallel(0x7000, ChunkWindow::Independent);
+                    assert_eq!(independent.get_or_insert_with(|| frame.clone()), &frame);
+                }
+            }
+        }
+    }
+    #[quickcheck]
+    fn fuzz(data: Vec<u8>, repeat: u16, dictionary: Vec<u8>, strategy: u8) {
+        let data = data.repeat(repeat as usize % 0x40 + 1);
+        let config = Config {
+            max_buffer_len: 0x100,
+            strategy: match strategy % 5 {
+                4 => Strategy::Optimal,
+                level => Strategy::Lazy(level as usize),
+            },
+            ..config()
+        };
+        assert_eq!(
+            decompress(&compress(&data, config.clone()), None).unwrap(),
+            data
+        );
+        let config = config.with_dictionary(&dictionary);
+        let frame = compress(&data, config);
+        assert_eq!(decompress(&frame, Some(&dictionary)).unwrap(), data);
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+use super::{
+    BLOCK_LEN, Error, FLAG_INDEPENDENT, FLAG_SEEK_TABLE, Header, compress_blocks, decompress_block,
+    fit_frame, read_varint, stream::skip_skippable, write_header, write_trailer,
+};
+use crate::{Slide, lz::Config, search_buffer::SearchBuffer, util::xxhash};
+
+/// Magic number ending the seek table appended to seekable frames.
+pub const SEEK_TABLE_MAGIC: [u8; 4] = *b"SLZs";
+
+/// Compresses `data` into a frame of independently decodable blocks followed by a seek table.
+///
+/// The seek table holds the compressed and uncompressed size (u32 LE each) of every block,
+/// then the block count (u32 LE) and [`SEEK_TABLE_MAGIC`], so it can be found from the end.
+/// Restarting the window every [`BLOCK_LEN`] costs some ratio in exchange for random access.
+pub fn compress_seekable(data: &[u8], config: Config) -> Vec<u8> {
+    match config.match_lengths.start {
+        1 => compress_seekable_with::<1>(data, config),
+        2 => compress_seekable_with::<2>(data, config),
+        3 => compress_seekable_with::<3>(data, config),
+        _ => compress_seekable_with::<4>(data, config),
+    }
+}
+
+fn compress_seekable_with<const N: usize>(data: &[u8], config: Config) -> Vec<u8> {
+    let config = fit_frame(config, N);
+    let mut out = vec![];
+    write_header(
+        &mut out,
+        &config,
+        FLAG_INDEPENDENT | FLAG_SEEK_TABLE,
+        Some(data.len()),
+        None,
+    );
+    let mut table = vec![];
+    // Exactly one block per seek table entry.
+    for chunk in data.chunks(config.block_sort.unwrap_or(BLOCK_LEN)) {
+        let start = out.len();
+        compress_blocks(
+            &mut SearchBuffer::<u8, N>::new(),
+            chunk,
+            config.clone(),
+            &mut out,
+        );
+        table.extend(((out.len() - start) as u32).to_le_bytes());
+        table.extend((chunk.len() as u32).to_le_bytes());
+    }
+    write_trailer(&mut out, xxhash::checksum(data));
+    let count = table.len() / 8;
+    out.extend(table);
+    out.extend((count as u32).to_le_bytes());
+    out.extend(SEEK_TABLE_MAGIC);
+    out
+}
+
+/// Random access into a frame produced by [`compress_seekable`], decoding only the needed blocks.
+///
+/// The frame must start at the reader's current position, after any skippable frames, and end
+/// at the end of the reader.
+pub struct SeekableDecoder<'a, R> {
+    reader: R,
+    config: Config<'a>,
+    /// Compressed and uncompressed start of every block, followed by the end of the last one.
+    blocks: Vec<(u64, u64)>,
+}
+impl<'a, R: Read + Seek> SeekableDecoder<'a, R> {
+    pub fn new(mut reader: R, dictionary: Option<&'a [u8]>) -> Result<Self, Error> {
+        skip_skippable(&mut reader)?;
+        let start = reader.seek(SeekFrom::Current(-4))?;
+        let mut head = Vec::with_capacity(Header::MAX_LEN);
+        (&mut reader)
+            .take(Header::MAX_LEN as u64)
+            .read_to_end(&mut head)?;
+        let (header, rest) = Header::read(&head)?;
+        // A filter carries state across blocks.
+        if header.flags & (FLAG_INDEPENDENT | FLAG_SEEK_TABLE) != FLAG_INDEPENDENT | FLAG_SEEK_TABLE
+            || header.filter.is_some()
+        {
+            return Err(Error::NotSeekable);
+        }
+        let config = header.config(dictionary)?;
+
+        let mut footer = [0; 8];
+        let footer_start = reader.seek(SeekFrom::End(-(footer.len() as i64)))?;
+        reader.read_exact(&mut footer)?;
+        let (count, magic) = footer.split_at(4);
+        if magic != SEEK_TABLE_MAGIC {
+            return Err(Error::NotSeekable);
+        }
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        // The count is untrusted, the table has to fit between header and footer.
+        if count as u64 * 8 > footer_start.saturating_sub(start) {
+            return Err(Error::Malformed);
+        }
+        let mut table = vec![0; count * 8];
+        let table_start = reader.seek(SeekFrom::End(-((table.len() + footer.len()) as i64)))?;
+        reader.read_exact(&mut table)?;
+
+        let mut block = (start + (head.len() - rest.len()) as u64, 0);
+        let mut blocks = vec![block];
+        for entry in table.chunks_exact(8) {
+            let (compressed, uncompressed) = entry.split_at(4);
+            block.0 += u32::from_le_bytes(compressed.try_into().unwrap()) as u64;
+            // Blocks are read whole, so they have to end before the table.
+            if block.0 > table_start {
+                return Err(Error::Malformed);
+            }
+            let uncompressed = u32::from_le_bytes(uncompressed.try_into().unwrap()) as usize;
+            if uncompressed > config.block_sort.unwrap_or(BLOCK_LEN) {
+                return Err(Error::Malformed);
+            }
+            block.1 += uncompressed as u64;
+            blocks.push(block);
+        }
+        if header
+            .content_size
+            .is_some_and(|size| size as u64 != block.1)
+        {
+            return Err(Error::Malformed);
+        }
+        Ok(Self {
+            reader,
+            config,
+            blocks,
+        })
+    }
+    /// Length of the decompressed content.
+    pub fn len(&self) -> u64 {
+        self.blocks.last().unwrap().1
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn block_count(&self) -> usize {
+        self.blocks.len() - 1
+    }
+    /// Decompresses the given range of the content, clamped to [`Self::len`].
+    pub fn read_at(&mut self, range: Range<u64>) -> Result<Vec<u8>, Error> {
+        let range = range.start.min(self.len())..range.end.clamp(range.start, self.len());
+        let mut out = Vec::with_capacity((range.end - range.start) as usize);
+        let first = self
+            .blocks
+            .partition_point(|&(_, start)| start <= range.start)
+            - 1;
+        let mut window = Slide::new();
+        let mut record = vec![];
+        let mut decoded = vec![];
+        for x in first..self.block_count() {
+            let ((offset, start), (end_offset, end)) = (self.blocks[x], self.blocks[x + 1]);
+            if start >= range.end {
+                break;
+            }
+            record.resize((end_offset - offset) as usize, 0);
+            self.reader.seek(SeekFrom::Start(offset))?;
+            self.reader.read_exact(&mut record)?;
+            let (len, block) = read_varint(&record)?;
+            if len != block.len() {
+                return Err(Error::Malformed);
+            }
+            window.clear();
+            decoded.clear();
+            let len = (end - start) as usize;
+            decompress_block(block, &mut window, &self.config, len, &mut decoded)?;
+            if decoded.len() as u64 != end - start {
+                return Err(Error::Malformed);
+            }
+            let needed = range.start.max(start) - start..range.end.min(end) - start;
+            out.extend_from_slice(&decoded[needed.start as usize..needed.end as usize]);
+        }
+        Ok(out)
+    }
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::tests::shared_config as config;
+    use crate::frame::{compress, decompress};
+    use std::io::Cursor;
+
+    #[test]
+    fn read_at() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 5 / 2).map(|x| (x / 5 % 253) as u8));
+        let frame = compress_seekable(&data, config());
+        assert_eq!(decompress(&frame, None).unwrap(), data);
+        let mut decoder = SeekableDecoder::new(Cursor::new(&frame), None).unwrap();
+        assert_eq!(decoder.len(), data.len() as u64);
+        assert_eq!(decoder.block_count(), 3);
+        let len = data.len() as u64;
+        let block = BLOCK_LEN as u64;
+        for range in [
+            0..10,
+            block - 3..block + 3,
+            10..2 * block + 100,
+            len - 5..len + 5,
+            len..len,
+            7..7,
+            0..len,
+        ] {
+            let expected = &data[range.start.min(len) as usize..range.end.min(len) as usize];
+            assert_eq!(decoder.read_at(range).unwrap(), expected);
+        }
+    }
+    #[test]
+    fn dictionary() {
+        let dictionary = b"0123456789".repeat(10);
+        let data = b"0123456789abcdefghij0123456789".repeat(0x2000);
+        let frame = compress_seekable(&data, config().with_dictionary(&dictionary));
+        assert_eq!(decompress(&frame, Some(&dictionary)).unwrap(), data);
+        assert!(matches!(
+            SeekableDecoder::new(Cursor::new(&frame), None),
+            Err(Error::DictionaryMismatch { .. })
+        ));
+        let mut decoder = SeekableDecoder::new(Cursor::new(&frame), Some(&dictionary)).unwrap();
+        let range = BLOCK_LEN as u64 - 10..BLOCK_LEN as u64 + 10;
+        assert_eq!(
+            decoder.read_at(range.clone()).unwrap(),
+            &data[range.start as usize..range.end as usize]
+        );
+    }
+    #[test]
+    fn forged_table() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 3 / 2).map(|x| (x / 5 % 253) as u8));
+        let frame = compress_seekable(&data, config());
+        let table = frame.len() - 8 - 2 * 8;
+        let forged = |at: usize, value: u32| {
+            let mut frame = frame.clone();
+            frame[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            SeekableDecoder::new(Cursor::new(frame), None).map(|_| ())
+        };
+        assert!(SeekableDecoder::new(Cursor::new(&frame), None).is_ok());
+        // Compressed sizes past the table, uncompressed sizes past a block, counts past the frame.
+        assert_eq!(forged(table, u32::MAX), Err(Error::Malformed));
+        assert_eq!(forged(table + 4, u32::MAX), Err(Error::Malformed));
+        assert_eq!(forged(frame.len() - 8, u32::MAX), Err(Error::Malformed));
+    }
+    #[test]
+    fn not_seekable() {
+        let frame = compress(b"abcabcabc", config());
+        assert!(matches!(
+            SeekableDecoder::new(Cursor::new(&frame), None),
+            Err(Error::NotSeekable)
+        ));
+    }
+}
@@ -0,0 +1,836 @@
+use std::{
+    hash::Hasher,
+    io::{self, Read, Write},
+};
+
+use super::{
+    BLOCK_LEN, Error, FLAG_BLOCK_SORT, FLAG_CHECKSUM, FLAG_CONTENT_SIZE, FLAG_DICTIONARY,
+    FLAG_DICTIONARY_ID, FLAG_FILTER, FLAG_INDEPENDENT, FLAG_SEEK_TABLE, FLAG_SETTINGS, Header,
+    MAGIC, SKIPPABLE_MAGIC, compress_blocks, content_overrun, decompress_block, fit_frame,
+    read_varint, write_header, write_trailer,
+};
+use crate::{
+    Slide,
+    filter::{Filter, Filtering},
+    lz::{Cancel, Config, Progress},
+    search_buffer::SearchBuffer,
+    util::{SliceExt, xxhash::XxHash64},
+};
+
+/// How much of the encoder state [`Encoder::flush_with`] resets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    /// Emit all pending input as a block, so the decoder can produce every byte written so far.
+    Sync,
+    /// Like [`Flush::Sync`], but also restart the window, so later blocks never reference
+    /// data from before the flush.
+    Full,
+}
+
+/// Writer independent state of a streaming frame encoder, collecting compressed bytes in `out`.
+pub(super) struct EncoderState<'a, const N: usize> {
+    config: Config<'a>,
+    search_buffer: SearchBuffer<u8, N>,
+    /// Input compressed into each block.
+    block_len: usize,
+    pending: Vec<u8>,
+    pub(super) out: Vec<u8>,
+    checksum: XxHash64,
+    content_size: Option<usize>,
+    filtering: Option<Filtering>,
+    written: usize,
+    finished: bool,
+}
+impl<'a, const N: usize> EncoderState<'a, N> {
+    /// Starts a new frame, leaving its header in `out`.
+    pub(super) fn new(
+        config: Config<'a>,
+        content_size: Option<usize>,
+        filter: Option<Filter>,
+    ) -> io::Result<Self> {
+        config
+            .validate_for(N)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let config = fit_frame(config, N);
+        let mut out = vec![];
+        write_header(&mut out, &config, 0, content_size, filter);
+        let block_len = config.block_sort.unwrap_or(BLOCK_LEN);
+        Ok(Self {
+            config,
+            search_buffer: SearchBuffer::new(),
+            block_len,
+            pending: Vec::with_capacity(block_len),
+            out,
+            checksum: XxHash64::default(),
+            content_size,
+            filtering: filter.map(Filtering::new),
+            written: 0,
+            finished: false,
+        })
+    }
+    pub(super) fn is_finished(&self) -> bool {
+        self.finished
+    }
+    fn write_block(&mut self, len: usize) {
+        let block = &mut self.pending[..len];
+        self.checksum.write(block);
+        if let Some(filtering) = &mut self.filtering {
+            filtering.encode(block);
+        }
+        compress_blocks(
+            &mut self.search_buffer,
+            block,
+            self.config.clone(),
+            &mut self.out,
+        );
+        // The dictionary only primes the window once.
+        self.config.dictionary = None;
+        self.pending.drain(..len);
+    }
+    /// Buffers a prefix of `buf`, compressing it into `out` once a block is complete.
+    pub(super) fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(finished());
+        }
+        if let Some(expected) = self.content_size
+            && self.written + buf.len() > expected
+        {
+            return Err(content_size_mismatch(expected, self.written + buf.len()));
+        }
+        let buf = buf.get_clamped(..self.block_len - self.pending.len());
+        self.pending.extend_from_slice(buf);
+        self.written += buf.len();
+        if self.pending.len() == self.block_len {
+            self.write_block(self.block_len);
+        }
+        Ok(buf.len())
+    }
+    /// Compresses all pending input into `out`, see [`Flush`].
+    pub(super) fn flush(&mut self, mode: Flush) {
+        if !self.pending.is_empty() {
+            self.write_block(self.pending.len());
+        }
+        if mode == Flush::Full {
+            self.search_buffer = SearchBuffer::new();
+        }
+    }
+    /// Compresses all pending input and the frame trailer into `out`.
+    pub(super) fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Err(finished());
+        }
+        self.flush(Flush::Sync);
+        if let Some(expected) = self.content_size
+            && self.written != expected
+        {
+            return Err(content_size_mismatch(expected, self.written));
+        }
+        write_trailer(&mut self.out, self.checksum.finish_u32());
+        self.finished = true;
+        Ok(())
+    }
+}
+fn finished() -> io::Error {
+    io::Error::other("Encoder already finished")
+}
+fn content_size_mismatch(expected: usize, actual: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        Error::ContentSizeMismatch { expected, actual },
+    )
+}
+
+/// Streaming frame encoder, compressing everything written to it into `W`.
+///
+/// Input is buffered until a full [`BLOCK_LEN`] block (or `block_sort` block) is available or the
+/// encoder is flushed.
+/// Blocks are byte aligned and self-delimiting, so a flushed frame prefix is always decodable.
+/// The frame is completed by [`Encoder::finish`], or on drop (ignoring errors).
+pub struct Encoder<'a, W: Write, const N: usize = 4> {
+    writer: Option<W>,
+    state: EncoderState<'a, N>,
+    progress: Option<Progress<'a>>,
+    cancel: Option<Cancel>,
+}
+impl<'a, W: Write, const N: usize> Encoder<'a, W, N> {
+    /// Writes the frame header and starts a new frame.
+    pub fn new(writer: W, config: Config<'a>) -> io::Result<Self> {
+        Self::with_header(writer, config, None, None)
+    }
+    /// Like [`Encoder::new`], but records `content_size` in the header.
+    ///
+    /// Writing more than `content_size` bytes, or finishing after fewer, fails with
+    /// [`io::ErrorKind::InvalidInput`].
+    pub fn with_content_size(
+        writer: W,
+        config: Config<'a>,
+        content_size: usize,
+    ) -> io::Result<Self> {
+        Self::with_header(writer, config, Some(content_size), None)
+    }
+    /// Like [`Encoder::new`], but applies `filter` to the input before compressing it and records
+    /// it in the header, so decoding undoes it.
+    pub fn with_filter(writer: W, config: Config<'a>, filter: Filter) -> io::Result<Self> {
+        Self::with_header(writer, config, None, Some(filter))
+    }
+    fn with_header(
+        writer: W,
+        config: Config<'a>,
+        content_size: Option<usize>,
+        filter: Option<Filter>,
+    ) -> io::Result<Self> {
+        let mut encoder = Self {
+            writer: Some(writer),
+            state: EncoderState::new(config, content_size, filter)?,
+            progress: None,
+            cancel: None,
+        };
+        encoder.write_out()?;
+        Ok(encoder)
+    }
+    pub fn get_ref(&self) -> &W {
+        self.writer.as_ref().unwrap()
+    }
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.as_mut().unwrap()
+    }
+    /// Reports the bytes written to the encoder and compressed bytes written to `W` to
+    /// `progress`, replacing any previous callback.
+    pub fn set_progress(&mut self, progress: Progress<'a>) {
+        self.progress = Some(progress);
+    }
+    /// Fails writes and finishing with [`Error::Cancelled`] once `cancel` is cancelled.
+    ///
+    /// Checked before each write, so at most one more block is compressed.
+    pub fn set_cancel(&mut self, cancel: Cancel) {
+        self.cancel = Some(cancel);
+    }
+    fn check_cancel(&self) -> io::Result<()> {
+        match self.cancel.as_ref().is_some_and(Cancel::is_cancelled) {
+            true => Err(Error::Cancelled.into()),
+            false => Ok(()),
+        }
+    }
+    /// Writes everything compressed so far to the inner writer.
+    fn write_out(&mut self) -> io::Result<()> {
+        self.writer
+            .as_mut()
+            .ok_or_else(finished)?
+            .write_all(&self.state.out)?;
+        if let Some(progress) = &mut self.progress {
+            progress.advance(0, self.state.out.len(), 0);
+        }
+        self.state.out.clear();
+        Ok(())
+    }
+    /// Emits all pending input, see [`Flush`].
+    pub fn flush_with(&mut self, mode: Flush) -> io::Result<()> {
+        self.state.flush(mode);
+        self.write_out()?;
+        self.writer.as_mut().ok_or_else(finished)?.flush()
+    }
+    /// Emits all pending input and the frame trailer, returning the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.try_finish()?;
+        Ok(self.writer.take().unwrap())
+    }
+    fn try_finish(&mut self) -> io::Result<()> {
+        self.check_cancel()?;
+        self.state.finish()?;
+        self.write_out()?;
+        self.writer.as_mut().ok_or_else(finished)?.flush()
+    }
+}
+impl<W: Write, const N: usize> Write for Encoder<'_, W, N> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_cancel()?;
+        let len = self.state.write(buf)?;
+        if let Some(progress) = &mut self.progress {
+            progress.advance(len, 0, 0);
+        }
+        if !self.state.out.is_empty() {
+            self.write_out()?;
+        }
+        Ok(len)
+    }
+    /// Same as [`Encoder::flush_with`]\([`Flush::Sync`]).
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_with(Flush::Sync)
+    }
+}
+impl<W: Write, const N: usize> Drop for Encoder<'_, W, N> {
+    fn drop(&mut self) {
+        if self.writer.is_some() && !self.state.is_finished() {
+            let _ = self.try_finish();
+        }
+    }
+}
+
+/// Reader independent state of a streaming frame decoder, decoding one block at a time.
+pub(super) struct BlockDecoder<'a> {
+    header: Header,
+    config: Config<'a>,
+    window: Slide<u8>,
+    filtering: Option<Filtering>,
+    checksum: XxHash64,
+    total: usize,
+}
+impl<'a> BlockDecoder<'a> {
+    /// Checks that `dictionary` is the one the frame was compressed with.
+    pub(super) fn new(header: Header, dictionary: Option<&'a [u8]>) -> Result<Self, Error> {
+        Ok(Self {
+            config: header.config(dictionary)?,
+            filtering: header.filter.map(Filtering::new),
+            header,
+            window: Slide::new(),
+            checksum: XxHash64::default(),
+            total: 0,
+        })
+    }
+    /// Whether the frame ends with a checksum to pass to [`BlockDecoder::end`].
+    pub(super) fn has_checksum(&self) -> bool {
+        self.header.flags & FLAG_CHECKSUM != 0
+    }
+    /// Decodes the payload of the next block, appending to `out`.
+    pub(super) fn block(&mut self, block: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+        let independent = self.header.flags & FLAG_INDEPENDENT != 0;
+        if independent {
+            self.window.clear();
+        }
+        let start = out.len();
+        let expected = self.header.content_size;
+        let max_len = expected.map_or(usize::MAX, |expected| expected.saturating_sub(self.total));
+        decompress_block(block, &mut self.window, &self.config, max_len, out)
+            .map_err(|err| content_overrun(err, expected, self.total))?;
+        if !independent {
+            self.config.dictionary = None;
+        }
+        if let Some(filtering) = &mut self.filtering {
+            filtering.decode(&mut out[start..]);
+        }
+        self.checksum.write(&out[start..]);
+        self.total += out.len() - start;
+        Ok(())
+    }
+    /// Checks the decoded content against the header and the trailer's `checksum`.
+    pub(super) fn end(&self, checksum: Option<u32>) -> Result<(), Error> {
+        if let Some(expected) = self.header.content_size
+            && self.total != expected
+        {
+            return Err(Error::ContentSizeMismatch {
+                expected,
+                actual: self.total,
+            });
+        }
+        let actual = self.checksum.finish_u32();
+        if let Some(expected) = checksum
+            && expected != actual
+        {
+            return Err(Error::ChecksumMismatch { expected, actual });
+        }
+        Ok(())
+    }
+}
+
+/// Streaming frame decoder, reading a frame from `R` block by block.
+pub struct Decoder<'a, R> {
+    reader: R,
+    dictionary: Option<&'a [u8]>,
+    blocks: BlockDecoder<'a>,
+    /// Blocks of the current frame so far.
+    count: usize,
+    decoded: Vec<u8>,
+    pos: usize,
+    done: bool,
+    concatenated: bool,
+    progress: Option<Progress<'a>>,
+    cancel: Option<Cancel>,
+}
+impl<'a, R: Read> Decoder<'a, R> {
+    /// Reads the frame header, using `dictionary` if the frame was compressed with one.
+    pub fn new(mut reader: R, dictionary: Option<&'a [u8]>) -> Result<Self, Error> {
+        let header = read_header(&mut reader)?;
+        Ok(Self {
+            reader,
+            dictionary,
+            blocks: BlockDecoder::new(header, dictionary)?,
+            count: 0,
+            decoded: vec![],
+            pos: 0,
+            done: false,
+            concatenated: false,
+            progress: None,
+            cancel: None,
+        })
+    }
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+    /// Reports the compressed bytes read from `R` and bytes decoded to `progress`, block by
+    /// block, replacing any previous callback.
+    pub fn set_progress(&mut self, progress: Progress<'a>) {
+        self.progress = Some(progress);
+    }
+    /// Fails reads with [`Error::Cancelled`] once `cancel` is cancelled, checked before each
+    /// block.
+    pub fn set_cancel(&mut self, cancel: Cancel) {
+        self.cancel = Some(cancel);
+    }
+    /// Keeps decoding the frames following the first one until `R` ends, as in files
+    /// concatenated with `cat a.slz b.slz`, instead of stopping after the first.
+    ///
+    /// Every frame is decoded with the dictionary passed to [`Decoder::new`], if compressed with
+    /// one, and seek tables are skipped.
+    pub fn set_concatenated(&mut self, concatenated: bool) {
+        self.concatenated = concatenated;
+    }
+    /// Decodes the next block, returning false once the frame is complete.
+    fn next_block(&mut self) -> Result<bool, Error> {
+        if self.done {
+            return Ok(false);
+        }
+        if self.cancel.as_ref().is_some_and(Cancel::is_cancelled) {
+            return Err(Error::Cancelled);
+        }
+        let head = read_varint_bytes(&mut self.reader)?;
+        let (len, _) = read_varint(&head)?;
+        if len == 0 {
+            self.done = true;
+            let mut checksum = None;
+            if self.blocks.has_checksum() {
+                let mut expected = [0; 4];
+                self.reader.read_exact(&mut expected)?;
+                checksum = Some(u32::from_le_bytes(expected));
+            }
+            self.blocks.end(checksum)?;
+            if self.concatenated {
+                return self.next_frame();
+            }
+            return Ok(false);
+        }
+        self.count += 1;
+        // len is untrusted, grow with the bytes actually read.
+        let mut block = vec![];
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut block)?;
+        if block.len() != len {
+            return Err(Error::UnexpectedEof);
+        }
+        self.decoded.clear();
+        self.pos = 0;
+        self.blocks.block(&block, &mut self.decoded)?;
+        if let Some(progress) = &mut self.progress {
+            progress.advance(head.len() + len, self.decoded.len(), 0);
+        }
+        Ok(true)
+    }
+    /// Skips the seek table of the frame just completed and starts the next one, if `R` holds
+    /// another, returning whether it does.
+    fn next_frame(&mut self) -> Result<bool, Error> {
+        if self.blocks.header.flags & FLAG_SEEK_TABLE != 0 {
+            let len = self.count as u64 * 8 + 8;
+            let skipped = io::copy(&mut self.reader.by_ref().take(len), &mut io::sink())?;
+            if skipped != len {
+                return Err(Error::UnexpectedEof);
+            }
+        }
+        let mut first = [0];
+        if self.reader.read(&mut first)? == 0 {
+            return Ok(false);
+        }
+        let header = match read_header(&mut first.as_slice().chain(&mut self.reader)) {
+            Err(Error::InvalidMagic) => return Err(Error::TrailingData),
+            header => header?,
+        };
+        self.blocks = BlockDecoder::new(header, self.dictionary)?;
+        self.count = 0;
+        self.done = false;
+        Ok(true)
+    }
+}
+impl<R: Read> Read for Decoder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            if !self.next_block()? {
+                return Ok(0);
+            }
+        }
+        let len = buf.len().min(self.decoded.len() - self.pos);
+        buf[..len].copy_from_slice(&self.decoded[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Decodes the frame from `reader` and compresses its content into a new frame in `writer`
+/// under `config`, e.g. to move cold data to a higher level, returning the writer.
+///
+/// The content size and filter of the frame are kept. It's decoded with the dictionary of
+/// `config`, if compressed with one. Every decoded block goes straight to the encoder, so
+/// memory stays bounded by a window and a block on either side.
+pub fn recompress<R: Read, W: Write>(reader: R, writer: W, config: Config) -> Result<W, Error> {
+    match config.match_lengths.start {
+        1 => recompress_with::<_, _, 1>(reader, writer, config),
+        2 => recompress_with::<_, _, 2>(reader, writer, config),
+        3 => recompress_with::<_, _, 3>(reader, writer, config),
+        _ => recompress_with::<_, _, 4>(reader, writer, config),
+    }
+}
+
+fn recompress_with<R: Read, W: Write, const N: usize>(
+    reader: R,
+    writer: W,
+    config: Config,
+) -> Result<W, Error> {
+    let mut decoder = Decoder::new(reader, config.dictionary)?;
+    let header = &decoder.blocks.header;
+    let mut encoder =
+        Encoder::<_, N>::with_header(writer, config, header.content_size, header.filter)?;
+    while decoder.next_block()? {
+        encoder.write_all(&decoder.decoded)?;
+    }
+    Ok(encoder.finish()?)
+}
+
+/// Reader independent streaming frame decoder, decoding from input pushed into it.
+#[cfg(any(feature = "async", feature = "ffi", feature = "wasm"))]
+pub(crate) struct PushDecoder<'a> {
+    dictionary: Option<&'a [u8]>,
+    blocks: Option<BlockDecoder<'a>>,
+    input: Vec<u8>,
+    decoded: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+#[cfg(any(feature = "async", feature = "ffi", feature = "wasm"))]
+impl<'a> PushDecoder<'a> {
+    pub(crate) fn new(dictionary: Option<&'a [u8]>) -> Self {
+        Self {
+            dictionary,
+            blocks: None,
+            input: vec![],
+            decoded: vec![],
+            pos: 0,
+            done: false,
+        }
+    }
+    /// Buffers more of the frame.
+    pub(crate) fn feed(&mut self, input: &[u8]) {
+        self.input.extend_from_slice(input);
+    }
+    /// Copies decoded bytes into `buf`, returning `None` if more input is needed first.
+    ///
+    /// Returns `Some(0)` once the frame is complete.
+    pub(crate) fn read(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+        loop {
+            if self.pos < self.decoded.len() {
+                let len = buf.len().min(self.decoded.len() - self.pos);
+                buf[..len].copy_from_slice(&self.decoded[self.pos..self.pos + len]);
+                self.pos += len;
+                return Ok(Some(len));
+            } else if self.done {
+                return Ok(Some(0));
+            } else if !self.step()? {
+                return Ok(None);
+            }
+        }
+    }
+    /// Decodes the header, a block or the trailer from the buffered input, returning false if
+    /// more input is needed.
+    fn step(&mut self) -> Result<bool, Error> {
+        let input = self.input.as_slice();
+        let rest = match &mut self.blocks {
+            None if input.len() < super::MAGIC.len() => return Ok(false),
+            None => {
+                let Some((header, rest)) = more(Header::read(input))? else {
+                    return Ok(false);
+                };
+                self.blocks = Some(BlockDecoder::new(header, self.dictionary)?);
+                rest
+            }
+            Some(blocks) => {
+                let Some((len, rest)) = more(read_varint(input))? else {
+                    return Ok(false);
+                };
+                if len == 0 {
+                    let (checksum, rest) = if blocks.has_checksum() {
+                        let Some((checksum, rest)) = more(super::read_u32(rest))? else {
+                            return Ok(false);
+                        };
+                        (Some(checksum), rest)
+                    } else {
+                        (None, rest)
+                    };
+                    blocks.end(checksum)?;
+                    self.done = true;
+                    rest
+                } else {
+                    let Some((block, rest)) = rest.split_at_checked(len) else {
+                        return Ok(false);
+                    };
+                    self.decoded.clear();
+                    self.pos = 0;
+                    blocks.block(block, &mut self.decoded)?;
+                    rest
+                }
+            }
+        };
+        let consumed = input.len() - rest.len();
+        self.input.drain(..consumed);
+        Ok(true)
+    }
+}
+/// Turns running out of input into `None`.
+#[cfg(any(feature = "async", feature = "ffi", feature = "wasm"))]
+fn more<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Err(Error::UnexpectedEof) => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+/// Reads the header of the frame `reader` continues with, after any skippable frames.
+fn read_header(reader: &mut impl Read) -> Result<Header, Error> {
+    let magic = skip_skippable(reader)?;
+    if magic != MAGIC {
+        return Err(Error::InvalidMagic);
+    }
+    let mut head = Vec::from(magic);
+    let flags = read_varint_bytes(reader)?;
+    head.extend(&flags);
+    let flags = u16::try_from(read_varint(&flags)?.0).map_err(|_| Error::Malformed)?;
+    head.extend(read_varint_bytes(reader)?);
+    if flags & FLAG_CONTENT_SIZE != 0 {
+        head.extend(read_varint_bytes(reader)?);
+    }
+    if flags & FLAG_DICTIONARY != 0 {
+        head.extend([0; 4]);
+        let len = head.len();
+        reader.read_exact(&mut head[len - 4..])?;
+    }
+    if flags & FLAG_DICTIONARY_ID != 0 {
+        head.extend(read_varint_bytes(reader)?);
+    }
+    if flags & FLAG_BLOCK_SORT != 0 {
+        head.extend(read_varint_bytes(reader)?);
+    }
+    if flags & FLAG_FILTER != 0 {
+        head.push(0);
+        let len = head.len();
+        reader.read_exact(&mut head[len - 1..])?;
+        head.extend(read_varint_bytes(reader)?);
+    }
+    if flags & FLAG_SETTINGS != 0 {
+        head.extend(read_varint_bytes(reader)?);
+        head.extend(read_varint_bytes(reader)?);
+        head.push(0);
+        let len = head.len();
+        reader.read_exact(&mut head[len - 1..])?;
+        for _ in 0..3 {
+            head.extend(read_varint_bytes(reader)?);
+        }
+    }
+    let (header, _) = Header::read(&head)?;
+    Ok(header)
+}
+
+/// Skips the skippable frames `reader` starts with, returning the magic number following them.
+pub(super) fn skip_skippable(reader: &mut impl Read) -> Result<[u8; 4], Error> {
+    loop {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != SKIPPABLE_MAGIC {
+            return Ok(magic);
+        }
+        let (len, _) = read_varint(&read_varint_bytes(reader)?)?;
+        let skipped = io::copy(&mut reader.by_ref().take(len as u64), &mut io::sink())?;
+        if skipped != len as u64 {
+            return Err(Error::UnexpectedEof);
+        }
+    }
+}
+
+/// Reads the bytes of a single varint.
+fn read_varint_bytes(reader: &mut impl Read) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![];
+    loop {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        bytes.push(byte[0]);
+        if byte[0] & 0x80 == 0 || bytes.len() == 10 {
+            return Ok(bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::tests::shared_config as config;
+    use crate::frame::{compress, decompress};
+
+    #[test]
+    fn recompress() {
+        use crate::frame::compress_filtered;
+        use std::num::NonZero;
+
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 3 + 100).map(|x| (x / 7 % 13 * x) as u8));
+        let filter = Filter::Delta(NonZero::new(2).unwrap());
+        let frame = compress_filtered(&data, Config::level(1), filter);
+        let upgraded = super::recompress(frame.as_slice(), vec![], Config::level(9)).unwrap();
+        assert_eq!(upgraded, compress_filtered(&data, Config::level(9), filter));
+        assert_eq!(decompress(&upgraded, None).unwrap(), data);
+        assert!(matches!(
+            super::recompress(&frame[..frame.len() - 1], vec![], config()),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+    #[test]
+    fn roundtrip() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 2 + 100).map(|x| (x / 7 % 251) as u8));
+        let mut encoder = Encoder::<_>::with_content_size(vec![], config(), data.len()).unwrap();
+        for chunk in data.chunks(1000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let frame = encoder.finish().unwrap();
+        assert_eq!(frame, compress(&data, config()));
+        let mut decoded = vec![];
+        Decoder::new(frame.as_slice(), None)
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, data);
+
+        let mut encoder = Encoder::<_>::with_content_size(vec![], config(), 4).unwrap();
+        encoder.write_all(b"abc").unwrap();
+        let err = encoder.write_all(b"de").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            encoder.finish().unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+    #[test]
+    fn huge_block_len() {
+        let frame = compress(b"abc", config());
+        let header_len = frame.len() - Header::read(&frame).unwrap().1.len();
+        let forged = [&frame[..header_len], &[0xff; 7], &[0x7f, 1, 2, 3]].concat();
+        let err = Decoder::new(forged.as_slice(), None)
+            .unwrap()
+            .read_to_end(&mut vec![])
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+    #[test]
+    fn progress() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 3).map(|x| (x / 7 % 251) as u8));
+        let mut reports = vec![];
+        let mut encoder = Encoder::<_>::new(vec![], config()).unwrap();
+        encoder.set_progress(Progress::new(BLOCK_LEN, |processed| {
+            reports.push(processed)
+        }));
+        for chunk in data.chunks(1000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let frame = encoder.finish().unwrap();
+        assert_eq!(reports.len(), 3);
+        assert!(
+            reports
+                .iter()
+                .all(|processed| processed.produced < frame.len())
+        );
+
+        let mut reports = vec![];
+        let mut decoder = Decoder::new(frame.as_slice(), None).unwrap();
+        decoder.set_progress(Progress::new(1, |processed| reports.push(processed)));
+        let mut decoded = vec![];
+        decoder.read_to_end(&mut decoded).unwrap();
+        drop(decoder);
+        assert_eq!(decoded, data);
+        assert_eq!(
+            Vec::from_iter(reports.iter().map(|processed| processed.produced)),
+            [BLOCK_LEN, 2 * BLOCK_LEN, 3 * BLOCK_LEN]
+        );
+        assert!(reports[2].consumed < frame.len());
+    }
+    #[test]
+    fn cancel() {
+        let data = Vec::from_iter((0..BLOCK_LEN as u32 * 3).map(|x| (x / 7 % 251) as u8));
+        let frame = compress(&data, config());
+        let cancel = Cancel::new();
+        let mut decoder = Decoder::new(frame.as_slice(), None).unwrap();
+        decoder.set_cancel(cancel.clone());
+        let mut buf = vec![0; BLOCK_LEN];
+        decoder.read_exact(&mut buf).unwrap();
+        cancel.cancel();
+        assert_eq!(
+            decoder.read(&mut buf).unwrap_err().to_string(),
+            Error::Cancelled.to_string()
+        );
+
+        let mut encoder = Encoder::<_>::new(vec![], config()).unwrap();
+        encoder.set_cancel(cancel);
+        assert_eq!(
+            encoder.write(&data).unwrap_err().to_string(),
+            Error::Cancelled.to_string()
+        );
+        assert!(encoder.finish().is_err());
+    }
+    #[test]
+    fn invalid_config() {
+        let config = Config {
+            match_lengths: 3..usize::MAX,
+            ..config()
+        };
+        let err = Encoder::<_, 4>::new(vec![], config).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    #[test]
+    fn flush() {
+        let mut encoder = Encoder::<_>::new(vec![], config()).unwrap();
+        encoder.write_all(b"hello hello ").unwrap();
+        encoder.flush().unwrap();
+        let mut decoder = Decoder::new(encoder.get_ref().as_slice(), None).unwrap();
+        let mut buf = [0; 12];
+        decoder.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello hello ");
+        assert_eq!(
+            decoder.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        let synced = encoder.get_ref().len();
+        encoder.write_all(b"hello").unwrap();
+        encoder.flush_with(Flush::Full).unwrap();
+        let full = encoder.get_ref().len();
+        encoder.write_all(b"hello").unwrap();
+        encoder.flush().unwrap();
+        // After a full flush, the same input can't reference anything from before it.
+        assert!(full - synced < encoder.get_ref().len() - full);
+        let frame = encoder.finish().unwrap();
+        assert_eq!(decompress(&frame, None).unwrap(), b"hello hello hellohello");
+    }
+    #[test]
+    fn dictionary() {
+        let dictionary = b"a dictionary";
+        let mut encoder = Encoder::<_>::new(vec![], config().with_dictionary(dictionary)).unwrap();
+        encoder.write_all(b"with a dictionary").unwrap();
+        drop(encoder.flush());
+        encoder.write_all(b" a dictionary").unwrap();
+        let frame = encoder.finish().unwrap();
+        let mut decoded = vec![];
+        Decoder::new(frame.as_slice(), Some(dictionary))
+            .unwrap()
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, b"with a dictionary a dictionary");
+        assert!(matches!(
+            Decoder::new(frame.as_slice(), None),
+            Err(Error::DictionaryMismatch { .. })
+        ));
+    }
+}
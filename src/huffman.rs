@@ -0,0 +1,335 @@
+//! Canonical, length-limited Huffman coding of small alphabets.
+//!
+//! A coded stream is laid out as its table, see [`write_table`], followed by the codes of the
+//! symbols. Everything is packed MSB-first and padded to a byte. The number of symbols is left
+//! to the caller.
+
+use std::{cmp::Reverse, collections::BinaryHeap, mem};
+
+use crate::util::bits::{BitOrder, BitReader, BitWriter};
+
+/// Longest code assigned, so every length fits into 4 bits.
+pub(crate) const MAX_CODE_LEN: u8 = 15;
+/// Code length symbols repeating the previous length 3-6 times (2 extra bits), and zero 3-10
+/// (3 extra bits) or 11-138 times (7 extra bits). Lower symbols are the lengths themselves.
+const REPEAT: u16 = 16;
+const ZEROS: u16 = 17;
+const MANY_ZEROS: u16 = 18;
+/// Order the code lengths of the code length code are written in, rarely used ones last.
+const LENGTH_ORDER: [u16; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+/// Longest code of the code length code, so every length fits into 3 bits.
+const MAX_LENGTH_CODE_LEN: u8 = 7;
+
+/// Code lengths of a Huffman code for symbols occurring `freqs` times, none longer than
+/// [`MAX_CODE_LEN`].
+///
+/// Unused symbols get length 0. A lone used symbol still gets a 1 bit code.
+pub(crate) fn code_lengths(freqs: &[usize]) -> Vec<u8> {
+    limited_code_lengths(freqs, MAX_CODE_LEN)
+}
+
+fn limited_code_lengths(freqs: &[usize], max_len: u8) -> Vec<u8> {
+    let mut freqs = freqs.to_vec();
+    loop {
+        let lengths = unlimited_code_lengths(&freqs);
+        if lengths.iter().all(|&len| len <= max_len) {
+            return lengths;
+        }
+        // Flattening the distribution shortens the longest codes.
+        for freq in freqs.iter_mut().filter(|freq| **freq > 0) {
+            *freq = *freq / 2 + 1;
+        }
+    }
+}
+
+fn unlimited_code_lengths(freqs: &[usize]) -> Vec<u8> {
+    let mut lengths = vec![0; freqs.len()];
+    let used = Vec::from_iter((0..freqs.len()).filter(|&symbol| freqs[symbol] > 0));
+    if let [symbol] = used[..] {
+        lengths[symbol] = 1;
+    }
+    if used.len() < 2 {
+        return lengths;
+    }
+    // Leaves are the used symbols in order, followed by the merged nodes.
+    let mut parents = vec![usize::MAX; used.len()];
+    let mut heap = BinaryHeap::from_iter(
+        used.iter()
+            .enumerate()
+            .map(|(node, &symbol)| Reverse((freqs[symbol], node))),
+    );
+    while let (Some(Reverse((a, x))), Some(Reverse((b, y)))) = (heap.pop(), heap.pop()) {
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[x] = node;
+        parents[y] = node;
+        heap.push(Reverse((a + b, node)));
+    }
+    for (leaf, &symbol) in used.iter().enumerate() {
+        let mut node = leaf;
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            lengths[symbol] += 1;
+        }
+    }
+    lengths
+}
+
+/// Canonical codes for `lengths`: shorter codes first, ties in symbol order.
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut counts = [0u32; MAX_CODE_LEN as usize + 1];
+    for &len in lengths {
+        counts[len as usize] += 1;
+    }
+    counts[0] = 0;
+    let mut next = [0u32; MAX_CODE_LEN as usize + 1];
+    for len in 1..next.len() {
+        next[len] = (next[len - 1] + counts[len - 1]) << 1;
+    }
+    Vec::from_iter(lengths.iter().map(|&len| {
+        let code = next[len as usize];
+        next[len as usize] += 1;
+        code
+    }))
+}
+
+/// Huffman codes `symbols` out of an alphabet of `alphabet` symbols into `out`.
+///
+/// Every symbol must be below the alphabet size.
+pub(crate) fn encode(symbols: &[u16], alphabet: usize, out: &mut Vec<u8>) {
+    let mut freqs = vec![0; alphabet];
+    for &symbol in symbols {
+        freqs[symbol as usize] += 1;
+    }
+    let lengths = code_lengths(&freqs);
+    let codes = canonical_codes(&lengths);
+    let mut writer = BitWriter::with_vec(mem::take(out), BitOrder::MsbFirst);
+    write_table(&mut writer, &lengths);
+    for &symbol in symbols {
+        let symbol = symbol as usize;
+        writer.write(codes[symbol], lengths[symbol] as u32);
+    }
+    *out = writer.finish();
+}
+
+/// Code length symbols coding `lengths`, each with its extra bits and their number.
+fn length_symbols(lengths: &[u8]) -> Vec<(u16, u32, u32)> {
+    let mut symbols = vec![];
+    let mut at = 0;
+    while at < lengths.len() {
+        let len = lengths[at];
+        let run = lengths[at..].iter().take_while(|&&x| x == len).count();
+        if len == 0 && run >= 3 {
+            let run = run.min(138);
+            symbols.push(match run {
+                ..11 => (ZEROS, run as u32 - 3, 3),
+                _ => (MANY_ZEROS, run as u32 - 11, 7),
+            });
+            at += run;
+            continue;
+        }
+        symbols.push((len as u16, 0, 0));
+        at += 1;
+        let mut left = run - 1;
+        while left >= 3 {
+            let repeat = left.min(6);
+            symbols.push((REPEAT, repeat as u32 - 3, 2));
+            at += repeat;
+            left -= repeat;
+        }
+    }
+    symbols
+}
+
+/// Writes a table of code `lengths` as their code length symbols, themselves Huffman coded.
+///
+/// Laid out as the number of code length code lengths minus 4 (4 bits), those lengths in
+/// [`LENGTH_ORDER`] (3 bits each) and the coded symbols, each followed by its extra bits.
+fn write_table(writer: &mut BitWriter, lengths: &[u8]) {
+    let symbols = length_symbols(lengths);
+    let mut freqs = [0; LENGTH_ORDER.len()];
+    for &(symbol, ..) in &symbols {
+        freqs[symbol as usize] += 1;
+    }
+    let length_lengths = limited_code_lengths(&freqs, MAX_LENGTH_CODE_LEN);
+    let codes = canonical_codes(&length_lengths);
+    let written = LENGTH_ORDER
+        .iter()
+        .rposition(|&symbol| length_lengths[symbol as usize] > 0)
+        .map_or(0, |last| last + 1)
+        .max(4);
+    writer.write(written as u32 - 4, 4);
+    for &symbol in &LENGTH_ORDER[..written] {
+        writer.write(length_lengths[symbol as usize] as u32, 3);
+    }
+    for (symbol, extra, extra_bits) in symbols {
+        let symbol = symbol as usize;
+        writer.write(codes[symbol], length_lengths[symbol] as u32);
+        writer.write(extra, extra_bits);
+    }
+}
+
+/// Reads a table written by [`write_table`] for `alphabet` symbols.
+///
+/// Fails unless the code length code is valid, see [`Decoder::new`], and its symbols exactly
+/// fill the alphabet. Validating the table itself is left to the caller.
+fn read_table(reader: &mut BitReader, alphabet: usize) -> Option<Vec<u8>> {
+    let written = reader.read(4)? as usize + 4;
+    let mut length_lengths = [0; LENGTH_ORDER.len()];
+    for &symbol in &LENGTH_ORDER[..written] {
+        length_lengths[symbol as usize] = reader.read(3)? as u8;
+    }
+    let decoder = Decoder::new(&length_lengths)?;
+    let mut lengths = Vec::with_capacity(alphabet);
+    while lengths.len() < alphabet {
+        let (len, run) = match decoder.decode(reader)? {
+            REPEAT => (*lengths.last()?, reader.read(2)? as usize + 3),
+            ZEROS => (0, reader.read(3)? as usize + 3),
+            MANY_ZEROS => (0, reader.read(7)? as usize + 11),
+            len => (len as u8, 1),
+        };
+        if lengths.len() + run > alphabet {
+            return None;
+        }
+        lengths.resize(lengths.len() + run, len);
+    }
+    Some(lengths)
+}
+
+/// Decodes `count` symbols coded by [`encode`] with the same `alphabet`, returning them and the
+/// rest of `data`.
+///
+/// Fails on truncated input, malformed tables and codes not assigned to any symbol.
+pub(crate) fn decode(data: &[u8], alphabet: usize, count: usize) -> Option<(Vec<u16>, &[u8])> {
+    let mut reader = BitReader::new(data, BitOrder::MsbFirst);
+    let lengths = read_table(&mut reader, alphabet)?;
+    let decoder = Decoder::new(&lengths)?;
+    // Every symbol takes at least one bit.
+    if count > reader.remaining() {
+        return None;
+    }
+    let mut symbols = Vec::with_capacity(count);
+    for _ in 0..count {
+        symbols.push(decoder.decode(&mut reader)?);
+    }
+    Some((symbols, reader.rest()))
+}
+
+/// Decoding table of a canonical code: the number of codes of every length and the symbols
+/// sorted by code.
+struct Decoder {
+    counts: [u32; MAX_CODE_LEN as usize + 1],
+    symbols: Vec<u16>,
+}
+impl Decoder {
+    /// Fails if `lengths` are not a complete code, unless at most one symbol is used.
+    fn new(lengths: &[u8]) -> Option<Self> {
+        let mut counts = [0u32; MAX_CODE_LEN as usize + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        // Codes left unassigned at each length, which must never go negative.
+        let mut left = 1i64;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i64;
+            if left < 0 {
+                return None;
+            }
+        }
+        // A lone symbol gets a 1 bit code, leaving the other one unassigned.
+        let used: u32 = counts.iter().sum();
+        if left != 0 && !(used == 0 || used == 1 && counts[1] == 1) {
+            return None;
+        }
+        let mut symbols =
+            Vec::from_iter((0..lengths.len() as u16).filter(|&s| lengths[s as usize] > 0));
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Some(Self { counts, symbols })
+    }
+    fn decode(&self, reader: &mut BitReader) -> Option<u16> {
+        // Canonical codes of each length are consecutive, starting right after the shorter ones.
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= reader.read(1)?;
+            if code - first < count {
+                return self.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn lengths() {
+        assert_eq!(code_lengths(&[0, 0]), [0, 0]);
+        assert_eq!(code_lengths(&[0, 5]), [0, 1]);
+        assert_eq!(code_lengths(&[1, 1, 2, 4]), [3, 3, 2, 1]);
+        assert_eq!(canonical_codes(&[3, 3, 2, 1]), [0b110, 0b111, 0b10, 0b0]);
+        // Fibonacci frequencies would need codes as long as the alphabet.
+        let mut fib = vec![1, 1];
+        while fib.len() < 40 {
+            fib.push(fib[fib.len() - 1] + fib[fib.len() - 2]);
+        }
+        let lengths = code_lengths(&fib);
+        assert!(lengths.iter().all(|&len| (1..=MAX_CODE_LEN).contains(&len)));
+        assert!(Decoder::new(&lengths).is_some());
+        assert!(Decoder::new(&[1, 1, 1]).is_none());
+        // Incomplete codes are only accepted for a lone symbol.
+        assert!(Decoder::new(&[2, 2, 2]).is_none());
+        assert!(Decoder::new(&[0, 2]).is_none());
+        assert!(Decoder::new(&[0, 1]).is_some());
+        assert!(Decoder::new(&[0, 0]).is_some());
+    }
+    #[quickcheck]
+    fn table(freqs: Vec<u16>, alphabet: u16) {
+        let alphabet = alphabet as usize % 300 + 1;
+        let freqs = Vec::from_iter((0..alphabet).map(|x| *freqs.get(x).unwrap_or(&0) as usize));
+        let lengths = code_lengths(&freqs);
+        let mut writer = BitWriter::new(BitOrder::MsbFirst);
+        write_table(&mut writer, &lengths);
+        let out = writer.finish();
+        let mut reader = BitReader::new(&out, BitOrder::MsbFirst);
+        assert_eq!(read_table(&mut reader, alphabet), Some(lengths));
+        assert_eq!(reader.position().div_ceil(8), out.len());
+    }
+    #[test]
+    fn compact() {
+        // Few used symbols of a large alphabet only cost a few bytes of table.
+        let mut out = vec![];
+        encode(&[5, 5, 6, 200], 256, &mut out);
+        assert!(out.len() < 16, "{out:?}");
+        assert_eq!(decode(&out, 256, 4), Some((vec![5, 5, 6, 200], &[][..])));
+        // The repeats of a table may not overrun the alphabet.
+        assert!(decode(&out, 199, 4).is_none());
+    }
+    #[quickcheck]
+    fn garbage(data: Vec<u8>, count: u8) {
+        if let Some((symbols, _)) = decode(&data, 40, count as usize) {
+            assert!(symbols.iter().all(|&symbol| symbol < 40));
+        }
+    }
+    #[quickcheck]
+    fn roundtrip(symbols: Vec<u8>, tail: Vec<u8>) {
+        let symbols = Vec::from_iter(symbols.iter().map(|&symbol| symbol as u16 % 40));
+        let mut out = vec![];
+        encode(&symbols, 40, &mut out);
+        let len = out.len();
+        out.extend_from_slice(&tail);
+        assert_eq!(
+            decode(&out, 40, symbols.len()),
+            Some((symbols.clone(), &tail[..]))
+        );
+        assert!(decode(&out[..len - 1], 40, symbols.len() + 8).is_none());
+    }
+}
@@ -1,8 +1,47 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+pub mod bare;
+#[cfg(feature = "std")]
+pub mod bwt;
+#[cfg(feature = "std")]
 mod slide;
+#[cfg(feature = "std")]
 pub use slide::*;
+#[cfg(feature = "std")]
+pub mod dict;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+pub mod filter;
+#[cfg(feature = "std")]
+pub use error::*;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod frame;
+#[cfg(feature = "std")]
+mod huffman;
+#[cfg(feature = "std")]
 pub mod lz;
+#[cfg(feature = "std")]
+pub mod lzss;
+#[cfg(feature = "std")]
+pub mod prelude;
+#[cfg(all(feature = "std", any(test, feature = "test-util")))]
+pub mod reference;
+#[cfg(feature = "std")]
+pub mod rle;
+#[cfg(feature = "std")]
 pub mod search_buffer;
+#[cfg(feature = "std")]
+pub mod snappy;
+#[cfg(feature = "std")]
+mod trace;
+#[cfg(feature = "std")]
 pub mod util;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 #[cfg(test)]
 mod tests {
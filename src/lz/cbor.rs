@@ -0,0 +1,56 @@
+use super::Item;
+use crate::{Error, frame};
+
+/// Serializes `items` as a CBOR array of items, each an array of unsigned integers laid out
+/// like the varints of a bare item stream.
+///
+/// Lets item streams travel inside CBOR based protocols and be inspected with standard CBOR
+/// tooling. Like bare item streams, it records neither config nor dictionary.
+pub fn to_cbor<'i>(items: impl IntoIterator<Item = Item<'i, u8>>) -> Vec<u8> {
+    let items = Vec::from_iter(items);
+    let mut out = vec![];
+    ciborium::into_writer(&items, &mut out).expect("items are serializable");
+    out
+}
+
+/// Reads the items [`to_cbor`] wrote, without checking them against a config.
+pub fn from_cbor(bytes: &[u8]) -> Result<Vec<Item<'static, u8>>, Error> {
+    ciborium::from_reader(bytes).map_err(|_| frame::Error::Malformed.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Slide, lz::Config, search_buffer::SearchBuffer};
+
+    #[test]
+    fn cbor() {
+        let data = Vec::from_iter((0..0x2000u32).map(|x| (x / 3 % 13) as u8 ^ (x >> 9) as u8));
+        let config = Config {
+            match_lengths: 3..0x100,
+            ..Config::level(4)
+        };
+        let mut search_buffer = SearchBuffer::<u8, 3>::new();
+        let items = Vec::from_iter(search_buffer.to_items_from_slice(&data, config.clone()));
+        let bytes = to_cbor(items.iter().cloned());
+        // Major type 4, an array.
+        assert_eq!(bytes[0] >> 5, 4);
+        let decoded = from_cbor(&bytes).unwrap();
+        assert_eq!(decoded, items);
+        assert_eq!(
+            Vec::from_iter(Slide::new().from_items(decoded, config)),
+            data
+        );
+
+        let value: ciborium::Value = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), items.len());
+        assert_eq!(
+            to_cbor([Item::Run { value: 7, len: 5 }]),
+            [0x81, 0x84, 0, 0, 5, 7]
+        );
+        assert!(matches!(
+            from_cbor(&bytes[..bytes.len() - 1]),
+            Err(Error::Frame(frame::Error::Malformed))
+        ));
+    }
+}
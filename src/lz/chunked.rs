@@ -0,0 +1,156 @@
+use std::{iter, mem};
+
+use serde::{Deserialize, Serialize};
+
+use super::Item;
+use crate::{Error, frame};
+
+/// Header starting every chunk of items, see [`chunk_items`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkHeader {
+    /// Number of items in the chunk.
+    pub items: usize,
+    /// Number of values they decode to.
+    pub values: usize,
+    /// Bytes the items take after the header.
+    pub len: usize,
+}
+
+/// Chunk of items read by [`read_chunk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    pub header: ChunkHeader,
+    pub items: Vec<Item<'a, u8>>,
+}
+
+/// Groups `items` into chunks of at most `target_len` bytes of items each, for storage that
+/// wants its input in pieces of about the same size.
+///
+/// Every chunk is a [`ChunkHeader`] followed by its items as in a bare item stream. Items are
+/// never split, so an item longer than `target_len` makes up a chunk of its own. They still
+/// refer to the values and distances of previous chunks, so chunks decode in order, see
+/// [`read_chunk`].
+pub fn chunk_items<'i>(
+    items: impl IntoIterator<Item = Item<'i, u8>>,
+    target_len: usize,
+) -> impl Iterator<Item = Vec<u8>> {
+    let mut items = items.into_iter();
+    let (mut payload, mut next) = (vec![], vec![]);
+    let mut header = ChunkHeader::default();
+    let mut done = false;
+    iter::from_fn(move || {
+        while !done {
+            let Some(item) = items.next() else {
+                done = true;
+                break;
+            };
+            next =
+                postcard::to_extend(&item, mem::take(&mut next)).expect("items are serializable");
+            let full = header.items > 0 && payload.len() + next.len() > target_len;
+            let ret = full.then(|| chunk(&mut header, &mut payload));
+            payload.append(&mut next);
+            header.items += 1;
+            header.values += item.len();
+            if ret.is_some() {
+                return ret;
+            }
+        }
+        (header.items > 0).then(|| chunk(&mut header, &mut payload))
+    })
+}
+
+/// Takes the pending `payload` as a chunk under `header`, starting the next one.
+fn chunk(header: &mut ChunkHeader, payload: &mut Vec<u8>) -> Vec<u8> {
+    let header = ChunkHeader {
+        len: payload.len(),
+        ..mem::take(header)
+    };
+    let mut out = postcard::to_allocvec(&header).expect("headers are serializable");
+    out.append(payload);
+    out
+}
+
+/// Reads the chunk of items `bytes` starts with, see [`chunk_items`], returning it and the
+/// bytes after it.
+///
+/// Fails if the chunk is cut short or its items don't match its header.
+pub fn read_chunk(bytes: &[u8]) -> Result<(Chunk<'_>, &[u8]), Error> {
+    let (header, bytes) =
+        postcard::take_from_bytes::<ChunkHeader>(bytes).map_err(|_| frame::Error::UnexpectedEof)?;
+    let (mut payload, rest) = bytes
+        .split_at_checked(header.len)
+        .ok_or(frame::Error::UnexpectedEof)?;
+    let mut items = Vec::with_capacity(header.items.min(payload.len()));
+    let mut values = 0usize;
+    while !payload.is_empty() {
+        let item;
+        (item, payload) = Item::take_from_bytes(payload).map_err(|_| frame::Error::Malformed)?;
+        values = values
+            .checked_add(item.len())
+            .ok_or(frame::Error::Malformed)?;
+        items.push(item);
+    }
+    if items.len() != header.items || values != header.values {
+        return Err(frame::Error::Malformed.into());
+    }
+    Ok((Chunk { header, items }, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Slide, lz::Config, search_buffer::SearchBuffer};
+
+    #[test]
+    fn chunked() {
+        let data = Vec::from_iter((0..0x8000u32).map(|x| (x / 5 % 11 * x) as u8));
+        let config = Config {
+            match_lengths: 3..0x100,
+            ..Config::level(3)
+        };
+        let mut search_buffer = SearchBuffer::<u8, 3>::new();
+        let items = Vec::from_iter(search_buffer.to_items_from_slice(&data, config.clone()));
+        let chunks = Vec::from_iter(chunk_items(items.iter().cloned(), 0x400));
+        assert!(chunks.len() > 4);
+
+        let mut read = vec![];
+        for chunk in &chunks {
+            let (Chunk { header, items }, rest) = read_chunk(chunk).unwrap();
+            assert!(rest.is_empty());
+            assert!(header.len <= 0x400 || header.items == 1);
+            assert_eq!(header.values, items.iter().map(Item::len).sum::<usize>());
+            read.extend(items);
+        }
+        assert_eq!(read, items);
+        assert_eq!(Vec::from_iter(Slide::new().from_items(read, config)), data);
+
+        let concatenated = chunks.concat();
+        let (_, rest) = read_chunk(&concatenated).unwrap();
+        assert_eq!(rest, &concatenated[chunks[0].len()..]);
+        assert!(matches!(
+            read_chunk(&chunks[0][..chunks[0].len() - 1]),
+            Err(Error::Frame(frame::Error::UnexpectedEof))
+        ));
+        assert_eq!(chunk_items(items.into_iter().take(0), 0x400).count(), 0);
+
+        // Item lengths adding up past usize::MAX are malformed, not an overflow.
+        let huge = Item::Run {
+            value: 0,
+            len: usize::MAX,
+        };
+        let payload = [huge.clone(), huge]
+            .iter()
+            .fold(vec![], |out, item| postcard::to_extend(item, out).unwrap());
+        let header = ChunkHeader {
+            items: 2,
+            values: 0,
+            len: payload.len(),
+        };
+        let mut chunk = postcard::to_allocvec(&header).unwrap();
+        chunk.extend(payload);
+        assert!(matches!(
+            read_chunk(&chunk),
+            Err(Error::Frame(frame::Error::Malformed))
+        ));
+    }
+}
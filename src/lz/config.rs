@@ -0,0 +1,466 @@
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
+#[derive(Debug, Clone)]
+pub struct Config<'a, T = u8> {
+    /// Maximum size of the search window. Default: 2^24
+    pub max_buffer_len: usize,
+    /// Range of accepted match lengths. Default: 1..usize::MAX
+    ///
+    /// Raising the minimum can exponentially speed up scanning over the search window,
+    /// while also exponentially increasing potential keys in the cache.
+    ///
+    /// Lowering the maximum limits the size of the lookahead window.
+    pub match_lengths: Range<usize>,
+    /// Preset dictionary priming the window before the first item. Default: None
+    ///
+    /// Only its last `max_buffer_len` values are reachable.
+    /// Encoder and decoder must use the same dictionary.
+    pub dictionary: Option<&'a [T]>,
+    /// How matches are chosen while encoding. Default: Greedy
+    pub strategy: Strategy,
+    /// Block length of the [`bwt`](crate::bwt) pipeline frames use instead of matching.
+    /// Default: None
+    ///
+    /// Often beats matching on text. Only [`frame`](crate::frame) encoders use it, and they
+    /// ignore the window, match lengths, strategy and dictionary then.
+    pub block_sort: Option<usize>,
+    /// Application assigned ID of `dictionary`, recorded in frame headers. Default: None
+    ///
+    /// Lets readers tell which dictionary a [`frame`](crate::frame) needs before decoding it.
+    /// Ignored without a dictionary.
+    pub dictionary_id: Option<u32>,
+    /// Most values buffered ahead of the current position while encoding.
+    /// Default: 2^16 ([`FEED_LOOKAHEAD`](super::FEED_LOOKAHEAD))
+    ///
+    /// Keeps memory bounded however large `match_lengths.end` is. Longer matches and runs are
+    /// split into several items.
+    pub lookahead: usize,
+    /// Bytes the encoder's window, match finder and lookahead may take. Default: None
+    ///
+    /// Encoders shrink the window below `max_buffer_len` until it fits, see
+    /// [`Config::window_for`], and frames record the window actually used. With a budget,
+    /// `max_buffer_len` may be left at `usize::MAX`.
+    pub memory_budget: Option<usize>,
+    /// Most hash chain entries a single match search visits. Default: None
+    ///
+    /// Bounds the time spent on highly repetitive data, where chains grow as long as the window,
+    /// at the cost of missing matches further back.
+    pub max_chain: Option<usize>,
+    /// Match length ending a search early, taking the match found. Default: None
+    ///
+    /// Speeds up encoding data with many long matches, which would otherwise keep searching for
+    /// a longer one. Not used by `Optimal`.
+    pub nice_len: Option<usize>,
+    /// Most values the encoder holds back as pending literals before emitting them as an
+    /// [`Item::Raw`](super::Item::Raw). Default: None
+    ///
+    /// Bounds how much input streaming consumers wait on before seeing output, as incompressible
+    /// data would otherwise only be emitted once a match or run ends it. Longer literal runs are
+    /// split into several items.
+    pub max_raw_len: Option<usize>,
+    /// Keeps references from overlapping the values they produce, so none is longer than its
+    /// distance. Default: false
+    ///
+    /// For third-party decoders copying matches with a plain memcpy. Repetitions with a short
+    /// period then take several references, while runs are still emitted as
+    /// [`Item::Run`](super::Item::Run).
+    pub forbid_overlap: bool,
+}
+impl<T> Default for Config<'_, T> {
+    fn default() -> Self {
+        Self {
+            max_buffer_len: 0x1000000,
+            match_lengths: 1..usize::MAX,
+            dictionary: None,
+            strategy: Strategy::Greedy,
+            block_sort: None,
+            dictionary_id: None,
+            lookahead: super::FEED_LOOKAHEAD,
+            memory_budget: None,
+            max_chain: None,
+            nice_len: None,
+            max_raw_len: None,
+            forbid_overlap: false,
+        }
+    }
+}
+
+/// Highest level accepted by [`Config::level`].
+pub const MAX_LEVEL: u8 = 9;
+
+impl<'a, T> Config<'a, T> {
+    pub fn builder() -> ConfigBuilder<'a, T> {
+        ConfigBuilder::new()
+    }
+    /// Preset trading speed for ratio, from 1 (fastest) to [`MAX_LEVEL`] (smallest output).
+    ///
+    /// Higher levels use larger windows and stronger strategies, all with a minimum match of 4.
+    /// Levels are clamped to that range.
+    pub fn level(level: u8) -> Self {
+        let (window_log, strategy) = match level.clamp(1, MAX_LEVEL) {
+            1 => (16, Strategy::Greedy),
+            2 => (18, Strategy::Greedy),
+            3 => (20, Strategy::Greedy),
+            4 => (20, Strategy::Lazy(1)),
+            5 => (22, Strategy::Lazy(2)),
+            6 => (22, Strategy::BestOf(4)),
+            7 => (24, Strategy::BestOf(8)),
+            8 => (24, Strategy::BestOf(16)),
+            _ => (24, Strategy::Optimal),
+        };
+        Self {
+            max_buffer_len: 1 << window_log,
+            match_lengths: 4..usize::MAX,
+            strategy,
+            ..Self::default()
+        }
+    }
+    /// Checks that the config can be used for encoding.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_buffer_len == 0 {
+            return Err(ConfigError::EmptyWindow);
+        }
+        let Range { start, end } = self.match_lengths;
+        if start == 0 {
+            return Err(ConfigError::ZeroMinMatch);
+        }
+        if start >= end {
+            return Err(ConfigError::EmptyMatchLengths { start, end });
+        }
+        if self.block_sort == Some(0) {
+            return Err(ConfigError::EmptyBlock);
+        }
+        if self.lookahead == 0 {
+            return Err(ConfigError::EmptyLookahead);
+        }
+        if self.max_raw_len == Some(0) {
+            return Err(ConfigError::ZeroMaxRaw);
+        }
+        self.validate_budget(1)
+    }
+    /// Like [`Config::validate`], also checking that a SearchBuffer keyed by `width` values
+    /// can find matches as short as `match_lengths.start`.
+    pub fn validate_for(&self, width: usize) -> Result<(), ConfigError> {
+        self.validate()?;
+        if width > self.match_lengths.start {
+            return Err(ConfigError::MinMatchBelowWidth {
+                min_match: self.match_lengths.start,
+                width,
+            });
+        }
+        self.validate_budget(width)
+    }
+    fn validate_budget(&self, width: usize) -> Result<(), ConfigError> {
+        match self.memory_budget {
+            Some(budget) if self.window_for(width) == 0 => Err(ConfigError::MemoryBudgetTooSmall {
+                budget,
+                required: self.lookahead_bytes() + Self::window_value_bytes(width),
+            }),
+            _ => Ok(()),
+        }
+    }
+    /// Estimated bytes a SearchBuffer keyed by `width` values takes per value of its window: the
+    /// value, its hash chain link and its share of the head table.
+    pub fn window_value_bytes(width: usize) -> usize {
+        size_of::<T>() * (width + 1) + 2 * size_of::<usize>() + 1
+    }
+    fn lookahead_bytes(&self) -> usize {
+        self.lookahead.saturating_mul(size_of::<T>())
+    }
+    /// The largest window up to `max_buffer_len` that a SearchBuffer keyed by `width` values
+    /// can use within `memory_budget`, after setting aside the lookahead.
+    pub fn window_for(&self, width: usize) -> usize {
+        let Some(budget) = self.memory_budget else {
+            return self.max_buffer_len;
+        };
+        let window =
+            budget.saturating_sub(self.lookahead_bytes()) / Self::window_value_bytes(width);
+        window.min(self.max_buffer_len)
+    }
+    /// Sets `max_buffer_len` to [`Config::window_for`] `width`, the window encoders use.
+    pub fn fit_memory(self, width: usize) -> Self {
+        Self {
+            max_buffer_len: self.window_for(width),
+            ..self
+        }
+    }
+    pub fn with_dictionary(self, dictionary: &'a [T]) -> Self {
+        Self {
+            dictionary: Some(dictionary),
+            ..self
+        }
+    }
+    /// The part of the dictionary reachable from within the window.
+    pub fn dictionary_window(&self) -> &'a [T] {
+        let dictionary = self.dictionary.unwrap_or_default();
+        &dictionary[dictionary.len().saturating_sub(self.max_buffer_len)..]
+    }
+}
+
+/// Match selection strategy of [`SearchBuffer::to_items`](crate::search_buffer::SearchBuffer::to_items).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// Take the longest match at the current position.
+    #[default]
+    Greedy,
+    /// Before taking a match, look for a longer one starting up to `level` values later,
+    /// emitting the values in between as raw.
+    ///
+    /// Costs up to `level` extra searches per match; `Lazy(0)` is the same as `Greedy`.
+    Lazy(usize),
+    /// Choose the items with the smallest postcard encoding, by a shortest path over all
+    /// candidate matches in chunks of up to 4096 values of lookahead.
+    ///
+    /// Much slower than the other strategies; the lookahead is further limited by
+    /// `match_lengths.end`.
+    Optimal,
+    /// Compare up to `k` of the matches found along the hash chain, each longer than the last,
+    /// and take the one saving the most bytes in the serialized [`Item`](super::Item) format.
+    ///
+    /// Prefers a close match over a slightly longer one whose distance costs more to encode.
+    /// Much faster than `Optimal`, as every position is still searched once. At least one
+    /// match is compared.
+    BestOf(usize),
+}
+impl Display for Strategy {
+    /// Writes the strategy as `greedy`, `lazy=N`, `optimal` or `best-of=K`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Greedy => write!(f, "greedy"),
+            Strategy::Lazy(level) => write!(f, "lazy={level}"),
+            Strategy::Optimal => write!(f, "optimal"),
+            Strategy::BestOf(k) => write!(f, "best-of={k}"),
+        }
+    }
+}
+
+/// Why a [`Config`] can't be used for encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `max_buffer_len` is 0, so nothing could ever be referenced.
+    EmptyWindow,
+    /// `match_lengths.start` is 0.
+    ZeroMinMatch,
+    /// `match_lengths` contains no lengths.
+    EmptyMatchLengths { start: usize, end: usize },
+    /// The SearchBuffer's key width `N` is larger than the minimum match length.
+    MinMatchBelowWidth { min_match: usize, width: usize },
+    /// `block_sort` is `Some(0)`.
+    EmptyBlock,
+    /// `lookahead` is 0.
+    EmptyLookahead,
+    /// `max_raw_len` is `Some(0)`.
+    ZeroMaxRaw,
+    /// A [`LongRange`](super::LongRange) window or minimum match of 0, or too little memory for
+    /// two index entries.
+    InvalidLongRange,
+    /// `memory_budget` doesn't even fit the lookahead and a window of one value.
+    MemoryBudgetTooSmall { budget: usize, required: usize },
+    /// `max_buffer_len` is longer than the SearchBuffer's positions can address, see
+    /// [`SearchBuffer::MAX_LEN`](crate::search_buffer::SearchBuffer::MAX_LEN).
+    WindowTooLong { max_buffer_len: usize, max: usize },
+}
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::EmptyWindow => write!(f, "max_buffer_len must not be 0"),
+            ConfigError::ZeroMinMatch => write!(f, "match_lengths.start must not be 0"),
+            ConfigError::EmptyMatchLengths { start, end } => {
+                write!(f, "match_lengths {start}..{end} is empty")
+            }
+            ConfigError::MinMatchBelowWidth { min_match, width } => write!(
+                f,
+                "match_lengths.start {min_match} is smaller than the SearchBuffer width {width}"
+            ),
+            ConfigError::EmptyBlock => write!(f, "block_sort must not be 0"),
+            ConfigError::EmptyLookahead => write!(f, "lookahead must not be 0"),
+            ConfigError::ZeroMaxRaw => write!(f, "max_raw_len must not be 0"),
+            ConfigError::InvalidLongRange => write!(
+                f,
+                "long range window_len and min_match must not be 0, memory must fit two entries"
+            ),
+            ConfigError::MemoryBudgetTooSmall { budget, required } => write!(
+                f,
+                "memory_budget of {budget} bytes is below the minimum of {required}"
+            ),
+            ConfigError::WindowTooLong {
+                max_buffer_len,
+                max,
+            } => write!(
+                f,
+                "max_buffer_len {max_buffer_len} is above the SearchBuffer maximum of {max}"
+            ),
+        }
+    }
+}
+impl std::error::Error for ConfigError {}
+
+/// Builds a [`Config`] field by field, validating it on [`ConfigBuilder::build`].
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder<'a, T = u8> {
+    config: Config<'a, T>,
+}
+impl<T> Default for ConfigBuilder<'_, T> {
+    fn default() -> Self {
+        Self {
+            config: Config::default(),
+        }
+    }
+}
+impl<'a, T> ConfigBuilder<'a, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn max_buffer_len(mut self, max_buffer_len: usize) -> Self {
+        self.config.max_buffer_len = max_buffer_len;
+        self
+    }
+    pub fn match_lengths(mut self, match_lengths: Range<usize>) -> Self {
+        self.config.match_lengths = match_lengths;
+        self
+    }
+    pub fn min_match(mut self, min_match: usize) -> Self {
+        self.config.match_lengths.start = min_match;
+        self
+    }
+    /// Longest accepted match, inclusive.
+    pub fn max_match(mut self, max_match: usize) -> Self {
+        self.config.match_lengths.end = max_match.saturating_add(1);
+        self
+    }
+    pub fn dictionary(mut self, dictionary: &'a [T]) -> Self {
+        self.config.dictionary = Some(dictionary);
+        self
+    }
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.config.strategy = strategy;
+        self
+    }
+    pub fn block_sort(mut self, block_len: usize) -> Self {
+        self.config.block_sort = Some(block_len);
+        self
+    }
+    pub fn dictionary_id(mut self, id: u32) -> Self {
+        self.config.dictionary_id = Some(id);
+        self
+    }
+    pub fn lookahead(mut self, lookahead: usize) -> Self {
+        self.config.lookahead = lookahead;
+        self
+    }
+    pub fn memory_budget(mut self, budget: usize) -> Self {
+        self.config.memory_budget = Some(budget);
+        self
+    }
+    pub fn max_chain(mut self, max_chain: usize) -> Self {
+        self.config.max_chain = Some(max_chain);
+        self
+    }
+    pub fn nice_len(mut self, nice_len: usize) -> Self {
+        self.config.nice_len = Some(nice_len);
+        self
+    }
+    pub fn max_raw_len(mut self, max_raw_len: usize) -> Self {
+        self.config.max_raw_len = Some(max_raw_len);
+        self
+    }
+    pub fn forbid_overlap(mut self, forbid_overlap: bool) -> Self {
+        self.config.forbid_overlap = forbid_overlap;
+        self
+    }
+    pub fn build(self) -> Result<Config<'a, T>, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        assert!(Config::<u8>::default().validate().is_ok());
+        let config = Config::<u8>::builder()
+            .max_buffer_len(0x100)
+            .min_match(3)
+            .max_match(10)
+            .strategy(Strategy::Lazy(1))
+            .build()
+            .unwrap();
+        assert_eq!(config.match_lengths, 3..11);
+        assert_eq!(config.validate_for(3), Ok(()));
+        assert_eq!(
+            config.validate_for(4),
+            Err(ConfigError::MinMatchBelowWidth {
+                min_match: 3,
+                width: 4
+            })
+        );
+        let builder = Config::<u8>::builder();
+        assert_eq!(
+            builder.clone().max_buffer_len(0).build().unwrap_err(),
+            ConfigError::EmptyWindow
+        );
+        assert_eq!(
+            builder.clone().min_match(0).build().unwrap_err(),
+            ConfigError::ZeroMinMatch
+        );
+        assert_eq!(
+            builder.clone().match_lengths(5..5).build().unwrap_err(),
+            ConfigError::EmptyMatchLengths { start: 5, end: 5 }
+        );
+        assert_eq!(
+            builder.clone().lookahead(0).build().unwrap_err(),
+            ConfigError::EmptyLookahead
+        );
+        assert_eq!(
+            builder.clone().max_raw_len(0).build().unwrap_err(),
+            ConfigError::ZeroMaxRaw
+        );
+
+        let per_value = Config::<u8>::window_value_bytes(4);
+        let config = builder
+            .max_buffer_len(usize::MAX)
+            .lookahead(0x100)
+            .memory_budget(0x100 + 1000 * per_value + 1)
+            .build()
+            .unwrap();
+        assert_eq!(config.window_for(4), 1000);
+        assert!(config.window_for(1) > 1000);
+        assert_eq!(config.clone().fit_memory(4).max_buffer_len, 1000);
+        let small = Config {
+            max_buffer_len: 10,
+            ..config.clone()
+        };
+        assert_eq!(small.window_for(4), 10);
+        let tiny = Config {
+            memory_budget: Some(0x100),
+            ..config
+        };
+        assert_eq!(
+            tiny.validate(),
+            Err(ConfigError::MemoryBudgetTooSmall {
+                budget: 0x100,
+                required: 0x100 + Config::<u8>::window_value_bytes(1)
+            })
+        );
+    }
+    #[test]
+    fn levels() {
+        let configs = Vec::from_iter((0..=MAX_LEVEL + 1).map(Config::<u8>::level));
+        for config in &configs {
+            assert_eq!(config.validate_for(4), Ok(()));
+        }
+        assert!(
+            configs
+                .windows(2)
+                .all(|x| x[0].max_buffer_len <= x[1].max_buffer_len)
+        );
+        assert_eq!(configs[0].strategy, configs[1].strategy);
+        assert_eq!(configs[MAX_LEVEL as usize + 1].strategy, Strategy::Optimal);
+    }
+}
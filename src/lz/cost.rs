@@ -0,0 +1,83 @@
+use std::num::NonZero;
+
+use super::{Item, REP_SLOTS};
+
+/// Encoded size of items under some wire format, in bits.
+///
+/// Lets parsers weigh items against each other without encoding them, and explains a parse
+/// after the fact: summing [`CostModel::item`] over the items gives their encoded size.
+pub trait CostModel<T: Clone> {
+    /// Bits a raw item of `len` values takes besides the values themselves.
+    fn raw(&self, len: usize) -> usize;
+    /// Bits `value` takes as a raw value.
+    fn literal(&self, value: &T) -> usize;
+    /// Bits a reference to the `len` values `back` values back takes.
+    fn reference(&self, back: NonZero<usize>, len: usize) -> usize;
+    /// Bits a reference of `len` values through rep slot `slot` takes.
+    fn rep_reference(&self, slot: u8, len: usize) -> usize;
+    /// Bits a run of `len` copies of `value` takes.
+    fn run(&self, value: &T, len: usize) -> usize;
+    /// Bits `item` takes.
+    fn item(&self, item: &Item<T>) -> usize {
+        match item {
+            Item::Raw(raw) => {
+                self.raw(raw.len()) + raw.iter().map(|value| self.literal(value)).sum::<usize>()
+            }
+            Item::Ref { back, len } => self.reference(*back, *len),
+            Item::RepRef { slot, len } => self.rep_reference(*slot, *len),
+            Item::Run { value, len } => self.run(value, *len),
+        }
+    }
+}
+
+/// Cost of the serialized [`Item`] format, counting every value as `size_of::<T>()` bytes.
+///
+/// The model [`Strategy::Optimal`](super::Strategy::Optimal),
+/// [`Strategy::BestOf`](super::Strategy::BestOf) and [`optimize`](super::optimize) use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PostcardCost;
+impl<T: Clone> CostModel<T> for PostcardCost {
+    fn raw(&self, len: usize) -> usize {
+        8 * (1 + varint_len(len))
+    }
+    fn literal(&self, _: &T) -> usize {
+        8 * size_of::<T>()
+    }
+    fn reference(&self, back: NonZero<usize>, len: usize) -> usize {
+        8 * (varint_len(back.get() + REP_SLOTS) + varint_len(len))
+    }
+    fn rep_reference(&self, slot: u8, len: usize) -> usize {
+        8 * (varint_len(slot as usize + 1) + varint_len(len))
+    }
+    fn run(&self, _: &T, len: usize) -> usize {
+        8 * (2 + varint_len(len) + size_of::<T>())
+    }
+}
+
+/// Bytes taken by `value` as a varint.
+pub(crate) fn varint_len(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()).div_ceil(7).max(1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[quickcheck]
+    fn postcard(mut raw: Vec<u8>, back: usize, len: usize, slot: u8) {
+        // Empty raw items are written like empty runs, but never emitted.
+        raw.push(0);
+        let back = NonZero::new(back / 2 + 1).unwrap();
+        let slot = slot % REP_SLOTS as u8;
+        for item in [
+            Item::from(raw),
+            Item::Ref { back, len },
+            Item::RepRef { slot, len },
+            Item::Run { value: slot, len },
+        ] {
+            let bytes = postcard::to_stdvec(&item).unwrap().len();
+            assert_eq!(PostcardCost.item(&item), 8 * bytes, "{item:?}");
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::iter;
+
+use super::{Item, RepDistances};
+
+/// How the items produce a value, see [`coverage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coverage {
+    /// Stored as is by an [`Item::Raw`].
+    Literal,
+    /// Repeated by an [`Item::Run`].
+    Run,
+    /// Copied from `back` values before by an [`Item::Ref`] or [`Item::RepRef`].
+    Match { back: usize },
+}
+impl Coverage {
+    /// Position the value at `position` was copied from, or None if it wasn't matched or was
+    /// copied from the dictionary before the data.
+    pub fn source(self, position: usize) -> Option<usize> {
+        match self {
+            Coverage::Match { back } => position.checked_sub(back),
+            Coverage::Literal | Coverage::Run => None,
+        }
+    }
+    pub fn is_match(self) -> bool {
+        matches!(self, Coverage::Match { .. })
+    }
+}
+
+/// Pairs every value of `data` with how `items` produce it, for showing what got matched
+/// against what.
+///
+/// `items` must be those `data` was encoded into, so [`Item::RepRef`] distances resolve. Ends
+/// with the shorter of the two.
+pub fn coverage<'d, 'i, 'c: 'i, T: Clone + 'c>(
+    data: &'d [T],
+    items: impl IntoIterator<Item = &'i Item<'c, T>>,
+) -> impl Iterator<Item = (&'d T, Coverage)> {
+    let mut reps = RepDistances::default();
+    let coverage = items.into_iter().flat_map(move |item| {
+        let back = reps.update(item);
+        let coverage = match item {
+            Item::Raw(_) => Coverage::Literal,
+            Item::Run { .. } => Coverage::Run,
+            Item::Ref { .. } | Item::RepRef { .. } => Coverage::Match { back },
+        };
+        iter::repeat_n(coverage, item.len())
+    });
+    data.iter().zip(coverage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lz::Config, search_buffer::SearchBuffer};
+
+    #[test]
+    fn coverage() {
+        let items = [
+            Item::from(b"ab"),
+            Item::try_from((0..2, 2)).unwrap(),
+            Item::Run {
+                value: b'c',
+                len: 3,
+            },
+            Item::RepRef { slot: 0, len: 2 },
+        ];
+        let data = b"ababccccc";
+        let (values, coverage): (Vec<&u8>, Vec<_>) = super::coverage(data, &items).unzip();
+        assert_eq!(values, Vec::from_iter(data));
+        let back = |back| Coverage::Match { back };
+        assert_eq!(
+            coverage,
+            [
+                Coverage::Literal,
+                Coverage::Literal,
+                back(2),
+                back(2),
+                Coverage::Run,
+                Coverage::Run,
+                Coverage::Run,
+                back(2),
+                back(2),
+            ]
+        );
+        assert_eq!(coverage[7].source(7), Some(5));
+        assert_eq!(back(9).source(7), None);
+
+        // Every matched value equals the one it was copied from.
+        let data = b"the cat sat on the mat, the cat sat on the hat";
+        let config = Config {
+            match_lengths: 3..0x40,
+            ..Config::default()
+        };
+        let items =
+            Vec::from_iter(SearchBuffer::<u8, 3>::new().to_items(data.iter().copied(), config));
+        let mut matched = 0;
+        for (position, (value, coverage)) in super::coverage(data, &items).enumerate() {
+            if let Some(source) = coverage.source(position) {
+                assert_eq!(data[source], *value);
+                matched += 1;
+            }
+        }
+        assert!(matched >= 19);
+    }
+}
@@ -0,0 +1,198 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Config, Item};
+use crate::{Error, Partial, Slide, frame};
+
+/// What decoding an item stream needs to know about the config it was encoded with, besides
+/// the dictionary.
+///
+/// Self-describing item streams start with it, see [`to_described_bytes`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamHeader {
+    pub max_buffer_len: usize,
+    pub match_lengths: Range<usize>,
+}
+impl StreamHeader {
+    pub fn new<T>(config: &Config<T>) -> Self {
+        Self {
+            max_buffer_len: config.max_buffer_len,
+            match_lengths: config.match_lengths.clone(),
+        }
+    }
+    /// Config decoding the items of the stream with `dictionary`.
+    pub fn config<'a, T>(&self, dictionary: Option<&'a [T]>) -> Config<'a, T> {
+        Config {
+            max_buffer_len: self.max_buffer_len,
+            match_lengths: self.match_lengths.clone(),
+            dictionary,
+            ..Config::default()
+        }
+    }
+}
+
+/// Serializes `items` encoded under `config` into a self-describing item stream: its
+/// [`StreamHeader`] followed by the items, each as in a bare item stream.
+///
+/// Like bare item streams, it has no checksum and records no dictionary.
+pub fn to_described_bytes<'i>(
+    items: impl IntoIterator<Item = Item<'i, u8>>,
+    config: &Config,
+) -> Vec<u8> {
+    let out = postcard::to_allocvec(&StreamHeader::new(config)).expect("headers are serializable");
+    items.into_iter().fold(out, |out, item| {
+        postcard::to_extend(&item, out).expect("items are serializable")
+    })
+}
+
+/// Decodes a self-describing item stream, checking every item against its header.
+///
+/// `dictionary` must be the one the stream was encoded with, if any.
+pub fn from_described_bytes(bytes: &[u8], dictionary: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+    from_described_bytes_partial(bytes, dictionary).map_err(|partial| partial.error)
+}
+
+/// Like [`from_described_bytes`], but recovers what it can of a stream that is cut short,
+/// even within an item, or corrupted: the values of all items before the first one that can't
+/// be decoded, along with its offset.
+pub fn from_described_bytes_partial(
+    bytes: &[u8],
+    dictionary: Option<&[u8]>,
+) -> Result<Vec<u8>, Partial> {
+    let malformed = || Error::from(frame::Error::Malformed);
+    let (header, mut rest) =
+        postcard::take_from_bytes::<StreamHeader>(bytes).map_err(|_| Partial {
+            data: vec![],
+            offset: 0,
+            error: malformed(),
+        })?;
+    let header_len = bytes.len() - rest.len();
+    // Every item with the offsets after it in `bytes` and in the values.
+    let (mut items, mut ends) = (vec![], vec![]);
+    let mut values = 0usize;
+    let parsed = loop {
+        if rest.is_empty() {
+            break Ok(());
+        }
+        let Ok((item, tail)) = Item::take_from_bytes(rest) else {
+            break Err(malformed());
+        };
+        let Some(end) = values.checked_add(item.len()) else {
+            break Err(malformed());
+        };
+        values = end;
+        ends.push((bytes.len() - tail.len(), values));
+        items.push(item);
+        rest = tail;
+    };
+    let mut data = vec![];
+    let mut window = Slide::new();
+    let decoded = (window.try_from_items(items, header.config(dictionary)))
+        .try_for_each(|value| value.map(|value| data.push(value)));
+    let error = match (decoded, parsed) {
+        (Ok(()), Ok(())) => return Ok(data),
+        (Err(error), _) | (Ok(()), Err(error)) => error,
+    };
+    // Items are checked before any of their values are decoded.
+    let failed = ends.partition_point(|&(_, values)| values <= data.len());
+    let offset = match failed {
+        0 => header_len,
+        failed => ends[failed - 1].0,
+    };
+    Err(Partial {
+        data,
+        offset,
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_buffer::SearchBuffer;
+    use std::num::NonZero;
+
+    #[test]
+    fn described() {
+        let data = Vec::from_iter((0..0x4000u32).map(|x| (x / 3 % 7) as u8 ^ (x >> 8) as u8));
+        let dictionary = &data[..0x100];
+        let config = Config {
+            max_buffer_len: 0x200,
+            match_lengths: 3..0x20,
+            dictionary: Some(dictionary),
+            ..Config::level(5)
+        };
+        let mut search_buffer = SearchBuffer::<u8, 3>::new();
+        let items = search_buffer.to_items_from_slice(&data, config.clone());
+        let bytes = to_described_bytes(items, &config);
+        let (header, _) = postcard::take_from_bytes::<StreamHeader>(&bytes).unwrap();
+        assert_eq!(header, StreamHeader::new(&config));
+        assert_eq!(
+            from_described_bytes(&bytes, Some(dictionary)).unwrap(),
+            data
+        );
+
+        let mut narrow = bytes.clone();
+        // The header starts with max_buffer_len 0x200 as a varint, make it 0x100.
+        assert_eq!(narrow[..2], [0x80, 0x04]);
+        narrow[1] = 0x02;
+        assert!(matches!(
+            from_described_bytes(&narrow, Some(dictionary)),
+            Err(Error::InvalidDistance { .. })
+        ));
+        assert!(matches!(
+            from_described_bytes(&bytes[..1], None),
+            Err(Error::Frame(frame::Error::Malformed))
+        ));
+    }
+    #[test]
+    fn partial() {
+        let data = Vec::from_iter((0..0x1000u32).map(|x| (x / 5 % 9) as u8 ^ (x >> 7) as u8));
+        let config = Config {
+            match_lengths: 3..0x20,
+            ..Config::level(5)
+        };
+        let mut search_buffer = SearchBuffer::<u8, 3>::new();
+        let items = Vec::from_iter(search_buffer.to_items_from_slice(&data, config.clone()));
+        let bytes = to_described_bytes(items.iter().cloned(), &config);
+        assert_eq!(from_described_bytes_partial(&bytes, None).unwrap(), data);
+        let mut truncated = 0;
+        for cut in (0..bytes.len()).step_by(7) {
+            // Item streams have no end marker, so a cut between items goes unnoticed.
+            let partial = match from_described_bytes_partial(&bytes[..cut], None) {
+                Ok(decoded) if cut > 0 => {
+                    assert!(data.starts_with(&decoded));
+                    continue;
+                }
+                result => result.unwrap_err(),
+            };
+            truncated += 1;
+            assert!(matches!(
+                partial.error,
+                Error::Frame(frame::Error::Malformed)
+            ));
+            assert!(partial.offset <= cut);
+            assert!(data.starts_with(&partial.data));
+            if partial.offset > 0 {
+                // Decoding the stream up to the offset recovers the same data.
+                assert_eq!(
+                    from_described_bytes(&bytes[..partial.offset], None).unwrap(),
+                    partial.data
+                );
+            }
+        }
+        assert!(truncated > 0);
+
+        // Lengths adding up past usize::MAX fail like any other corruption.
+        let huge = Item::Ref {
+            back: NonZero::<usize>::MIN,
+            len: usize::MAX,
+        };
+        let bytes = to_described_bytes([Item::from(b"ab"), huge.clone(), huge], &config);
+        let partial = from_described_bytes_partial(&bytes, None).unwrap_err();
+        assert_eq!(partial.data, b"ab");
+        let raw_end = to_described_bytes([Item::from(b"ab")], &config).len();
+        assert_eq!(partial.offset, raw_end);
+    }
+}
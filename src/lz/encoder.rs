@@ -0,0 +1,485 @@
+use std::{collections::VecDeque, iter, num::NonZero, ops::Range};
+
+use super::{
+    Config, ConfigError, Item, MIN_RUN_LEN, PostcardCost, RepDistances, Strategy, match_len_at,
+    optimal, run_len,
+};
+use crate::{
+    Error, Slide,
+    search_buffer::{BuildHeads, Position, SearchBuffer},
+    util::BuildSlideHasher,
+};
+
+/// Default [`Config::lookahead`], the most values buffered ahead while encoding, including
+/// by [`Encoder::feed`].
+pub const FEED_LOOKAHEAD: usize = 0x10000;
+
+/// State of the item parser, shared by [`SearchBuffer::to_items`] and [`Encoder`].
+pub(super) struct Parser<'a, T: Clone> {
+    config: Config<'a, T>,
+    min_run: usize,
+    /// Most values in `match_window`, bounding the length of matches.
+    lookahead: usize,
+    match_window: Slide<T>,
+    raw_len: usize,
+    next_item: Option<Item<'a, T>>,
+    /// Distance of the last match if it was cut short by the end of `match_window`, so the next
+    /// one tries to continue it.
+    chain: Option<NonZero<usize>>,
+    reps: RepDistances,
+    parsed: VecDeque<optimal::Step>,
+}
+impl<'a, T: Copy + Eq> Parser<'a, T> {
+    /// Primes `search_buffer` with the dictionary of `config`, if it is valid for a key width
+    /// of `N` and its window fits the positions `P`.
+    pub(super) fn new<const N: usize, S: BuildHeads<[T; N], P>, P: Position>(
+        search_buffer: &mut SearchBuffer<T, N, S, P>,
+        config: Config<'a, T>,
+    ) -> Result<Self, ConfigError> {
+        config.validate_for(N)?;
+        let config = config.fit_memory(N);
+        let max = SearchBuffer::<T, N, S, P>::MAX_LEN;
+        if config.max_buffer_len > max {
+            return Err(ConfigError::WindowTooLong {
+                max_buffer_len: config.max_buffer_len,
+                max,
+            });
+        }
+        search_buffer.set_max_chain(config.max_chain);
+        search_buffer.set_overlap(!config.forbid_overlap);
+        search_buffer
+            .extend_slide(
+                config.dictionary_window().iter().copied(),
+                config.max_buffer_len,
+            )
+            .for_each(drop);
+        Ok(Self {
+            min_run: MIN_RUN_LEN.max(config.match_lengths.start),
+            lookahead: config
+                .match_lengths
+                .end
+                .saturating_sub(1)
+                .min(config.lookahead),
+            config,
+            match_window: Slide::new(),
+            raw_len: 0,
+            next_item: None,
+            chain: None,
+            reps: RepDistances::default(),
+            parsed: VecDeque::new(),
+        })
+    }
+    /// Parses the next item, pulling as many values from `iter` as needed.
+    ///
+    /// Returns `None` once `iter` and all values pulled from it are exhausted, but may be called
+    /// again after more values became available.
+    pub(super) fn next<const N: usize, S: BuildHeads<[T; N], P>, P: Position>(
+        &mut self,
+        search_buffer: &mut SearchBuffer<T, N, S, P>,
+        iter: &mut impl Iterator<Item = T>,
+    ) -> Option<Item<'a, T>> {
+        let Self {
+            config,
+            min_run,
+            lookahead,
+            match_window,
+            raw_len,
+            next_item,
+            chain,
+            reps,
+            parsed,
+        } = self;
+        loop {
+            // Return items already found in previous call/iteration.
+            if *raw_len > 0 {
+                // Optimal parses and lazy skips may exceed max_raw_len, split them up.
+                let len = config.max_raw_len.map_or(*raw_len, |max| max.min(*raw_len));
+                let item = Item::from(Vec::from_iter(match_window.pop_n(len)));
+                *raw_len -= len;
+                return Some(item);
+            } else if let Some(item) = next_item.take() {
+                match_window.pop_n(item.len()).for_each(drop);
+                return Some(reps.encode(item));
+            } else if let Some((raw, back_ref)) = parsed.pop_front() {
+                // Matches found by the parser always reach back.
+                let back_ref = back_ref.map(|back_ref| Item::try_from(back_ref).unwrap());
+                (*raw_len, *next_item) = (raw, back_ref);
+                continue;
+            }
+            match_window.extend(
+                iter.by_ref()
+                    .take(lookahead.saturating_sub(match_window.len())),
+            );
+            if match_window.is_empty() {
+                return None;
+            }
+            if config.strategy == Strategy::Optimal && run_len(match_window, *min_run).is_none() {
+                *chain = None;
+                parsed.extend(search_buffer.parse_optimal(match_window, config, &PostcardCost));
+                continue;
+            }
+            // Keep pushing/sliding in values popped of data until valid match is found.
+            while let data @ [head, ..] = &match_window[*raw_len..] {
+                debug_assert!(data.len() <= *lookahead);
+                // Matches longer than the window allows become a chain of maximal references,
+                // the later ones to the most recent distance.
+                if let Some(back) = chain.take() {
+                    let len = match_len_at(search_buffer, data, search_buffer.end(), back.get());
+                    if len >= config.match_lengths.start {
+                        search_buffer
+                            .extend_slide(data[..len].iter().copied(), config.max_buffer_len)
+                            .for_each(drop);
+                        *chain = (len == data.len()).then_some(back);
+                        *next_item = Some(Item::Ref { back, len });
+                        break;
+                    }
+                }
+                // Runs are cheaper to detect directly than through the hash chains.
+                if let Some(len) = run_len(data, *min_run) {
+                    search_buffer
+                        .extend_slide(data[..len].iter().copied(), config.max_buffer_len)
+                        .for_each(drop);
+                    *next_item = Some(Item::Run { value: *head, len });
+                    break;
+                } else if let Some(range) = match config.strategy {
+                    Strategy::BestOf(k) => search_buffer.find_cheapest_match(
+                        data,
+                        k,
+                        config.match_lengths.start,
+                        config.nice_len,
+                        reps,
+                        &PostcardCost,
+                    ),
+                    _ => find_match(search_buffer, data, config.nice_len),
+                }
+                // Matches only need to be longer than N.
+                .filter(|range| range.len() >= config.match_lengths.start)
+                {
+                    let mut best = (0, range, search_buffer.end());
+                    // Values of data already pushed into the search buffer.
+                    let mut pushed = 0;
+                    if let Strategy::Lazy(level) = config.strategy {
+                        // Only look inside the best match, so nothing gets pushed twice.
+                        while pushed < level && pushed + 1 < best.0 + best.1.len() {
+                            search_buffer.push_step(data[pushed], config.max_buffer_len);
+                            pushed += 1;
+                            if let Some(range) =
+                                find_match(search_buffer, &data[pushed..], config.nice_len)
+                                && range.len() > best.1.len()
+                            {
+                                best = (pushed, range, search_buffer.end());
+                            }
+                        }
+                    }
+                    let (skip, range, end) = best;
+                    let len = range.len();
+                    // Prefer an equally long match at a recent, cheaper to encode distance.
+                    let back = reps
+                        .0
+                        .into_iter()
+                        .find(|back| {
+                            match_len_at(search_buffer, &data[skip..], end, back.get()) >= len
+                        })
+                        .unwrap_or(NonZero::new(end - range.start).unwrap());
+                    search_buffer
+                        .extend_slide(
+                            data[pushed..skip + len].iter().copied(),
+                            config.max_buffer_len,
+                        )
+                        .for_each(drop);
+                    *raw_len += skip;
+                    *chain = (skip + len == data.len()).then_some(back);
+                    *next_item = Some(Item::Ref { back, len });
+                    break;
+                } else {
+                    search_buffer.push_step(*head, config.max_buffer_len);
+                    if let Some(val) = iter.next() {
+                        match_window.push(val);
+                    }
+                    *raw_len += 1;
+                    if config.max_raw_len.is_some_and(|max| *raw_len >= max) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    /// Encodes `values` as a match `back` values before them, found outside of `search_buffer`,
+    /// and pushes them into it.
+    ///
+    /// Only valid once [`Parser::next`] returned `None`.
+    pub(super) fn external_match<const N: usize, S: BuildHeads<[T; N], P>, P: Position>(
+        &mut self,
+        search_buffer: &mut SearchBuffer<T, N, S, P>,
+        back: NonZero<usize>,
+        values: &[T],
+    ) -> Item<'a, T> {
+        debug_assert!(self.match_window.is_empty() && self.parsed.is_empty());
+        search_buffer
+            .extend_slide(values.iter().copied(), self.config.max_buffer_len)
+            .for_each(drop);
+        self.reps.encode(Item::Ref {
+            back,
+            len: values.len(),
+        })
+    }
+}
+
+/// Longest match of `data`, or the first at least `nice_len` long.
+fn find_match<T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position>(
+    search_buffer: &SearchBuffer<T, N, S, P>,
+    data: &[T],
+    nice_len: Option<usize>,
+) -> Option<Range<usize>> {
+    match nice_len {
+        Some(nice_len) => search_buffer
+            .find_longest_match_by(data, |_, candidate| Ok(candidate.len() >= nice_len)),
+        None => search_buffer.find_longest_match(data),
+    }
+}
+
+/// Push-based counterpart of [`SearchBuffer::to_items`], for input arriving in pieces.
+///
+/// Every item returned by [`Encoder::feed`] is final and only covers values fed so far, so it
+/// can be sent right away. Concatenated with the items of [`Encoder::finish`], they decode with
+/// [`Slide::from_items`] like the items of [`SearchBuffer::to_items`].
+pub struct Encoder<
+    'a,
+    T: Clone,
+    const N: usize,
+    S: BuildHeads<[T; N], P> = BuildSlideHasher,
+    P: Position = usize,
+> {
+    search_buffer: SearchBuffer<T, N, S, P>,
+    parser: Parser<'a, T>,
+    pending: VecDeque<T>,
+    lookahead: usize,
+}
+impl<'a, T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position>
+    Encoder<'a, T, N, S, P>
+{
+    /// Fails if `config` is invalid for a key width of `N` or its window is too long for
+    /// positions of `P`.
+    pub fn new(config: Config<'a, T>) -> Result<Self, Error>
+    where
+        S: Default,
+    {
+        Self::with_search_buffer(SearchBuffer::new(), config)
+    }
+    /// Like [`Encoder::new`], continuing from the window of `search_buffer`.
+    pub fn with_search_buffer(
+        mut search_buffer: SearchBuffer<T, N, S, P>,
+        config: Config<'a, T>,
+    ) -> Result<Self, Error> {
+        let parser = Parser::new(&mut search_buffer, config)?;
+        Ok(Self {
+            lookahead: parser.lookahead,
+            parser,
+            search_buffer,
+            pending: VecDeque::new(),
+        })
+    }
+    /// Number of values fed but not yet covered by a returned item.
+    pub fn pending(&self) -> usize {
+        self.parser.match_window.len() + self.pending.len()
+    }
+    /// Feeds `data`, returning all items that no longer depend on values fed later.
+    ///
+    /// Values not covered by the returned items stay buffered until the next call.
+    pub fn feed(&mut self, data: &[T]) -> impl Iterator<Item = Item<'a, T>> {
+        self.pending.extend(data);
+        iter::from_fn(move || {
+            if self.pending.len() < self.lookahead {
+                return None;
+            }
+            let pending = &mut self.pending;
+            self.parser.next(
+                &mut self.search_buffer,
+                &mut iter::from_fn(|| pending.pop_front()),
+            )
+        })
+    }
+    /// Encodes all buffered values, returning the remaining items.
+    pub fn finish(mut self) -> impl Iterator<Item = Item<'a, T>> {
+        iter::from_fn(move || {
+            let pending = &mut self.pending;
+            self.parser.next(
+                &mut self.search_buffer,
+                &mut iter::from_fn(|| pending.pop_front()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lz::Stats;
+    use std::cell::Cell;
+
+    fn config() -> Config<'static, u8> {
+        Config {
+            max_buffer_len: 0x400,
+            match_lengths: 3..0x40,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn feed() {
+        let data = Vec::from_iter((0..0x2000u32).map(|x| (x / 3 % 53) as u8 ^ (x >> 9) as u8));
+        for strategy in [
+            Strategy::Greedy,
+            Strategy::Lazy(2),
+            Strategy::Optimal,
+            Strategy::BestOf(4),
+        ] {
+            let config = Config {
+                strategy,
+                ..config()
+            };
+            // Fed at once, the items match the pull-based parser.
+            let mut encoder = Encoder::<_, 3>::new(config.clone()).unwrap();
+            let mut items = Vec::from_iter(encoder.feed(&data));
+            assert!(!items.is_empty());
+            assert!(encoder.pending() < 2 * config.match_lengths.end);
+            items.extend(encoder.finish());
+            let expected = Vec::from_iter(
+                SearchBuffer::<_, 3>::new().to_items(data.iter().copied(), config.clone()),
+            );
+            assert_eq!(items, expected);
+
+            for chunk_len in [1, 7, 0x100] {
+                let mut encoder = Encoder::<_, 3>::new(config.clone()).unwrap();
+                let mut items = vec![];
+                let mut fed = 0;
+                for chunk in data.chunks(chunk_len) {
+                    fed += chunk.len();
+                    items.extend(encoder.feed(chunk));
+                    let covered = items.iter().map(Item::len).sum::<usize>();
+                    assert_eq!(covered + encoder.pending(), fed);
+                }
+                items.extend(encoder.finish());
+                let decoded = Vec::from_iter(Slide::new().from_items(items, config.clone()));
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+    #[test]
+    fn lookahead() {
+        // Long repetitions and runs, with matches of unbounded length.
+        let data = [
+            Vec::from_iter((0..0x4000u32).map(|x| (x % 0x321) as u8 ^ (x >> 12) as u8)),
+            vec![7; 0x1000],
+        ]
+        .concat();
+        for strategy in [
+            Strategy::Greedy,
+            Strategy::Lazy(2),
+            Strategy::Optimal,
+            Strategy::BestOf(4),
+        ] {
+            let config = Config {
+                match_lengths: 3..usize::MAX,
+                strategy,
+                lookahead: 0x100,
+                ..config()
+            };
+            let pulled = Cell::new(0);
+            let mut covered = 0;
+            let mut items = vec![];
+            for item in SearchBuffer::<_, 3>::new().to_items(
+                data.iter()
+                    .inspect(|_| pulled.set(pulled.get() + 1))
+                    .copied(),
+                config.clone(),
+            ) {
+                assert!(item.len() <= config.lookahead);
+                covered += item.len();
+                assert!(pulled.get() - covered <= config.lookahead);
+                items.push(item);
+            }
+            let decoded = Vec::from_iter(Slide::new().from_items(items, config.clone()));
+            assert_eq!(decoded, data);
+
+            let mut encoder = Encoder::<_, 3>::new(config.clone()).unwrap();
+            let items = Vec::from_iter(encoder.feed(&data));
+            assert!(encoder.pending() < 2 * config.lookahead);
+            assert!(items.iter().all(|item| item.len() <= config.lookahead));
+        }
+    }
+    #[test]
+    fn chained_matches() {
+        let data = b"abcde".repeat(0x200);
+        for strategy in [Strategy::Greedy, Strategy::Lazy(2), Strategy::BestOf(4)] {
+            let config = Config {
+                strategy,
+                ..config()
+            };
+            let max_len = config.match_lengths.end - 1;
+            let mut stats = Stats::new();
+            let items = Vec::from_iter(SearchBuffer::<_, 3>::new().to_items_with_stats(
+                data.iter().copied(),
+                config.clone(),
+                &mut stats,
+            ));
+            // One match split into maximal references, found by a single search.
+            assert_eq!(items[0], Item::from(b"abcde"));
+            assert_eq!(items.len(), 1 + (data.len() - 5).div_ceil(max_len));
+            assert!(
+                items[1..items.len() - 1]
+                    .iter()
+                    .all(|item| item.len() == max_len)
+            );
+            assert!(items[2..].iter().all(|item| *item
+                == Item::RepRef {
+                    slot: 0,
+                    len: item.len()
+                }));
+            assert!(stats.searches < 10);
+            let decoded = Vec::from_iter(Slide::new().from_items(items, config));
+            assert_eq!(decoded, data);
+        }
+    }
+    #[test]
+    fn forbid_overlap() {
+        // Short periods, which overlapping matches cover best.
+        let data =
+            Vec::from_iter((0..0x2000u32).map(|x| (x % (3 + x / 0x400)) as u8 ^ (x >> 8) as u8));
+        let overlapping = |items: &[Item<u8>]| {
+            let mut reps = RepDistances::default();
+            items
+                .iter()
+                .filter(|item| {
+                    matches!(reps.decode((*item).clone()), Item::Ref { back, len } if len > back.get())
+                })
+                .count()
+        };
+        for strategy in [
+            Strategy::Greedy,
+            Strategy::Lazy(2),
+            Strategy::Optimal,
+            Strategy::BestOf(4),
+        ] {
+            let config = Config {
+                strategy,
+                ..config()
+            };
+            let items = Vec::from_iter(
+                SearchBuffer::<_, 3>::new().to_items(data.iter().copied(), config.clone()),
+            );
+            assert!(overlapping(&items) > 0);
+            let config = Config {
+                forbid_overlap: true,
+                ..config
+            };
+            let items = Vec::from_iter(
+                SearchBuffer::<_, 3>::new().to_items(data.iter().copied(), config.clone()),
+            );
+            assert_eq!(overlapping(&items), 0);
+            assert!(items.iter().any(|item| item.len() > 8));
+            let decoded = Vec::from_iter(Slide::new().from_items(items, config));
+            assert_eq!(decoded, data);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use postcard::ser_flavors::Size;
+
+use super::{Config, Item};
+use crate::search_buffer::SearchBuffer;
+
+/// Size of the serialized items `data` compresses to under `config`, relative to its length.
+///
+/// Runs the match finder, costing every item as it is found instead of collecting items or
+/// output. Below 1 compression pays off; empty data has a ratio of 1. The SearchBuffer key width
+/// is chosen like [`frame::compress`](crate::frame::compress) does.
+///
+/// Panics if `config` is invalid.
+pub fn estimate_ratio(data: &[u8], config: &Config) -> f64 {
+    ratio(estimated_len(data, config), data.len())
+}
+
+/// Like [`estimate_ratio`], but only compresses `samples` windows of `sample_len` bytes spread
+/// evenly over `data`, each starting out empty.
+///
+/// Matches between samples are missed, so the estimate leans pessimistic for inputs repeating
+/// at long distances. Looks at all of `data` if the samples would cover it anyway.
+pub fn estimate_ratio_sampled(
+    data: &[u8],
+    config: &Config,
+    samples: usize,
+    sample_len: usize,
+) -> f64 {
+    if samples.saturating_mul(sample_len) >= data.len() {
+        return estimate_ratio(data, config);
+    }
+    let last = data.len() - sample_len;
+    let (len, sampled) = (0..samples)
+        .map(|i| {
+            let start = match samples {
+                1 => last / 2,
+                _ => last * i / (samples - 1),
+            };
+            let sample = &data[start..start + sample_len];
+            (estimated_len(sample, config), sample.len())
+        })
+        .fold((0, 0), |(len, sampled), (sample_len, sample)| {
+            (len + sample_len, sampled + sample)
+        });
+    ratio(len, sampled)
+}
+
+fn ratio(len: usize, data_len: usize) -> f64 {
+    match data_len {
+        0 => 1.0,
+        _ => len as f64 / data_len as f64,
+    }
+}
+
+/// Serialized length of the items `data` compresses to.
+fn estimated_len(data: &[u8], config: &Config) -> usize {
+    match config.match_lengths.start {
+        1 => estimated_len_with::<1>(data, config),
+        2 => estimated_len_with::<2>(data, config),
+        3 => estimated_len_with::<3>(data, config),
+        _ => estimated_len_with::<4>(data, config),
+    }
+}
+
+fn estimated_len_with<const N: usize>(data: &[u8], config: &Config) -> usize {
+    SearchBuffer::<u8, N>::new()
+        .to_items(data.iter().copied(), config.clone())
+        .map(|item| serialized_len(&item))
+        .sum()
+}
+
+fn serialized_len(item: &Item<u8>) -> usize {
+    postcard::serialize_with_flavor(item, Size::default()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config<'static> {
+        Config {
+            max_buffer_len: 0x1000,
+            match_lengths: 4..0x100,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn estimate_ratio() {
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(0x100);
+        let items =
+            Vec::from_iter(SearchBuffer::<u8, 4>::new().to_items(text.iter().copied(), config()));
+        let len: usize = items
+            .iter()
+            .map(|item| postcard::to_stdvec(item).unwrap().len())
+            .sum();
+        let ratio = super::estimate_ratio(&text, &config());
+        assert_eq!(ratio, len as f64 / text.len() as f64);
+        assert!(ratio < 0.1);
+
+        let mut x = 1u32;
+        let noise = Vec::from_iter((0..0x4000).map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        }));
+        assert!(super::estimate_ratio(&noise, &config()) > 1.0);
+        assert_eq!(super::estimate_ratio(&[], &config()), 1.0);
+
+        let sampled = estimate_ratio_sampled(&text, &config(), 4, 0x400);
+        assert!(sampled < 0.1 && sampled >= ratio, "{sampled} {ratio}");
+        assert!(estimate_ratio_sampled(&noise, &config(), 4, 0x400) > 1.0);
+        assert_eq!(estimate_ratio_sampled(&text, &config(), 100, 0x400), ratio);
+    }
+}
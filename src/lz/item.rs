@@ -1,107 +1,263 @@
-use std::{fmt::Debug, marker::PhantomData, num::NonZero, ops::Range};
+use std::{borrow::Cow, fmt::Debug, marker::PhantomData, num::NonZero, ops::Range};
 
 use serde::{
     Deserialize, Serialize,
     de::{Error, Visitor},
     ser::SerializeTuple as _,
 };
-use smallvec::SmallVec;
 
-#[derive(PartialEq, Eq, Debug)]
-pub enum Item<T> {
-    Raw(SmallVec<[T; 256]>),
-    Ref { back: NonZero<usize>, len: usize },
+/// A literal run of values or an instruction to reproduce earlier ones.
+///
+/// Raw values either borrow from the buffer the item was read from, see
+/// [`Item::take_from_bytes`], or are owned.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Item<'a, T: Clone> {
+    Raw(Cow<'a, [T]>),
+    Ref {
+        back: NonZero<usize>,
+        len: usize,
+    },
+    /// `len` repetitions of `value`.
+    Run {
+        value: T,
+        len: usize,
+    },
+    /// Reference reusing the distance in `slot` of the [`RepDistances`].
+    RepRef {
+        slot: u8,
+        len: usize,
+    },
 }
-impl<T, const N: usize> From<[T; N]> for Item<T> {
+impl<T: Clone, const N: usize> From<[T; N]> for Item<'_, T> {
     fn from(value: [T; N]) -> Self {
-        Self::Raw(SmallVec::from_iter(value))
+        Self::Raw(Cow::Owned(value.into()))
     }
 }
-impl<T> From<Vec<T>> for Item<T> {
+impl<T: Clone> From<Vec<T>> for Item<'_, T> {
     fn from(value: Vec<T>) -> Self {
-        Self::Raw(SmallVec::from_vec(value))
+        Self::Raw(Cow::Owned(value))
     }
 }
-impl<T> From<Box<[T]>> for Item<T> {
+impl<T: Clone> From<Box<[T]>> for Item<'_, T> {
     fn from(value: Box<[T]>) -> Self {
-        Self::Raw(SmallVec::from_vec(value.into()))
+        Self::Raw(Cow::Owned(value.into()))
     }
 }
-impl<T: Clone, const N: usize> From<&[T; N]> for Item<T> {
-    fn from(value: &[T; N]) -> Self {
-        Self::Raw(SmallVec::from_iter(value.iter().cloned()))
+impl<'a, T: Clone, const N: usize> From<&'a [T; N]> for Item<'a, T> {
+    fn from(value: &'a [T; N]) -> Self {
+        Self::Raw(Cow::Borrowed(value))
     }
 }
-impl<T: Clone> From<&[T]> for Item<T> {
-    fn from(value: &[T]) -> Self {
-        Self::Raw(SmallVec::from_iter(value.iter().cloned()))
+impl<'a, T: Clone> From<&'a [T]> for Item<'a, T> {
+    fn from(value: &'a [T]) -> Self {
+        Self::Raw(Cow::Borrowed(value))
     }
 }
-impl<T> From<(Range<usize>, usize)> for Item<T> {
-    fn from((index, end): (Range<usize>, usize)) -> Self {
-        Self::Ref {
-            back: NonZero::try_from(end - index.start).unwrap(),
+/// Reference to the values at `index`, from a position of `end`.
+impl<T: Clone> TryFrom<(Range<usize>, usize)> for Item<'_, T> {
+    type Error = crate::Error;
+    fn try_from((index, end): (Range<usize>, usize)) -> Result<Self, Self::Error> {
+        Ok(Self::Ref {
+            back: end
+                .checked_sub(index.start)
+                .and_then(NonZero::new)
+                .ok_or(crate::Error::ZeroDistance)?,
             len: index.len(),
+        })
+    }
+}
+/// Number of recently used distances [`Item::RepRef`] can refer to.
+pub const REP_SLOTS: usize = 4;
+
+/// The most recently used reference distances, most recent first.
+///
+/// Encoder and decoder both start with the default and update it with every item,
+/// so [`Item::RepRef`] slots resolve to the same distances on both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepDistances(pub [NonZero<usize>; REP_SLOTS]);
+impl Default for RepDistances {
+    fn default() -> Self {
+        Self([1, 2, 3, 4].map(|back| NonZero::new(back).unwrap()))
+    }
+}
+impl RepDistances {
+    /// Turns a [`Item::Ref`] to a recent distance into a [`Item::RepRef`], updating the history.
+    pub fn encode<'a, T: Clone>(&mut self, item: Item<'a, T>) -> Item<'a, T> {
+        match item {
+            Item::Ref { back, len } => {
+                let slot = self.0.iter().position(|&rep| rep == back);
+                self.push(back, slot);
+                match slot {
+                    Some(slot) => Item::RepRef {
+                        slot: slot as u8,
+                        len,
+                    },
+                    None => Item::Ref { back, len },
+                }
+            }
+            item => item,
+        }
+    }
+    /// Turns a [`Item::RepRef`] back into a [`Item::Ref`], updating the history.
+    ///
+    /// Panics if the slot is not below [`REP_SLOTS`].
+    pub fn decode<'a, T: Clone>(&mut self, item: Item<'a, T>) -> Item<'a, T> {
+        match item {
+            Item::Ref { len, .. } | Item::RepRef { len, .. } => Item::Ref {
+                back: NonZero::new(self.update(&item)).unwrap(),
+                len,
+            },
+            item => item,
         }
     }
+    /// Distance `item` refers to, or 0 if it isn't a reference, updating the history.
+    ///
+    /// Panics if the slot is not below [`REP_SLOTS`].
+    pub fn update<T: Clone>(&mut self, item: &Item<T>) -> usize {
+        let (back, slot) = match *item {
+            Item::Ref { back, .. } => (back, self.0.iter().position(|&rep| rep == back)),
+            Item::RepRef { slot, .. } => (self.0[slot as usize], Some(slot as usize)),
+            _ => return 0,
+        };
+        self.push(back, slot);
+        back.get()
+    }
+    fn push(&mut self, back: NonZero<usize>, slot: Option<usize>) {
+        let end = slot.unwrap_or(REP_SLOTS - 1);
+        self.0[..=end].rotate_right(1);
+        self.0[0] = back;
+    }
 }
-impl<T> Item<T> {
+
+impl<T: Clone> Item<'_, T> {
+    /// Distance of a [`Item::Ref`], 0 for all other items.
+    ///
+    /// The distance of a [`Item::RepRef`] depends on the preceding items, see [`RepDistances`].
     pub fn back(&self) -> usize {
         match self {
-            Item::Raw(_) => 0,
+            Item::Raw(_) | Item::Run { .. } | Item::RepRef { .. } => 0,
             Item::Ref { back, len: _ } => (*back).into(),
         }
     }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
     pub fn len(&self) -> usize {
         match self {
             Item::Raw(raw) => raw.len(),
-            Item::Ref { back: _, len } => *len,
+            Item::Ref { back: _, len }
+            | Item::Run { value: _, len }
+            | Item::RepRef { slot: _, len } => *len,
         }
     }
     pub fn as_raw(&self) -> Option<&[T]> {
         match self {
-            Item::Raw(raw) => Some(&raw),
-            Item::Ref { .. } => None,
+            Item::Raw(raw) => Some(raw),
+            Item::Ref { .. } | Item::Run { .. } | Item::RepRef { .. } => None,
+        }
+    }
+    /// Copies borrowed raw values, detaching the item from the buffer they point into.
+    pub fn into_owned(self) -> Item<'static, T> {
+        match self {
+            Item::Raw(raw) => Item::Raw(Cow::Owned(raw.into_owned())),
+            Item::Ref { back, len } => Item::Ref { back, len },
+            Item::Run { value, len } => Item::Run { value, len },
+            Item::RepRef { slot, len } => Item::RepRef { slot, len },
+        }
+    }
+}
+impl<'a> Item<'a, u8> {
+    /// Zero-copy counterpart of [`postcard::take_from_bytes`], returning the item and the
+    /// remaining bytes.
+    ///
+    /// Accepts exactly what [`Item`]'s [`Deserialize`] accepts, but raw values borrow from
+    /// `bytes` instead of being copied.
+    pub fn take_from_bytes(bytes: &'a [u8]) -> postcard::Result<(Self, &'a [u8])> {
+        let (back, bytes) = postcard::take_from_bytes::<usize>(bytes)?;
+        let (len, bytes) = postcard::take_from_bytes::<usize>(bytes)?;
+        if let Some(back) = back.checked_sub(REP_SLOTS).and_then(NonZero::new) {
+            Ok((Item::Ref { back, len }, bytes))
+        } else if back > 0 {
+            let slot = (back - 1) as u8;
+            Ok((Item::RepRef { slot, len }, bytes))
+        } else if len == 0 {
+            let (len, bytes) = postcard::take_from_bytes::<usize>(bytes)?;
+            if len == 0 {
+                return Ok((Item::Raw(Cow::Borrowed(&[])), bytes));
+            }
+            let (value, bytes) = postcard::take_from_bytes(bytes)?;
+            Ok((Item::Run { value, len }, bytes))
+        } else {
+            let (raw, bytes) = bytes
+                .split_at_checked(len)
+                .ok_or(postcard::Error::DeserializeUnexpectedEnd)?;
+            Ok((Item::Raw(Cow::Borrowed(raw)), bytes))
         }
     }
 }
 
-impl<T: Serialize> Serialize for Item<T> {
+impl<T: Clone + Serialize> Serialize for Item<'_, T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut s = serializer.serialize_tuple(0)?;
+        // Formats like postcard leave tuple lengths implicit, self-describing ones record them.
+        let len = match self {
+            Item::Raw(raw) if raw.is_empty() => 3,
+            Item::Raw(raw) => 2 + raw.len(),
+            Item::Ref { .. } | Item::RepRef { .. } => 2,
+            Item::Run { .. } => 4,
+        };
+        let mut s = serializer.serialize_tuple(len)?;
         match self {
+            // The first varint tells items apart: 0 for raw values and runs, 1 up to REP_SLOTS
+            // for rep references, and the distance offset by REP_SLOTS for references.
+            // Raw items are never empty, so (0, 0) introduces a run, with a run of 0 being empty.
+            Item::Raw(raw) if raw.is_empty() => {
+                s.serialize_element(&0)?;
+                s.serialize_element(&0)?;
+                s.serialize_element(&0)?;
+            }
             Item::Raw(raw) => {
                 s.serialize_element(&0)?;
                 s.serialize_element(&raw.len())?;
-                for value in raw {
+                for value in raw.iter() {
                     s.serialize_element(value)?;
                 }
             }
             Item::Ref { back, len } => {
-                s.serialize_element(back)?;
+                s.serialize_element(&(back.get() + REP_SLOTS))?;
+                s.serialize_element(len)?;
+            }
+            Item::RepRef { slot, len } => {
+                s.serialize_element(&(*slot as usize + 1))?;
                 s.serialize_element(len)?;
             }
+            Item::Run { value, len } => {
+                s.serialize_element(&0)?;
+                s.serialize_element(&0)?;
+                s.serialize_element(len)?;
+                s.serialize_element(value)?;
+            }
         }
         s.end()
     }
 }
-impl<'a, T: 'a + Copy + Deserialize<'a>> Deserialize<'a> for Item<T> {
+/// Always copies raw values, see [`Item::take_from_bytes`] for borrowing them.
+impl<'de, T: 'de + Copy + Deserialize<'de>> Deserialize<'de> for Item<'_, T> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
-        D: serde::Deserializer<'a>,
+        D: serde::Deserializer<'de>,
     {
-        struct Vis<'a, T>(PhantomData<&'a T>);
-        impl<'a, T: Deserialize<'a>> Visitor<'a> for Vis<'a, T> {
-            type Value = Item<T>;
+        struct Vis<'a, 'de, T: Clone>(PhantomData<(&'de T, Item<'a, T>)>);
+        impl<'a, 'de, T: Copy + Deserialize<'de>> Visitor<'de> for Vis<'a, 'de, T> {
+            type Value = Item<'a, T>;
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 write!(formatter, "a valid Item")
             }
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
             where
-                A: serde::de::SeqAccess<'a>,
+                A: serde::de::SeqAccess<'de>,
             {
                 let back: usize = seq
                     .next_element()?
@@ -109,17 +265,34 @@ impl<'a, T: 'a + Copy + Deserialize<'a>> Deserialize<'a> for Item<T> {
                 let len: usize = seq
                     .next_element()?
                     .ok_or_else(|| A::Error::missing_field("len"))?;
-                if let Ok(back) = NonZero::try_from(back) {
+                if let Some(back) = back.checked_sub(REP_SLOTS).and_then(NonZero::new) {
                     Ok(Item::Ref { back, len })
+                } else if back > 0 {
+                    Ok(Item::RepRef {
+                        slot: (back - 1) as u8,
+                        len,
+                    })
+                } else if len == 0 {
+                    let len: usize = seq
+                        .next_element()?
+                        .ok_or_else(|| A::Error::missing_field("run"))?;
+                    if len == 0 {
+                        return Ok(Item::Raw(Cow::Owned(vec![])));
+                    }
+                    let value = seq
+                        .next_element()?
+                        .ok_or_else(|| A::Error::missing_field("value"))?;
+                    Ok(Item::Run { value, len })
                 } else {
-                    let mut raw: SmallVec<[T; 256]> = SmallVec::with_capacity(len);
+                    // len is untrusted, grow with the values actually deserialized.
+                    let mut raw = Vec::with_capacity(len.min(0x1000));
                     for x in 0..len {
                         let value = seq
                             .next_element()?
                             .ok_or_else(|| A::Error::invalid_length(x, &self))?;
                         raw.push(value);
                     }
-                    Ok(Item::Raw(raw))
+                    Ok(Item::Raw(Cow::Owned(raw)))
                 }
             }
         }
@@ -138,8 +311,18 @@ mod tests {
             start.min(end) as usize..end.max(start.saturating_add(1)) as usize
         }
         for index in index.into_iter().map(normalize) {
-            let item = if index.start % 2 == 0 {
-                Item::Raw(vec![index.start; index.len()].into())
+            let item = if index.start % 3 == 0 {
+                Item::Raw(vec![index.start; index.len().saturating_sub(1)].into())
+            } else if index.start % 3 == 1 {
+                Item::Run {
+                    value: index.start,
+                    len: index.len(),
+                }
+            } else if index.start < 10 {
+                Item::RepRef {
+                    slot: (index.start % REP_SLOTS) as u8,
+                    len: index.len(),
+                }
             } else {
                 Item::Ref {
                     back: NonZero::try_from(index.start).unwrap(),
@@ -152,4 +335,115 @@ mod tests {
             assert_eq!(item, decoded);
         }
     }
+    #[quickcheck]
+    fn borrowed(items: Vec<(u8, Vec<u8>)>) {
+        // Runs of 0 are indistinguishable from empty raw items, so all lengths are offset.
+        let items = Vec::from_iter(items.iter().map(|(kind, raw)| match kind % 4 {
+            0 => Item::from(raw.as_slice()),
+            1 => Item::Run {
+                value: *kind,
+                len: raw.len() + 1,
+            },
+            2 => Item::RepRef {
+                slot: kind % REP_SLOTS as u8,
+                len: raw.len() + 1,
+            },
+            _ => Item::Ref {
+                back: NonZero::new(*kind as usize).unwrap(),
+                len: raw.len() + 1,
+            },
+        }));
+        let mut bytes = vec![];
+        for item in &items {
+            bytes.extend(postcard::to_stdvec(item).unwrap());
+        }
+        let mut rest = bytes.as_slice();
+        for item in items {
+            let expected: Item<u8>;
+            (expected, _) = postcard::take_from_bytes(rest).unwrap();
+            let decoded;
+            (decoded, rest) = Item::take_from_bytes(rest).unwrap();
+            assert_eq!(decoded, item);
+            assert_eq!(decoded, expected);
+            assert!(!matches!(decoded, Item::Raw(Cow::Owned(_))));
+        }
+        assert_eq!(rest, &[]);
+        if !bytes.is_empty() {
+            let truncated = &bytes[..bytes.len() - 1];
+            let mut rest = truncated;
+            while !rest.is_empty() {
+                match Item::take_from_bytes(rest) {
+                    Ok((_, next)) => rest = next,
+                    Err(err) => {
+                        assert_eq!(err, postcard::Error::DeserializeUnexpectedEnd);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    #[test]
+    fn huge_raw_len() {
+        // A raw item claiming usize::MAX values, followed by a single one.
+        let bytes = [&[0][..], &[0xff; 9], &[0x01, 7]].concat();
+        assert_eq!(bytes.len(), 12);
+        assert!(postcard::from_bytes::<Item<u8>>(&bytes).is_err());
+        assert!(Item::take_from_bytes(&bytes).is_err());
+    }
+    #[test]
+    fn rep_distances() {
+        let back = |back| NonZero::new(back).unwrap();
+        let mut encoder = RepDistances::default();
+        let mut decoder = RepDistances::default();
+        for (item, expected) in [
+            (
+                Item::Ref {
+                    back: back(9),
+                    len: 4,
+                },
+                Item::Ref {
+                    back: back(9),
+                    len: 4,
+                },
+            ),
+            (
+                Item::Ref {
+                    back: back(2),
+                    len: 5,
+                },
+                Item::RepRef { slot: 2, len: 5 },
+            ),
+            (
+                Item::Ref {
+                    back: back(9),
+                    len: 6,
+                },
+                Item::RepRef { slot: 1, len: 6 },
+            ),
+            (Item::from(b"raw"), Item::from(b"raw")),
+            (
+                Item::Ref {
+                    back: back(7),
+                    len: 7,
+                },
+                Item::Ref {
+                    back: back(7),
+                    len: 7,
+                },
+            ),
+            (
+                Item::Ref {
+                    back: back(2),
+                    len: 8,
+                },
+                Item::RepRef { slot: 2, len: 8 },
+            ),
+        ] {
+            let encoded = encoder.encode(item.clone());
+            assert_eq!(encoded, expected);
+            assert_eq!(decoder.decode(encoded), item);
+            assert_eq!(encoder, decoder);
+        }
+        assert_eq!(encoder.0, [2, 7, 9, 1].map(back));
+    }
 }
@@ -0,0 +1,304 @@
+use std::{num::NonZero, ops::Range};
+
+use super::{Config, ConfigError, Item, encoder::Parser};
+use crate::search_buffer::{BuildHeads, Position, SearchBuffer};
+
+/// Parameters of a [`LongRangeMatcher`], finding long repetitions far beyond `max_buffer_len`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LongRange {
+    /// Farthest distance searched. Decoders need a window this large. Default: 2^27
+    pub window_len: usize,
+    /// Shortest match found, also the length of the hashed prefixes. Default: 64
+    pub min_match: usize,
+    /// Bytes the hash index may take. Default: 2^24
+    ///
+    /// Every entry takes 8 bytes, and the index needs at least two. If the window holds more positions than the index has entries,
+    /// only a content defined sample of positions is indexed, so repetitions shorter than about
+    /// `min_match` times the sampling rate may be missed.
+    pub memory: usize,
+}
+impl Default for LongRange {
+    fn default() -> Self {
+        Self {
+            window_len: 1 << 27,
+            min_match: 64,
+            memory: 1 << 24,
+        }
+    }
+}
+impl LongRange {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.window_len == 0 || self.min_match == 0 || self.memory < 2 * size_of::<u64>() {
+            return Err(ConfigError::InvalidLongRange);
+        }
+        Ok(())
+    }
+}
+
+/// Multiplier of the rolling hash over `min_match` bytes.
+const PRIME: u64 = 0x100000001B3;
+
+/// Sampled index of the positions of a large history, keyed by the hash of the `min_match` bytes
+/// starting there.
+///
+/// Positions are indexed in order, so successive calls to [`LongRangeMatcher::find`] must cover
+/// consecutive ranges of the same data, starting at 0.
+#[derive(Debug, Clone)]
+pub struct LongRangeMatcher {
+    long: LongRange,
+    /// Position + 1 of the last indexed occurrence of each hash, 0 if none.
+    table: Box<[u64]>,
+    table_bits: u32,
+    sample_bits: u32,
+    /// `PRIME^(min_match - 1)`, for removing the oldest byte from the hash.
+    outgoing: u64,
+    /// Next position to index and the hash of the bytes starting there.
+    pos: usize,
+    hash: u64,
+}
+impl LongRangeMatcher {
+    /// Fails if `long` is invalid.
+    pub fn new(long: LongRange) -> Result<Self, ConfigError> {
+        long.validate()?;
+        let table_bits = (long.memory / size_of::<u64>()).ilog2();
+        let sample_bits = (long.window_len >> table_bits)
+            .next_power_of_two()
+            .ilog2()
+            .min(64 - table_bits);
+        Ok(Self {
+            long,
+            table: vec![0; 1 << table_bits].into_boxed_slice(),
+            table_bits,
+            sample_bits,
+            outgoing: (1..long.min_match).fold(1, |pow, _| pow.wrapping_mul(PRIME)),
+            pos: 0,
+            hash: 0,
+        })
+    }
+    pub fn long_range(&self) -> &LongRange {
+        &self.long
+    }
+    /// Finds long matches inside `data[range]`, each as the range it covers and its distance.
+    ///
+    /// Matches are at least `min_match` long, don't overlap and are extended as far as possible
+    /// within `range`, but kept shorter than `max_len`.
+    pub fn find(
+        &mut self,
+        data: &[u8],
+        range: Range<usize>,
+        max_len: usize,
+    ) -> Vec<(Range<usize>, NonZero<usize>)> {
+        self.find_with(data, range, max_len, true)
+    }
+    /// Like [`LongRangeMatcher::find`], keeping matches no longer than their distance without
+    /// `overlap`.
+    fn find_with(
+        &mut self,
+        data: &[u8],
+        range: Range<usize>,
+        max_len: usize,
+        overlap: bool,
+    ) -> Vec<(Range<usize>, NonZero<usize>)> {
+        debug_assert_eq!(range.start, self.pos, "ranges must be consecutive");
+        let k = self.long.min_match;
+        let mut matches = vec![];
+        // End of the last match, nothing before it is looked up again.
+        let mut covered = range.start;
+        while self.pos < range.end && self.pos + k <= data.len() {
+            let pos = self.pos;
+            if pos == 0 {
+                self.hash = data[..k].iter().fold(0, |hash, &byte| {
+                    hash.wrapping_mul(PRIME).wrapping_add(byte as u64)
+                });
+            }
+            let mixed = self.hash.wrapping_mul(0x9E3779B97F4A7C15);
+            if self.sample_bits == 0 || mixed >> (64 - self.sample_bits) == 0 {
+                let slot = (mixed << self.sample_bits >> (64 - self.table_bits)) as usize;
+                let candidate = std::mem::replace(&mut self.table[slot], pos as u64 + 1);
+                if let Some(found) = (candidate as usize).checked_sub(1)
+                    && pos >= covered
+                    && pos + k <= range.end
+                    && pos - found <= self.long.window_len
+                    && data[found..found + k] == data[pos..pos + k]
+                {
+                    let back = pos - found;
+                    let max_len = match overlap {
+                        true => max_len,
+                        false => max_len.min(back + 1),
+                    };
+                    let mut start = pos;
+                    while start > covered
+                        && start > back
+                        && (overlap || pos + k - start < back)
+                        && data[start - 1] == data[start - 1 - back]
+                    {
+                        start -= 1;
+                    }
+                    let mut end = pos + k;
+                    while end < range.end
+                        && end - start + 1 < max_len
+                        && data[end] == data[end - back]
+                    {
+                        end += 1;
+                    }
+                    if end - start < max_len {
+                        matches.push((start..end, NonZero::new(back).unwrap()));
+                        covered = end;
+                    }
+                }
+            }
+            if let Some(&incoming) = data.get(pos + k) {
+                self.hash = self
+                    .hash
+                    .wrapping_sub((data[pos] as u64).wrapping_mul(self.outgoing))
+                    .wrapping_mul(PRIME)
+                    .wrapping_add(incoming as u64);
+            }
+            self.pos += 1;
+        }
+        // Positions too close to the end to be hashed.
+        self.pos = self.pos.max(range.end);
+        matches
+    }
+    /// Items covering `data[range]`, taking the long matches [`LongRangeMatcher::find`] finds and
+    /// matching the rest with `search_buffer`.
+    ///
+    /// `search_buffer` must hold the values before `range`, as after the previous call.
+    ///
+    /// Panics if `config` is invalid for a key width of `N`.
+    pub fn to_items<'a, const N: usize, S: BuildHeads<[u8; N], P>, P: Position>(
+        &mut self,
+        search_buffer: &mut SearchBuffer<u8, N, S, P>,
+        data: &[u8],
+        range: Range<usize>,
+        config: Config<'a, u8>,
+    ) -> Vec<Item<'a, u8>> {
+        let matches = self.find_with(
+            data,
+            range.clone(),
+            config.match_lengths.end,
+            !config.forbid_overlap,
+        );
+        let mut parser = Parser::new(search_buffer, config).unwrap();
+        let mut items = vec![];
+        let mut pos = range.start;
+        for (target, back) in matches {
+            let mut gap = data[pos..target.start].iter().copied();
+            items.extend(std::iter::from_fn(|| parser.next(search_buffer, &mut gap)));
+            items.push(parser.external_match(search_buffer, back, &data[target.clone()]));
+            pos = target.end;
+        }
+        let mut gap = data[pos..range.end].iter().copied();
+        items.extend(std::iter::from_fn(|| parser.next(search_buffer, &mut gap)));
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Slide, lz::RepDistances};
+
+    #[test]
+    fn long_range() {
+        let mut x = 1u32;
+        let mut noise = |len| {
+            Vec::from_iter((0..len).map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            }))
+        };
+        // A region repeated far outside of the regular window.
+        let region = noise(0x3000);
+        let data = [
+            noise(0x100),
+            region.clone(),
+            noise(0x20000),
+            region,
+            noise(0x100),
+        ]
+        .concat();
+        let config = Config {
+            max_buffer_len: 0x1000,
+            match_lengths: 4..usize::MAX,
+            ..Config::default()
+        };
+        let long = LongRange {
+            window_len: 0x40000,
+            min_match: 32,
+            memory: 0x1000,
+        };
+        let mut matcher = LongRangeMatcher::new(long).unwrap();
+        let mut search_buffer = SearchBuffer::<u8, 4>::new();
+        let mut items = vec![];
+        for start in (0..data.len()).step_by(0x8000) {
+            let range = start..data.len().min(start + 0x8000);
+            items.extend(matcher.to_items(&mut search_buffer, &data, range, config.clone()));
+        }
+        let long_refs = Vec::from_iter(items.iter().filter(|item| item.back() > 0x1000));
+        assert!(!long_refs.is_empty());
+        // Sampling may miss the first few bytes, but covers the bulk of the region.
+        let covered: usize = long_refs.iter().map(|item| item.len()).sum();
+        assert!(covered > 0x2c00, "{covered}");
+        assert!(long_refs.iter().all(|item| item.back() == 0x23000));
+
+        let decode = Config {
+            max_buffer_len: long.window_len,
+            ..config.clone()
+        };
+        assert_eq!(
+            Vec::from_iter(Slide::new().from_items(items, decode.clone())),
+            data
+        );
+
+        // A period beyond the regular window repeated for long, found as one long match unless
+        // overlap is forbidden.
+        let data = [noise(0x40), noise(0x2000).repeat(0x10)].concat();
+        for forbid_overlap in [false, true] {
+            let config = Config {
+                forbid_overlap,
+                ..config.clone()
+            };
+            let mut matcher = LongRangeMatcher::new(long).unwrap();
+            let mut search_buffer = SearchBuffer::<u8, 4>::new();
+            let items = matcher.to_items(&mut search_buffer, &data, 0..data.len(), config.clone());
+            let mut reps = RepDistances::default();
+            let overlapping = items
+                .iter()
+                .filter(|item| {
+                    let back = reps.update(item);
+                    back > 0 && item.len() > back
+                })
+                .count();
+            assert_eq!(overlapping > 0, !forbid_overlap);
+            // Either way, all repetitions are matched.
+            let matched: usize = items
+                .iter()
+                .filter(|item| item.as_raw().is_none())
+                .map(Item::len)
+                .sum();
+            assert!(matched >= 0x1e000, "{matched}");
+            assert_eq!(
+                Vec::from_iter(Slide::new().from_items(items, decode.clone())),
+                data
+            );
+        }
+        for memory in [4, 8] {
+            assert_eq!(
+                LongRangeMatcher::new(LongRange { memory, ..long }).err(),
+                Some(ConfigError::InvalidLongRange)
+            );
+        }
+        // The smallest index still works, a single table bit.
+        let tiny = LongRange {
+            window_len: 1,
+            memory: 16,
+            ..long
+        };
+        assert_eq!(tiny.validate(), Ok(()));
+        let frame = crate::frame::compress_long_range(&data, config.clone(), tiny);
+        assert_eq!(crate::frame::decompress(&frame, None).unwrap(), data);
+    }
+}
@@ -1,123 +1,397 @@
+mod cancel;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod chunked;
+mod config;
+mod cost;
+mod coverage;
+mod described;
+mod encoder;
+mod estimate;
 mod item;
-use crate::{Slide, search_buffer::SearchBuffer};
-pub use item::*;
-use smallvec::SmallVec;
-use std::{
-    fmt::Debug,
-    hash::{BuildHasher, Hash},
-    iter,
-    ops::Range,
-    usize,
+mod long_range;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod multi;
+mod optimal;
+mod optimize;
+mod progress;
+mod stats;
+mod transcode;
+use crate::{
+    Error, Slide,
+    search_buffer::{BuildHeads, Position, SearchBuffer},
+    util::SliceExt,
 };
-#[derive(Debug)]
-pub struct Config {
-    /// Maximum size of the search window. Default: 2^24
-    pub max_buffer_len: usize,
-    /// Range of accepted match lengths. Default: 1..usize::MAX
-    ///
-    /// Raising the minimum can exponentially speed up scanning over the search window,
-    /// while also exponentially increasing potential keys in the cache.
-    ///
-    /// Lowering the maximum limits the size of the lookahead window.
-    pub match_lengths: Range<usize>,
+pub use cancel::*;
+#[cfg(feature = "cbor")]
+pub use cbor::*;
+pub use chunked::*;
+pub use config::*;
+pub use cost::*;
+pub use coverage::*;
+pub use described::*;
+pub use encoder::*;
+pub use estimate::*;
+pub use item::*;
+pub use long_range::*;
+#[cfg(feature = "msgpack")]
+pub use msgpack::*;
+pub use multi::*;
+pub use optimize::*;
+pub use progress::*;
+pub use stats::*;
+use std::{borrow::Cow, iter, ops::Range};
+pub use transcode::*;
+/// Shortest run of a single value encoded as [`Item::Run`] instead of searching for matches.
+///
+/// Never shorter than `match_lengths.start`.
+pub const MIN_RUN_LEN: usize = 0x20;
+
+/// Length of the run of equal values `data` starts with, if it is at least `min_len` long.
+fn run_len<T: Eq>(data: &[T], min_len: usize) -> Option<usize> {
+    let (head, rest) = data.split_first()?;
+    let is_run = |rest: &[T]| rest.iter().take_while(|value| *value == head).count() + 1;
+    (is_run(rest.get_clamped(..min_len - 1)) >= min_len).then(|| is_run(rest))
 }
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            max_buffer_len: 0x1000000,
-            match_lengths: 1..usize::MAX,
+
+/// Length of the match of `data` at `back` before `end`, overlapping into `data` itself if
+/// `search_buffer` allows it.
+fn match_len_at<T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position>(
+    search_buffer: &SearchBuffer<T, N, S, P>,
+    data: &[T],
+    end: usize,
+    back: usize,
+) -> usize {
+    let Some(start) = end
+        .checked_sub(back)
+        .filter(|start| *start >= search_buffer.start())
+    else {
+        return 0;
+    };
+    let value_at = |index: usize| {
+        if index < end {
+            search_buffer[index]
+        } else {
+            data[index - end]
         }
-    }
+    };
+    let max_len = match search_buffer.overlap() {
+        true => usize::MAX,
+        false => back,
+    };
+    data.iter()
+        .zip(start..)
+        .take(max_len)
+        .take_while(|&(value, index)| *value == value_at(index))
+        .count()
 }
-impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S> {
-    pub fn to_items(
+
+impl<T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position> SearchBuffer<T, N, S, P> {
+    /// Encodes `iter` into items.
+    ///
+    /// Panics if `config` is invalid for a key width of `N`, see [`Encoder::new`] for a fallible
+    /// alternative.
+    pub fn to_items<'c>(
         &mut self,
         iter: impl IntoIterator<Item = T>,
-        config: Config,
-    ) -> impl Iterator<Item = Item<T>> {
-        assert!(N <= config.match_lengths.start);
+        config: Config<'c, T>,
+    ) -> impl Iterator<Item = Item<'c, T>> {
+        self.items_with(iter, config, None, None, None)
+    }
+    /// Like [`to_items`](Self::to_items), but raw items borrow their values from `data`.
+    pub fn to_items_from_slice<'c>(
+        &mut self,
+        data: &'c [T],
+        config: Config<'c, T>,
+    ) -> impl Iterator<Item = Item<'c, T>> {
+        let mut pos = 0;
+        self.to_items(data.iter().copied(), config)
+            .map(move |item| {
+                let start = pos;
+                pos += item.len();
+                match item {
+                    Item::Raw(_) => Item::Raw(Cow::Borrowed(&data[start..pos])),
+                    item => item,
+                }
+            })
+    }
+    /// Like [`to_items`](Self::to_items), recording every emitted item into `stats`.
+    pub fn to_items_with_stats<'a, 'c>(
+        &'a mut self,
+        iter: impl IntoIterator<Item = T>,
+        config: Config<'c, T>,
+        stats: &'a mut Stats,
+    ) -> impl Iterator<Item = Item<'c, T>> {
+        self.items_with(iter, config, Some(stats), None, None)
+    }
+    /// Like [`to_items`](Self::to_items), reporting the values covered and items emitted to
+    /// `progress`.
+    pub fn to_items_with_progress<'a, 'c>(
+        &'a mut self,
+        iter: impl IntoIterator<Item = T>,
+        config: Config<'c, T>,
+        progress: &'a mut Progress<'_>,
+    ) -> impl Iterator<Item = Item<'c, T>> {
+        self.items_with(iter, config, None, Some(progress), None)
+    }
+    /// Like [`to_items`](Self::to_items), but ends early once `cancel` is cancelled.
+    ///
+    /// The items emitted until then decode to a prefix of `iter`.
+    pub fn to_items_until<'a, 'c>(
+        &'a mut self,
+        iter: impl IntoIterator<Item = T>,
+        config: Config<'c, T>,
+        cancel: &'a Cancel,
+    ) -> impl Iterator<Item = Item<'c, T>> {
+        self.items_with(iter, config, None, None, Some(cancel))
+    }
+    fn items_with<'a, 'c>(
+        &'a mut self,
+        iter: impl IntoIterator<Item = T>,
+        config: Config<'c, T>,
+        mut stats: Option<&'a mut Stats>,
+        mut progress: Option<&'a mut Progress<'_>>,
+        cancel: Option<&'a Cancel>,
+    ) -> impl Iterator<Item = Item<'c, T>> {
+        let mut parser =
+            Parser::new(self, config).unwrap_or_else(|err| panic!("Invalid Config: {err}."));
         let mut iter = iter.into_iter();
-        let mut match_window = Slide::new();
         let search_buffer = self;
-        let mut raw_len: usize = 0;
-        let mut back_ref: Option<(Range<usize>, usize)> = None;
+        let (searches, chain_steps) = (search_buffer.searches(), search_buffer.chain_steps());
+        let chain_depths = search_buffer.chain_depth_histogram();
         iter::from_fn(move || {
-            loop {
-                // Return items already found in previous call/iteration.
-                if raw_len > 0 {
-                    let item = Item::Raw(Vec::from_iter(match_window.drain(0..raw_len)).into());
-                    raw_len = 0;
-                    return Some(item);
-                } else if let Some((index, end)) = back_ref.take() {
-                    match_window.drain(0..index.len()).for_each(drop);
-                    return Some(Item::from((index, end)));
-                }
-                match_window.extend(
-                    (&mut iter).take(
-                        config
-                            .match_lengths
-                            .end
-                            .saturating_sub(match_window.len() + 1),
-                    ),
-                );
-                if match_window.is_empty() {
-                    return None;
-                }
-                // Keep pushing/sliding in values popped of data until valid match is found.
-                while let data @ [head, ..] = &match_window[raw_len..] {
-                    debug_assert!(data.len() < config.match_lengths.end);
-                    if let Some(range) = search_buffer.find_longest_match(data) {
-                        back_ref = Some((range.clone(), search_buffer.end()));
-                        search_buffer
-                            .extend_slide(
-                                data[..range.len()].into_iter().copied(),
-                                config.max_buffer_len,
-                            )
-                            .for_each(drop);
-                        break;
-                    } else {
-                        search_buffer.push_step(*head, config.max_buffer_len);
-                        iter.next().map(|val| match_window.push(val));
-                        raw_len += 1;
-                    }
+            if cancel.is_some_and(Cancel::is_cancelled) {
+                return None;
+            }
+            let item = parser.next(search_buffer, &mut iter)?;
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.record(&item);
+                stats.searches = search_buffer.searches() - searches;
+                stats.chain_steps = search_buffer.chain_steps() - chain_steps;
+                stats.heads = search_buffer.head_count();
+                let histogram = search_buffer.chain_depth_histogram();
+                for ((stat, now), before) in (stats.chain_depth_histogram.iter_mut())
+                    .zip(histogram)
+                    .zip(chain_depths)
+                {
+                    *stat = now - before;
                 }
             }
+            if let Some(progress) = progress.as_deref_mut() {
+                progress.advance(item.len(), 0, 1);
+            }
+            Some(item)
         })
     }
 }
-impl<T: Copy + Eq + Hash> Slide<T> {
-    pub fn from_items(
+impl<T: Copy + Eq> Slide<T> {
+    /// Decodes `items`, yielding the values they stand for.
+    ///
+    /// Values are yielded straight from the window, so decoding allocates nothing per item once
+    /// the window reached `config.max_buffer_len`. Raw items borrowing their values, like those
+    /// of [`Item::take_from_bytes`], are copied straight into the window.
+    pub fn from_items<'i>(
         &mut self,
-        items: impl IntoIterator<Item = Item<T>>,
-        config: Config,
-    ) -> impl IntoIterator<Item = T> {
-        let buffer = self;
-        items.into_iter().flat_map(move |item| {
-            let len = item.len();
-            match item {
-                Item::Raw(raw) => {
-                    buffer.extend(raw.into_iter());
-                }
-                Item::Ref { back, len } => {
-                    debug_assert!(usize::from(back) <= buffer.len());
-                    debug_assert!(len >= config.match_lengths.start);
-                    debug_assert!(
-                        len < config.match_lengths.end,
-                        "len {len} >= max_len {max_len}",
-                        max_len = config.match_lengths.end
-                    );
-                    let base = buffer.len() - usize::from(back);
-                    buffer.extend_from_within(base..base + len);
-                }
+        items: impl IntoIterator<Item = Item<'i, T>>,
+        config: Config<T>,
+    ) -> impl IntoIterator<Item = T>
+    where
+        T: 'i,
+    {
+        self.decode_with(items, config, None, None)
+    }
+    /// Like [`from_items`](Self::from_items), reporting the items decoded and values they stand
+    /// for to `progress`.
+    pub fn from_items_with_progress<'a, 'i>(
+        &'a mut self,
+        items: impl IntoIterator<Item = Item<'i, T>>,
+        config: Config<T>,
+        progress: &'a mut Progress<'_>,
+    ) -> impl IntoIterator<Item = T>
+    where
+        T: 'i,
+    {
+        self.decode_with(items, config, Some(progress), None)
+    }
+    /// Like [`from_items`](Self::from_items), but ends early once `cancel` is cancelled.
+    ///
+    /// Checked before each item, so the values yielded until then are those of whole items.
+    pub fn from_items_until<'a, 'i>(
+        &'a mut self,
+        items: impl IntoIterator<Item = Item<'i, T>>,
+        config: Config<T>,
+        cancel: &'a Cancel,
+    ) -> impl IntoIterator<Item = T>
+    where
+        T: 'i,
+    {
+        self.decode_with(items, config, None, Some(cancel))
+    }
+    /// Like [`from_items`](Self::from_items), but checks `config` and every item instead of
+    /// trusting them, ending with the first error.
+    pub fn try_from_items<'i>(
+        &mut self,
+        items: impl IntoIterator<Item = Item<'i, T>>,
+        config: Config<T>,
+    ) -> impl Iterator<Item = Result<T, Error>>
+    where
+        T: 'i,
+    {
+        let invalid = config.validate().err().map(Error::from);
+        let mut values = self.decode_with(items, config, None, None);
+        values.checked = true;
+        values.error = invalid;
+        let mut done = false;
+        iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let value = values.next();
+            done = value.is_none();
+            match value {
+                Some(value) => Some(Ok(value)),
+                None => values.error.take().map(Err),
+            }
+        })
+    }
+    fn decode_with<'a, 'p, 'i, I: IntoIterator<Item = Item<'i, T>>>(
+        &'a mut self,
+        items: I,
+        config: Config<T>,
+        progress: Option<&'a mut Progress<'p>>,
+        cancel: Option<&'a Cancel>,
+    ) -> FromItems<'a, 'p, T, I::IntoIter>
+    where
+        T: 'i,
+    {
+        self.extend(config.dictionary_window().iter().copied());
+        self.pop_n(self.len().saturating_sub(config.max_buffer_len))
+            .for_each(drop);
+        FromItems {
+            window: self,
+            items: items.into_iter(),
+            reps: RepDistances::default(),
+            max_buffer_len: config.max_buffer_len,
+            match_lengths: config.match_lengths,
+            remaining: 0,
+            run: None,
+            progress,
+            cancel,
+            checked: false,
+            error: None,
+        }
+    }
+}
+
+/// Iterator returned by [`Slide::from_items`].
+struct FromItems<'s, 'p, T, I> {
+    window: &'s mut Slide<T>,
+    items: I,
+    reps: RepDistances,
+    max_buffer_len: usize,
+    match_lengths: Range<usize>,
+    /// Values of the current item not yet yielded, from the end of the window.
+    remaining: usize,
+    /// Value of the current item if it's a run, which may not fit into the window.
+    run: Option<T>,
+    progress: Option<&'s mut Progress<'p>>,
+    cancel: Option<&'s Cancel>,
+    /// Whether items are checked, stopping at the first invalid one.
+    checked: bool,
+    error: Option<Error>,
+}
+impl<'i, T: Copy + 'i, I: Iterator<Item = Item<'i, T>>> Iterator for FromItems<'_, '_, T, I> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let window = &mut *self.window;
+        while self.remaining == 0 {
+            // Trimmed only once all values of the previous item were yielded.
+            window
+                .pop_n(window.len().saturating_sub(self.max_buffer_len))
+                .for_each(drop);
+            if self.cancel.is_some_and(Cancel::is_cancelled) || self.error.is_some() {
+                return None;
+            }
+            let item = self.items.next()?;
+            if self.checked
+                && let Err(err) = check_item(&item, window.len(), &self.reps, &self.match_lengths)
+            {
+                self.error = Some(err);
+                return None;
+            }
+            let item = self.reps.decode(item);
+            self.remaining = item.len();
+            if let Some(progress) = self.progress.as_deref_mut() {
+                progress.advance(0, item.len(), 1);
+            }
+            self.run = match item {
+                Item::Run { value, .. } => Some(value),
+                _ => None,
             };
-            let ret = SmallVec::<[T; 0x100]>::from(&buffer[buffer.len() - len..]);
-            let over = buffer.len().saturating_sub(config.max_buffer_len);
-            if over > 0 {
-                buffer.drain(0..over).for_each(drop);
+            if let Item::Ref { len, .. } = item {
+                debug_assert!(len >= self.match_lengths.start);
+                debug_assert!(
+                    len < self.match_lengths.end,
+                    "len {len} >= max_len {max_len}",
+                    max_len = self.match_lengths.end
+                );
             }
-            ret
+            extend_window(window, &item, self.max_buffer_len);
+        }
+        self.remaining -= 1;
+        Some(match self.run {
+            Some(value) => value,
+            None => window[window.len() - 1 - self.remaining],
         })
     }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, None)
+    }
+}
+/// Checks that `item` can be decoded from a window of `available` values.
+fn check_item<T: Clone>(
+    item: &Item<T>,
+    available: usize,
+    reps: &RepDistances,
+    match_lengths: &Range<usize>,
+) -> Result<(), Error> {
+    let (back, len) = match *item {
+        Item::Ref { back, len } => (back.get(), len),
+        Item::RepRef { slot, len } => {
+            let back = reps.0.get(slot as usize).ok_or(Error::InvalidSlot(slot))?;
+            (back.get(), len)
+        }
+        _ => return Ok(()),
+    };
+    if back > available {
+        return Err(Error::InvalidDistance { back, available });
+    }
+    if !match_lengths.contains(&len) {
+        return Err(Error::InvalidLength {
+            len,
+            start: match_lengths.start,
+            end: match_lengths.end,
+        });
+    }
+    Ok(())
+}
+
+/// Appends the values of `item` to `window`, without trimming it.
+///
+/// Only the tail of a run stays reachable through the window, so at most `max_buffer_len` of
+/// its values are appended. Panics on [`Item::RepRef`], see [`RepDistances::decode`].
+fn extend_window<T: Copy>(window: &mut Slide<T>, item: &Item<T>, max_buffer_len: usize) {
+    match *item {
+        Item::Raw(ref raw) => window.extend(raw.iter().copied()),
+        Item::Run { value, len } => window.extend(iter::repeat_n(value, len.min(max_buffer_len))),
+        Item::RepRef { .. } => unreachable!("Resolved by RepDistances::decode."),
+        Item::Ref { back, len } => {
+            debug_assert!(usize::from(back) <= window.len());
+            let base = window.len() - usize::from(back);
+            window.extend_from_within(base..base + len);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -130,10 +404,11 @@ mod tests {
         let data = b"vwabcdeabcabcabcxvw";
         let items = SearchBuffer::<_, 2>::new()
             .to_items(
-                data.into_iter().copied(),
+                data.iter().copied(),
                 Config {
                     max_buffer_len: 8,
                     match_lengths: 2..usize::MAX,
+                    ..Config::default()
                 },
             )
             .take(5)
@@ -142,18 +417,156 @@ mod tests {
             items,
             vec![
                 Item::from(b"vwabcde"),
-                Item::from((2..5, 7)),
-                Item::from((7..13, 10)),
+                Item::try_from((2..5, 7)).unwrap(),
+                Item::RepRef { slot: 3, len: 6 },
                 Item::from(b"xvw")
             ]
         );
     }
     #[test]
+    fn to_items_from_slice() {
+        let data = b"vwabcdeabcabcabcxvw";
+        let config = Config {
+            max_buffer_len: 8,
+            match_lengths: 2..usize::MAX,
+            ..Config::default()
+        };
+        let items =
+            Vec::from_iter(SearchBuffer::<_, 2>::new().to_items_from_slice(data, config.clone()));
+        let expected = Vec::from_iter(
+            SearchBuffer::<_, 2>::new().to_items(data.iter().copied(), config.clone()),
+        );
+        assert_eq!(items, expected);
+        assert!(
+            items
+                .iter()
+                .all(|item| !matches!(item, Item::Raw(Cow::Owned(_))))
+        );
+        let decoded = Vec::from_iter(Slide::new().from_items(items, config));
+        assert_eq!(decoded, data);
+    }
+    #[test]
+    fn cancel() {
+        let data = Vec::from_iter((0..0x3000u32).map(|x| (x / 3 % 37) as u8 ^ (x >> 10) as u8));
+        let config = Config {
+            max_buffer_len: 0x100,
+            match_lengths: 2..0x200,
+            ..Config::default()
+        };
+        let cancel = Cancel::new();
+        let mut search_buffer = SearchBuffer::<_, 2>::new();
+        let items = Vec::from_iter(
+            search_buffer
+                .to_items_until(data.iter().copied(), config.clone(), &cancel)
+                .enumerate()
+                .inspect(|&(i, _)| {
+                    if i == 10 {
+                        cancel.cancel()
+                    }
+                })
+                .map(|(_, item)| item),
+        );
+        assert_eq!(items.len(), 11);
+        let decoded = Vec::from_iter(Slide::new().from_items(items.clone(), config.clone()));
+        assert_eq!(decoded, data[..decoded.len()]);
+
+        let cancel = Cancel::new();
+        let mut window = Slide::new();
+        let mut values = window
+            .from_items_until(items.clone(), config, &cancel)
+            .into_iter();
+        let first = Vec::from_iter((&mut values).take(items[0].len()));
+        cancel.cancel();
+        assert_eq!(values.next(), None);
+        assert_eq!(first, data[..first.len()]);
+    }
+    #[test]
+    fn min_match_above_width() {
+        let data = Vec::from_iter((0..0x2000u32).map(|x| (x % 7 == 0) as u8 * (x % 13) as u8));
+        for strategy in [Strategy::Greedy, Strategy::Lazy(2), Strategy::Optimal] {
+            let config = Config {
+                max_buffer_len: 0x1000,
+                match_lengths: 16..0x100,
+                strategy,
+                ..Config::default()
+            };
+            let items = Vec::from_iter(
+                SearchBuffer::<_, 4>::new().to_items(data.iter().copied(), config.clone()),
+            );
+            assert!(items.iter().any(|item| item.back() > 0));
+            let decoded: Result<Vec<_>, _> = Slide::new().try_from_items(items, config).collect();
+            assert_eq!(decoded.unwrap(), data);
+        }
+    }
+    #[test]
+    fn try_from_items() {
+        let config = Config {
+            max_buffer_len: 8,
+            match_lengths: 2..6,
+            ..Config::default()
+        };
+        let decode = |items: Vec<Item<u8>>, config: Config<u8>| {
+            let mut values = vec![];
+            let result = Slide::new()
+                .try_from_items(items, config)
+                .try_for_each(|value| value.map(|value| values.push(value)));
+            (values, result)
+        };
+        let items = vec![Item::from(b"abcde"), Item::try_from((1..4, 5)).unwrap()];
+        let (values, result) = decode(items.clone(), config.clone());
+        assert!(result.is_ok());
+        assert_eq!(values, b"abcdebcd");
+
+        let (values, result) = decode(
+            vec![Item::from(b"abc"), Item::try_from((0..2, 4)).unwrap()],
+            config.clone(),
+        );
+        assert_eq!(values, b"abc");
+        assert!(matches!(
+            result,
+            Err(Error::InvalidDistance {
+                back: 4,
+                available: 3
+            })
+        ));
+        let (_, result) = decode(
+            vec![Item::from(b"abcdefgh"), Item::try_from((0..6, 8)).unwrap()],
+            config.clone(),
+        );
+        assert!(matches!(result, Err(Error::InvalidLength { len: 6, .. })));
+        let (_, result) = decode(
+            vec![Item::from(b"abcd"), Item::RepRef { slot: 9, len: 2 }],
+            config.clone(),
+        );
+        assert!(matches!(result, Err(Error::InvalidSlot(9))));
+        let (values, result) = decode(
+            items,
+            Config {
+                max_buffer_len: 0,
+                ..config.clone()
+            },
+        );
+        assert!(values.is_empty());
+        assert!(matches!(
+            result,
+            Err(Error::Config(ConfigError::EmptyWindow))
+        ));
+
+        assert!(matches!(
+            Item::<u8>::try_from((3..5, 3)),
+            Err(Error::ZeroDistance)
+        ));
+        assert!(matches!(
+            Encoder::<u8, 3>::new(config),
+            Err(Error::Config(ConfigError::MinMatchBelowWidth { .. }))
+        ));
+    }
+    #[test]
     fn from_items() {
         let items = [
             Item::from(b"vwabcde"),
-            Item::from((2..5, 7)),
-            Item::from((7..13, 10)),
+            Item::try_from((2..5, 7)).unwrap(),
+            Item::try_from((7..13, 10)).unwrap(),
             Item::from(b"xvw"),
         ];
         let data = Slide::new()
@@ -162,6 +575,7 @@ mod tests {
                 Config {
                     max_buffer_len: 8,
                     match_lengths: 0..usize::MAX,
+                    ..Config::default()
                 },
             )
             .into_iter()
@@ -169,14 +583,287 @@ mod tests {
         assert_eq!(data.iter().as_slice(), b"vwabcdeabcabcabcxvw".as_slice());
     }
     #[test]
+    fn from_items_window() {
+        let data = Vec::from_iter((0..0x3000u32).map(|x| (x / 3 % 37) as u8 ^ (x >> 10) as u8));
+        let config = Config {
+            max_buffer_len: 0x100,
+            match_lengths: 2..0x200,
+            ..Config::default()
+        };
+        let mut items = Vec::from_iter(
+            SearchBuffer::<_, 2>::new().to_items(data.iter().copied(), config.clone()),
+        );
+        items.push(Item::Run {
+            value: 7,
+            len: 0x300,
+        });
+        let mut window = Slide::new();
+        let decoded = Vec::from_iter(window.from_items(items, config.clone()));
+        assert_eq!(decoded[..data.len()], data);
+        assert_eq!(decoded[data.len()..], [7; 0x300]);
+        // The window is trimmed once the last item was yielded.
+        assert_eq!(&*window, &[7; 0x100]);
+    }
+    #[test]
+    fn dictionary() {
+        let config = Config {
+            max_buffer_len: 9,
+            match_lengths: 2..usize::MAX,
+            dictionary: Some(b"__abcdefgh".as_slice()),
+            ..Config::default()
+        };
+        let items = SearchBuffer::<_, 2>::new()
+            .to_items(b"xabcdx".iter().copied(), config.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                Item::from(b"x"),
+                Item::try_from((1..5, 10)).unwrap(),
+                Item::from(b"x")
+            ]
+        );
+        let data = Slide::new()
+            .from_items(items, config)
+            .into_iter()
+            .collect::<Box<[_]>>();
+        assert_eq!(&*data, b"xabcdx");
+    }
+    #[test]
+    fn lazy() {
+        let data = b"abcXbcdefYabcdefZ";
+        let encode = |strategy| {
+            let config = Config {
+                max_buffer_len: 0x100,
+                match_lengths: 3..usize::MAX,
+                strategy,
+                ..Config::default()
+            };
+            let items = SearchBuffer::<_, 3>::new()
+                .to_items(data.iter().copied(), config.clone())
+                .collect::<Vec<_>>();
+            let decoded = Slide::new()
+                .from_items(items.clone(), config)
+                .into_iter()
+                .collect::<Box<[_]>>();
+            assert_eq!(&*decoded, data);
+            items
+        };
+        assert_eq!(
+            encode(Strategy::Greedy),
+            vec![
+                Item::from(b"abcXbcdefY"),
+                Item::try_from((0..3, 10)).unwrap(),
+                Item::try_from((6..9, 13)).unwrap(),
+                Item::from(b"Z"),
+            ]
+        );
+        assert_eq!(encode(Strategy::Lazy(0)), encode(Strategy::Greedy));
+        assert_eq!(
+            encode(Strategy::Lazy(1)),
+            vec![
+                Item::from(b"abcXbcdefYa"),
+                Item::try_from((4..9, 11)).unwrap(),
+                Item::from(b"Z")
+            ]
+        );
+    }
+    #[test]
+    fn search_limits() {
+        // Few distinct values make for long hash chains.
+        let mut x = 1u32;
+        let data = Vec::from_iter((0..0x4000).map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            b"abcd"[x as usize % 4]
+        }));
+        let encode = |max_chain, nice_len| {
+            let config = Config {
+                max_chain,
+                nice_len,
+                ..Config::level(4)
+            };
+            let mut stats = Stats::new();
+            let items = Vec::from_iter(SearchBuffer::<_, 4>::new().to_items_with_stats(
+                data.iter().copied(),
+                config.clone(),
+                &mut stats,
+            ));
+            let decoded = Vec::from_iter(Slide::new().from_items(items, config));
+            assert_eq!(decoded, data);
+            stats
+        };
+        let unlimited = encode(None, None);
+        let chained = encode(Some(4), None);
+        assert!(chained.chain_steps <= 4 * chained.searches);
+        assert!(chained.chain_steps < unlimited.chain_steps);
+        let nice = encode(None, Some(8));
+        assert!(nice.chain_steps < unlimited.chain_steps);
+        assert!(nice.length_histogram[bucket(8)..].iter().sum::<usize>() > 0);
+    }
+    #[test]
+    fn max_raw_len() {
+        let mut x = 7u32;
+        let noise = Vec::from_iter((0..0x800).map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        }));
+        let data = [&noise[..], &noise[..0x100], &noise[0x300..]].concat();
+        for strategy in [
+            Strategy::Greedy,
+            Strategy::Lazy(2),
+            Strategy::BestOf(4),
+            Strategy::Optimal,
+        ] {
+            let config = Config {
+                match_lengths: 3..0x100,
+                strategy,
+                lookahead: 0x40,
+                max_raw_len: Some(16),
+                ..Config::default()
+            };
+            let pulled = std::cell::Cell::new(0);
+            let values = data.iter().inspect(|_| pulled.set(pulled.get() + 1));
+            let mut search_buffer = SearchBuffer::<_, 3>::new();
+            let mut items = search_buffer.to_items(values.copied(), config.clone());
+            // Incompressible data doesn't hold back the first item.
+            let first = items.next().unwrap();
+            assert_eq!(first, Item::from(&data[..16]));
+            assert!(pulled.get() <= 0x40 + 16);
+            let items = Vec::from_iter(iter::once(first).chain(items));
+            assert!(items.iter().all(|item| match item {
+                Item::Raw(raw) => raw.len() <= 16,
+                _ => true,
+            }));
+            assert!(items.iter().any(|item| item.len() > 16));
+            assert_eq!(Vec::from_iter(Slide::new().from_items(items, config)), data);
+        }
+    }
+    #[test]
+    fn optimal() {
+        let data = Vec::from_iter(
+            (0..0x3000u32)
+                .flat_map(|x| format!("{} {} {};", x % 13, x * x % 97, x / 7 % 31).into_bytes()),
+        );
+        let encode = |strategy| {
+            let config = Config {
+                max_buffer_len: 0x800,
+                match_lengths: 2..0x2000,
+                strategy,
+                ..Config::default()
+            };
+            let items = SearchBuffer::<_, 2>::new()
+                .to_items(data.iter().copied(), config.clone())
+                .collect::<Vec<_>>();
+            let decoded = Slide::new()
+                .from_items(items.clone(), config)
+                .into_iter()
+                .collect::<Vec<_>>();
+            assert_eq!(decoded, data);
+            postcard::to_stdvec(&items).unwrap().len()
+        };
+        let greedy = encode(Strategy::Greedy);
+        let lazy = encode(Strategy::Lazy(2));
+        let optimal = encode(Strategy::Optimal);
+        assert!(
+            optimal < greedy && optimal < lazy,
+            "optimal {optimal}, lazy {lazy}, greedy {greedy}"
+        );
+        let best_of = encode(Strategy::BestOf(8));
+        assert!(
+            optimal < best_of && best_of < greedy,
+            "optimal {optimal}, best of 8 {best_of}, greedy {greedy}"
+        );
+    }
+    #[test]
+    fn best_of() {
+        // A close match and one a value longer, but too far back to pay for its distance.
+        let filler = Vec::from_iter((0..0x100u32).map(|x| (x * 7 % 251) as u8 | 0x80));
+        let data = [b"abcdefgh".as_slice(), &filler, b"abcdefg!", b"abcdefgh"].concat();
+        let encode = |strategy| {
+            let config = Config {
+                max_buffer_len: 0x1000,
+                match_lengths: 4..usize::MAX,
+                strategy,
+                ..Config::default()
+            };
+            let items = Vec::from_iter(
+                SearchBuffer::<_, 4>::new().to_items(data.iter().copied(), config.clone()),
+            );
+            let decoded = Vec::from_iter(Slide::new().from_items(items.clone(), config));
+            assert_eq!(decoded, data);
+            items
+        };
+        let far = Item::try_from((0..8, data.len() - 8)).unwrap();
+        assert!(encode(Strategy::Greedy).contains(&far));
+        let close = Item::try_from((data.len() - 16..data.len() - 9, data.len() - 8)).unwrap();
+        let items = encode(Strategy::BestOf(2));
+        assert!(items.contains(&close), "{items:?}");
+        // Only the first match found is compared.
+        assert_eq!(encode(Strategy::BestOf(1)), items);
+    }
+    #[test]
+    fn run() {
+        let data = [b"ab".as_slice(), &[b'x'; 1 << 16], b"abx"].concat();
+        let config = Config {
+            max_buffer_len: 0x100,
+            match_lengths: 2..usize::MAX,
+            ..Config::default()
+        };
+        let items = SearchBuffer::<_, 2>::new()
+            .to_items(data.iter().copied(), config.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                Item::from(b"ab"),
+                Item::Run {
+                    value: b'x',
+                    len: 1 << 16
+                },
+                Item::from(b"abx")
+            ]
+        );
+        let decoded = Slide::new()
+            .from_items(items, config)
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, data);
+    }
+    #[test]
+    fn rep_refs() {
+        // Fixed stride records, where only some fields change.
+        let data =
+            Vec::from_iter((0..0x40u8).flat_map(|x| [x, 0xAA, 0xBB, 0xCC, x ^ 0x55, 0xDD, 0xEE]));
+        let config = Config {
+            max_buffer_len: 0x100,
+            match_lengths: 2..usize::MAX,
+            ..Config::default()
+        };
+        let items = SearchBuffer::<_, 2>::new()
+            .to_items(data.iter().copied(), config.clone())
+            .collect::<Vec<_>>();
+        let count = |f: fn(&Item<u8>) -> bool| items.iter().filter(|item| f(item)).count();
+        let rep_refs = count(|item| matches!(item, Item::RepRef { .. }));
+        assert!(rep_refs > 0x40 && count(|item| matches!(item, Item::Ref { .. })) < 4);
+        let decoded = Slide::new()
+            .from_items(items, config)
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, data);
+    }
+    #[test]
     fn serde_items() {
         let bytes = [
-            0, 7, 118, 119, 97, 98, 99, 100, 101, 5, 3, 3, 6, 0, 3, 120, 118, 119,
+            0, 7, 118, 119, 97, 98, 99, 100, 101, 9, 3, 7, 6, 0, 3, 120, 118, 119,
         ];
         let items = [
             Item::from(b"vwabcde"),
-            Item::from((2..5, 7)),
-            Item::from((7..13, 10)),
+            Item::try_from((2..5, 7)).unwrap(),
+            Item::try_from((7..13, 10)).unwrap(),
             Item::from(b"xvw"),
         ];
         let bytes2 = postcard::to_stdvec(&items).unwrap();
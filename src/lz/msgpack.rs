@@ -0,0 +1,54 @@
+use super::Item;
+use crate::{Error, frame};
+
+/// Serializes `items` as a MessagePack array of items, each an array of unsigned integers laid
+/// out like the varints of a bare item stream.
+///
+/// Lets item streams travel inside MessagePack based protocols and be inspected with standard
+/// MessagePack tooling. Like bare item streams, it records neither config nor dictionary.
+pub fn to_msgpack<'i>(items: impl IntoIterator<Item = Item<'i, u8>>) -> Vec<u8> {
+    let items = Vec::from_iter(items);
+    rmp_serde::to_vec(&items).expect("items are serializable")
+}
+
+/// Reads the items [`to_msgpack`] wrote, without checking them against a config.
+pub fn from_msgpack(bytes: &[u8]) -> Result<Vec<Item<'static, u8>>, Error> {
+    rmp_serde::from_slice(bytes).map_err(|_| frame::Error::Malformed.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Slide, lz::Config, search_buffer::SearchBuffer};
+
+    #[test]
+    fn msgpack() {
+        let data = Vec::from_iter((0..0x2000u32).map(|x| (x / 3 % 13) as u8 ^ (x >> 9) as u8));
+        let config = Config {
+            match_lengths: 3..0x100,
+            ..Config::level(4)
+        };
+        let mut search_buffer = SearchBuffer::<u8, 3>::new();
+        let items = Vec::from_iter(search_buffer.to_items_from_slice(&data, config.clone()));
+        let bytes = to_msgpack(items.iter().cloned());
+        let decoded = from_msgpack(&bytes).unwrap();
+        assert_eq!(decoded, items);
+        assert_eq!(
+            Vec::from_iter(Slide::new().from_items(decoded, config)),
+            data
+        );
+
+        // Readable without knowing about items.
+        let arrays: Vec<Vec<u64>> = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(arrays.len(), items.len());
+        // A fixarray of one fixarray of four positive fixints.
+        assert_eq!(
+            to_msgpack([Item::Run { value: 7, len: 5 }]),
+            [0x91, 0x94, 0, 0, 5, 7]
+        );
+        assert!(matches!(
+            from_msgpack(&bytes[..bytes.len() - 1]),
+            Err(Error::Frame(frame::Error::Malformed))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use std::{collections::BTreeMap, iter};
+
+use serde::Serialize;
+
+use super::{Config, Item, encoder::Parser};
+use crate::{
+    Error, Slide,
+    search_buffer::{BuildHeads, Position, SearchBuffer},
+    util::BuildSlideHasher,
+};
+
+/// Item tagged with the ID of the logical stream its values belong to, see [`MultiEncoder`].
+///
+/// Serialized as the ID byte followed by the item.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Tagged<'a, T: Clone> {
+    pub stream: u8,
+    pub item: Item<'a, T>,
+}
+impl<'a> Tagged<'a, u8> {
+    /// Deserializes a tagged item from the start of `bytes`, see [`Item::take_from_bytes`].
+    pub fn take_from_bytes(bytes: &'a [u8]) -> postcard::Result<(Self, &'a [u8])> {
+        let (stream, bytes) = postcard::take_from_bytes(bytes)?;
+        let (item, bytes) = Item::take_from_bytes(bytes)?;
+        Ok((Self { stream, item }, bytes))
+    }
+}
+
+/// Encoder interleaving several logical streams through one window, so each can reference the
+/// history of all others, like correlated columns of a table.
+///
+/// Every segment pushed is covered by its own items, tagged with its stream ID. Decode them with
+/// [`demultiplex`].
+pub struct MultiEncoder<
+    'a,
+    T: Clone,
+    const N: usize,
+    S: BuildHeads<[T; N], P> = BuildSlideHasher,
+    P: Position = usize,
+> {
+    search_buffer: SearchBuffer<T, N, S, P>,
+    parser: Parser<'a, T>,
+}
+impl<'a, T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position>
+    MultiEncoder<'a, T, N, S, P>
+{
+    /// Fails if `config` is invalid for a key width of `N` or its window is too long for
+    /// positions of `P`.
+    pub fn new(config: Config<'a, T>) -> Result<Self, Error>
+    where
+        S: Default,
+    {
+        let mut search_buffer = SearchBuffer::new();
+        Ok(Self {
+            parser: Parser::new(&mut search_buffer, config)?,
+            search_buffer,
+        })
+    }
+    /// Encodes `data` as the next segment of `stream`.
+    ///
+    /// The returned items cover all of `data` and must be consumed completely before pushing the
+    /// next segment.
+    pub fn push<'s>(
+        &'s mut self,
+        stream: u8,
+        data: &'s [T],
+    ) -> impl Iterator<Item = Tagged<'a, T>> + 's {
+        let mut data = data.iter().copied();
+        iter::from_fn(move || {
+            let item = self.parser.next(&mut self.search_buffer, &mut data)?;
+            Some(Tagged { stream, item })
+        })
+    }
+}
+
+/// Decodes the items of a [`MultiEncoder`], returning the values of every stream by ID.
+///
+/// Items are checked like [`Slide::try_from_items`] does.
+pub fn demultiplex<'i, T: Copy + Eq + 'i>(
+    items: impl IntoIterator<Item = Tagged<'i, T>>,
+    config: Config<T>,
+) -> Result<BTreeMap<u8, Vec<T>>, Error> {
+    let (tags, items): (Vec<_>, Vec<_>) = items
+        .into_iter()
+        .map(|Tagged { stream, item }| ((stream, item.len()), item))
+        .unzip();
+    let mut window = Slide::new();
+    let mut values = window.try_from_items(items, config);
+    let mut streams = BTreeMap::<_, Vec<_>>::new();
+    for (stream, len) in tags {
+        let out = streams.entry(stream).or_default();
+        for value in (&mut values).take(len) {
+            out.push(value?);
+        }
+    }
+    // Invalid items end the values early, reporting the error last.
+    values.next().transpose()?;
+    Ok(streams)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplex() {
+        let config = Config {
+            max_buffer_len: 0x1000,
+            match_lengths: 3..0x100,
+            ..Config::default()
+        };
+        // Two columns of the same rows, the second one row ahead of the first.
+        let column = |shift: u32| {
+            Vec::from_iter((0..0x200u32).flat_map(|x| ((x + shift) * 37 % 101).to_le_bytes()))
+        };
+        let (ids, next) = (column(0), column(1));
+        let mut encoder = MultiEncoder::<_, 3>::new(config.clone()).unwrap();
+        let mut items = vec![];
+        for (ids, next) in ids.chunks(0x40).zip(next.chunks(0x40)) {
+            items.extend(encoder.push(0, ids));
+            items.extend(encoder.push(7, next));
+        }
+        let mut pos = 0;
+        let mut crossing = 0;
+        for Tagged { stream, item } in &items {
+            // References into the previous segment of the other stream.
+            if item.back() > 0 && item.back() <= 0x40 && stream == &7 {
+                crossing += 1;
+            }
+            pos += item.len();
+        }
+        assert_eq!(pos, ids.len() + next.len());
+        assert!(crossing > 0);
+
+        let bytes = Vec::from_iter(
+            items
+                .iter()
+                .flat_map(|tagged| postcard::to_stdvec(tagged).unwrap()),
+        );
+        let mut rest = bytes.as_slice();
+        let mut read = vec![];
+        while !rest.is_empty() {
+            let tagged;
+            (tagged, rest) = Tagged::take_from_bytes(rest).unwrap();
+            read.push(tagged);
+        }
+        assert_eq!(read, items);
+        let streams = demultiplex(read, config.clone()).unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[&0], ids);
+        assert_eq!(streams[&7], next);
+
+        items.push(Tagged {
+            stream: 0,
+            item: Item::try_from((0..3, 0x2000)).unwrap(),
+        });
+        assert!(demultiplex(items, config).is_err());
+    }
+}
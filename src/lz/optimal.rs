@@ -0,0 +1,197 @@
+use std::{mem, num::NonZero, ops::Range};
+
+use super::{Config, CostModel, RepDistances};
+use crate::search_buffer::{BuildHeads, Position, SearchBuffer};
+
+/// Number of values parsed at once by [`Strategy::Optimal`](super::Strategy::Optimal).
+const OPTIMAL_HORIZON: usize = 0x1000;
+/// Matches at least this long are taken as is, skipping the search inside them.
+const NICE_LEN: usize = 0x80;
+
+/// Raw values followed by an optional back reference, as returned by [`SearchBuffer::parse_optimal`].
+pub(super) type Step = (usize, Option<(Range<usize>, usize)>);
+
+#[derive(Clone, Copy)]
+enum Edge {
+    Raw,
+    /// Reference of `len` using candidate `candidate` found at the edge's start.
+    Ref {
+        len: usize,
+        candidate: usize,
+    },
+}
+
+/// Cheapest known way of reaching a position, ending in a raw value or a reference.
+#[derive(Clone, Copy)]
+struct Node {
+    cost: usize,
+    from: usize,
+    from_raw: bool,
+    edge: Edge,
+}
+const UNREACHED: Node = Node {
+    cost: usize::MAX,
+    from: 0,
+    from_raw: false,
+    edge: Edge::Raw,
+};
+
+impl<T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position> SearchBuffer<T, N, S, P> {
+    /// Parses up to [`OPTIMAL_HORIZON`] values of `data` into the steps with the lowest total
+    /// cost under `model`, pushing every parsed value into the search buffer.
+    ///
+    /// Every reachable (length, distance) pair is considered, using the shortest distance found
+    /// for each length. Matches are cut off at the horizon, so no parsed step reaches beyond it.
+    /// Raw items are priced as if only one value long.
+    pub(super) fn parse_optimal(
+        &mut self,
+        data: &[T],
+        config: &Config<T>,
+        model: &impl CostModel<T>,
+    ) -> Vec<Step> {
+        let horizon = data.len().min(OPTIMAL_HORIZON);
+        let min_len = config.match_lengths.start.max(N).max(1);
+        let raw_cost = model.raw(1);
+        // Best way to reach each position, once ending in a raw value and once in a reference.
+        let mut raw = vec![UNREACHED; horizon + 1];
+        let mut refs = vec![UNREACHED; horizon + 1];
+        refs[0].cost = 0;
+        // Matches found at each position as (max len, range, end), sorted by length.
+        let mut candidates: Vec<Vec<(usize, Range<usize>, usize)>> = vec![vec![]; horizon];
+        let mut found = vec![];
+        let mut skip_until = 0;
+        for at in 0..horizon {
+            for (from_raw, from) in [(true, raw[at]), (false, refs[at])] {
+                if from.cost == usize::MAX {
+                    continue;
+                }
+                let literal = model.literal(&data[at]).max(1);
+                let cost = from.cost + literal + if from_raw { 0 } else { raw_cost };
+                if cost < raw[at + 1].cost {
+                    raw[at + 1] = Node {
+                        cost,
+                        from: at,
+                        from_raw,
+                        edge: Edge::Raw,
+                    };
+                }
+            }
+            if at >= skip_until {
+                found.clear();
+                let longest = self.find_longest_match_by(&data[at..], |_, candidate| {
+                    found.push(candidate);
+                    Ok(false)
+                });
+                if let Some(longest) = longest
+                    && found.last() != Some(&longest)
+                {
+                    found.push(longest);
+                }
+                found.sort_by_key(Range::len);
+                let end = self.end();
+                let at_candidates = &mut candidates[at];
+                for range in found.drain(..) {
+                    // Longer matches further back are only worth it for their extra length.
+                    while at_candidates
+                        .last()
+                        .is_some_and(|(_, last, _)| end - last.start >= end - range.start)
+                    {
+                        at_candidates.pop();
+                    }
+                    at_candidates.push((range.len(), range, end));
+                }
+                let cost = raw[at].cost.min(refs[at].cost);
+                let from_raw = raw[at].cost < refs[at].cost;
+                let mut len = min_len;
+                for (candidate, (max_len, range, end)) in at_candidates.iter().enumerate() {
+                    let max_len = (*max_len).min(horizon - at);
+                    let back = NonZero::new(end - range.start).unwrap();
+                    if max_len >= NICE_LEN {
+                        len = max_len;
+                        skip_until = at + max_len;
+                    }
+                    while len <= max_len {
+                        let cost = cost + model.reference(back, len);
+                        if cost < refs[at + len].cost {
+                            refs[at + len] = Node {
+                                cost,
+                                from: at,
+                                from_raw,
+                                edge: Edge::Ref { len, candidate },
+                            };
+                        }
+                        len += 1;
+                    }
+                }
+            }
+            self.push_step(data[at], config.max_buffer_len);
+        }
+
+        let mut steps = vec![];
+        let mut raw_len = 0;
+        let (mut at, mut is_raw) = (horizon, raw[horizon].cost <= refs[horizon].cost);
+        while at > 0 {
+            let node = if is_raw { raw[at] } else { refs[at] };
+            match node.edge {
+                Edge::Raw => raw_len += 1,
+                Edge::Ref { len, candidate } => {
+                    let (_, range, end) = &candidates[node.from][candidate];
+                    let back_ref = (range.start..range.start + len, *end);
+                    steps.push((mem::take(&mut raw_len), Some(back_ref)));
+                }
+            }
+            (at, is_raw) = (node.from, node.from_raw);
+        }
+        // Steps were collected back to front, with raw values counted after their reference.
+        for (step_raw, _) in steps.iter_mut().rev() {
+            raw_len = mem::replace(step_raw, raw_len);
+        }
+        steps.reverse();
+        if raw_len > 0 {
+            steps.push((raw_len, None));
+        }
+        steps
+    }
+    /// Compares up to `k` matches of `data` found along the hash chain, returning the one of at
+    /// least `min_len` saving the most over raw values under `model`, see
+    /// [`Strategy::BestOf`](super::Strategy::BestOf).
+    ///
+    /// Distances in `reps` are priced as rep references. Ties go to the closer match. Stops
+    /// comparing after the first match at least `nice_len` long.
+    pub(super) fn find_cheapest_match(
+        &self,
+        data: &[T],
+        k: usize,
+        min_len: usize,
+        nice_len: Option<usize>,
+        reps: &RepDistances,
+        model: &impl CostModel<T>,
+    ) -> Option<Range<usize>> {
+        let mut found = vec![];
+        let longest = self.find_longest_match_by(data, |_, candidate| {
+            let nice = nice_len.is_some_and(|nice_len| candidate.len() >= nice_len);
+            found.push(candidate);
+            Ok(found.len() >= k || nice)
+        });
+        if let Some(longest) = longest
+            && found.last() != Some(&longest)
+        {
+            found.push(longest);
+        }
+        let end = self.end();
+        let mut best: Option<(usize, Range<usize>)> = None;
+        for range in found.into_iter().filter(|range| range.len() >= min_len) {
+            let (back, len) = (NonZero::new(end - range.start).unwrap(), range.len());
+            let cost = match reps.0.iter().position(|&rep| rep == back) {
+                Some(slot) => model.rep_reference(slot as u8, len),
+                None => model.reference(back, len),
+            };
+            let literals: usize = data[..len].iter().map(|value| model.literal(value)).sum();
+            let saved = literals.saturating_sub(cost);
+            if best.as_ref().is_none_or(|(best, _)| saved > *best) {
+                best = Some((saved, range));
+            }
+        }
+        best.map(|(_, range)| range)
+    }
+}
@@ -0,0 +1,120 @@
+use std::iter;
+
+use super::{CostModel, Item, PostcardCost, RepDistances};
+
+/// Merges consecutive raw items and turns references into raw values where that's cheaper.
+///
+/// `data` must be exactly the values `items` decode to, the raw items returned borrow from it.
+/// Costs are counted by [`PostcardCost`]. Recent distances are encoded anew, so the result decodes like `items` under the same
+/// [`Config`](super::Config).
+pub fn optimize<'a, T: Clone>(
+    items: impl IntoIterator<Item = Item<'a, T>>,
+    data: &'a [T],
+) -> impl Iterator<Item = Item<'a, T>> {
+    let mut items = items.into_iter();
+    let model: &dyn CostModel<T> = &PostcardCost;
+    let (mut decode_reps, mut encode_reps) = (RepDistances::default(), RepDistances::default());
+    // Values of data before raw_start are covered by returned items, up to pos by parsed ones.
+    let (mut raw_start, mut pos) = (0, 0);
+    let mut next = None;
+    iter::from_fn(move || {
+        loop {
+            if let Some(item) = next.take() {
+                return Some(item);
+            }
+            let Some(item) = items.next() else {
+                let raw = &data[raw_start..pos];
+                raw_start = pos;
+                return (!raw.is_empty()).then(|| Item::from(raw));
+            };
+            let item = decode_reps.decode(item);
+            let start = pos;
+            pos += item.len();
+            let literal = match item {
+                Item::Raw(_) => true,
+                Item::Ref { back, len } => {
+                    let cost = match encode_reps.0.iter().position(|&rep| rep == back) {
+                        Some(slot) => model.rep_reference(slot as u8, len),
+                        None => model.reference(back, len),
+                    };
+                    // Raw values not following others need a header of their own.
+                    let header = match raw_start < start {
+                        true => 0,
+                        false => model.raw(len),
+                    };
+                    let literals: usize = data[start..pos].iter().map(|v| model.literal(v)).sum();
+                    literals + header <= cost
+                }
+                Item::Run { .. } | Item::RepRef { .. } => false,
+            };
+            if literal {
+                continue;
+            }
+            let item = encode_reps.encode(item);
+            let raw = &data[raw_start..start];
+            raw_start = pos;
+            if raw.is_empty() {
+                return Some(item);
+            }
+            next = Some(item);
+            return Some(Item::from(raw));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Slide, lz::Config, search_buffer::SearchBuffer};
+    use quickcheck_macros::quickcheck;
+
+    fn config() -> Config<'static, u8> {
+        Config {
+            max_buffer_len: 0x1000,
+            match_lengths: 1..0x100,
+            ..Config::default()
+        }
+    }
+    fn serialized_len(items: &[Item<u8>]) -> usize {
+        items
+            .iter()
+            .map(|item| postcard::to_stdvec(item).unwrap().len())
+            .sum()
+    }
+
+    #[test]
+    fn optimize() {
+        let data =
+            Vec::from_iter((0..0x4000u32).map(|x| (x * 7 % 13) as u8 ^ ((x * x) >> 11) as u8));
+        let items =
+            Vec::from_iter(SearchBuffer::<_, 1>::new().to_items(data.iter().copied(), config()));
+        let optimized = Vec::from_iter(super::optimize(items.clone(), &data));
+        assert!(
+            optimized
+                .windows(2)
+                .all(|pair| pair[0].as_raw().is_none() || pair[1].as_raw().is_none())
+        );
+        assert!(
+            serialized_len(&optimized) < serialized_len(&items),
+            "{} >= {}",
+            serialized_len(&optimized),
+            serialized_len(&items)
+        );
+        let decoded = Vec::from_iter(Slide::new().from_items(optimized, config()));
+        assert_eq!(decoded, data);
+    }
+    #[quickcheck]
+    fn fuzz(data: Vec<u8>) {
+        let data = Vec::from_iter(data.iter().map(|x| x % 5));
+        let mut search_buffer = SearchBuffer::<_, 1>::new();
+        let items = search_buffer.to_items(data.iter().copied(), config());
+        let optimized = Vec::from_iter(super::optimize(items, &data));
+        assert!(
+            optimized
+                .windows(2)
+                .all(|pair| pair[0].as_raw().is_none() || pair[1].as_raw().is_none())
+        );
+        let decoded = Vec::from_iter(Slide::new().from_items(optimized, config()));
+        assert_eq!(decoded, data);
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+/// Amounts processed so far, as passed to a [`Progress`] callback.
+///
+/// Counts a stage doesn't track stay zero.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Processed {
+    /// Input values consumed.
+    pub consumed: usize,
+    /// Output values produced.
+    pub produced: usize,
+    /// Items emitted or decoded.
+    pub items: usize,
+}
+
+/// Callback invoked each time another `interval` values were consumed or produced.
+///
+/// Accepted by [`SearchBuffer::to_items_with_progress`](crate::search_buffer::SearchBuffer::to_items_with_progress),
+/// [`Slide::from_items_with_progress`](crate::Slide::from_items_with_progress) and the streaming
+/// [`frame`](crate::frame) types.
+pub struct Progress<'a> {
+    callback: Box<dyn FnMut(Processed) + Send + 'a>,
+    interval: usize,
+    next: usize,
+    processed: Processed,
+}
+impl<'a> Progress<'a> {
+    pub fn new(interval: usize, callback: impl FnMut(Processed) + Send + 'a) -> Self {
+        let interval = interval.max(1);
+        Self {
+            callback: Box::new(callback),
+            interval,
+            next: interval,
+            processed: Processed::default(),
+        }
+    }
+    /// Amounts recorded so far.
+    pub fn processed(&self) -> Processed {
+        self.processed
+    }
+    /// Adds to the amounts processed, invoking the callback if an interval was crossed.
+    pub(crate) fn advance(&mut self, consumed: usize, produced: usize, items: usize) {
+        let processed = &mut self.processed;
+        processed.consumed += consumed;
+        processed.produced += produced;
+        processed.items += items;
+        let total = processed.consumed.max(processed.produced);
+        if total >= self.next {
+            self.next = (total / self.interval + 1) * self.interval;
+            (self.callback)(self.processed);
+        }
+    }
+}
+impl fmt::Debug for Progress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("interval", &self.interval)
+            .field("processed", &self.processed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals() {
+        let mut calls = vec![];
+        let mut progress = Progress::new(10, |processed| calls.push(processed.consumed));
+        for consumed in [3, 3, 3, 3, 25, 1] {
+            progress.advance(consumed, 0, 1);
+        }
+        assert_eq!(progress.processed().items, 6);
+        drop(progress);
+        assert_eq!(calls, [12, 37]);
+    }
+}
@@ -0,0 +1,307 @@
+use std::{cmp::Reverse, collections::HashMap, fmt};
+
+use super::{Item, RepDistances};
+use crate::util::histogram::Histogram;
+
+/// Number of buckets of the [`Stats`] histograms, one per bit length of a `usize`.
+pub const HISTOGRAM_BUCKETS: usize = usize::BITS as usize + 1;
+
+/// Statistics about the items emitted by [`SearchBuffer::to_items_with_stats`](crate::search_buffer::SearchBuffer::to_items_with_stats).
+///
+/// Histograms count values by bit length, so bucket `i` holds values in `2^(i-1)..2^i`.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// Total number of items emitted.
+    pub items: usize,
+    pub raw_items: usize,
+    pub ref_items: usize,
+    /// References to a recent distance, also counted in `ref_items`.
+    pub rep_ref_items: usize,
+    pub run_items: usize,
+    /// Values emitted as raw literals.
+    pub literal_len: usize,
+    /// Values covered by references.
+    pub match_len: usize,
+    /// Values covered by runs.
+    pub run_len: usize,
+    /// Reference lengths by bit length.
+    pub length_histogram: [usize; HISTOGRAM_BUCKETS],
+    /// Resolved reference distances by bit length.
+    pub distance_histogram: [usize; HISTOGRAM_BUCKETS],
+    /// Match searches run by the search buffer.
+    pub searches: usize,
+    /// Hash chain entries visited by those searches.
+    pub chain_steps: usize,
+    /// Searches by the number of hash chain entries they visited.
+    pub chain_depth_histogram: [usize; HISTOGRAM_BUCKETS],
+    /// Distinct `N` values the search buffer had a latest position for after the last item.
+    pub heads: usize,
+    /// How often each position of the stream was the start of a reference, if tracked.
+    references: Option<HashMap<usize, usize>>,
+    reps: RepDistances,
+}
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            items: 0,
+            raw_items: 0,
+            ref_items: 0,
+            rep_ref_items: 0,
+            run_items: 0,
+            literal_len: 0,
+            match_len: 0,
+            run_len: 0,
+            length_histogram: [0; HISTOGRAM_BUCKETS],
+            distance_histogram: [0; HISTOGRAM_BUCKETS],
+            searches: 0,
+            chain_steps: 0,
+            chain_depth_histogram: [0; HISTOGRAM_BUCKETS],
+            heads: 0,
+            references: None,
+            reps: RepDistances::default(),
+        }
+    }
+}
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Like [`new`](Self::new), but also counts the positions references start at, for
+    /// [`most_referenced`](Self::most_referenced).
+    ///
+    /// Costs a map entry per referenced position.
+    pub fn with_references() -> Self {
+        Self {
+            references: Some(HashMap::new()),
+            ..Self::default()
+        }
+    }
+    /// Counts `item`, which must follow the previously recorded items of the same stream.
+    pub fn record<T: Clone>(&mut self, item: &Item<T>) {
+        let pos = self.total_len();
+        self.items += 1;
+        let back = self.reps.update(item);
+        match item {
+            Item::Raw(raw) => {
+                self.raw_items += 1;
+                self.literal_len += raw.len();
+            }
+            Item::Run { len, .. } => {
+                self.run_items += 1;
+                self.run_len += len;
+            }
+            Item::Ref { len, .. } | Item::RepRef { len, .. } => {
+                self.ref_items += 1;
+                self.rep_ref_items += matches!(item, Item::RepRef { .. }) as usize;
+                self.match_len += len;
+                self.length_histogram[bucket(*len)] += 1;
+                self.distance_histogram[bucket(back)] += 1;
+                // References into a dictionary start before the stream.
+                if let Some(references) = &mut self.references
+                    && let Some(start) = pos.checked_sub(back)
+                {
+                    *references.entry(start).or_default() += 1;
+                }
+            }
+        }
+    }
+    /// Adds the counts of `other`, recorded from another stream.
+    pub fn merge(&mut self, other: &Stats) {
+        self.items += other.items;
+        self.raw_items += other.raw_items;
+        self.ref_items += other.ref_items;
+        self.rep_ref_items += other.rep_ref_items;
+        self.run_items += other.run_items;
+        self.literal_len += other.literal_len;
+        self.match_len += other.match_len;
+        self.run_len += other.run_len;
+        for (a, b) in self
+            .length_histogram
+            .iter_mut()
+            .zip(&other.length_histogram)
+        {
+            *a += b;
+        }
+        for (a, b) in (self.distance_histogram.iter_mut()).zip(&other.distance_histogram) {
+            *a += b;
+        }
+        self.searches += other.searches;
+        self.chain_steps += other.chain_steps;
+        for (a, b) in (self.chain_depth_histogram.iter_mut()).zip(&other.chain_depth_histogram) {
+            *a += b;
+        }
+        self.heads += other.heads;
+    }
+    /// Up to `k` stream positions most often referenced, with how often, most referenced first.
+    ///
+    /// Empty unless created [`with_references`](Self::with_references). Positions of merged
+    /// stats aren't comparable, so only those recorded directly are counted.
+    pub fn most_referenced(&self, k: usize) -> Vec<(usize, usize)> {
+        let mut references =
+            Vec::from_iter(self.references.iter().flatten().map(|(&a, &b)| (a, b)));
+        references.sort_unstable_by_key(|&(pos, count)| (Reverse(count), pos));
+        references.truncate(k);
+        references
+    }
+    /// Starts recording a new stream, whose rep references don't refer to the previous ones.
+    pub fn restart(&mut self) {
+        self.reps = RepDistances::default();
+    }
+    /// Total number of values covered by the recorded items.
+    pub fn total_len(&self) -> usize {
+        self.literal_len + self.match_len + self.run_len
+    }
+    /// Average number of hash chain entries visited per search.
+    pub fn average_chain_depth(&self) -> f64 {
+        if self.searches == 0 {
+            return 0.0;
+        }
+        self.chain_steps as f64 / self.searches as f64
+    }
+    /// Entropy of the bit lengths of the reference lengths, in bits per reference.
+    pub fn length_entropy(&self) -> f64 {
+        Histogram::from_counts(&self.length_histogram).entropy()
+    }
+    /// Entropy of the bit lengths of the reference distances, in bits per reference.
+    pub fn distance_entropy(&self) -> f64 {
+        Histogram::from_counts(&self.distance_histogram).entropy()
+    }
+    /// Bits an entropy coder would spend on the lengths and distances of the references, coding
+    /// their bit lengths at their entropy followed by the bits below the leading one as is.
+    pub fn reference_bits(&self) -> f64 {
+        [&self.length_histogram, &self.distance_histogram]
+            .map(|histogram| {
+                let extra = (histogram.iter().enumerate())
+                    .map(|(bucket, count)| bucket.saturating_sub(1) * count)
+                    .sum::<usize>();
+                Histogram::from_counts(histogram).entropy_bits() + extra as f64
+            })
+            .iter()
+            .sum()
+    }
+}
+pub(crate) fn bucket(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()) as usize
+}
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |len: usize| len as f64 * 100.0 / self.total_len().max(1) as f64;
+        writeln!(
+            f,
+            "items: {} ({} raw, {} ref, {} rep ref, {} run)",
+            self.items, self.raw_items, self.ref_items, self.rep_ref_items, self.run_items
+        )?;
+        writeln!(
+            f,
+            "values: {} literal ({:.1}%), {} matched ({:.1}%), {} run ({:.1}%)",
+            self.literal_len,
+            percent(self.literal_len),
+            self.match_len,
+            percent(self.match_len),
+            self.run_len,
+            percent(self.run_len)
+        )?;
+        writeln!(
+            f,
+            "searches: {}, average chain depth: {:.2}",
+            self.searches,
+            self.average_chain_depth()
+        )?;
+        writeln!(
+            f,
+            "bit length entropy: {:.2} bits per length, {:.2} per distance",
+            self.length_entropy(),
+            self.distance_entropy()
+        )?;
+        for (name, histogram) in [
+            ("length", &self.length_histogram),
+            ("distance", &self.distance_histogram),
+            ("chain depth", &self.chain_depth_histogram),
+        ] {
+            writeln!(f, "{name} histogram:")?;
+            for (bucket, count) in histogram
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+            {
+                let start = if bucket == 0 {
+                    0
+                } else {
+                    1usize << (bucket - 1)
+                };
+                writeln!(f, "  >= {start:>10}: {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lz::Config, search_buffer::SearchBuffer};
+
+    #[test]
+    fn stats() {
+        let data = Vec::from_iter(
+            (0..0x1000u32).flat_map(|i| (i % 7 * 3 + i % 5).to_le_bytes().into_iter().take(2)),
+        );
+        let mut stats = Stats::with_references();
+        let items = Vec::from_iter(SearchBuffer::<u8, 4>::new().to_items_with_stats(
+            data.iter().copied(),
+            Config {
+                max_buffer_len: 0x10000,
+                match_lengths: 4..0x1000,
+                ..Config::default()
+            },
+            &mut stats,
+        ));
+        assert_eq!(stats.items, items.len());
+        assert_eq!(stats.total_len(), data.len());
+        assert_eq!(
+            stats.raw_items + stats.ref_items + stats.run_items,
+            stats.items
+        );
+        assert!(stats.match_len > stats.literal_len);
+        assert!(stats.rep_ref_items > 0);
+        assert_eq!(
+            stats.length_histogram.iter().sum::<usize>(),
+            stats.ref_items
+        );
+        assert_eq!(
+            stats.distance_histogram.iter().sum::<usize>(),
+            stats.ref_items
+        );
+        assert_eq!(stats.distance_histogram[0], 0);
+        assert!(stats.searches > 0);
+        assert!(stats.average_chain_depth() > 0.0);
+        assert!(stats.to_string().contains("average chain depth"));
+        let max_entropy = (HISTOGRAM_BUCKETS as f64).log2();
+        assert!((0.0..=max_entropy).contains(&stats.length_entropy()));
+        assert!((0.0..=max_entropy).contains(&stats.distance_entropy()));
+        assert!(stats.reference_bits() >= stats.ref_items as f64);
+        assert_eq!(
+            stats.chain_depth_histogram.iter().sum::<usize>(),
+            stats.searches
+        );
+        assert!(stats.heads > 0);
+        let top = stats.most_referenced(3);
+        assert_eq!(top.len(), 3);
+        assert!(top.is_sorted_by(|a, b| a.1 >= b.1));
+        assert!(
+            stats
+                .most_referenced(usize::MAX)
+                .iter()
+                .map(|(_, count)| count)
+                .sum::<usize>()
+                <= stats.ref_items
+        );
+        assert!(Stats::new().most_referenced(3).is_empty());
+
+        let mut merged = stats.clone();
+        merged.merge(&stats);
+        assert_eq!(merged.items, 2 * stats.items);
+        assert_eq!(merged.total_len(), 2 * data.len());
+        assert_eq!(merged.length_histogram[3], 2 * stats.length_histogram[3]);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{collections::VecDeque, iter, mem};
+
+use super::{Config, ConfigError, Item, RepDistances, extend_window};
+use crate::Slide;
+
+/// Rewrites `items` encoded under `from` into items decoding to the same values under `to`,
+/// without searching for matches again.
+///
+/// References reaching further back than `to` allows, or into a dictionary the configs don't
+/// share, become raw values. So do references shorter than `to.match_lengths`, while longer ones
+/// are split, as are overlapping ones if `to` forbids them. Runs are kept and raw values merged
+/// with their neighbours.
+///
+/// Fails if `to` is invalid, see [`Config::validate`].
+pub fn transcode<'i, T: Copy + PartialEq + 'i>(
+    items: impl IntoIterator<Item = Item<'i, T>>,
+    from: Config<T>,
+    to: Config<T>,
+) -> Result<impl Iterator<Item = Item<'i, T>>, ConfigError> {
+    to.validate()?;
+    let mut items = items.into_iter();
+    let mut window = Slide::from_iter(from.dictionary_window().iter().copied());
+    // Dictionary values a reference may reach under both configs.
+    let dictionary_len = match from.dictionary == to.dictionary {
+        true => to.dictionary_window().len().min(window.len()),
+        false => 0,
+    };
+    let max_back = to.max_buffer_len;
+    let (min_len, max_len) = (to.match_lengths.start, to.match_lengths.end - 1);
+    let (mut decode_reps, mut encode_reps) = (RepDistances::default(), RepDistances::default());
+    let mut decoded = 0;
+    let mut raw = vec![];
+    let mut queue = VecDeque::new();
+    Ok(iter::from_fn(move || {
+        loop {
+            if let Some(item) = queue.pop_front() {
+                return Some(item);
+            }
+            let Some(item) = items.next() else {
+                return (!raw.is_empty()).then(|| Item::from(mem::take(&mut raw)));
+            };
+            let item = decode_reps.decode(item);
+            let len = item.len();
+            extend_window(&mut window, &item, from.max_buffer_len);
+            let values = &window[window.len() - len.min(window.len())..];
+            let mut kept = 0;
+            match item {
+                Item::Raw(_) => {}
+                Item::Run { .. } => {
+                    kept = len;
+                    if !raw.is_empty() {
+                        queue.push_back(Item::from(mem::take(&mut raw)));
+                    }
+                    queue.push_back(item);
+                }
+                Item::RepRef { .. } => unreachable!("Resolved by RepDistances::decode."),
+                Item::Ref { back, len } => {
+                    // Parts no longer than their distance only copy values before them.
+                    let max_len = match to.forbid_overlap {
+                        true => max_len.min(back.get()),
+                        false => max_len,
+                    };
+                    if back.get() <= max_back.min(decoded + dictionary_len) && max_len >= min_len {
+                        while len - kept >= min_len {
+                            if !raw.is_empty() {
+                                queue.push_back(Item::from(mem::take(&mut raw)));
+                            }
+                            let part = (len - kept).min(max_len);
+                            queue.push_back(encode_reps.encode(Item::Ref { back, len: part }));
+                            kept += part;
+                        }
+                    }
+                }
+            }
+            if kept < len {
+                raw.extend_from_slice(&values[kept..]);
+            }
+            decoded += len;
+            window
+                .drain(0..window.len().saturating_sub(from.max_buffer_len))
+                .for_each(drop);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_buffer::SearchBuffer;
+
+    #[test]
+    fn transcode() {
+        let data = Vec::from_iter(
+            (0..0x8000u32).map(|x| (x / 3 % 41) as u8 ^ (x >> 11) as u8 ^ (x % 0x1000 == 7) as u8),
+        );
+        let dictionary = Vec::from_iter((0..0x400u32).map(|x| (x / 3 % 41) as u8));
+        let from = Config {
+            max_buffer_len: 0x4000,
+            match_lengths: 3..0x400,
+            dictionary: Some(dictionary.as_slice()),
+            ..Config::default()
+        };
+        let items = Vec::from_iter(
+            SearchBuffer::<_, 3>::new().to_items(data.iter().copied(), from.clone()),
+        );
+        assert!(items.iter().any(|item| item.back() > 0x100));
+        for (dictionary, forbid_overlap) in [
+            (None, false),
+            (Some(dictionary.as_slice()), false),
+            (None, true),
+        ] {
+            let to = Config {
+                max_buffer_len: 0x100,
+                match_lengths: 4..0x20,
+                dictionary,
+                forbid_overlap,
+                ..Config::default()
+            };
+            let transcoded = Vec::from_iter(
+                super::transcode(items.iter().cloned(), from.clone(), to.clone()).unwrap(),
+            );
+            let mut reps = RepDistances::default();
+            for item in &transcoded {
+                let back = reps.update(item);
+                assert!(back <= to.max_buffer_len);
+                if back > 0 {
+                    assert!(to.match_lengths.contains(&item.len()));
+                    assert!(!forbid_overlap || item.len() <= back);
+                }
+            }
+            assert!(transcoded.len() > items.len() / 2);
+            let decoded = Vec::from_iter(Slide::new().from_items(transcoded, to));
+            assert_eq!(decoded, data);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    lz::{Config, Item, RepDistances},
+    search_buffer::SearchBuffer,
+    util::bits::{BitOrder, BitReader, BitWriter},
+};
+
+/// Field widths of the classic LZSS bit-flag format.
+///
+/// Every token starts with one control bit: `1` is followed by an 8 bit literal,
+/// `0` by a reference made of `offset_bits` bits holding `back - 1`
+/// and `length_bits` bits holding `len - min_match`.
+/// Fields are packed MSB-first and the stream is prefixed with the uncompressed length (u32 LE).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    /// Width of the offset field. Default: 12 (4 KiB window)
+    pub offset_bits: u32,
+    /// Width of the length field. Default: 4
+    pub length_bits: u32,
+    /// Shortest encodable match. Default: 3
+    pub min_match: usize,
+}
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            offset_bits: 12,
+            length_bits: 4,
+            min_match: 3,
+        }
+    }
+}
+impl Format {
+    /// Longest distance the offset field can express.
+    pub fn window_len(&self) -> usize {
+        1 << self.offset_bits
+    }
+    /// Longest match the length field can express.
+    pub fn max_match(&self) -> usize {
+        self.min_match + (1 << self.length_bits) - 1
+    }
+    /// Config restricting the search to what the fields can express.
+    pub fn config(&self) -> Config<'static> {
+        Config {
+            max_buffer_len: self.window_len(),
+            match_lengths: self.min_match..self.max_match() + 1,
+            ..Config::default()
+        }
+    }
+    /// Checks that the fields are between 1 and 24 and 16 bits wide and the minimum match
+    /// between 1 and 2^16.
+    pub fn validate(&self) -> Result<(), Error> {
+        match (1..=24).contains(&self.offset_bits)
+            && (1..=16).contains(&self.length_bits)
+            && (1..=0x10000).contains(&self.min_match)
+        {
+            true => Ok(()),
+            false => Err(Error::InvalidFormat(*self)),
+        }
+    }
+    fn assert_valid(&self) {
+        if let Err(err) = self.validate() {
+            panic!("{err}");
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input ended before the announced number of bytes was decoded.
+    UnexpectedEof,
+    /// A reference pointed before the start of the output.
+    InvalidOffset { back: usize, position: usize },
+    /// The [`Format`] can't be used, see [`Format::validate`].
+    InvalidFormat(Format),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => write!(f, "unexpected end of lzss input"),
+            Error::InvalidOffset { back, position } => {
+                write!(
+                    f,
+                    "reference {back} back out of bounds at output position {position}"
+                )
+            }
+            Error::InvalidFormat(format) => write!(f, "invalid lzss format {format:?}"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// Compresses `data` into an LZSS stream of `format`.
+///
+/// Panics if `format` is invalid or `data` exceeds `u32::MAX` bytes.
+pub fn compress(data: &[u8], format: Format) -> Vec<u8> {
+    format.assert_valid();
+    match format.min_match {
+        1 => compress_with::<1>(data, format),
+        2 => compress_with::<2>(data, format),
+        3 => compress_with::<3>(data, format),
+        _ => compress_with::<4>(data, format),
+    }
+}
+
+fn compress_with<const N: usize>(data: &[u8], format: Format) -> Vec<u8> {
+    let len = u32::try_from(data.len()).expect("LZSS streams are limited to u32::MAX bytes.");
+    let mut writer = BitWriter::with_vec(len.to_le_bytes().to_vec(), BitOrder::MsbFirst);
+    let mut pos = 0;
+    let mut search_buffer = SearchBuffer::<u8, N>::new();
+    let mut reps = RepDistances::default();
+    for item in search_buffer.to_items(data.iter().copied(), format.config()) {
+        let item_len = item.len();
+        match reps.decode(item) {
+            Item::Ref { back, len } if len >= format.min_match => {
+                writer.write(0, 1);
+                writer.write(back.get() as u32 - 1, format.offset_bits);
+                writer.write((len - format.min_match) as u32, format.length_bits);
+            }
+            Item::Run { value, len } if len > format.min_match => {
+                writer.write(1, 1);
+                writer.write(value as u32, 8);
+                writer.write(0, 1);
+                writer.write(0, format.offset_bits);
+                writer.write((len - 1 - format.min_match) as u32, format.length_bits);
+            }
+            _ => {
+                // Matches shorter than min_match are only found for min_match > N.
+                for &byte in &data[pos..pos + item_len] {
+                    writer.write(1, 1);
+                    writer.write(byte as u32, 8);
+                }
+            }
+        }
+        pos += item_len;
+    }
+    writer.finish()
+}
+
+/// Decompresses an LZSS stream of `format`, failing if `format` is invalid or the stream is
+/// cut short or refers before the start of its output.
+pub fn decompress(data: &[u8], format: Format) -> Result<Vec<u8>, Error> {
+    format.validate()?;
+    let (len, data) = data.split_first_chunk::<4>().ok_or(Error::UnexpectedEof)?;
+    let len = u32::from_le_bytes(*len) as usize;
+    let mut reader = BitReader::new(data, BitOrder::MsbFirst);
+    let mut read = |bits| reader.read(bits).ok_or(Error::UnexpectedEof);
+    // The announced length is untrusted, no bit of input decodes to more than a whole match.
+    let mut out = Vec::with_capacity(len.min(data.len().saturating_mul(8 * format.max_match())));
+    while out.len() < len {
+        if read(1)? == 1 {
+            out.push(read(8)? as u8);
+        } else {
+            let back = read(format.offset_bits)? as usize + 1;
+            let len = (read(format.length_bits)? as usize + format.min_match).min(len - out.len());
+            let position = out.len();
+            if back > position {
+                return Err(Error::InvalidOffset { back, position });
+            }
+            for x in position - back..position - back + len {
+                out.push(out[x]);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn bit_layout() {
+        let format = Format {
+            offset_bits: 4,
+            length_bits: 2,
+            min_match: 2,
+        };
+        let compressed = compress(b"abababa", format);
+        // 1 'a', 1 'b', 0 back=2 len=5 (stored 1 / 3)
+        #[rustfmt::skip]
+        assert_eq!(compressed, [
+            7, 0, 0, 0,
+            0b1011_0000, 0b1101_1000, 0b1000_0011, 0b1000_0000,
+        ]);
+        assert_eq!(decompress(&compressed, format).unwrap(), b"abababa");
+    }
+    #[test]
+    fn invalid() {
+        let format = Format::default();
+        assert_eq!(decompress(&[1, 0, 0], format), Err(Error::UnexpectedEof));
+        assert_eq!(decompress(&[1, 0, 0, 0], format), Err(Error::UnexpectedEof));
+        assert_eq!(
+            decompress(&[1, 0, 0, 0, 0, 0, 0], format),
+            Err(Error::InvalidOffset {
+                back: 1,
+                position: 0
+            })
+        );
+        // Announces 4 GiB without the bits to back it up.
+        assert_eq!(
+            decompress(&[0xff, 0xff, 0xff, 0xff, 0x80], format),
+            Err(Error::UnexpectedEof)
+        );
+        let invalid = Format {
+            length_bits: 0,
+            ..format
+        };
+        assert_eq!(
+            decompress(&[1, 0, 0, 0, 0x80], invalid),
+            Err(Error::InvalidFormat(invalid))
+        );
+    }
+    #[quickcheck]
+    fn fuzz(data: Vec<u8>, repeat: u8, offset_bits: u8, length_bits: u8, min_match: u8) {
+        let data = data.repeat(repeat as usize % 8 + 1);
+        let format = Format {
+            offset_bits: offset_bits as u32 % 12 + 1,
+            length_bits: length_bits as u32 % 8 + 1,
+            min_match: min_match as usize % 6 + 1,
+        };
+        let compressed = compress(&data, format);
+        assert_eq!(decompress(&compressed, format).unwrap(), data);
+    }
+}
@@ -0,0 +1,11 @@
+//! The types and functions most uses of the crate need, for `use slide::prelude::*`.
+//!
+//! Frames are the format [`compress`] and [`decompress`] use and [`Encoder`] and [`Decoder`]
+//! stream. Their errors convert into the crate-wide [`Error`], so `?` works for both.
+
+pub use crate::{
+    Error, Slide,
+    frame::{Decoder, Encoder, compress, decompress},
+    lz::{Config, Item},
+    search_buffer::SearchBuffer,
+};
@@ -0,0 +1,225 @@
+//! Naive reference codec for differential testing, behind the `test-util` feature.
+//!
+//! [`encode`] tries every position of the window for the longest match, taking `O(n²)` time,
+//! and [`decode`] copies references value by value. Both are written to be obviously correct
+//! rather than fast, so that [`verify_equivalence`] can hold the [`SearchBuffer`] encoder and
+//! [`Slide`] decoder against them.
+
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+    num::NonZero,
+};
+
+use crate::{
+    Error, Slide,
+    lz::{Config, Item, RepDistances},
+    search_buffer::SearchBuffer,
+};
+
+/// Greedily encodes `data` into raw items and references, always taking the longest match in
+/// the window and the nearest one of those.
+///
+/// Matches are as long as `config.match_lengths` allows, regardless of the lookahead, and may
+/// overlap the values they produce. `config.dictionary` primes the window.
+pub fn encode<'a, T: Copy + Eq>(data: &[T], config: &Config<'a, T>) -> Vec<Item<'a, T>> {
+    let dictionary = config.dictionary_window();
+    let values = [dictionary, data].concat();
+    let mut items = vec![];
+    let mut raw = vec![];
+    let mut pos = dictionary.len();
+    while pos < values.len() {
+        let max_len = (values.len() - pos).min(config.match_lengths.end - 1);
+        let mut best = (0, 0);
+        for start in (pos.saturating_sub(config.max_buffer_len)..pos).rev() {
+            let len = (0..max_len)
+                .take_while(|&x| values[start + x] == values[pos + x])
+                .count();
+            if len > best.0 {
+                best = (len, pos - start);
+            }
+        }
+        let (len, back) = best;
+        if len < config.match_lengths.start {
+            raw.push(values[pos]);
+            pos += 1;
+            continue;
+        }
+        if !raw.is_empty() {
+            items.push(Item::Raw(Cow::Owned(raw.split_off(0))));
+        }
+        items.push(Item::Ref {
+            back: NonZero::new(back).unwrap(),
+            len,
+        });
+        pos += len;
+    }
+    if !raw.is_empty() {
+        items.push(Item::Raw(Cow::Owned(raw)));
+    }
+    items
+}
+
+/// Decodes `items`, checking every one of them against `config`.
+pub fn decode<T: Copy>(items: &[Item<T>], config: &Config<T>) -> Result<Vec<T>, Error> {
+    let dictionary = config.dictionary_window();
+    let mut out = dictionary.to_vec();
+    let mut reps = RepDistances::default();
+    for item in items {
+        match item {
+            Item::Raw(raw) => out.extend_from_slice(raw),
+            Item::Run { value, len } => out.extend(std::iter::repeat_n(*value, *len)),
+            &Item::Ref { len, .. } | &Item::RepRef { len, .. } => {
+                if let &Item::RepRef { slot, .. } = item
+                    && slot as usize >= reps.0.len()
+                {
+                    return Err(Error::InvalidSlot(slot));
+                }
+                let back = reps.update(item);
+                let available = out.len().min(config.max_buffer_len);
+                if back > available {
+                    return Err(Error::InvalidDistance { back, available });
+                }
+                if !config.match_lengths.contains(&len) {
+                    return Err(Error::InvalidLength {
+                        len,
+                        start: config.match_lengths.start,
+                        end: config.match_lengths.end,
+                    });
+                }
+                for _ in 0..len {
+                    out.push(out[out.len() - back]);
+                }
+            }
+        }
+    }
+    Ok(out.split_off(dictionary.len()))
+}
+
+/// How the optimized codec disagreed with the reference, see [`verify_equivalence`].
+#[derive(Debug)]
+pub enum Mismatch {
+    /// The items of the [`SearchBuffer`] encoder are invalid for the reference decoder.
+    InvalidItems(Error),
+    /// The items of the [`SearchBuffer`] encoder decode to different data, first differing at
+    /// `position`.
+    EncodedDiffers { position: usize },
+    /// The [`Slide`] decoder rejected the reference items.
+    DecodeFailed(Error),
+    /// The [`Slide`] decoder decoded the reference items to different data, first differing at
+    /// `position`.
+    DecodedDiffers { position: usize },
+}
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::InvalidItems(err) => write!(f, "encoder produced invalid items: {err}"),
+            Mismatch::EncodedDiffers { position } => {
+                write!(f, "encoder items decode to different data at {position}")
+            }
+            Mismatch::DecodeFailed(err) => write!(f, "decoder rejected reference items: {err}"),
+            Mismatch::DecodedDiffers { position } => {
+                write!(f, "decoder produced different data at {position}")
+            }
+        }
+    }
+}
+impl std::error::Error for Mismatch {}
+
+/// Checks the [`SearchBuffer`] encoder and the [`Slide`] decoder against the reference codec:
+/// the items of either encoder must decode to `data` with either decoder.
+///
+/// The encoders needn't agree on the items themselves, as the optimized one also emits runs and
+/// splits matches at the lookahead. `config` must be valid.
+pub fn verify_equivalence(data: &[u8], config: Config) -> Result<(), Mismatch> {
+    match config.match_lengths.start {
+        1 => verify::<1>(data, config),
+        2 => verify::<2>(data, config),
+        3 => verify::<3>(data, config),
+        _ => verify::<4>(data, config),
+    }
+}
+
+fn verify<const N: usize>(data: &[u8], config: Config) -> Result<(), Mismatch> {
+    let items =
+        Vec::from_iter(SearchBuffer::<u8, N>::new().to_items(data.iter().copied(), config.clone()));
+    let decoded = decode(&items, &config).map_err(Mismatch::InvalidItems)?;
+    if let Some(position) = first_difference(&decoded, data) {
+        return Err(Mismatch::EncodedDiffers { position });
+    }
+    let expected = encode(data, &config);
+    let decoded = Result::<Vec<_>, _>::from_iter(
+        Slide::new().try_from_items(expected.iter().cloned(), config.clone()),
+    )
+    .map_err(Mismatch::DecodeFailed)?;
+    if let Some(position) = first_difference(&decoded, data) {
+        return Err(Mismatch::DecodedDiffers { position });
+    }
+    Ok(())
+}
+
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    (a.iter().zip(b).position(|(a, b)| a != b))
+        .or((a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lz::Strategy;
+    use quickcheck_macros::quickcheck;
+
+    fn config(max_buffer_len: usize, min_match: usize, max_match: usize) -> Config<'static> {
+        Config {
+            max_buffer_len,
+            match_lengths: min_match..max_match,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn reference() {
+        let config = config(8, 2, 5);
+        let items = encode(b"abcabcabcxab", &config);
+        assert_eq!(
+            items,
+            vec![
+                Item::from(b"abc"),
+                Item::try_from((0..4, 3)).unwrap(),
+                Item::try_from((0..2, 3)).unwrap(),
+                Item::from(b"x"),
+                Item::try_from((6..8, 10)).unwrap(),
+            ]
+        );
+        assert_eq!(decode(&items, &config).unwrap(), b"abcabcabcxab");
+        let far = [
+            Item::from(b"ab"),
+            Item::Ref {
+                back: NonZero::new(3).unwrap(),
+                len: 2,
+            },
+        ];
+        assert!(matches!(
+            decode(&far, &config),
+            Err(Error::InvalidDistance {
+                back: 3,
+                available: 2
+            })
+        ));
+    }
+    #[quickcheck]
+    fn equivalence(data: Vec<u8>, alphabet: u8, window: u8, min_match: u8, strategy: u8) {
+        // A small alphabet makes for plenty of matches.
+        let data = Vec::from_iter(data.iter().map(|&byte| byte % (alphabet % 8 + 1)));
+        let mut config = config(window as usize + 1, min_match as usize % 5 + 1, 0x40);
+        config.strategy = match strategy % 4 {
+            0 => Strategy::Greedy,
+            1 => Strategy::Lazy(2),
+            2 => Strategy::BestOf(4),
+            _ => Strategy::Optimal,
+        };
+        if let Err(err) = verify_equivalence(&data, config) {
+            panic!("{err}");
+        }
+    }
+}
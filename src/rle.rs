@@ -0,0 +1,171 @@
+//! Byte-oriented run-length coding, for data dominated by runs that doesn't need matching.
+//!
+//! Compressed data is laid out as the escape byte, the least frequent byte of the input,
+//! followed by the input with its runs replaced:
+//! - a run of at least [`MIN_RUN`] copies of a byte, or of at least 2 copies of the escape byte,
+//!   is the escape byte, the run length minus 1 (varint, at least 1) and the byte.
+//! - a single escape byte is the escape byte followed by 0.
+//! - every other byte is itself.
+
+use std::fmt::{self, Display};
+
+use crate::util::varint;
+
+/// Shortest run of a byte other than the escape byte that is coded as a run.
+pub const MIN_RUN: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input ended in the middle of a run or before the escape byte.
+    UnexpectedEof,
+    /// A run length is malformed, or the decompressed length overflows.
+    Malformed,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => write!(f, "unexpected end of run-length coded data"),
+            Error::Malformed => write!(f, "malformed run-length coded data"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// Run-length codes `data`.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + 1);
+    compress_to(data, &mut out);
+    out
+}
+
+/// Like [`compress`], appending to `out`.
+pub fn compress_to(data: &[u8], out: &mut Vec<u8>) {
+    let mut freqs = [0usize; 256];
+    for &byte in data {
+        freqs[byte as usize] += 1;
+    }
+    let escape = (0..=255).min_by_key(|&byte| freqs[byte as usize]).unwrap();
+    out.push(escape);
+    let mut at = 0;
+    while at < data.len() {
+        let value = data[at];
+        let run = data[at..].iter().take_while(|&&byte| byte == value).count();
+        at += run;
+        if run >= MIN_RUN || value == escape && run >= 2 {
+            out.push(escape);
+            varint::write(out, (run - 1) as u64);
+            out.push(value);
+        } else if value == escape {
+            out.extend([escape, 0]);
+        } else {
+            out.extend(std::iter::repeat_n(value, run));
+        }
+    }
+}
+
+/// Length `data` decompresses to, without decompressing it.
+///
+/// Lets callers reject data expanding beyond what they are willing to allocate.
+pub fn decompressed_len(data: &[u8]) -> Result<usize, Error> {
+    let mut len = 0usize;
+    parse(data, |_, run| {
+        len = len.checked_add(run).ok_or(Error::Malformed)?;
+        Ok(())
+    })?;
+    Ok(len)
+}
+
+/// Decompresses data coded by [`compress`].
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = vec![];
+    decompress_to(data, &mut out)?;
+    Ok(out)
+}
+
+/// Like [`decompress`], appending to `out`. On error, `out` may hold part of the data.
+pub fn decompress_to(data: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    parse(data, |value, run| {
+        out.extend(std::iter::repeat_n(value, run));
+        Ok(())
+    })
+}
+
+/// Calls `f` with every byte of the coded data and the number of times it repeats.
+fn parse(data: &[u8], mut f: impl FnMut(u8, usize) -> Result<(), Error>) -> Result<(), Error> {
+    let (&escape, mut data) = data.split_first().ok_or(Error::UnexpectedEof)?;
+    while let Some((&byte, rest)) = data.split_first() {
+        data = rest;
+        if byte != escape {
+            f(byte, 1)?;
+            continue;
+        }
+        let run;
+        (run, data) = read_varint(data)?;
+        if run == 0 {
+            f(escape, 1)?;
+            continue;
+        }
+        let (&value, rest) = data.split_first().ok_or(Error::UnexpectedEof)?;
+        data = rest;
+        f(value, run.checked_add(1).ok_or(Error::Malformed)?)?;
+    }
+    Ok(())
+}
+
+fn read_varint(data: &[u8]) -> Result<(usize, &[u8]), Error> {
+    varint::read_usize(data).map_err(|err| match err {
+        varint::Error::UnexpectedEof => Error::UnexpectedEof,
+        varint::Error::Overflow => Error::Malformed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn layout() {
+        // Every byte occurs, 0 least often, so it is the escape.
+        let mut data = Vec::from_iter(0..=255);
+        data.extend(b"aaaaa");
+        let mut expected = vec![0, 0, 0];
+        expected.extend(1..=255);
+        expected.extend([0, 4, b'a']);
+        assert_eq!(compress(&data), expected);
+        // Even short runs of the escape are coded as runs.
+        let mut data = vec![0, 0];
+        data.extend((1..=255).flat_map(|byte| [byte, byte]));
+        let mut expected = vec![0, 0, 1, 0];
+        expected.extend(&data[2..]);
+        assert_eq!(compress(&data), expected);
+        // Runs shorter than MIN_RUN are left alone.
+        assert_eq!(compress(b"aaabb"), [0, b'a', b'a', b'a', b'b', b'b']);
+        assert_eq!(compress(b""), [0]);
+        let long = vec![7; 1000];
+        assert_eq!(compress(&long), [0, 0, 0xE7, 0x07, 7]);
+        assert_eq!(decompressed_len(&compress(&long)), Ok(1000));
+    }
+    #[test]
+    fn corrupt() {
+        assert_eq!(decompress(b""), Err(Error::UnexpectedEof));
+        assert_eq!(decompress(&[0, b'a', 0]), Err(Error::UnexpectedEof));
+        assert_eq!(decompress(&[0, 0, 4]), Err(Error::UnexpectedEof));
+        assert_eq!(decompress(&[0, 0, 0x80]), Err(Error::UnexpectedEof));
+        let mut huge = vec![0, 0];
+        huge.extend_from_slice(postcard::to_slice(&usize::MAX, &mut [0; 10]).unwrap());
+        huge.push(1);
+        assert_eq!(decompressed_len(&huge), Err(Error::Malformed));
+    }
+    #[quickcheck]
+    fn roundtrip(runs: Vec<(u8, u8)>) {
+        let data = Vec::from_iter(
+            runs.iter()
+                .flat_map(|&(value, len)| std::iter::repeat_n(value % 4, len as usize % 9)),
+        );
+        let compressed = compress(&data);
+        assert!(compressed.len() <= data.len() + 1);
+        assert_eq!(decompressed_len(&compressed), Ok(data.len()));
+        assert_eq!(decompress(&compressed).unwrap(), data);
+    }
+}
@@ -1,27 +1,174 @@
 use std::{
-    collections::HashMap,
-    hash::{BuildHasher, Hash, RandomState},
+    cell::Cell,
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
     iter,
     ops::{Index, Range},
 };
 
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
-use crate::Slide;
+use crate::{
+    Slide,
+    lz::{HISTOGRAM_BUCKETS, bucket},
+    util::{BuildSlideHasher, SliceExt},
+};
+
+/// Integer a [`SearchBuffer`] stores positions as, in its hash chains and its map of heads.
+///
+/// Narrower integers take less memory per value of the window, but limit its length to
+/// [`SearchBuffer::MAX_LEN`]. Stored positions are rebased whenever they would overflow, which
+/// is rare as long as the window is well below that.
+pub trait Position: Copy + Default + Eq + Debug {
+    /// Largest position this can hold.
+    const MAX: usize;
+    /// Converts `position`, which must not exceed [`MAX`](Self::MAX).
+    fn from_usize(position: usize) -> Self;
+    fn to_usize(self) -> usize;
+}
+macro_rules! impl_position {
+    ($($ty:ty),*) => {$(
+        impl Position for $ty {
+            const MAX: usize = if size_of::<$ty>() < size_of::<usize>() {
+                <$ty>::MAX as usize
+            } else {
+                usize::MAX
+            };
+            fn from_usize(position: usize) -> Self {
+                debug_assert!(position <= <Self as Position>::MAX);
+                position as $ty
+            }
+            fn to_usize(self) -> usize {
+                self as usize
+            }
+        }
+    )*};
+}
+impl_position!(u16, u32, u64, usize);
 
-pub struct SearchBuffer<T, const N: usize, S = RandomState> {
+/// Builds the map from every `N` values in a [`SearchBuffer`] to the latest position they start at.
+///
+/// Every [`BuildHasher`] builds a [`HashMap`], for values that are [`Hash`]. [`Ordered`] builds a
+/// [`BTreeMap`] instead, for values that are only [`Ord`].
+pub trait BuildHeads<K, P: Position = usize> {
+    type Heads;
+    fn build_heads(self) -> Self::Heads;
+    /// Sets the latest position of `key`, returning the previous one.
+    fn insert(heads: &mut Self::Heads, key: K, position: P) -> Option<P>;
+    fn get(heads: &Self::Heads, key: &K) -> Option<P>;
+    /// Number of keys with a latest position.
+    fn len(heads: &Self::Heads) -> usize;
+    /// Moves every position `by` back, dropping the keys whose position would drop below 1.
+    fn rebase(heads: &mut Self::Heads, by: usize);
+}
+impl<K: Hash + Eq, P: Position, S: BuildHasher> BuildHeads<K, P> for S {
+    type Heads = HashMap<K, P, S>;
+    fn build_heads(self) -> Self::Heads {
+        HashMap::with_hasher(self)
+    }
+    fn insert(heads: &mut Self::Heads, key: K, position: P) -> Option<P> {
+        heads.insert(key, position)
+    }
+    fn get(heads: &Self::Heads, key: &K) -> Option<P> {
+        heads.get(key).copied()
+    }
+    fn len(heads: &Self::Heads) -> usize {
+        heads.len()
+    }
+    fn rebase(heads: &mut Self::Heads, by: usize) {
+        heads.retain(|_, position| rebase(position, by));
+    }
+}
+/// [`BuildHeads`] keying matches by [`Ord`], for tokens that can't be hashed.
+///
+/// Lookups take `O(log n)` comparisons of `N` values instead of a single hash.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Ordered;
+impl<K: Ord, P: Position> BuildHeads<K, P> for Ordered {
+    type Heads = BTreeMap<K, P>;
+    fn build_heads(self) -> Self::Heads {
+        BTreeMap::new()
+    }
+    fn insert(heads: &mut Self::Heads, key: K, position: P) -> Option<P> {
+        heads.insert(key, position)
+    }
+    fn get(heads: &Self::Heads, key: &K) -> Option<P> {
+        heads.get(key).copied()
+    }
+    fn len(heads: &Self::Heads) -> usize {
+        heads.len()
+    }
+    fn rebase(heads: &mut Self::Heads, by: usize) {
+        heads.retain(|_, position| rebase(position, by));
+    }
+}
+
+/// Moves `position` `by` back, returning whether it still is a position, 0 being none.
+fn rebase<P: Position>(position: &mut P, by: usize) -> bool {
+    *position = P::from_usize(position.to_usize().saturating_sub(by));
+    position.to_usize() > 0
+}
+
+/// End of the window of a [`SearchBuffer`], to continue it elsewhere, see
+/// [`SearchBuffer::export`].
+///
+/// Hash chains only link positions within the window, so the values rebuild them exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowExport<T> {
+    /// Position of the first value.
+    pub start: usize,
+    pub values: Vec<T>,
+}
+
+pub struct SearchBuffer<
+    T,
+    const N: usize,
+    S: BuildHeads<[T; N], P> = BuildSlideHasher,
+    P: Position = usize,
+> {
     values: Slide<T>,
-    offsets: Slide<usize>,
-    heads: HashMap<[T; N], usize, S>,
+    offsets: Slide<P>,
+    heads: S::Heads,
+    /// Stored position of the first value in the window.
     offset: usize,
+    /// Sum of all rebases, which stored positions are behind the actual ones.
+    rebased: usize,
+    searches: Cell<usize>,
+    chain_steps: Cell<usize>,
+    /// Searches by the bit length of the hash chain entries they visited.
+    chain_depths: [Cell<usize>; HISTOGRAM_BUCKETS],
+    /// Most hash chain entries visited per search.
+    max_chain: usize,
+    /// Whether matches may reach into the values they are compared to.
+    overlap: bool,
+}
+/// Shows the window and counters, see [`SearchBuffer::dump_chains`] for the hash chains.
+impl<T: Copy + Eq + Debug, const N: usize, S: BuildHeads<[T; N], P>, P: Position> Debug
+    for SearchBuffer<T, N, S, P>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max_chain = (self.max_chain != usize::MAX).then_some(self.max_chain);
+        f.debug_struct("SearchBuffer")
+            .field("range", &self.range())
+            .field("values", &&*self.values)
+            .field("heads", &self.head_count())
+            .field("searches", &self.searches())
+            .field("chain_steps", &self.chain_steps())
+            .field("max_chain", &max_chain)
+            .finish()
+    }
 }
-impl<T, const N: usize, S: Default> Default for SearchBuffer<T, N, S> {
+impl<T, const N: usize, S: BuildHeads<[T; N], P> + Default, P: Position> Default
+    for SearchBuffer<T, N, S, P>
+{
     fn default() -> Self {
         Self::with_hasher(S::default())
     }
 }
-impl<T: Copy + Eq + Hash, const N: usize, S: Default + BuildHasher> FromIterator<T>
-    for SearchBuffer<T, N, S>
+impl<T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P> + Default, P: Position> FromIterator<T>
+    for SearchBuffer<T, N, S, P>
 {
     fn from_iter<Iter: IntoIterator<Item = T>>(iter: Iter) -> Self {
         let mut ret = Self::default();
@@ -29,13 +176,19 @@ impl<T: Copy + Eq + Hash, const N: usize, S: Default + BuildHasher> FromIterator
         ret
     }
 }
-impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> Extend<T> for SearchBuffer<T, N, S> {
+impl<T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position> Extend<T>
+    for SearchBuffer<T, N, S, P>
+{
     fn extend<Iter: IntoIterator<Item = T>>(&mut self, iter: Iter) {
         self.values.extend(iter);
         self.extend_offsets();
     }
 }
-impl<T, const N: usize, S> SearchBuffer<T, N, S> {
+impl<T, const N: usize, S: BuildHeads<[T; N], P>, P: Position> SearchBuffer<T, N, S, P> {
+    /// Longest window positions of `P` can address.
+    ///
+    /// A window that long or close to it rebases often, so keep the window well below it.
+    pub const MAX_LEN: usize = P::MAX - 1;
     pub fn new() -> Self
     where
         S: Default,
@@ -46,12 +199,18 @@ impl<T, const N: usize, S> SearchBuffer<T, N, S> {
         Self {
             values: Default::default(),
             offsets: Default::default(),
-            heads: HashMap::with_hasher(hash_builder),
+            heads: hash_builder.build_heads(),
             offset: 1,
+            rebased: 0,
+            searches: Cell::new(0),
+            chain_steps: Cell::new(0),
+            chain_depths: [const { Cell::new(0) }; HISTOGRAM_BUCKETS],
+            max_chain: usize::MAX,
+            overlap: true,
         }
     }
 }
-impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S> {
+impl<T: Copy + Eq, const N: usize, S: BuildHeads<[T; N], P>, P: Position> SearchBuffer<T, N, S, P> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -63,7 +222,7 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
         self.values.len()
     }
     pub fn start(&self) -> usize {
-        self.offset - 1
+        self.offset + self.rebased - 1
     }
     pub fn end(&self) -> usize {
         self.start() + self.len()
@@ -77,7 +236,8 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
     }
     pub fn pop(&mut self) -> Option<T> {
         self.values.pop().inspect(|_| {
-            self.offsets.pop().unwrap();
+            // Windows shorter than N have no offsets yet.
+            self.offsets.pop();
             self.offset += 1
         })
     }
@@ -138,7 +298,28 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
         }
         self.slide_from_within(index)
     }
+    /// Moves all stored positions back to start the window at 1 again if the values not yet in
+    /// the hash chains would overflow `P`.
+    fn rebase(&mut self) {
+        if self.offset + self.values.len() <= P::MAX {
+            return;
+        }
+        assert!(
+            self.values.len() <= Self::MAX_LEN,
+            "The window ({}) is longer than the positions of the SearchBuffer can address ({}).",
+            self.values.len(),
+            Self::MAX_LEN
+        );
+        let by = self.offset - 1;
+        for position in self.offsets.iter_mut() {
+            rebase(position, by);
+        }
+        S::rebase(&mut self.heads, by);
+        self.offset = 1;
+        self.rebased += by;
+    }
     fn extend_offsets(&mut self) {
+        self.rebase();
         let mut base = self.offsets.len();
         let bases = SmallVec::<[_; 256]>::from_iter(iter::from_fn(|| {
             if base < self.values.len()
@@ -152,8 +333,7 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
             }
         }));
         let offsets = SmallVec::<[_; 256]>::from_iter(bases.into_iter().map(|(window, base)| {
-            self.heads
-                .insert(window, base + self.offset)
+            S::insert(&mut self.heads, window, P::from_usize(base + self.offset))
                 .unwrap_or_default()
         }));
         self.offsets.extend(offsets);
@@ -190,10 +370,16 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
         } else {
             0
         };
+        // Without overlap, matches end with the window.
+        let end = match self.overlap {
+            true => usize::MAX,
+            false => self.values.len(),
+        };
         // If check at min_len doesn't exist or doesn't match, candidate must be shorter.
         // We can therefore disregard it without a full count.
-        if check((base + min_len, min_len))
-            && let len = count((base + skip..usize::MAX, skip)) + skip
+        if base + min_len < end
+            && check((base + min_len, min_len))
+            && let len = count((base + skip..end, skip)) + skip
             && len > min_len
         {
             let start = base + self.start();
@@ -203,6 +389,54 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
         }
     }
 
+    /// Number of match searches run so far.
+    pub fn searches(&self) -> usize {
+        self.searches.get()
+    }
+    /// Number of hash chain entries visited by all searches so far.
+    pub fn chain_steps(&self) -> usize {
+        self.chain_steps.get()
+    }
+    /// Searches so far by the number of hash chain entries each visited, bucketed like the
+    /// histograms of [`Stats`](crate::lz::Stats).
+    pub fn chain_depth_histogram(&self) -> [usize; HISTOGRAM_BUCKETS] {
+        self.chain_depths.each_ref().map(Cell::get)
+    }
+    /// Number of distinct `N` values the map from values to their latest position holds.
+    ///
+    /// Values are only removed once they left the window and positions are rebased, so with
+    /// positions of `usize` this only grows.
+    pub fn head_count(&self) -> usize {
+        S::len(&self.heads)
+    }
+    /// Limits every search to the first `max_chain` hash chain entries, or none if None, as in
+    /// [`Config::max_chain`](crate::lz::Config::max_chain).
+    pub fn set_max_chain(&mut self, max_chain: Option<usize>) {
+        self.max_chain = max_chain.unwrap_or(usize::MAX);
+    }
+    /// Lets matches reach into the values they are compared to, or else keeps them no longer
+    /// than their distance, as in [`Config::forbid_overlap`](crate::lz::Config::forbid_overlap).
+    pub fn set_overlap(&mut self, overlap: bool) {
+        self.overlap = overlap;
+    }
+    pub fn overlap(&self) -> bool {
+        self.overlap
+    }
+    /// Positions the hash chain of `key` reaches within the window, latest first: the
+    /// candidates a search for values starting with `key` visits, `max_chain` aside.
+    ///
+    /// A match starting anywhere else can't be found, except within the last `N - 1` positions,
+    /// whose keys aren't complete yet. Not counted as a search.
+    pub fn dump_chains(&self, key: &[T; N]) -> Vec<usize> {
+        let mut positions = vec![];
+        let mut next =
+            S::get(&self.heads, key).and_then(|head| head.to_usize().checked_sub(self.offset));
+        while let Some(index) = next {
+            positions.push(self.start() + index);
+            next = self.offsets[index].to_usize().checked_sub(self.offset);
+        }
+        positions
+    }
     pub fn find_longest_match(&self, arr: &[T]) -> Option<Range<usize>> {
         self.find_longest_match_by(arr, |_max, _candidate| Ok(false))
     }
@@ -215,21 +449,25 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
         if N >= arr.len() {
             return None;
         }
+        self.searches.set(self.searches.get() + 1);
+        let mut depth = 0;
         let mut max = (self.len().saturating_sub(N)..self.len())
-            .into_iter()
             .flat_map(|base| self.get_match::<false>(base, arr, N))
             .max_by_key(Range::len);
         'ret: {
             let Some(mut next) = arr
                 .first_chunk::<N>()
-                .and_then(|head| self.heads.get(head))
-                .and_then(|next| next.checked_sub(self.offset))
+                .and_then(|head| S::get(&self.heads, head))
+                .and_then(|next| next.to_usize().checked_sub(self.offset))
             else {
                 break 'ret;
             };
             while let max_len = max.as_ref().map(Range::len).unwrap_or_default()
                 && max_len < arr.len()
+                && depth < self.max_chain
             {
+                self.chain_steps.set(self.chain_steps.get() + 1);
+                depth += 1;
                 if let Some(candidate) = self.get_match::<true>(next, arr, max_len) {
                     match predicate(max.clone(), candidate.clone()) {
                         Ok(done) => {
@@ -245,12 +483,14 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
                         }
                     }
                 }
-                let Some(_next) = self.offsets[next].checked_sub(self.offset) else {
+                let Some(_next) = self.offsets[next].to_usize().checked_sub(self.offset) else {
                     break 'ret;
                 };
                 next = _next;
             }
         }
+        let searches = &self.chain_depths[bucket(depth)];
+        searches.set(searches.get() + 1);
         debug_assert!(max.as_ref().map(Range::len).unwrap_or_default() <= arr.len());
         max
     }
@@ -285,18 +525,49 @@ impl<T: Copy + Eq + Hash, const N: usize, S: BuildHasher> SearchBuffer<T, N, S>
     pub fn to_values(self) -> Box<[T]> {
         self.values.to_vec().into_boxed_slice()
     }
+    /// Exports up to the last `len` values of the window with their positions, for
+    /// [`import`](Self::import) to continue from.
+    pub fn export(&self, len: usize) -> WindowExport<T> {
+        let values = self.values.get_clamped(self.len().saturating_sub(len)..);
+        WindowExport {
+            start: self.end() - values.len(),
+            values: values.to_vec(),
+        }
+    }
+    /// SearchBuffer continuing from `export`, finding the same matches within its values as the
+    /// one exported and at the same positions.
+    ///
+    /// Panics if the values don't fit positions of `P`.
+    pub fn import(export: &WindowExport<T>) -> Self
+    where
+        S: Default,
+    {
+        Self::import_with_hasher(export, S::default())
+    }
+    /// Like [`import`](Self::import), with the given `hash_builder`.
+    pub fn import_with_hasher(export: &WindowExport<T>, hash_builder: S) -> Self {
+        let mut ret = Self::with_hasher(hash_builder);
+        ret.rebased = export.start;
+        ret.extend(export.values.iter().copied());
+        ret
+    }
 }
 
-impl<T, const N: usize, S> Index<usize> for SearchBuffer<T, N, S> {
+impl<T, const N: usize, S: BuildHeads<[T; N], P>, P: Position> Index<usize>
+    for SearchBuffer<T, N, S, P>
+{
     type Output = T;
     fn index(&self, index: usize) -> &Self::Output {
-        &self.values[index + 1 - self.offset]
+        &self.values[index + 1 - self.offset - self.rebased]
     }
 }
-impl<T, const N: usize, S> Index<Range<usize>> for SearchBuffer<T, N, S> {
+impl<T, const N: usize, S: BuildHeads<[T; N], P>, P: Position> Index<Range<usize>>
+    for SearchBuffer<T, N, S, P>
+{
     type Output = [T];
     fn index(&self, index: Range<usize>) -> &Self::Output {
-        &self.values[index.start + 1 - self.offset..index.end + 1 - self.offset]
+        let start = self.offset + self.rebased - 1;
+        &self.values[index.start - start..index.end - start]
     }
 }
 #[cfg(test)]
@@ -369,4 +640,130 @@ mod tests {
         );
         assert_eq!(sb.find_longest_match(&['d', 'd', 'd', 'd']), Some(6..10));
     }
+
+    #[test]
+    fn dump_chains() {
+        let mut sb: SearchBuffer<char, 2> =
+            SearchBuffer::from_iter(['a', 'b', 'c', 'a', 'b', 'a', 'b']);
+        assert_eq!(sb.dump_chains(&['a', 'b']), [5, 3, 0]);
+        assert_eq!(sb.dump_chains(&['c', 'a']), [2]);
+        assert_eq!(sb.dump_chains(&['x', 'y']), []);
+        sb.drain(2).for_each(drop);
+        assert_eq!(sb.dump_chains(&['a', 'b']), [5, 3]);
+        assert_eq!(sb.searches(), 0);
+        sb.set_max_chain(Some(8));
+        let debug = format!("{sb:?}");
+        assert!(
+            debug.starts_with("SearchBuffer { range: 2..7, values: ['c', 'a', 'b', 'a', 'b'],")
+        );
+        assert!(debug.ends_with("max_chain: Some(8) }"));
+    }
+    #[test]
+    fn ordered() {
+        use crate::{
+            Slide,
+            lz::{Config, Strategy},
+        };
+        // Only Ord, like floats ordered by total_cmp.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        struct Token(u8);
+
+        let data =
+            Vec::from_iter((0..0x1000u32).map(|x| Token((x / 3 % 17) as u8 ^ (x >> 9) as u8)));
+        let config = Config {
+            max_buffer_len: 0x400,
+            match_lengths: 2..0x40,
+            strategy: Strategy::Lazy(1),
+            ..Config::default()
+        };
+        let items = Vec::from_iter(
+            SearchBuffer::<_, 2, Ordered>::new().to_items(data.iter().copied(), config.clone()),
+        );
+        let bytes = Vec::from_iter(data.iter().map(|token| token.0));
+        let hashed = Vec::from_iter(SearchBuffer::<_, 2>::new().to_items(
+            bytes.iter().copied(),
+            Config {
+                max_buffer_len: config.max_buffer_len,
+                match_lengths: config.match_lengths.clone(),
+                strategy: config.strategy,
+                ..Config::default()
+            },
+        ));
+        assert_eq!(items.len(), hashed.len());
+        let decoded = Vec::from_iter(Slide::new().from_items(items, config));
+        assert_eq!(decoded, data);
+    }
+    #[test]
+    fn export() {
+        let data = Vec::from_iter((0..0x3000u32).map(|x| (x / 3 % 11) as u8 ^ (x >> 10) as u8));
+        let (head, tail) = data.split_at(0x2000);
+        let mut sb = SearchBuffer::<u8, 3>::new();
+        sb.extend_slide(head.iter().copied(), 0x1000).for_each(drop);
+        let export = sb.export(0x1000);
+        assert_eq!(export.start, 0x1000);
+        assert_eq!(export.values, &head[0x1000..]);
+        assert_eq!(sb.export(0x10).start, 0x1FF0);
+        assert_eq!(sb.export(usize::MAX), export);
+
+        let bytes = postcard::to_allocvec(&export).unwrap();
+        let export = postcard::from_bytes::<WindowExport<u8>>(&bytes).unwrap();
+        let mut imported = SearchBuffer::<u8, 3>::import(&export);
+        let mut narrow = SearchBuffer::<u8, 3, BuildSlideHasher, u16>::import(&export);
+        assert_eq!(imported.range(), sb.range());
+        for (index, &value) in tail.iter().enumerate() {
+            let arr = tail.get_clamped(index..index + 0x20);
+            let expected = sb.find_longest_match(arr);
+            assert_eq!(imported.find_longest_match(arr), expected);
+            assert_eq!(narrow.find_longest_match(arr), expected);
+            for sb in [&mut sb, &mut imported] {
+                sb.push_step(value, 0x1000);
+            }
+            narrow.push_step(value, 0x1000);
+        }
+    }
+    #[test]
+    fn positions() {
+        use crate::{
+            Error, Slide,
+            lz::{Config, ConfigError, Encoder},
+        };
+        type Narrow = SearchBuffer<u8, 3, BuildSlideHasher, u16>;
+
+        // Far more values than positions of u16 address, so they are rebased again and again.
+        let data = Vec::from_iter((0..0x40000u32).map(|x| (x / 5 % 13) as u8 ^ (x >> 11) as u8));
+        let mut wide = SearchBuffer::<u8, 3>::new();
+        let mut narrow = Narrow::new();
+        for (index, &value) in data.iter().enumerate() {
+            wide.push_step(value, 0x1000);
+            narrow.push_step(value, 0x1000);
+            if index % 7 == 0 {
+                let arr = data.get_clamped(index + 1..index + 0x20);
+                assert_eq!(narrow.find_longest_match(arr), wide.find_longest_match(arr));
+            }
+        }
+        assert_eq!(narrow.range(), wide.range());
+        assert_eq!(narrow.dump_chains(&[0, 0, 0]), wide.dump_chains(&[0, 0, 0]));
+
+        let config = Config {
+            max_buffer_len: 0x1000,
+            match_lengths: 3..0x100,
+            ..Config::default()
+        };
+        let items = Vec::from_iter(Narrow::new().to_items(data.iter().copied(), config.clone()));
+        assert_eq!(
+            Vec::from_iter(Slide::new().from_items(items, config.clone())),
+            data
+        );
+        assert_eq!(Narrow::MAX_LEN, 0xFFFE);
+        assert!(matches!(
+            Encoder::<u8, 3, BuildSlideHasher, u16>::new(Config {
+                max_buffer_len: 0x10000,
+                ..config
+            }),
+            Err(Error::Config(ConfigError::WindowTooLong {
+                max_buffer_len: 0x10000,
+                max: 0xFFFE
+            }))
+        ));
+    }
 }
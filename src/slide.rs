@@ -1,10 +1,12 @@
 use std::{
-    mem::{MaybeUninit, replace, transmute},
+    collections::VecDeque,
+    io::{self, Read, Write},
+    mem::{ManuallyDrop, MaybeUninit, replace, take, transmute},
     ops::{Deref, DerefMut, Range},
+    ptr,
+    sync::Arc,
 };
 
-use smallvec::SmallVec;
-
 pub struct Slide<T> {
     data: Box<[MaybeUninit<T>]>,
     start: usize,
@@ -37,6 +39,56 @@ impl<T> FromIterator<T> for Slide<T> {
         ret
     }
 }
+/// Takes over the allocation of `vec`, its spare capacity becoming tail capacity.
+impl<T> From<Vec<T>> for Slide<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let mut vec = ManuallyDrop::new(vec);
+        let (len, capacity) = (vec.len(), vec.capacity());
+        // Safety: MaybeUninit<T> has the layout of T, and any of it may be uninitialized.
+        let data = unsafe {
+            Vec::from_raw_parts(
+                vec.as_mut_ptr().cast::<MaybeUninit<T>>(),
+                capacity,
+                capacity,
+            )
+        };
+        Self {
+            data: data.into_boxed_slice(),
+            start: 0,
+            end: len,
+        }
+    }
+}
+/// Goes through a [`Vec`], so the allocation is kept.
+impl<T> From<VecDeque<T>> for Slide<T> {
+    fn from(deque: VecDeque<T>) -> Self {
+        Vec::from(deque).into()
+    }
+}
+/// Takes over the allocation of `slide`, moving its values to the front if values were removed
+/// from it.
+impl<T> From<Slide<T>> for Vec<T> {
+    fn from(mut slide: Slide<T>) -> Self {
+        let (start, len) = (slide.start, slide.len());
+        let mut data = ManuallyDrop::new(take(&mut slide.data).into_vec());
+        // Leaves nothing for the Slide to drop.
+        slide.start = 0;
+        slide.end = 0;
+        let values = data.as_mut_ptr().cast::<T>();
+        // Safety: All values start..end are valid and initialized, and are moved to 0..len,
+        // which ptr::copy allows to overlap. The rest is spare capacity.
+        unsafe {
+            ptr::copy(values.add(start), values, len);
+            Vec::from_raw_parts(values, len, data.capacity())
+        }
+    }
+}
+/// Goes through a [`Vec`], so the allocation is kept.
+impl<T> From<Slide<T>> for VecDeque<T> {
+    fn from(slide: Slide<T>) -> Self {
+        Vec::from(slide).into()
+    }
+}
 impl<T> Slide<T> {
     pub fn new() -> Self {
         Self::default()
@@ -73,6 +125,25 @@ impl<T> Slide<T> {
             unsafe { Some(replace(&mut self.data[idx], MaybeUninit::uninit()).assume_init()) }
         }
     }
+    /// First value, the one [`pop`](Self::pop) removes next.
+    pub fn front(&self) -> Option<&T> {
+        self.first()
+    }
+    /// Last value, the one [`push`](Self::push) added last.
+    pub fn back(&self) -> Option<&T> {
+        self.last()
+    }
+    /// Up to the first `n` values, the ones [`pop_n`](Self::pop_n) would remove.
+    pub fn peek_n(&self, n: usize) -> &[T] {
+        &self[..n.min(self.len())]
+    }
+    /// Removes up to the first `n` values, returning them in order.
+    pub fn pop_n(
+        &mut self,
+        n: usize,
+    ) -> impl ExactSizeIterator<Item = T> + DoubleEndedIterator<Item = T> {
+        self.drain(0..n.min(self.len()))
+    }
     pub fn step(&mut self, val: T) -> T {
         if let Some(ret) = self.pop() {
             self.push(val);
@@ -125,7 +196,7 @@ impl<T> Slide<T> {
             range = self.end - range.len()..self.end;
             self.end = range.start;
         }
-        if self.len() == 0 {
+        if self.is_empty() {
             self.start = 0;
             self.end = 0;
         }
@@ -137,6 +208,84 @@ impl<T> Slide<T> {
     pub fn slide(&mut self, iter: impl IntoIterator<Item = T>) -> impl Iterator<Item = T> {
         iter.into_iter().map(|val| self.step(val))
     }
+    /// Copies the window into an immutable snapshot, which can be shared with other threads
+    /// while this keeps changing.
+    pub fn freeze(&self) -> Arc<[T]>
+    where
+        T: Clone,
+    {
+        Arc::from(&**self)
+    }
+    /// Makes room for at least `additional` more values in the tail capacity.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.tail_capacity() < additional {
+            self.ensure_capacity(self.len() + additional);
+        }
+    }
+    /// The tail capacity, for writing values in place before adding them with
+    /// [`set_len`](Self::set_len).
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        &mut self.data[self.end..]
+    }
+    /// Sets the number of values, adding or forgetting values at the end, like [`Vec::set_len`].
+    ///
+    /// # Safety
+    ///
+    /// `len` must not exceed the length plus tail capacity, and values added must have been
+    /// initialized through [`spare_capacity_mut`](Self::spare_capacity_mut).
+    pub unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(self.start + len <= self.capacity());
+        self.end = self.start + len;
+    }
+    /// Moves the values to the start of the allocation, so all spare capacity becomes tail
+    /// capacity. Takes `O(len)`, which pushing otherwise spends only once the tail is full.
+    pub fn compact(&mut self) {
+        let len = self.len();
+        for x in 0..len {
+            self.data[x] = replace(&mut self.data[self.start + x], MaybeUninit::uninit());
+        }
+        self.start = 0;
+        self.end = len;
+    }
+    /// Same as `rotate_left(n)` on the values, but moves only the first `n` values, past the
+    /// end, if the tail capacity allows.
+    ///
+    /// Panics if `n` is greater than the length.
+    pub fn rotate_left_positions(&mut self, n: usize) {
+        assert!(
+            n <= self.len(),
+            "Cannot rotate a Slide of {} by {n}.",
+            self.len()
+        );
+        if self.tail_capacity() < n {
+            self.ensure_capacity(self.len() + n);
+        }
+        let (values, tail) = self.data.split_at_mut(self.end);
+        tail[..n].swap_with_slice(&mut values[self.start..self.start + n]);
+        self.start += n;
+        self.end += n;
+    }
+    /// Same as `rotate_right(n)` on the values, but moves only the last `n` values, before the
+    /// start, if values were removed from the front for them to fit. Otherwise all values are
+    /// moved.
+    ///
+    /// Panics if `n` is greater than the length.
+    pub fn rotate_right_positions(&mut self, n: usize) {
+        assert!(
+            n <= self.len(),
+            "Cannot rotate a Slide of {} by {n}.",
+            self.len()
+        );
+        if self.start < n {
+            self.deref_mut().rotate_right(n);
+            return;
+        }
+        let len = self.len();
+        let (head, values) = self.data.split_at_mut(self.start);
+        head[self.start - n..].swap_with_slice(&mut values[len - n..len]);
+        self.start -= n;
+        self.end -= n;
+    }
     fn ensure_capacity(&mut self, new_capacity: usize) {
         let len = self.len();
         let new_capacity = new_capacity.max(len);
@@ -152,15 +301,15 @@ impl<T> Slide<T> {
                         .into_boxed_slice()
                 });
                 self.data[..len].swap_with_slice(&mut old[self.start..self.end]);
+                self.start = 0;
+                self.end = len;
             } else {
-                for x in 0..len {
-                    self.data[x] = replace(&mut self.data[self.start + x], MaybeUninit::uninit());
-                }
+                self.compact();
             }
-            self.start = 0;
-            self.end = len;
         }
     }
+    /// Appends the values in `index`, which may extend past the end of the window, in which
+    /// case the values appended first are repeated, like a LZ77 copy.
     pub fn extend_from_within(&mut self, mut index: Range<usize>)
     where
         T: Copy,
@@ -170,13 +319,50 @@ impl<T> Slide<T> {
             "The value of index.start ({index:?}) is out of bounds of the Slide ({len:?})",
             len = self.len()
         );
+        self.ensure_capacity(self.len() + index.len());
         while !index.is_empty() {
-            let _index = index.start..index.end.min(self.len());
-            index.end -= _index.len();
-            self.extend(SmallVec::<[_; 256]>::from_iter(
-                self[_index].iter().copied(),
-            ));
+            let len = index.len().min(self.len() - index.start);
+            let source = self.start + index.start;
+            self.data.copy_within(source..source + len, self.end);
+            self.end += len;
+            index.start += len;
+        }
+    }
+}
+impl Slide<u8> {
+    /// Appends up to `len` bytes read from `reader` straight into the tail capacity, growing it
+    /// first if needed, and returns the number of bytes appended.
+    ///
+    /// Calls [`Read::read`] once, so 0 means the reader ended, or `len` was 0.
+    pub fn fill_from_reader(&mut self, reader: &mut impl Read, len: usize) -> io::Result<usize> {
+        self.reserve(len);
+        let tail = &mut self.spare_capacity_mut()[..len];
+        tail.fill(MaybeUninit::new(0));
+        // Safety: The tail was just initialized.
+        let tail: &mut [u8] = unsafe { transmute(tail) };
+        let read = reader.read(tail)?;
+        assert!(
+            read <= len,
+            "Read::read returned more bytes than it was given room for."
+        );
+        // Safety: The bytes read are initialized.
+        unsafe { self.set_len(self.len() + read) };
+        Ok(read)
+    }
+}
+/// Appends everything written, never failing.
+impl Write for Slide<u8> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.reserve(buf.len());
+        for (slot, &byte) in self.spare_capacity_mut().iter_mut().zip(buf) {
+            slot.write(byte);
         }
+        // Safety: The bytes of `buf` were just written to the tail.
+        unsafe { self.set_len(self.len() + buf.len()) };
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 impl<T> Extend<T> for Slide<T> {
@@ -271,6 +457,36 @@ pub(crate) mod tests {
         assert_eq!(slide.pop(), Some(240));
     }
     #[test]
+    fn peek_pop_n() {
+        let mut slide = Slide::from_iter([42, 24, 4, 20, 240]);
+        assert_eq!(slide.front(), Some(&42));
+        assert_eq!(slide.back(), Some(&240));
+        assert_eq!(slide.peek_n(2), [42, 24]);
+        assert_eq!(slide.peek_n(9), [42, 24, 4, 20, 240]);
+        let popped = slide.pop_n(2);
+        assert_eq!(popped.len(), 2);
+        assert_eq!(Vec::from_iter(popped), [42, 24]);
+        assert_eq!(&*slide, &[4, 20, 240]);
+        assert_eq!(Vec::from_iter(slide.pop_n(9).rev()), [240, 20, 4]);
+        assert_eq!(slide.front(), None);
+        assert_eq!(slide.back(), None);
+        assert!(slide.peek_n(1).is_empty());
+        assert_eq!(slide.pop_n(1).len(), 0);
+    }
+    #[test]
+    fn freeze() {
+        let mut slide = Slide::from_iter(0..0x100);
+        let frozen = slide.freeze();
+        let reader = std::thread::spawn({
+            let frozen = frozen.clone();
+            move || frozen.iter().sum::<i32>()
+        });
+        slide.slide(0x100..0x200).for_each(std::mem::drop);
+        assert_eq!(reader.join().unwrap(), (0..0x100).sum());
+        assert_eq!(*frozen, *Vec::from_iter(0..0x100));
+        assert_eq!(*slide.freeze(), *Vec::from_iter(0x100..0x200));
+    }
+    #[test]
     fn shrink() {
         let mut slide = Slide::from_iter(0..16);
         assert_eq!(slide.len(), 16);
@@ -293,6 +509,17 @@ pub(crate) mod tests {
         assert_eq!(slide.tail_capacity(), 2);
     }
     #[test]
+    fn extend_from_within() {
+        let mut slide = Slide::from_iter(*b"xabc");
+        slide.extend_from_within(1..3);
+        assert_eq!(&*slide, b"xabcab");
+        slide.extend_from_within(4..11);
+        assert_eq!(&*slide, b"xabcababababa");
+        slide.drain(0..10).count();
+        slide.extend_from_within(2..4);
+        assert_eq!(&*slide, b"abaaa");
+    }
+    #[test]
     fn drop() {
         struct Foo<'a>(&'a std::cell::RefCell<usize>);
         impl<'a> Drop for Foo<'a> {
@@ -304,6 +531,95 @@ pub(crate) mod tests {
         let _ = Slide::from_iter((0..128).map(|_| Foo(&count)));
         assert_eq!(*count.borrow(), 128);
     }
+    #[test]
+    fn rotate_positions() {
+        let mut slide = Slide::from_iter(0..6);
+        assert_eq!(slide.capacity(), 16);
+        slide.rotate_left_positions(2);
+        assert_eq!(&*slide, &[2, 3, 4, 5, 0, 1]);
+        assert_eq!((slide.start, slide.end), (2, 8));
+        slide.rotate_right_positions(1);
+        assert_eq!(&*slide, &[1, 2, 3, 4, 5, 0]);
+        assert_eq!((slide.start, slide.end), (1, 7));
+        slide.rotate_right_positions(3);
+        assert_eq!(&*slide, &[4, 5, 0, 1, 2, 3]);
+        slide.rotate_left_positions(6);
+        assert_eq!(&*slide, &[4, 5, 0, 1, 2, 3]);
+        assert_eq!(slide.tail_capacity(), 3);
+        slide.compact();
+        assert_eq!((slide.start, slide.end, slide.tail_capacity()), (0, 6, 10));
+        assert_eq!(&*slide, &[4, 5, 0, 1, 2, 3]);
+    }
+    #[quickcheck]
+    fn rotate_positions_fuzz(len: u8, rotations: Vec<(bool, u8)>) {
+        let mut slide = Slide::from_iter((0..len).map(|x| x.to_string()));
+        let mut expected = Vec::from_iter(slide.iter().cloned());
+        for (left, n) in rotations {
+            let n = n as usize % (expected.len() + 1);
+            if left {
+                slide.rotate_left_positions(n);
+                expected.rotate_left(n);
+            } else {
+                slide.rotate_right_positions(n);
+                expected.rotate_right(n);
+            }
+            assert_eq!(&*slide, &expected[..]);
+        }
+        slide.compact();
+        assert_eq!(Vec::from(slide), expected);
+    }
+    #[test]
+    fn fill_from_reader() {
+        let mut reader = &b"abcdefgh"[..];
+        let mut slide = Slide::from_iter(*b"xy");
+        slide.pop();
+        assert_eq!(slide.fill_from_reader(&mut reader, 3).unwrap(), 3);
+        assert_eq!(&*slide, b"yabc");
+        assert_eq!(slide.fill_from_reader(&mut reader, 0).unwrap(), 0);
+        assert_eq!(slide.fill_from_reader(&mut reader, 100).unwrap(), 5);
+        assert!(slide.tail_capacity() >= 95);
+        assert_eq!(slide.fill_from_reader(&mut reader, 100).unwrap(), 0);
+        assert_eq!(&*slide, b"yabcdefgh");
+    }
+    #[test]
+    fn write() {
+        let mut slide = Slide::from_iter(*b"xa");
+        slide.pop();
+        let c = 'c';
+        write!(slide, "b{c}").unwrap();
+        slide.write_all(b"def").unwrap();
+        assert_eq!(&*slide, b"abcdef");
+        slide.reserve(4);
+        let spare = slide.spare_capacity_mut();
+        assert!(spare.len() >= 4);
+        spare[0].write(b'g');
+        unsafe { slide.set_len(7) };
+        assert_eq!(&*slide, b"abcdefg");
+    }
+    #[test]
+    fn conversions() {
+        let mut vec = Vec::with_capacity(10);
+        vec.extend([1, 2, 3]);
+        let ptr = vec.as_ptr();
+        let mut slide = Slide::from(vec);
+        assert_eq!((&*slide, slide.capacity()), (&[1, 2, 3][..], 10));
+        slide.push(4);
+        assert_eq!(slide.pop(), Some(1));
+        let vec = Vec::from(slide);
+        assert_eq!(
+            (&*vec, vec.capacity(), vec.as_ptr()),
+            (&[2, 3, 4][..], 10, ptr)
+        );
+        let mut deque = VecDeque::from(Slide::from(vec));
+        deque.push_front(1);
+        let slide = Slide::from(deque);
+        assert_eq!(&*slide, &[1, 2, 3, 4]);
+        assert_eq!(VecDeque::from(slide), [1, 2, 3, 4]);
+        assert!(Vec::from(Slide::<String>::new()).is_empty());
+        let mut strings = Slide::from_iter(["a", "b", "c"].map(String::from));
+        strings.pop();
+        assert_eq!(Vec::from(strings), ["b", "c"]);
+    }
     #[quickcheck]
     fn fuzz(drain: Vec<Range<u8>>) {
         struct Foo<'a>(usize, &'a std::cell::RefCell<usize>);
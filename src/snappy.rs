@@ -0,0 +1,407 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    lz::{Config, Item, RepDistances},
+    search_buffer::SearchBuffer,
+    util::crc32c,
+};
+
+/// Largest amount of uncompressed data a single framing chunk may carry.
+pub const MAX_CHUNK_LEN: usize = 0x10000;
+const STREAM_IDENTIFIER: [u8; 10] = *b"\xff\x06\x00\x00sNaPpY";
+const CHUNK_COMPRESSED: u8 = 0x00;
+const CHUNK_UNCOMPRESSED: u8 = 0x01;
+const CHUNK_PADDING: u8 = 0xfe;
+const CHUNK_STREAM_IDENTIFIER: u8 = 0xff;
+
+const TAG_LITERAL: u8 = 0b00;
+const TAG_COPY_1: u8 = 0b01;
+const TAG_COPY_2: u8 = 0b10;
+const TAG_COPY_4: u8 = 0b11;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The input ended in the middle of a preamble, tag or chunk.
+    UnexpectedEof,
+    /// The uncompressed length preamble is malformed or does not fit in 32 bits.
+    InvalidPreamble,
+    /// A copy referenced data before the start of the output.
+    InvalidOffset { offset: usize, position: usize },
+    /// The decoded length differs from the one announced by the preamble.
+    LengthMismatch { expected: usize, actual: usize },
+    /// A framing chunk's masked CRC-32C does not match its contents.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The framed stream does not start with the `sNaPpY` stream identifier.
+    MissingStreamIdentifier,
+    /// A reserved chunk type that must not be skipped was encountered.
+    UnskippableChunk(u8),
+    /// A framing chunk is larger than allowed for its type.
+    ChunkTooLarge(usize),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => write!(f, "unexpected end of snappy input"),
+            Error::InvalidPreamble => write!(f, "invalid snappy length preamble"),
+            Error::InvalidOffset { offset, position } => {
+                write!(
+                    f,
+                    "copy offset {offset} out of bounds at output position {position}"
+                )
+            }
+            Error::LengthMismatch { expected, actual } => {
+                write!(f, "expected {expected} decompressed bytes, got {actual}")
+            }
+            Error::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "checksum mismatch: expected {expected:#010x}, got {actual:#010x}"
+                )
+            }
+            Error::MissingStreamIdentifier => write!(f, "missing snappy stream identifier"),
+            Error::UnskippableChunk(ty) => write!(f, "unskippable reserved chunk type {ty:#04x}"),
+            Error::ChunkTooLarge(len) => write!(f, "chunk of {len} bytes exceeds the limit"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// Worst-case size of [`compress`]ing `len` bytes, matching the reference implementation.
+pub fn max_compress_len(len: usize) -> usize {
+    32 + len + len / 6
+}
+
+/// Compresses `data` into a single raw Snappy block.
+///
+/// Panics if `data` exceeds `u32::MAX` bytes, the most a block can announce.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(data.len()).expect("Snappy blocks are limited to u32::MAX bytes.");
+    let mut out = Vec::with_capacity(max_compress_len(data.len()));
+    write_varint(&mut out, len);
+    let config = Config {
+        max_buffer_len: MAX_CHUNK_LEN,
+        match_lengths: 4..65,
+        ..Config::default()
+    };
+    let mut reps = RepDistances::default();
+    for item in SearchBuffer::<u8, 4>::new().to_items(data.iter().copied(), config) {
+        match reps.decode(item) {
+            Item::Raw(raw) => write_literal(&mut out, &raw),
+            Item::Ref { back, len } => write_copy(&mut out, back.get(), len),
+            Item::RepRef { .. } => unreachable!(),
+            // Runs are at least 4 long, so the copy is too.
+            Item::Run { value, len } => {
+                write_literal(&mut out, &[value]);
+                write_copy(&mut out, 1, len - 1);
+            }
+        }
+    }
+    out
+}
+
+/// Reads the uncompressed length announced by a raw Snappy block.
+pub fn decompress_len(data: &[u8]) -> Result<usize, Error> {
+    read_varint(data).map(|(len, _)| len as usize)
+}
+
+/// Decompresses a single raw Snappy block.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let (len, mut data) = read_varint(data)?;
+    let expected = len as usize;
+    // The preamble is untrusted, and no tag expands to more than 32 times its own length.
+    let mut out = Vec::with_capacity(expected.min(data.len().saturating_mul(32)));
+    while let [tag, rest @ ..] = data {
+        let (len, offset);
+        (len, offset, data) = match tag & 0b11 {
+            TAG_LITERAL => {
+                let (len, rest) = match tag >> 2 {
+                    len @ 0..60 => (len as usize + 1, rest),
+                    extra => {
+                        let (bytes, rest) = split(rest, (extra - 59) as usize)?;
+                        (read_le(bytes) + 1, rest)
+                    }
+                };
+                let (literal, rest) = split(rest, len)?;
+                if out.len() + literal.len() > expected {
+                    return Err(Error::LengthMismatch {
+                        expected,
+                        actual: out.len() + literal.len(),
+                    });
+                }
+                out.extend_from_slice(literal);
+                data = rest;
+                continue;
+            }
+            TAG_COPY_1 => {
+                let (bytes, rest) = split(rest, 1)?;
+                let offset = ((*tag as usize & 0b1110_0000) << 3) | bytes[0] as usize;
+                ((*tag as usize >> 2 & 0b111) + 4, offset, rest)
+            }
+            TAG_COPY_2 => {
+                let (bytes, rest) = split(rest, 2)?;
+                ((*tag >> 2) as usize + 1, read_le(bytes), rest)
+            }
+            TAG_COPY_4 => {
+                let (bytes, rest) = split(rest, 4)?;
+                ((*tag >> 2) as usize + 1, read_le(bytes), rest)
+            }
+            _ => unreachable!(),
+        };
+        let position = out.len();
+        if offset == 0 || offset > position {
+            return Err(Error::InvalidOffset { offset, position });
+        }
+        if position + len > expected {
+            return Err(Error::LengthMismatch {
+                expected,
+                actual: position + len,
+            });
+        }
+        let base = position - offset;
+        if offset >= len {
+            out.extend_from_within(base..base + len);
+        } else {
+            // Overlapping copy: replicates the last `offset` bytes as a repeating pattern.
+            for x in base..base + len {
+                out.push(out[x]);
+            }
+        }
+    }
+    if out.len() != expected {
+        return Err(Error::LengthMismatch {
+            expected,
+            actual: out.len(),
+        });
+    }
+    Ok(out)
+}
+
+/// Masks a CRC-32C the way the Snappy framing format stores it.
+pub fn mask_crc(crc: u32) -> u32 {
+    crc.rotate_right(15).wrapping_add(0xa282ead8)
+}
+
+/// Compresses `data` into the Snappy framing format, starting with a stream identifier.
+pub fn compress_frame(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::from(STREAM_IDENTIFIER);
+    for chunk in data.chunks(MAX_CHUNK_LEN) {
+        let crc = mask_crc(crc32c(chunk));
+        let compressed = compress(chunk);
+        // Same heuristic as the reference encoder: only keep compression if it saves at least 12.5%.
+        let (ty, body) = if compressed.len() < chunk.len() - chunk.len() / 8 {
+            (CHUNK_COMPRESSED, compressed.as_slice())
+        } else {
+            (CHUNK_UNCOMPRESSED, chunk)
+        };
+        out.push(ty);
+        out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes()[..3]);
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(body);
+    }
+    out
+}
+
+/// Decompresses a stream in the Snappy framing format, skipping padding and skippable chunks.
+pub fn decompress_frame(mut data: &[u8]) -> Result<Vec<u8>, Error> {
+    if !data.starts_with(&STREAM_IDENTIFIER) {
+        return Err(Error::MissingStreamIdentifier);
+    }
+    let mut out = vec![];
+    while !data.is_empty() {
+        let (header, rest) = split(data, 4)?;
+        let len = read_le(&header[1..]);
+        let body;
+        (body, data) = split(rest, len)?;
+        match header[0] {
+            ty @ (CHUNK_COMPRESSED | CHUNK_UNCOMPRESSED) => {
+                let (crc, body) = split(body, 4)?;
+                let expected = read_le(crc) as u32;
+                let chunk = if ty == CHUNK_COMPRESSED {
+                    if decompress_len(body)? > MAX_CHUNK_LEN {
+                        return Err(Error::ChunkTooLarge(decompress_len(body)?));
+                    }
+                    decompress(body)?
+                } else if body.len() > MAX_CHUNK_LEN {
+                    return Err(Error::ChunkTooLarge(body.len()));
+                } else {
+                    body.to_vec()
+                };
+                let actual = mask_crc(crc32c(&chunk));
+                if actual != expected {
+                    return Err(Error::ChecksumMismatch { expected, actual });
+                }
+                out.extend(chunk);
+            }
+            CHUNK_STREAM_IDENTIFIER => {
+                if body != &STREAM_IDENTIFIER[4..] {
+                    return Err(Error::MissingStreamIdentifier);
+                }
+            }
+            CHUNK_PADDING | 0x80..=0xfd => {}
+            ty => return Err(Error::UnskippableChunk(ty)),
+        }
+    }
+    Ok(out)
+}
+
+fn write_literal(out: &mut Vec<u8>, literal: &[u8]) {
+    for literal in literal.chunks(u32::MAX as usize) {
+        let n = literal.len() - 1;
+        if n < 60 {
+            out.push((n as u8) << 2 | TAG_LITERAL);
+        } else {
+            let bytes = (usize::BITS - n.leading_zeros()).div_ceil(8) as usize;
+            out.push((59 + bytes as u8) << 2 | TAG_LITERAL);
+            out.extend_from_slice(&n.to_le_bytes()[..bytes]);
+        }
+        out.extend_from_slice(literal);
+    }
+}
+
+fn write_copy(out: &mut Vec<u8>, offset: usize, len: usize) {
+    debug_assert!((1..=64).contains(&len));
+    if (4..12).contains(&len) && offset < 0x800 {
+        out.push(((offset >> 8) as u8) << 5 | ((len - 4) as u8) << 2 | TAG_COPY_1);
+        out.push(offset as u8);
+    } else if offset <= 0xFFFF {
+        out.push(((len - 1) as u8) << 2 | TAG_COPY_2);
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+    } else {
+        out.push(((len - 1) as u8) << 2 | TAG_COPY_4);
+        out.extend_from_slice(&(offset as u32).to_le_bytes());
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8]) -> Result<(u32, &[u8]), Error> {
+    let mut value = 0u32;
+    for (x, &byte) in data.iter().enumerate().take(5) {
+        let bits = (byte & 0x7F) as u32;
+        if x == 4 && bits > 0b1111 {
+            return Err(Error::InvalidPreamble);
+        }
+        value |= bits << (7 * x);
+        if byte & 0x80 == 0 {
+            return Ok((value, &data[x + 1..]));
+        }
+    }
+    Err(if data.len() < 5 {
+        Error::UnexpectedEof
+    } else {
+        Error::InvalidPreamble
+    })
+}
+
+fn split(data: &[u8], len: usize) -> Result<(&[u8], &[u8]), Error> {
+    data.split_at_checked(len).ok_or(Error::UnexpectedEof)
+}
+
+fn read_le(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, &byte| acc << 8 | byte as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn decompress_reference() {
+        // Literal "abcdefg", copy-1 (len 4, offset 7), copy-2 (len 2, offset 11).
+        let block = [
+            0x0d, 0x18, b'a', b'b', b'c', b'd', b'e', b'f', b'g', 0x01, 0x07, 0x06, 0x0b, 0x00,
+        ];
+        assert_eq!(decompress(&block).unwrap(), b"abcdefgabcdab");
+    }
+    #[test]
+    fn copy_tags() {
+        let mut out = vec![];
+        write_copy(&mut out, 7, 4);
+        write_copy(&mut out, 0x800, 4);
+        write_copy(&mut out, 0x10000, 64);
+        #[rustfmt::skip]
+        assert_eq!(out, [
+            0x01, 0x07,
+            0x0e, 0x00, 0x08,
+            0xff, 0x00, 0x00, 0x01, 0x00,
+        ]);
+    }
+    #[test]
+    fn invalid() {
+        assert_eq!(decompress(&[]), Err(Error::UnexpectedEof));
+        assert_eq!(decompress(&[0xff; 6]), Err(Error::InvalidPreamble));
+        // Announces almost 4 GiB without any tags.
+        assert_eq!(
+            decompress(&[0xfe, 0xff, 0xff, 0xff, 0x0f]),
+            Err(Error::LengthMismatch {
+                expected: 0xffff_fffe,
+                actual: 0
+            })
+        );
+        assert_eq!(
+            decompress_len(&[0xfe, 0xff, 0xff, 0xff, 0x0f]),
+            Ok(0xffff_fffe)
+        );
+        assert_eq!(
+            decompress(&[4, 0x01, 1, 0]),
+            Err(Error::InvalidOffset {
+                offset: 1,
+                position: 0
+            })
+        );
+        assert_eq!(
+            decompress(&[1, 0x04, 1, 2]),
+            Err(Error::LengthMismatch {
+                expected: 1,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            decompress_frame(b"sNaPpY"),
+            Err(Error::MissingStreamIdentifier)
+        );
+        let mut frame = compress_frame(b"abc");
+        *frame.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            decompress_frame(&frame),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        let mut frame = compress_frame(b"abc");
+        frame.extend([0x02, 0, 0, 0]);
+        assert_eq!(decompress_frame(&frame), Err(Error::UnskippableChunk(0x02)));
+        assert_eq!(
+            decompress_frame(&frame[..frame.len() - 2]),
+            Err(Error::UnexpectedEof)
+        );
+    }
+    #[test]
+    fn frame() {
+        let data = Vec::from_iter((0..0x28000u32).map(|x| (x % 251) as u8 ^ (x >> 12) as u8));
+        let mut frame = compress_frame(&data);
+        assert!(frame.len() < data.len() / 2);
+        frame.extend([CHUNK_PADDING, 2, 0, 0, 0, 0, 0x80, 0, 0, 0]);
+        frame.extend(compress_frame(b"tail"));
+        let mut expected = data.clone();
+        expected.extend(b"tail");
+        assert_eq!(decompress_frame(&frame).unwrap(), expected);
+    }
+    #[quickcheck]
+    fn fuzz(data: Vec<u8>, repeat: u8) {
+        let data = data.repeat(repeat as usize % 8 + 1);
+        let compressed = compress(&data);
+        assert!(compressed.len() <= max_compress_len(data.len()));
+        assert_eq!(decompress_len(&compressed), Ok(data.len()));
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert_eq!(decompress_frame(&compress_frame(&data)).unwrap(), data);
+    }
+}
@@ -0,0 +1,113 @@
+//! Spans and events of the `tracing` feature, which compile to nothing without it.
+//!
+//! Field values are only evaluated with the feature enabled. Fields recorded later are declared
+//! after a `;` and set with `record` on the span, which is a no-op without the feature.
+
+/// Enters a span until the end of the scope, evaluating to it.
+macro_rules! enter {
+    ($level:ident, $name:literal $(, $field:ident = $value:expr)* $(; $($later:ident),*)?) => {{
+        #[cfg(feature = "tracing")]
+        let span = tracing::span!(
+            tracing::Level::$level,
+            $name,
+            $($field = $value,)*
+            $($($later = tracing::field::Empty,)*)?
+        )
+        .entered();
+        #[cfg(not(feature = "tracing"))]
+        let span = $crate::trace::NoSpan;
+        span
+    }};
+}
+
+/// Emits an event in the current span.
+macro_rules! event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($arg)*);
+    };
+}
+
+pub(crate) use {enter, event};
+
+/// Stands in for an entered span without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+#[cfg(not(feature = "tracing"))]
+impl NoSpan {
+    pub(crate) fn record<V>(&self, _field: &str, _value: V) -> &Self {
+        self
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        Event, Metadata, Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+    };
+
+    use crate::{frame, lz::Config};
+
+    /// Fields recorded on a span, the integer ones only.
+    #[derive(Default)]
+    struct Fields(Vec<(&'static str, u64)>);
+    impl Visit for Fields {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            self.0.push((field.name(), value));
+        }
+        fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+    }
+    /// Collects the names and fields of every span.
+    #[derive(Clone, Default)]
+    struct Spans(Arc<Mutex<Vec<(&'static str, Fields)>>>);
+    impl Subscriber for Spans {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.0.lock().unwrap();
+            let mut fields = Fields::default();
+            span.record(&mut fields);
+            spans.push((span.metadata().name(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.0.lock().unwrap();
+            values.record(&mut spans[span.into_u64() as usize - 1].1);
+        }
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        fn event(&self, _event: &Event<'_>) {}
+        fn enter(&self, _span: &Id) {}
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn spans() {
+        let spans = Spans::default();
+        let data = b"abcabcabcabc".repeat(100);
+        tracing::subscriber::with_default(spans.clone(), || {
+            let compressed = frame::compress(&data, Config::default());
+            frame::decompress(&compressed, None).unwrap();
+        });
+        let spans = spans.0.lock().unwrap();
+        let field = |name, field| {
+            let (_, fields) = spans.iter().find(|(span, _)| *span == name).unwrap();
+            fields.0.iter().find(|(name, _)| *name == field).unwrap().1
+        };
+        assert_eq!(field("encode_block", "bytes"), data.len() as u64);
+        assert_eq!(
+            field("match_search", "items"),
+            field("encode_block", "items")
+        );
+        assert_eq!(
+            field("decode_block", "items"),
+            field("encode_block", "items")
+        );
+        assert_eq!(field("decode_block", "bytes"), data.len() as u64);
+        assert!(spans.iter().any(|(name, _)| *name == "serialize"));
+    }
+}
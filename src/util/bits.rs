@@ -0,0 +1,232 @@
+//! Packing values of up to 32 bits into bytes and reading them back, as every entropy coding
+//! stage needs.
+//!
+//! Values are packed in one of two orders, see [`BitOrder`]. The last byte is padded with zero
+//! bits.
+
+/// Order the bits of values are packed into bytes in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Values start at the most significant bit of a byte, their own most significant bit first.
+    /// Used by the Huffman and LZSS stages.
+    MsbFirst,
+    /// Values start at the least significant bit of a byte, their own least significant bit
+    /// first, as in DEFLATE.
+    LsbFirst,
+}
+
+/// Appends values bit by bit to a byte vector.
+#[derive(Debug, Clone)]
+pub struct BitWriter {
+    out: Vec<u8>,
+    /// Bits not yet written to `out`, in its low `bits` bits for MSB-first and its low bits
+    /// for LSB-first order.
+    acc: u64,
+    bits: u32,
+    order: BitOrder,
+}
+impl BitWriter {
+    pub fn new(order: BitOrder) -> Self {
+        Self::with_vec(Vec::new(), order)
+    }
+    /// Appends to the bytes already in `out`.
+    pub fn with_vec(out: Vec<u8>, order: BitOrder) -> Self {
+        Self {
+            out,
+            acc: 0,
+            bits: 0,
+            order,
+        }
+    }
+    /// Writes the low `bits` bits of `value`, which must be 0 above them.
+    ///
+    /// Writes up to 32 bits at once.
+    pub fn write(&mut self, value: u32, bits: u32) {
+        debug_assert!(bits <= 32 && (bits == 32 || value >> bits == 0));
+        match self.order {
+            BitOrder::MsbFirst => {
+                self.acc = self.acc << bits | value as u64;
+                self.bits += bits;
+                while self.bits >= 8 {
+                    self.bits -= 8;
+                    self.out.push((self.acc >> self.bits) as u8);
+                }
+            }
+            BitOrder::LsbFirst => {
+                self.acc |= (value as u64) << self.bits;
+                self.bits += bits;
+                while self.bits >= 8 {
+                    self.out.push(self.acc as u8);
+                    self.acc >>= 8;
+                    self.bits -= 8;
+                }
+            }
+        }
+    }
+    /// Pads the current byte with zero bits, so the next value starts a new one.
+    pub fn align(&mut self) {
+        self.write(0, (8 - self.bits % 8) % 8);
+    }
+    /// Number of bits written, including the bytes the writer started with.
+    pub fn bit_len(&self) -> usize {
+        self.out.len() * 8 + self.bits as usize
+    }
+    /// Pads the last byte and returns the bytes written.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.out
+    }
+}
+
+/// Reads values bit by bit from a byte slice, as written by a [`BitWriter`] of the same order.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    /// Bytes of `data` moved into `acc`.
+    loaded: usize,
+    /// Bits loaded but not yet read, starting at its most significant bit for MSB-first and its
+    /// least significant bit for LSB-first order.
+    acc: u64,
+    bits: u32,
+    order: BitOrder,
+}
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8], order: BitOrder) -> Self {
+        Self {
+            data,
+            loaded: 0,
+            acc: 0,
+            bits: 0,
+            order,
+        }
+    }
+    /// Loads bytes of the input until at least 57 bits are buffered or the input ends.
+    pub fn refill(&mut self) {
+        while self.bits <= 56 {
+            let Some(&byte) = self.data.get(self.loaded) else {
+                break;
+            };
+            self.acc |= match self.order {
+                BitOrder::MsbFirst => (byte as u64) << (56 - self.bits),
+                BitOrder::LsbFirst => (byte as u64) << self.bits,
+            };
+            self.bits += 8;
+            self.loaded += 1;
+        }
+    }
+    /// The next `bits` bits without consuming them, padded with zero bits past the end of the
+    /// input.
+    ///
+    /// Peeks up to 32 bits at once.
+    pub fn peek(&mut self, bits: u32) -> u32 {
+        debug_assert!(bits <= 32);
+        if bits > self.bits {
+            self.refill();
+        }
+        match self.order {
+            _ if bits == 0 => 0,
+            BitOrder::MsbFirst => (self.acc >> (64 - bits)) as u32,
+            BitOrder::LsbFirst => (self.acc & ((1 << bits) - 1)) as u32,
+        }
+    }
+    /// Skips `bits` bits, failing without skipping anything if fewer are left.
+    pub fn consume(&mut self, bits: u32) -> Option<()> {
+        if bits > self.bits {
+            self.refill();
+            if bits > self.bits {
+                return None;
+            }
+        }
+        match self.order {
+            BitOrder::MsbFirst => self.acc <<= bits,
+            BitOrder::LsbFirst => self.acc >>= bits,
+        }
+        self.bits -= bits;
+        Some(())
+    }
+    /// Reads `bits` bits, failing if fewer are left.
+    ///
+    /// Reads up to 32 bits at once.
+    pub fn read(&mut self, bits: u32) -> Option<u32> {
+        let value = self.peek(bits);
+        self.consume(bits)?;
+        Some(value)
+    }
+    /// Skips the rest of the current byte, so the next value is read from a new one.
+    pub fn align(&mut self) {
+        let skipped = self.consume(self.bits % 8);
+        debug_assert!(skipped.is_some());
+    }
+    /// Number of bits read so far.
+    pub fn position(&self) -> usize {
+        self.loaded * 8 - self.bits as usize
+    }
+    /// Number of bits left to read.
+    pub fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position()
+    }
+    /// The input from the first byte boundary not before the bits read on.
+    pub fn rest(&self) -> &'a [u8] {
+        &self.data[self.position().div_ceil(8)..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn layout() {
+        for (order, expected) in [
+            (BitOrder::MsbFirst, [0b1011_1111, 0b1111_1000]),
+            (BitOrder::LsbFirst, [0b1111_0111, 0b0001_1111]),
+        ] {
+            let mut writer = BitWriter::new(order);
+            writer.write(0b1, 1);
+            writer.write(0b011, 3);
+            writer.write(0xff, 8);
+            writer.write(0b1, 1);
+            assert_eq!(writer.bit_len(), 13);
+            let out = writer.finish();
+            assert_eq!(out, expected, "{order:?}");
+            let mut reader = BitReader::new(&out, order);
+            assert_eq!(reader.read(1), Some(1));
+            assert_eq!(reader.read(3), Some(0b011));
+            assert_eq!(reader.read(8), Some(0xff));
+            assert_eq!(reader.read(1), Some(1));
+            assert_eq!(reader.remaining(), 3);
+            reader.align();
+            assert_eq!(reader.rest(), &[] as &[u8]);
+            assert_eq!(reader.peek(8), 0);
+            assert_eq!(reader.read(1), None);
+        }
+    }
+    #[quickcheck]
+    fn roundtrip(values: Vec<(u32, u8)>, lsb: bool, tail: Vec<u8>) {
+        let order = match lsb {
+            true => BitOrder::LsbFirst,
+            false => BitOrder::MsbFirst,
+        };
+        let values = Vec::from_iter(values.iter().map(|&(value, bits)| {
+            let bits = bits as u32 % 33;
+            (value & ((1u64 << bits) - 1) as u32, bits)
+        }));
+        let mut writer = BitWriter::with_vec(vec![0xaa], order);
+        for &(value, bits) in &values {
+            writer.write(value, bits);
+        }
+        let bits = writer.bit_len();
+        let mut out = writer.finish();
+        assert_eq!(out.len(), bits.div_ceil(8));
+        out.extend_from_slice(&tail);
+        let mut reader = BitReader::new(&out, order);
+        assert_eq!(reader.read(8), Some(0xaa));
+        for &(value, bits) in &values {
+            assert_eq!(reader.peek(bits), value);
+            assert_eq!(reader.read(bits), Some(value));
+        }
+        assert_eq!(reader.position(), bits);
+        assert_eq!(reader.rest(), &tail[..]);
+    }
+}
@@ -0,0 +1,134 @@
+//! Symbol frequencies of a stream and the order-0 Shannon entropy they imply, the least any
+//! entropy coder coding the symbols independently can get away with.
+
+/// How often each symbol of a stream occurs, symbols being indices into the counts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<usize>,
+    total: usize,
+}
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Histogram of counts taken elsewhere, like the bucketed ones of [`Stats`](crate::lz::Stats).
+    pub fn from_counts(counts: &[usize]) -> Self {
+        Self {
+            counts: counts.to_vec(),
+            total: counts.iter().sum(),
+        }
+    }
+    /// Histogram of the bytes of `data`, over all 256 of them.
+    pub fn of_bytes(data: &[u8]) -> Self {
+        let mut counts = vec![0; 256];
+        for &byte in data {
+            counts[byte as usize] += 1;
+        }
+        Self {
+            counts,
+            total: data.len(),
+        }
+    }
+    /// Counts `symbol` once.
+    pub fn add(&mut self, symbol: usize) {
+        self.add_n(symbol, 1);
+    }
+    /// Counts `symbol` `n` times.
+    pub fn add_n(&mut self, symbol: usize, n: usize) {
+        if symbol >= self.counts.len() {
+            self.counts.resize(symbol + 1, 0);
+        }
+        self.counts[symbol] += n;
+        self.total += n;
+    }
+    /// Adds the counts of `other`.
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        self.total += other.total;
+    }
+    pub fn count(&self, symbol: usize) -> usize {
+        self.counts.get(symbol).copied().unwrap_or(0)
+    }
+    /// Counts of every symbol up to the largest one counted or given.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+    /// Number of symbols counted.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+    /// Number of different symbols counted.
+    pub fn distinct(&self) -> usize {
+        self.counts.iter().filter(|&&count| count > 0).count()
+    }
+    /// Shannon entropy in bits per symbol, 0 if nothing was counted.
+    pub fn entropy(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.entropy_bits() / total as f64,
+        }
+    }
+    /// Shannon entropy of all symbols counted, in bits.
+    pub fn entropy_bits(&self) -> f64 {
+        let total = self.total as f64;
+        (self.counts.iter())
+            .filter(|&&count| count > 0)
+            .map(|&count| count as f64 * (total / count as f64).log2())
+            .sum()
+    }
+    /// Bytes the symbols take at their entropy, rounded up.
+    ///
+    /// Huffman codes, which spend whole bits on every symbol, never beat this.
+    pub fn estimated_len(&self) -> usize {
+        (self.entropy_bits() / 8.0).ceil() as usize
+    }
+}
+impl Extend<usize> for Histogram {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for symbol in iter {
+            self.add(symbol);
+        }
+    }
+}
+impl FromIterator<usize> for Histogram {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut histogram = Self::new();
+        histogram.extend(iter);
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn entropy() {
+        assert_eq!(Histogram::new().entropy(), 0.0);
+        assert_eq!(Histogram::of_bytes(b"aaaa").entropy(), 0.0);
+        assert_eq!(Histogram::of_bytes(b"abab").entropy(), 1.0);
+        let histogram = Histogram::from_iter([0, 1, 2, 2]);
+        assert_eq!(histogram.entropy(), 1.5);
+        assert_eq!(histogram.entropy_bits(), 6.0);
+        assert_eq!(histogram.estimated_len(), 1);
+        assert_eq!((histogram.count(2), histogram.count(9)), (2, 0));
+        assert_eq!((histogram.total(), histogram.distinct()), (4, 3));
+        assert_eq!(Histogram::from_counts(&[1, 1, 2]), histogram);
+        let data = Vec::from_iter((0..=255).cycle().take(1024));
+        assert_eq!(Histogram::of_bytes(&data).entropy(), 8.0);
+    }
+    #[quickcheck]
+    fn merge(a: Vec<u8>, b: Vec<u8>) {
+        let mut merged = Histogram::of_bytes(&a);
+        merged.merge(&Histogram::from_iter(b.iter().map(|&byte| byte as usize)));
+        let all = Histogram::of_bytes(&[a, b].concat());
+        assert_eq!(merged, all);
+        assert!(all.entropy() <= (all.distinct().max(1) as f64).log2() + 1e-9);
+    }
+}
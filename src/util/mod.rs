@@ -0,0 +1,221 @@
+#![allow(dead_code)]
+pub mod bits;
+pub mod histogram;
+pub mod varint;
+pub mod xxhash;
+
+use std::{
+    hash::{BuildHasherDefault, Hasher},
+    ops::{Bound, Range, RangeBounds},
+};
+
+/// Slicing that clamps out of range indices instead of panicking.
+pub trait SliceExt<T> {
+    /// `&self[index]` with both bounds clamped to the slice, empty if the start is past the end.
+    fn get_clamped(&self, index: impl RangeBounds<usize>) -> &[T];
+    /// Mutable [`get_clamped`](SliceExt::get_clamped).
+    fn get_clamped_mut(&mut self, index: impl RangeBounds<usize>) -> &mut [T];
+    /// `self.split_at(mid)` with `mid` clamped to the slice, so the tail may be empty.
+    fn split_at_clamped(&self, mid: usize) -> (&[T], &[T]);
+}
+impl<T> SliceExt<T> for [T] {
+    fn get_clamped(&self, index: impl RangeBounds<usize>) -> &[T] {
+        &self[clamp_range(index, self.len())]
+    }
+    fn get_clamped_mut(&mut self, index: impl RangeBounds<usize>) -> &mut [T] {
+        let range = clamp_range(index, self.len());
+        &mut self[range]
+    }
+    fn split_at_clamped(&self, mid: usize) -> (&[T], &[T]) {
+        self.split_at(mid.min(self.len()))
+    }
+}
+
+/// `index` as a range within `0..len`.
+fn clamp_range(index: impl RangeBounds<usize>, len: usize) -> Range<usize> {
+    let end = match index.end_bound() {
+        Bound::Included(&end) => end.saturating_add(1),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    }
+    .min(len);
+    let start = match index.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    }
+    .min(end);
+    start..end
+}
+
+/// Fast hasher mixing every word into its state with a folded 128-bit multiplication, as wyhash
+/// does, so that every input bit affects every output bit and the order of words matters.
+///
+/// Not resistant to inputs crafted to collide, which a [`SearchBuffer`] only pays for with
+/// longer chains. [`BuildSlideHasher`] is its default.
+///
+/// [`SearchBuffer`]: crate::search_buffer::SearchBuffer
+#[derive(Debug, Clone, Copy)]
+pub struct SlideHasher(u64);
+pub type BuildSlideHasher = BuildHasherDefault<SlideHasher>;
+impl Default for SlideHasher {
+    fn default() -> Self {
+        Self(MIX_SEED)
+    }
+}
+const MIX_SEED: u64 = 0xA076_1D64_78BD_642F;
+const MIX_WORD: u64 = 0xE703_7ED1_A0B4_28DB;
+const MIX_FINISH: u64 = 0x8EBC_6AF0_9C88_C6E3;
+/// Multiplies `a` and `b`, folding the high half of the product into the low one.
+fn fold_mul(a: u64, b: u64) -> u64 {
+    let product = a as u128 * b as u128;
+    product as u64 ^ (product >> 64) as u64
+}
+impl Hasher for SlideHasher {
+    fn finish(&self) -> u64 {
+        fold_mul(self.0, MIX_FINISH)
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        let (chunks, tail) = bytes.as_chunks::<8>();
+        for &chunk in chunks {
+            self.write_u64(u64::from_le_bytes(chunk));
+        }
+        if !tail.is_empty() {
+            let mut last = [0; 8];
+            last[..tail.len()].copy_from_slice(tail);
+            // Sets a bit past the tail, so zero padding differs from zero bytes.
+            self.write_u64(u64::from_le_bytes(last) | 1 << (tail.len() * 8));
+        }
+    }
+    fn write_u8(&mut self, i: u8) {
+        self.write_u64(i as u64);
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i as u64);
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.0 = fold_mul(self.0 ^ i, MIX_WORD);
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// Identity hasher XORing the words written together, for keys that already are well mixed
+/// hashes.
+///
+/// Any other keys collide as soon as they hold the same words in any order, like `[a, b]` and
+/// `[b, a]`: use [`SlideHasher`] for those.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdentityHasher(u64);
+pub type BuildIdentityHasher = BuildHasherDefault<IdentityHasher>;
+impl Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        let (chunks, tail) = bytes.as_chunks::<8>();
+        for chunk in chunks.iter().copied() {
+            self.write_u64(u64::from_ne_bytes(chunk));
+        }
+        if !tail.is_empty() {
+            self.write_u64(u64::from_ne_bytes([(); 8].map({
+                let mut tail = tail.iter().copied();
+                move |()| tail.next().unwrap_or_default()
+            })));
+        }
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.0 ^= i;
+    }
+}
+
+const fn crc32_table(poly: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            k += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+}
+const CRC32C_TABLE: [u32; 256] = crc32_table(0x82F63B78);
+
+/// CRC-32C (Castagnoli), as used by Snappy framing.
+pub fn crc32c(data: &[u8]) -> u32 {
+    crc32c_update(0, data)
+}
+/// Continues the CRC-32C `crc` of previous data with `data`.
+pub fn crc32c_update(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |crc, &byte| {
+        CRC32C_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashSet, hash::Hash};
+
+    #[test]
+    fn crc32c_check() {
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(b"123456789"), 0xE3069283);
+        assert_eq!(crc32c_update(crc32c(b"1234"), b"56789"), 0xE3069283);
+    }
+    #[test]
+    fn slide_hasher() {
+        let hash = |value: &dyn Fn(&mut SlideHasher)| {
+            let mut hasher = SlideHasher::default();
+            value(&mut hasher);
+            hasher.finish()
+        };
+        assert_ne!(hash(&|h| "ab".hash(h)), hash(&|h| "ba".hash(h)));
+        assert_ne!(hash(&|h| [1u8, 2].hash(h)), hash(&|h| [2u8, 1].hash(h)));
+        assert_ne!(hash(&|h| h.write(&[0])), hash(&|h| h.write(&[0, 0])));
+        assert_eq!(hash(&|h| h.write(b"abc")), hash(&|h| h.write(b"abc")));
+        let mut identity = IdentityHasher::default();
+        identity.write(b"abcdefghabcdefgh");
+        assert_eq!(identity.finish(), 0);
+        let keys = HashSet::<u64>::from_iter((0..0x10000u32).map(|x| {
+            let mut hasher = SlideHasher::default();
+            x.to_le_bytes().hash(&mut hasher);
+            // The buckets of a map of this size.
+            hasher.finish() & 0xffff
+        }));
+        assert!(keys.len() > 0x10000 * 6 / 10);
+    }
+    #[test]
+    fn clamped() {
+        let mut data = [0, 1, 2, 3];
+        assert_eq!(data.get_clamped(2..9), [2, 3]);
+        assert_eq!(data.get_clamped(..=1), [0, 1]);
+        assert_eq!(data.get_clamped(5..), []);
+        assert_eq!(
+            data.get_clamped((Bound::Included(3), Bound::Excluded(1))),
+            []
+        );
+        assert_eq!(data.get_clamped(..=usize::MAX), data);
+        assert_eq!(
+            data.get_clamped((Bound::Excluded(0), Bound::Unbounded)),
+            [1, 2, 3]
+        );
+        data.get_clamped_mut(3..).fill(9);
+        assert_eq!(data, [0, 1, 2, 9]);
+        assert_eq!(data.split_at_clamped(1), (&[0][..], &[1, 2, 9][..]));
+        assert_eq!(data.split_at_clamped(7), (&data[..], &[][..]));
+    }
+}
@@ -0,0 +1,183 @@
+//! LEB128 varints: 7 bits per byte, least significant group first, the high bit set on every
+//! byte but the last. Signed values are zigzag encoded first, so small magnitudes stay short.
+//!
+//! Unsigned values are laid out like postcard's, which frames and the other formats used before.
+
+use std::{
+    fmt::{self, Display},
+    io::{self, Write},
+};
+
+/// Longest encoding of a `u64`.
+pub const MAX_LEN: usize = 10;
+
+/// Why a varint couldn't be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The input ended before the last byte of the varint.
+    UnexpectedEof,
+    /// The value doesn't fit the type read.
+    Overflow,
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnexpectedEof => write!(f, "unexpected end of varint"),
+            Error::Overflow => write!(f, "varint overflows"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+/// Encodes `value`, returning the buffer and the length used of it.
+pub fn encode(mut value: u64) -> ([u8; MAX_LEN], usize) {
+    let mut buf = [0; MAX_LEN];
+    let mut len = 0;
+    while value >= 0x80 {
+        buf[len] = value as u8 | 0x80;
+        value >>= 7;
+        len += 1;
+    }
+    buf[len] = value as u8;
+    (buf, len + 1)
+}
+
+/// Number of bytes `value` is encoded in.
+pub fn len(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+/// Appends `value` to `out`.
+pub fn write(out: &mut Vec<u8>, value: u64) {
+    let (buf, len) = encode(value);
+    out.extend_from_slice(&buf[..len]);
+}
+
+/// Writes `value` to `writer`.
+pub fn write_to(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    let (buf, len) = encode(value);
+    writer.write_all(&buf[..len])
+}
+
+/// Reads the varint `data` starts with, returning it and the rest of `data`.
+pub fn read(data: &[u8]) -> Result<(u64, &[u8]), Error> {
+    let mut value = 0;
+    for (x, &byte) in data.iter().enumerate().take(MAX_LEN) {
+        let bits = (byte & 0x7f) as u64;
+        // The tenth byte only holds the top bit of a u64.
+        if x == MAX_LEN - 1 && bits > 1 {
+            return Err(Error::Overflow);
+        }
+        value |= bits << (7 * x);
+        if byte & 0x80 == 0 {
+            return Ok((value, &data[x + 1..]));
+        }
+    }
+    match data.len() < MAX_LEN {
+        true => Err(Error::UnexpectedEof),
+        false => Err(Error::Overflow),
+    }
+}
+
+/// Like [`read`] for a `usize`.
+pub fn read_usize(data: &[u8]) -> Result<(usize, &[u8]), Error> {
+    let (value, data) = read(data)?;
+    Ok((usize::try_from(value).map_err(|_| Error::Overflow)?, data))
+}
+
+/// Maps signed values to unsigned ones, alternating between positive and negative: 0, -1, 1,
+/// -2, ... become 0, 1, 2, 3, ...
+pub fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Inverse of [`zigzag`].
+pub fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Appends the zigzag encoded `value` to `out`.
+pub fn write_signed(out: &mut Vec<u8>, value: i64) {
+    write(out, zigzag(value));
+}
+
+/// Reads a varint written by [`write_signed`].
+pub fn read_signed(data: &[u8]) -> Result<(i64, &[u8]), Error> {
+    let (value, data) = read(data)?;
+    Ok((unzigzag(value), data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn layout() {
+        for (value, expected) in [
+            (0, &[0][..]),
+            (0x7f, &[0x7f]),
+            (0x80, &[0x80, 0x01]),
+            (300, &[0xac, 0x02]),
+            (
+                u64::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            ),
+        ] {
+            let mut out = vec![];
+            write(&mut out, value);
+            assert_eq!(out, expected);
+            assert_eq!(len(value), expected.len());
+            assert_eq!(read(&out), Ok((value, &[][..])));
+        }
+        assert_eq!(read(&[]), Err(Error::UnexpectedEof));
+        assert_eq!(read(&[0x80, 0x80]), Err(Error::UnexpectedEof));
+        let mut too_large = vec![0xff; 9];
+        too_large.push(0x02);
+        assert_eq!(read(&too_large), Err(Error::Overflow));
+        assert_eq!(read(&[0x80; 11]), Err(Error::Overflow));
+        assert_eq!(
+            read_usize(&[0xff, 0xff, 0xff, 0xff, 0x1f]).map(|(value, _)| value as u64),
+            Ok(u32::MAX as u64 * 2 + 1)
+        );
+        assert_eq!((zigzag(0), zigzag(-1), zigzag(1), zigzag(-2)), (0, 1, 2, 3));
+        assert_eq!(
+            (zigzag(i64::MAX), zigzag(i64::MIN)),
+            (u64::MAX - 1, u64::MAX)
+        );
+    }
+    #[quickcheck]
+    fn roundtrip(values: Vec<u64>, signed: Vec<i64>, tail: Vec<u8>) {
+        let mut out = vec![];
+        for &value in &values {
+            write(&mut out, value);
+        }
+        for &value in &signed {
+            write_signed(&mut out, value);
+        }
+        let mut writer = vec![];
+        for &value in &values {
+            write_to(&mut writer, value).unwrap();
+        }
+        assert_eq!(writer, out[..writer.len()]);
+        out.extend_from_slice(&tail);
+        let mut data = &out[..];
+        for &value in &values {
+            let read_value;
+            (read_value, data) = read(data).unwrap();
+            assert_eq!(read_value, value);
+        }
+        for &value in &signed {
+            let read_value;
+            (read_value, data) = read_signed(data).unwrap();
+            assert_eq!(read_value, value);
+        }
+        assert_eq!(data, tail);
+    }
+    #[quickcheck]
+    fn postcard_compatible(value: u64) {
+        let mut out = vec![];
+        write(&mut out, value);
+        assert_eq!(out, postcard::to_stdvec(&value).unwrap());
+    }
+}
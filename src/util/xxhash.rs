@@ -0,0 +1,174 @@
+//! XXH64, a fast non-cryptographic 64-bit hash whose output is fixed by its specification, so it
+//! can be stored in formats: frames checksum their content and dictionaries with it.
+//!
+//! Input is consumed in stripes of four 64-bit lanes, each mixed into an accumulator of its own,
+//! which [`XxHash64`] buffers for input arriving in pieces.
+
+use std::hash::{BuildHasherDefault, Hasher};
+
+const PRIME_1: u64 = 0x9E3779B185EBCA87;
+const PRIME_2: u64 = 0xC2B2AE3D27D4EB4F;
+const PRIME_3: u64 = 0x165667B19E3779F9;
+const PRIME_4: u64 = 0x85EBCA77C2B2AE63;
+const PRIME_5: u64 = 0x27D4EB2F165667C5;
+
+/// Bytes mixed into the accumulators at once.
+const STRIPE_LEN: usize = 32;
+
+/// XXH64 of `data` with `seed`.
+pub fn xxhash64(data: &[u8], seed: u64) -> u64 {
+    let mut hasher = XxHash64::with_seed(seed);
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// Low 32 bits of the XXH64 of `data` with seed 0, the checksum frames and dictionaries are
+/// identified by.
+pub fn checksum(data: &[u8]) -> u32 {
+    xxhash64(data, 0) as u32
+}
+
+/// Streaming XXH64: [`finish`](Hasher::finish) returns the hash of everything written so far,
+/// however it was split up.
+#[derive(Debug, Clone)]
+pub struct XxHash64 {
+    seed: u64,
+    acc: [u64; 4],
+    /// Input not yet making up a whole stripe.
+    buf: [u8; STRIPE_LEN],
+    buf_len: usize,
+    total: u64,
+}
+pub type BuildXxHash64 = BuildHasherDefault<XxHash64>;
+impl XxHash64 {
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            acc: [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ],
+            buf: [0; STRIPE_LEN],
+            buf_len: 0,
+            total: 0,
+        }
+    }
+    /// Low 32 bits of [`finish`](Hasher::finish), see [`checksum`].
+    pub fn finish_u32(&self) -> u32 {
+        self.finish() as u32
+    }
+    fn stripe(&mut self, stripe: &[u8; STRIPE_LEN]) {
+        for (acc, lane) in self.acc.iter_mut().zip(stripe.as_chunks::<8>().0) {
+            *acc = round(*acc, u64::from_le_bytes(*lane));
+        }
+    }
+}
+impl Default for XxHash64 {
+    fn default() -> Self {
+        Self::with_seed(0)
+    }
+}
+impl Hasher for XxHash64 {
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.total += bytes.len() as u64;
+        if self.buf_len > 0 {
+            let len = bytes.len().min(STRIPE_LEN - self.buf_len);
+            self.buf[self.buf_len..][..len].copy_from_slice(&bytes[..len]);
+            self.buf_len += len;
+            bytes = &bytes[len..];
+            if self.buf_len < STRIPE_LEN {
+                return;
+            }
+            self.stripe(&self.buf.clone());
+            self.buf_len = 0;
+        }
+        let (stripes, tail) = bytes.as_chunks::<STRIPE_LEN>();
+        for stripe in stripes {
+            self.stripe(stripe);
+        }
+        self.buf[..tail.len()].copy_from_slice(tail);
+        self.buf_len = tail.len();
+    }
+    fn finish(&self) -> u64 {
+        let mut hash = match self.total >= STRIPE_LEN as u64 {
+            true => {
+                let [a, b, c, d] = self.acc;
+                let hash = (a.rotate_left(1))
+                    .wrapping_add(b.rotate_left(7))
+                    .wrapping_add(c.rotate_left(12))
+                    .wrapping_add(d.rotate_left(18));
+                self.acc.iter().fold(hash, |hash, &acc| merge(hash, acc))
+            }
+            false => self.seed.wrapping_add(PRIME_5),
+        };
+        hash = hash.wrapping_add(self.total);
+        let (lanes, tail) = self.buf[..self.buf_len].as_chunks::<8>();
+        for lane in lanes {
+            hash ^= round(0, u64::from_le_bytes(*lane));
+            hash = (hash.rotate_left(27))
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+        }
+        let (words, tail) = tail.as_chunks::<4>();
+        for word in words {
+            hash ^= (u32::from_le_bytes(*word) as u64).wrapping_mul(PRIME_1);
+            hash = (hash.rotate_left(23))
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+        }
+        for &byte in tail {
+            hash ^= (byte as u64).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^ hash >> 32
+    }
+}
+
+fn round(acc: u64, lane: u64) -> u64 {
+    (acc.wrapping_add(lane.wrapping_mul(PRIME_2)))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+fn merge(hash: u64, acc: u64) -> u64 {
+    (hash ^ round(0, acc))
+        .wrapping_mul(PRIME_1)
+        .wrapping_add(PRIME_4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn known() {
+        assert_eq!(xxhash64(b"", 0), 0xEF46DB3751D8E999);
+        assert_eq!(xxhash64(b"a", 0), 0xD24EC4F1A98C6E5B);
+        assert_eq!(xxhash64(b"abc", 0), 0x44BC2CF5AD770999);
+        assert_eq!(
+            xxhash64(b"Nobody inspects the spammish repetition", 0),
+            0xFBCEA83C8A378BF1
+        );
+        assert_eq!(checksum(b"abc"), 0xAD770999);
+        assert_ne!(xxhash64(b"abc", 1), xxhash64(b"abc", 0));
+    }
+    #[quickcheck]
+    fn streaming(data: Vec<u8>, splits: Vec<usize>, seed: u64) {
+        let mut hasher = XxHash64::with_seed(seed);
+        let mut rest = &data[..];
+        for split in splits {
+            let (piece, tail) = rest.split_at(split % (rest.len() + 1));
+            hasher.write(piece);
+            rest = tail;
+        }
+        hasher.write(rest);
+        assert_eq!(hasher.finish(), xxhash64(&data, seed));
+    }
+}
@@ -0,0 +1,193 @@
+//! JavaScript bindings, built on the single threaded [`frame`] functions.
+//!
+//! Nothing here spawns threads, and since [`frame::compress`] never depends on the hasher seed,
+//! frames compressed in the browser are byte-identical to those compressed natively.
+
+use std::{io::Write, mem};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    frame::{self, Encoder, PushDecoder},
+    lz::{self, Config},
+};
+
+/// How matches are chosen, see [`lz::Strategy`].
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Greedy,
+    Lazy,
+    Optimal,
+}
+
+/// Compression options, converted to a [`Config`] without a dictionary.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Default: 0x10000
+    pub window_len: usize,
+    /// Default: 4
+    pub min_match: usize,
+    /// Inclusive. Default: 0xFFF
+    pub max_match: usize,
+    /// Default: [`Strategy::Greedy`]
+    pub strategy: Strategy,
+    /// Positions looked ahead by [`Strategy::Lazy`]. Default: 0
+    pub lazy_level: usize,
+}
+#[wasm_bindgen]
+impl Options {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            window_len: 0x10000,
+            min_match: 4,
+            max_match: 0xFFF,
+            strategy: Strategy::Greedy,
+            lazy_level: 0,
+        }
+    }
+}
+impl Options {
+    fn to_config(self) -> Result<Config<'static>, JsError> {
+        let config = Config {
+            max_buffer_len: self.window_len,
+            match_lengths: self.min_match..self.max_match.saturating_add(1),
+            strategy: match self.strategy {
+                Strategy::Greedy => lz::Strategy::Greedy,
+                Strategy::Lazy => lz::Strategy::Lazy(self.lazy_level),
+                Strategy::Optimal => lz::Strategy::Optimal,
+            },
+            ..Config::default()
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Compresses `data` into a single frame.
+#[wasm_bindgen]
+pub fn compress(data: &[u8], options: Option<Options>) -> Result<Vec<u8>, JsError> {
+    let config = options.unwrap_or_default().to_config()?;
+    Ok(frame::compress(data, config))
+}
+
+/// Decompresses a single frame.
+#[wasm_bindgen]
+pub fn decompress(frame: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(frame::decompress(frame, None)?)
+}
+
+/// Streaming compressor, shaped to back the `transform` and `flush` callbacks of a
+/// `TransformStream`.
+///
+/// Streaming needs a `min_match` of at least 4.
+#[wasm_bindgen]
+pub struct CompressStream {
+    encoder: Option<Encoder<'static, Vec<u8>>>,
+}
+#[wasm_bindgen]
+impl CompressStream {
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: Option<Options>) -> Result<CompressStream, JsError> {
+        let config = options.unwrap_or_default().to_config()?;
+        Ok(Self {
+            encoder: Some(Encoder::new(vec![], config)?),
+        })
+    }
+    /// Compresses `chunk`, returning the output that became available.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, JsError> {
+        let encoder = self.encoder.as_mut().ok_or_else(finished)?;
+        encoder.write_all(chunk)?;
+        Ok(mem::take(encoder.get_mut()))
+    }
+    /// Ends the frame, returning the remaining output.
+    pub fn finish(&mut self) -> Result<Vec<u8>, JsError> {
+        Ok(self.encoder.take().ok_or_else(finished)?.finish()?)
+    }
+}
+
+/// Streaming decompressor, the counterpart of [`CompressStream`].
+#[wasm_bindgen]
+pub struct DecompressStream {
+    state: PushDecoder<'static>,
+    done: bool,
+}
+#[wasm_bindgen]
+impl DecompressStream {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Decompresses as much as possible after buffering `chunk`.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.state.feed(chunk);
+        let mut out = vec![];
+        let mut buf = [0; 0x2000];
+        while !self.done {
+            match self.state.read(&mut buf)? {
+                Some(0) => self.done = true,
+                Some(len) => out.extend_from_slice(&buf[..len]),
+                None => break,
+            }
+        }
+        Ok(out)
+    }
+    /// Fails if the frame is incomplete.
+    pub fn finish(&mut self) -> Result<(), JsError> {
+        match self.done {
+            true => Ok(()),
+            false => Err(frame::Error::UnexpectedEof.into()),
+        }
+    }
+}
+impl Default for DecompressStream {
+    fn default() -> Self {
+        Self {
+            state: PushDecoder::new(None),
+            done: false,
+        }
+    }
+}
+
+fn finished() -> JsError {
+    JsError::new("stream already finished")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let data = Vec::from_iter((0..0x30000u32).map(|x| (x / 5 % 199) as u8));
+        let options = Options {
+            strategy: Strategy::Lazy,
+            lazy_level: 2,
+            ..Options::new()
+        };
+        let frame = compress(&data, Some(options)).unwrap();
+        assert_eq!(frame, frame::compress(&data, options.to_config().unwrap()));
+        assert_eq!(decompress(&frame).unwrap(), data);
+
+        let mut stream = CompressStream::new(Some(options)).unwrap();
+        let mut frame = vec![];
+        for chunk in data.chunks(0x7777) {
+            frame.extend(stream.push(chunk).unwrap());
+        }
+        frame.extend(stream.finish().unwrap());
+        let mut stream = DecompressStream::new();
+        let mut decoded = vec![];
+        for chunk in frame.chunks(0x333) {
+            decoded.extend(stream.push(chunk).unwrap());
+        }
+        stream.finish().unwrap();
+        assert_eq!(decoded, data);
+    }
+}